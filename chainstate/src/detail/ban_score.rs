@@ -74,9 +74,12 @@ impl BanScore for ConnectTransactionError {
             // Even though this is an invariant error, it stems from referencing a block for reward that doesn't exist
             ConnectTransactionError::MissingOutputOrSpent => 100,
             ConnectTransactionError::MissingCoinOutputToStake => 100,
+            ConnectTransactionError::DuplicateInputInTransaction(_) => 100,
             ConnectTransactionError::AttemptToPrintMoney(_, _) => 100,
             ConnectTransactionError::TxFeeTotalCalcFailed(_, _) => 100,
+            ConnectTransactionError::SurplusInNonFeeCurrency(_, _) => 100,
             ConnectTransactionError::SignatureVerificationFailed(_) => 100,
+            ConnectTransactionError::InputWitnessTooLarge(_, _) => 100,
             ConnectTransactionError::BlockHeightArithmeticError => 100,
             ConnectTransactionError::BlockTimestampArithmeticError => 100,
             // Even though this is an invariant error, it stems from a block reward that doesn't exist
@@ -169,8 +172,6 @@ impl BanScore for CheckBlockError {
             CheckBlockError::WitnessMerkleRootMismatch => 100,
             // even though this may be an invariant error, we treat it strictly
             CheckBlockError::PrevBlockNotFound(_, _) => 100,
-            CheckBlockError::BlockTimeOrderInvalid => 100,
-            CheckBlockError::BlockFromTheFuture => 100,
             CheckBlockError::BlockSizeError(err) => err.ban_score(),
             CheckBlockError::CheckTransactionFailed(err) => err.ban_score(),
             CheckBlockError::ConsensusVerificationFailed(err) => err.ban_score(),
@@ -190,8 +191,10 @@ impl BanScore for TokensError {
             TokensError::IssueErrorTickerHasNoneAlphaNumericChar(_, _) => 100,
             TokensError::IssueAmountIsZero(_, _) => 100,
             TokensError::IssueErrorTooManyDecimals(_, _) => 100,
+            TokensError::IssueAmountNotRepresentable(_, _) => 100,
             TokensError::IssueErrorIncorrectMetadataURI(_, _) => 100,
             TokensError::MultipleTokenIssuanceInTransaction(_, _) => 100,
+            TokensError::CannotTransferAndIssueSameToken(_, _) => 100,
             TokensError::CoinOrTokenOverflow => 100,
             TokensError::InsufficientTokenFees(_, _) => 100,
             TokensError::NoTxInMainChainByOutpoint => 100,
@@ -208,6 +211,11 @@ impl BanScore for TokensError {
             TokensError::IssueErrorIncorrectMediaURI(_, _) => 100,
             TokensError::MediaHashTooShort => 100,
             TokensError::MediaHashTooLong => 100,
+            TokensError::TokenIsFrozen(_) => 100,
+            TokensError::TokenAlreadyFrozen(_) => 100,
+            TokensError::TokenNotFrozen(_) => 100,
+            TokensError::FreezeOfNonexistentToken(_) => 100,
+            TokensError::FreezeNotAuthorized(_, _) => 100,
         }
     }
 }
@@ -234,6 +242,8 @@ impl BanScore for ConsensusVerificationError {
             ConsensusVerificationError::ConsensusTypeMismatch(_) => 100,
             ConsensusVerificationError::PoWError(err) => err.ban_score(),
             ConsensusVerificationError::UnsupportedConsensusType => 100,
+            ConsensusVerificationError::BlockTimeOrderInvalid(_, _) => 100,
+            ConsensusVerificationError::BlockFromTheFuture(_) => 100,
         }
     }
 }
@@ -242,6 +252,8 @@ impl BanScore for ConsensusPoWError {
     fn ban_score(&self) -> u32 {
         match self {
             ConsensusPoWError::InvalidPoW(_) => 100,
+            ConsensusPoWError::NoPowData(_) => 100,
+            ConsensusPoWError::InvalidTargetBits(_, _, _) => 100,
             ConsensusPoWError::PrevBlockLoadError(_, _, _) => 0,
             ConsensusPoWError::PrevBlockNotFound(_, _) => 100,
             ConsensusPoWError::AncestorAtHeightNotFound(_, _, _) => 0,
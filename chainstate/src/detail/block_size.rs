@@ -0,0 +1,54 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use common::chain::{Block, ChainConfig};
+use utils::ensure;
+
+use super::BlockSizeError;
+
+/// Check that the size of each part of the block is within the limits of the chain
+///
+/// This check doesn't need the chainstate, so it can be done before the block is processed,
+/// for example when the block is received from a peer.
+pub fn check_block_size(chain_config: &ChainConfig, block: &Block) -> Result<(), BlockSizeError> {
+    let block_size = block.block_size();
+
+    ensure!(
+        block_size.size_from_header() <= chain_config.max_block_header_size(),
+        BlockSizeError::Header(
+            block_size.size_from_header(),
+            chain_config.max_block_header_size()
+        )
+    );
+
+    ensure!(
+        block_size.size_from_txs() <= chain_config.max_block_size_from_txs(),
+        BlockSizeError::SizeOfTxs(
+            block_size.size_from_txs(),
+            chain_config.max_block_size_from_txs()
+        )
+    );
+
+    ensure!(
+        block_size.size_from_smart_contracts()
+            <= chain_config.max_block_size_from_smart_contracts(),
+        BlockSizeError::SizeOfSmartContracts(
+            block_size.size_from_smart_contracts(),
+            chain_config.max_block_size_from_smart_contracts()
+        )
+    );
+
+    Ok(())
+}
@@ -26,7 +26,7 @@ use common::{
             calculate_tx_merkle_root, calculate_witness_merkle_root, BlockHeader, BlockReward,
         },
         tokens::TokenAuxiliaryData,
-        tokens::{get_tokens_issuance_count, OutputValue, TokenId},
+        tokens::{get_tokens_issuance_count, token_id, OutputValue, TokenData, TokenId},
         Block, ChainConfig, GenBlock, GenBlockId, OutPointSourceId, Transaction,
     },
     primitives::{id::WithId, BlockDistance, BlockHeight, Id, Idable},
@@ -44,6 +44,7 @@ use crate::{BlockError, BlockSource, ChainstateConfig};
 use self::tx_verifier_storage::gen_block_index_getter;
 
 use super::{
+    check_block_size,
     median_time::calculate_median_time_past,
     orphan_blocks::{OrphanBlocks, OrphanBlocksMut},
     tokens::check_tokens_data,
@@ -397,20 +398,15 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
             .map_err(CheckBlockError::ConsensusVerificationFailed)
             .log_err()?;
 
-        let prev_block_id = header.prev_block_id();
-        let median_time_past = calculate_median_time_past(self, prev_block_id);
-        ensure!(
-            header.timestamp() >= median_time_past,
-            CheckBlockError::BlockTimeOrderInvalid,
-        );
-
-        let max_future_offset = self.chain_config.max_future_block_time_offset();
-        let current_time = self.current_time();
-        let block_timestamp = header.timestamp();
-        ensure!(
-            block_timestamp.as_duration_since_epoch() <= current_time + *max_future_offset,
-            CheckBlockError::BlockFromTheFuture,
-        );
+        let median_time_past = calculate_median_time_past(self, header.prev_block_id());
+        consensus::check_block_timestamp(
+            header,
+            median_time_past,
+            self.current_time(),
+            *self.chain_config.max_future_block_time_offset(),
+        )
+        .map_err(CheckBlockError::ConsensusVerificationFailed)
+        .log_err()?;
         Ok(())
     }
 
@@ -485,34 +481,7 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
     }
 
     fn check_block_size(&self, block: &Block) -> Result<(), BlockSizeError> {
-        let block_size = block.block_size();
-
-        ensure!(
-            block_size.size_from_header() <= self.chain_config.max_block_header_size(),
-            BlockSizeError::Header(
-                block_size.size_from_header(),
-                self.chain_config.max_block_header_size()
-            )
-        );
-
-        ensure!(
-            block_size.size_from_txs() <= self.chain_config.max_block_size_from_txs(),
-            BlockSizeError::SizeOfTxs(
-                block_size.size_from_txs(),
-                self.chain_config.max_block_size_from_txs()
-            )
-        );
-
-        ensure!(
-            block_size.size_from_smart_contracts()
-                <= self.chain_config.max_block_size_from_smart_contracts(),
-            BlockSizeError::SizeOfSmartContracts(
-                block_size.size_from_smart_contracts(),
-                self.chain_config.max_block_size_from_smart_contracts()
-            )
-        );
-
-        Ok(())
+        check_block_size(self.chain_config, block)
     }
 
     fn check_witness_count(&self, block: &Block) -> Result<(), CheckBlockTransactionsError> {
@@ -569,6 +538,35 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
                 )
             );
 
+            // The token issued in the tx can't be transferred in the same tx, otherwise the amounts
+            // of the token would be ambiguous
+            if issuance_count == 1 {
+                let issued_token_id = token_id(tx.transaction());
+                let transfers_issued_token =
+                    tx.outputs().iter().any(|output| match output.value() {
+                        OutputValue::Coin(_) => false,
+                        OutputValue::Token(token_data) => match &**token_data {
+                            TokenData::TokenTransfer(transfer) => {
+                                Some(transfer.token_id) == issued_token_id
+                            }
+                            TokenData::TokenIssuance(_)
+                            | TokenData::TokenIssuanceV2(_)
+                            | TokenData::NftIssuance(_)
+                            | TokenData::TokenFreezeV1 { .. }
+                            | TokenData::TokenUnfreezeV1 { .. } => false,
+                        },
+                    });
+                ensure!(
+                    !transfers_issued_token,
+                    CheckBlockTransactionsError::TokensError(
+                        TokensError::CannotTransferAndIssueSameToken(
+                            tx.transaction().get_id(),
+                            block.get_id()
+                        ),
+                    )
+                );
+            }
+
             // Check tokens
             tx.outputs()
                 .iter()
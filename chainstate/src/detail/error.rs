@@ -71,10 +71,6 @@ pub enum CheckBlockError {
     WitnessMerkleRootMismatch,
     #[error("Previous block {0} of block {1} not found in database")]
     PrevBlockNotFound(Id<Block>, Id<Block>),
-    #[error("Block time must be equal or higher than the median of its ancestors")]
-    BlockTimeOrderInvalid,
-    #[error("Block time too far into the future")]
-    BlockFromTheFuture,
     #[error("Block size is too large: {0}")]
     BlockSizeError(#[from] BlockSizeError),
     #[error("Check transaction failed: {0}")]
@@ -18,6 +18,7 @@ pub mod query;
 pub mod tokens;
 
 mod block_index_history_iter;
+mod block_size;
 pub mod bootstrap;
 mod chainstateref;
 mod error;
@@ -25,6 +26,7 @@ mod median_time;
 mod orphan_blocks;
 pub mod tx_verification_strategy;
 
+pub use self::block_size::check_block_size;
 pub use self::error::*;
 pub use self::median_time::calculate_median_time_past;
 pub use self::tokens::is_rfc3986_valid_symbol;
@@ -20,7 +20,7 @@ use common::{
         block::{BlockHeader, BlockReward},
         tokens::{
             OutputValue, RPCFungibleTokenInfo, RPCNonFungibleTokenInfo, RPCTokenInfo,
-            TokenAuxiliaryData, TokenData, TokenId,
+            TokenAuxiliaryData, TokenData, TokenId, TokenIssuance,
         },
         Block, GenBlock, OutPointSourceId, Transaction, TxMainChainIndex,
     },
@@ -202,6 +202,17 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
             Some(data) => data,
             None => return Ok(None),
         };
+        let fungible_token_info = |issuance: &TokenIssuance| {
+            RPCTokenInfo::new_fungible(RPCFungibleTokenInfo::new(
+                token_id,
+                token_aux_data.issuance_tx().get_id(),
+                token_aux_data.issuance_block_id(),
+                issuance.token_ticker.clone(),
+                issuance.amount_to_issue,
+                issuance.number_of_decimals,
+                issuance.metadata_uri.clone(),
+            ))
+        };
 
         Ok(token_aux_data
             .issuance_tx()
@@ -214,16 +225,9 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
             })
             // Find issuance data and return RPCTokenInfo
             .find_map(|token_data| match &**token_data {
-                TokenData::TokenIssuance(issuance) => {
-                    Some(RPCTokenInfo::new_fungible(RPCFungibleTokenInfo::new(
-                        token_id,
-                        token_aux_data.issuance_tx().get_id(),
-                        token_aux_data.issuance_block_id(),
-                        issuance.token_ticker.clone(),
-                        issuance.amount_to_issue,
-                        issuance.number_of_decimals,
-                        issuance.metadata_uri.clone(),
-                    )))
+                TokenData::TokenIssuance(issuance) => Some(fungible_token_info(issuance)),
+                TokenData::TokenIssuanceV2(issuance) => {
+                    Some(fungible_token_info(&issuance.issuance))
                 }
                 TokenData::NftIssuance(nft) => {
                     Some(RPCTokenInfo::new_nonfungible(RPCNonFungibleTokenInfo::new(
@@ -233,7 +237,9 @@ impl<'a, S: BlockchainStorageRead, O: OrphanBlocks, V: TransactionVerificationSt
                         &nft.metadata,
                     )))
                 }
-                TokenData::TokenTransfer(_) => None,
+                TokenData::TokenTransfer(_)
+                | TokenData::TokenFreezeV1 { .. }
+                | TokenData::TokenUnfreezeV1 { .. } => None,
             }))
     }
 
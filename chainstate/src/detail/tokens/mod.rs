@@ -18,7 +18,7 @@ use self::check_utils::check_media_hash;
 use super::transaction_verifier::error::TokensError;
 use common::{
     chain::{
-        tokens::{NftIssuance, TokenData},
+        tokens::{is_token_amount_representable, NftIssuance, TokenData},
         Block, ChainConfig, Transaction,
     },
     primitives::{Amount, Id, Idable},
@@ -137,6 +137,16 @@ pub fn check_tokens_issuance_data(
         ));
     }
 
+    // Check that the amount fits the declared decimals
+    ensure!(
+        is_token_amount_representable(
+            *amount_to_issue,
+            *number_of_decimals,
+            chain_config.token_max_supply_atoms()
+        ),
+        TokensError::IssueAmountNotRepresentable(tx_id, source_block_id)
+    );
+
     // Check URI
     ensure!(
         is_uri_valid(metadata_uri),
@@ -171,9 +181,23 @@ pub fn check_tokens_data(
                 source_block_id,
             )?;
         }
+        TokenData::TokenIssuanceV2(issuance) => {
+            let issuance = &issuance.issuance;
+            check_tokens_issuance_data(
+                chain_config,
+                &issuance.token_ticker,
+                &issuance.amount_to_issue,
+                &issuance.number_of_decimals,
+                &issuance.metadata_uri,
+                tx.get_id(),
+                source_block_id,
+            )?;
+        }
         TokenData::NftIssuance(issuance) => {
             check_nft_issuance_data(chain_config, issuance, tx.get_id(), source_block_id)?
         }
+        // The authority and the state of the token are checked by the transaction verifier
+        TokenData::TokenFreezeV1 { .. } | TokenData::TokenUnfreezeV1 { .. } => {}
     }
     Ok(())
 }
@@ -19,16 +19,18 @@ pub use detail::tx_verification_strategy::*;
 pub use interface::chainstate_interface;
 use interface::chainstate_interface_impl;
 pub use interface::chainstate_interface_impl_delegation;
+#[cfg(any(test, feature = "mock"))]
+pub use interface::mock;
 
 pub mod rpc;
 
 pub use crate::{
     config::ChainstateConfig,
     detail::{
-        ban_score, calculate_median_time_past, is_rfc3986_valid_symbol, BlockError, BlockSource,
-        CheckBlockError, CheckBlockTransactionsError, ConnectTransactionError, InitializationError,
-        Locator, OrphanCheckError, TokensError, TransactionVerifierStorageError, TxIndexError,
-        HEADER_LIMIT,
+        ban_score, calculate_median_time_past, check_block_size, is_rfc3986_valid_symbol,
+        BlockError, BlockSizeError, BlockSource, CheckBlockError, CheckBlockTransactionsError,
+        ConnectTransactionError, InitializationError, Locator, OrphanCheckError, TokensError,
+        TransactionVerifierStorageError, TxIndexError, HEADER_LIMIT,
     },
 };
 
@@ -42,6 +44,7 @@ use common::{
     chain::{Block, ChainConfig, GenBlock},
     primitives::{BlockHeight, Id},
 };
+pub use consensus::ConsensusVerificationError;
 
 use chainstate_interface::ChainstateInterface;
 use chainstate_interface_impl::ChainstateInterfaceImpl;
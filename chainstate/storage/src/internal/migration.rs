@@ -0,0 +1,60 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migration of the data stored by the older versions of the storage
+
+use common::{
+    chain::{tokens::TokenAuxiliaryData, Block, Transaction},
+    primitives::Id,
+};
+use serialization::{Decode, DecodeAll, Encode};
+
+use super::StoreTxRw;
+use crate::schema as db;
+
+/// The token auxiliary data stored by the version 1 of the storage, before tokens could be frozen
+#[derive(Encode, Decode)]
+pub struct TokenAuxiliaryDataV1 {
+    pub issuance_tx: Transaction,
+    pub issuance_block_id: Id<Block>,
+}
+
+/// Re-encode the token auxiliary data stored by the version 1, none of the tokens are frozen
+///
+/// The issuance transactions are decoded with the current types, the token data variants added
+/// since the version 1 didn't change the encoding of the existing ones.
+pub fn migrate_token_aux_data_v1<B: storage::Backend>(
+    tx: &mut StoreTxRw<'_, B>,
+) -> crate::Result<()> {
+    let records =
+        tx.0.get::<db::DBTokensAuxData, _>()
+            .prefix_iter(&())?
+            .map(|(token_id, data)| {
+                TokenAuxiliaryDataV1::decode_all(&mut data.bytes())
+                    .map(|data| (token_id, data))
+                    .map_err(|err| crate::Error::MigrationFailed(1, err.to_string()))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+    let mut map = tx.0.get_mut::<db::DBTokensAuxData, _>();
+    for (token_id, data) in records {
+        map.put(
+            token_id,
+            TokenAuxiliaryData::new(data.issuance_tx, data.issuance_block_id),
+        )?;
+    }
+
+    Ok(())
+}
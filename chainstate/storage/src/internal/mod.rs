@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod migration;
 pub mod utxo_db;
 
 use chainstate_types::BlockIndex;
@@ -66,17 +67,39 @@ mod well_known {
     declare_entry!(TxIndexEnabled: bool);
 }
 
+/// The version of the storage format, bumped when the encoding of the stored data changes
+pub const CURRENT_STORAGE_VERSION: u32 = 2;
+
 /// Store for blockchain data, parametrized over the backend B
 pub struct Store<B: storage::Backend>(storage::Storage<B, Schema>);
 
 impl<B: storage::Backend> Store<B> {
     /// Create a new chainstate storage
     pub fn new(backend: B) -> crate::Result<Self> {
-        let mut storage = Self(storage::Storage::new(backend).map_err(crate::Error::from)?);
-        storage.set_storage_version(1)?;
+        let storage = Self(storage::Storage::new(backend).map_err(crate::Error::from)?);
+        storage.upgrade_storage()?;
         Ok(storage)
     }
 
+    /// Bring the storage to the current version, migrating the data stored by older versions
+    fn upgrade_storage(&self) -> crate::Result<()> {
+        let mut tx = self.transaction_rw(None)?;
+        match tx.get_storage_version()? {
+            // The storage is new, there's nothing to migrate
+            0 => {}
+            1 => migration::migrate_token_aux_data_v1(&mut tx)?,
+            CURRENT_STORAGE_VERSION => return Ok(()),
+            version => {
+                return Err(crate::Error::UnsupportedStorageVersion(
+                    version,
+                    CURRENT_STORAGE_VERSION,
+                ))
+            }
+        }
+        tx.set_storage_version(CURRENT_STORAGE_VERSION)?;
+        tx.commit()
+    }
+
     /// Dump raw database contents
     pub fn dump_raw(&self) -> crate::Result<storage::raw::StorageContents<Schema>> {
         self.0.dump_raw().map_err(crate::Error::from)
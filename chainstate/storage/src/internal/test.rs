@@ -14,9 +14,9 @@
 // limitations under the License.
 
 use super::*;
-use common::chain::tokens::OutputValue;
+use common::chain::tokens::{OutputValue, TokenIssuance};
 use common::chain::transaction::signed_transaction::SignedTransaction;
-use common::chain::{Destination, OutputPurpose, TxOutput};
+use common::chain::{Destination, OutputPurpose, TxInput, TxOutput};
 use common::primitives::{Amount, H256};
 use crypto::key::{KeyKind, PrivateKey};
 use crypto::random::{CryptoRng, Rng};
@@ -32,7 +32,10 @@ fn test_storage_get_default_version_in_tx() {
         let store = TestStore::new_empty().unwrap();
         let vtx = store.transaction_ro().unwrap().get_storage_version().unwrap();
         let vst = store.get_storage_version().unwrap();
-        assert_eq!(vtx, 1, "Default storage version wrong");
+        assert_eq!(
+            vtx, CURRENT_STORAGE_VERSION,
+            "Default storage version wrong"
+        );
         assert_eq!(vtx, vst, "Transaction and non-transaction inconsistency");
     })
 }
@@ -72,9 +75,9 @@ fn test_storage_manipulation() {
     let mut store = TestStore::new_empty().unwrap();
 
     // Storage version manipulation
-    assert_eq!(store.get_storage_version(), Ok(1));
-    assert_eq!(store.set_storage_version(2), Ok(()));
-    assert_eq!(store.get_storage_version(), Ok(2));
+    assert_eq!(store.get_storage_version(), Ok(CURRENT_STORAGE_VERSION));
+    assert_eq!(store.set_storage_version(3), Ok(()));
+    assert_eq!(store.get_storage_version(), Ok(3));
 
     // Store is now empty, the block is not there
     assert_eq!(store.get_block(block0.get_id()), Ok(None));
@@ -246,6 +249,105 @@ fn test_storage_transactions_with_result_check() {
     })
 }
 
+impl EncodeLike<TokenAuxiliaryData> for migration::TokenAuxiliaryDataV1 {}
+
+#[test]
+#[cfg(not(loom))]
+fn migrate_token_aux_data_v1() {
+    let issuance = TokenIssuance {
+        token_ticker: b"XYZ".to_vec(),
+        amount_to_issue: Amount::from_atoms(1_000_000),
+        number_of_decimals: 2,
+        metadata_uri: b"https://some_site.some".to_vec(),
+    };
+    let issuance_tx = Transaction::new(
+        0,
+        vec![TxInput::new(
+            OutPointSourceId::BlockReward(Id::new(H256::from_low_u64_be(3))),
+            0,
+        )],
+        vec![
+            TxOutput::new(
+                issuance.clone().into(),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            ),
+            TxOutput::new(
+                OutputValue::Coin(Amount::from_atoms(100)),
+                OutputPurpose::Burn,
+            ),
+        ],
+        0,
+    )
+    .unwrap();
+    let issuance_block_id = Id::<Block>::new(H256::from_low_u64_be(1));
+    let token_id = H256::from_low_u64_be(2);
+
+    let store = TestStore::new_empty().unwrap();
+    let mut tx = store.transaction_rw(None).unwrap();
+    tx.set_storage_version(1).unwrap();
+    tx.0.get_mut::<db::DBTokensAuxData, _>()
+        .put(
+            token_id,
+            migration::TokenAuxiliaryDataV1 {
+                issuance_tx: issuance_tx.clone(),
+                issuance_block_id,
+            },
+        )
+        .unwrap();
+    tx.commit().unwrap();
+
+    store.upgrade_storage().unwrap();
+
+    assert_eq!(store.get_storage_version(), Ok(CURRENT_STORAGE_VERSION));
+    let data = store.get_token_aux_data(&token_id).unwrap().unwrap();
+    assert_eq!(
+        data,
+        TokenAuxiliaryData::new(issuance_tx, issuance_block_id)
+    );
+    assert_eq!(data.token_issuance(), Some(&issuance));
+    assert_eq!(data.freeze_authority(), None);
+    assert!(!data.is_frozen());
+}
+
+#[test]
+#[cfg(not(loom))]
+fn migrate_token_aux_data_v1_invalid_record() {
+    let issuance_tx = Transaction::new(0, vec![], vec![], 0).unwrap();
+    let issuance_block_id = Id::<Block>::new(H256::from_low_u64_be(1));
+    let token_id = H256::from_low_u64_be(2);
+
+    // The record of the current version has the trailing frozen flag, so it can't be decoded as
+    // the version 1 record
+    let store = TestStore::new_empty().unwrap();
+    let mut tx = store.transaction_rw(None).unwrap();
+    tx.set_storage_version(1).unwrap();
+    tx.set_token_aux_data(
+        &token_id,
+        &TokenAuxiliaryData::new(issuance_tx, issuance_block_id),
+    )
+    .unwrap();
+    tx.commit().unwrap();
+
+    assert!(matches!(
+        store.upgrade_storage(),
+        Err(crate::Error::MigrationFailed(1, _))
+    ));
+    assert_eq!(store.get_storage_version(), Ok(1));
+}
+
+#[test]
+fn unsupported_storage_version() {
+    let store = TestStore::new_empty().unwrap();
+    store.set_storage_version(CURRENT_STORAGE_VERSION + 1).unwrap();
+    assert_eq!(
+        store.upgrade_storage(),
+        Err(crate::Error::UnsupportedStorageVersion(
+            CURRENT_STORAGE_VERSION + 1,
+            CURRENT_STORAGE_VERSION
+        ))
+    );
+}
+
 /// returns a tuple of utxo and outpoint, for testing.
 fn create_rand_utxo(rng: &mut (impl Rng + CryptoRng), block_height: u64) -> Utxo {
     // just a random value generated, and also a random `is_block_reward` value.
@@ -76,9 +76,14 @@ pub fn create_utxo_data(
             TokenData::TokenIssuance(issuance) => {
                 new_token_transfer_output(chainstate, &outsrc, issuance.amount_to_issue)
             }
+            TokenData::TokenIssuanceV2(issuance) => {
+                new_token_transfer_output(chainstate, &outsrc, issuance.issuance.amount_to_issue)
+            }
             TokenData::NftIssuance(_issuance) => {
                 new_token_transfer_output(chainstate, &outsrc, Amount::from_atoms(1))
             }
+            // Freeze operations don't carry any value to spend
+            TokenData::TokenFreezeV1 { .. } | TokenData::TokenUnfreezeV1 { .. } => return None,
         },
     };
 
@@ -194,15 +199,10 @@ pub fn create_multiple_utxo_data(
                 }
             }
             TokenData::TokenIssuance(issuance) => {
-                if rng.gen::<bool>() {
-                    vec![new_token_burn_output(
-                        chainstate,
-                        &outsrc,
-                        Amount::from_atoms(rng.gen_range(1..issuance.amount_to_issue.into_atoms())),
-                    )]
-                } else {
-                    vec![new_token_transfer_output(chainstate, &outsrc, issuance.amount_to_issue)]
-                }
+                spend_token_issuance(chainstate, &outsrc, issuance.amount_to_issue, rng)
+            }
+            TokenData::TokenIssuanceV2(issuance) => {
+                spend_token_issuance(chainstate, &outsrc, issuance.issuance.amount_to_issue, rng)
             }
             TokenData::NftIssuance(_issuance) => {
                 if rng.gen::<bool>() {
@@ -211,6 +211,8 @@ pub fn create_multiple_utxo_data(
                     vec![new_token_transfer_output(chainstate, &outsrc, Amount::from_atoms(1))]
                 }
             }
+            // Freeze operations don't carry any value to spend
+            TokenData::TokenFreezeV1 { .. } | TokenData::TokenUnfreezeV1 { .. } => return None,
         },
     };
 
@@ -265,6 +267,24 @@ fn new_token_burn_output(
     )
 }
 
+// Either burns a random part of the issued tokens or transfers all of them
+fn spend_token_issuance(
+    chainstate: &TestChainstate,
+    outsrc: &OutPointSourceId,
+    amount_to_issue: Amount,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Vec<TxOutput> {
+    if rng.gen::<bool>() {
+        vec![new_token_burn_output(
+            chainstate,
+            outsrc,
+            Amount::from_atoms(rng.gen_range(1..amount_to_issue.into_atoms())),
+        )]
+    } else {
+        vec![new_token_transfer_output(chainstate, outsrc, amount_to_issue)]
+    }
+}
+
 pub fn outputs_from_genesis(genesis: &Genesis) -> BlockOutputs {
     [(
         OutPointSourceId::BlockReward(genesis.get_id().into()),
@@ -25,6 +25,7 @@ use chainstate::OrphanCheckError;
 use common::chain::block::timestamp::BlockTimestamp;
 use common::primitives::id::Idable;
 use common::primitives::BlockHeight;
+use consensus::ConsensusVerificationError;
 use crypto::random::Rng;
 use rstest::rstest;
 use test_utils::random::make_seedable_rng;
@@ -184,10 +185,11 @@ fn custom_orphan_error_hook(#[case] seed: Seed) {
         let timestamp = tf.genesis().timestamp().as_int_seconds()
             + tf.chainstate.get_chain_config().max_future_block_time_offset().as_secs()
             + 1;
+        let timestamp = BlockTimestamp::from_int_seconds(timestamp);
         let second_block = tf
             .make_block_builder()
             .with_parent(first_block.get_id().into())
-            .with_timestamp(BlockTimestamp::from_int_seconds(timestamp))
+            .with_timestamp(timestamp)
             .build();
 
         // The second block isn't processed because its parent isn't known.
@@ -209,7 +211,9 @@ fn custom_orphan_error_hook(#[case] seed: Seed) {
         assert_eq!(errors_guard.len(), 1);
         assert_eq!(
             errors_guard[0],
-            BlockError::CheckBlockFailed(CheckBlockError::BlockFromTheFuture)
+            BlockError::CheckBlockFailed(CheckBlockError::ConsensusVerificationFailed(
+                ConsensusVerificationError::BlockFromTheFuture(timestamp)
+            ))
         );
     });
 }
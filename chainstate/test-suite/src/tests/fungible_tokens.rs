@@ -20,17 +20,20 @@ use chainstate::{
     ConnectTransactionError, TokensError,
 };
 use chainstate_test_framework::{TestFramework, TransactionBuilder};
-use common::chain::tokens::{Metadata, NftIssuance, TokenIssuance, TokenTransfer};
-use common::primitives::{id, Id};
+use common::chain::tokens::{Metadata, NftIssuance, TokenIssuance, TokenIssuanceV2, TokenTransfer};
+use common::primitives::{id, Id, H256};
 use common::{
     chain::{
-        signature::inputsig::InputWitness,
+        signature::{inputsig::InputWitness, TransactionSigError},
         tokens::{token_id, OutputValue, TokenData, TokenId},
-        Destination, OutPointSourceId, OutputPurpose, TxInput, TxOutput,
+        Destination, OutPointSourceId, OutputPurpose, SignedTransaction, TxInput, TxOutput,
     },
     primitives::{Amount, Idable},
 };
-use crypto::{hash::StreamHasher, random::Rng};
+use crypto::{
+    hash::StreamHasher,
+    random::{CryptoRng, Rng},
+};
 use expect_test::expect;
 use rstest::rstest;
 use serialization::extras::non_empty_vec::DataOrNoVec;
@@ -1995,3 +1998,611 @@ fn issue_and_transfer_in_the_same_block(#[case] seed: Seed) {
             .unwrap();
     })
 }
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn token_issue_max_supply_test(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let max_supply_atoms = Amount::from_atoms(100_000);
+        let chain_config = common::chain::config::Builder::test_chain()
+            .token_max_supply_atoms(max_supply_atoms)
+            .build();
+        let mut tf = TestFramework::builder(&mut rng).with_chain_config(chain_config).build();
+        let outpoint_source_id: OutPointSourceId = tf.genesis().get_id().into();
+
+        let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+
+        let mut make_issuance_tx = |amount_to_issue: Amount| {
+            let issuance = TokenIssuance {
+                token_ticker: random_string(&mut rng, 1..5).as_bytes().to_vec(),
+                amount_to_issue,
+                number_of_decimals: 2,
+                metadata_uri: random_string(&mut rng, 1..1024).as_bytes().to_vec(),
+            };
+            TransactionBuilder::new()
+                .add_input(
+                    TxInput::new(outpoint_source_id.clone(), 0),
+                    InputWitness::NoSignature(None),
+                )
+                .add_output(TxOutput::new(
+                    issuance.into(),
+                    OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                ))
+                .add_output(TxOutput::new(
+                    OutputValue::Coin(token_min_issuance_fee),
+                    OutputPurpose::Burn,
+                ))
+                .build()
+        };
+
+        // The amount exceeds the atom limit
+        let tx = make_issuance_tx((max_supply_atoms + Amount::from_atoms(1)).unwrap());
+        let result = tf.make_block_builder().add_transaction(tx).build_and_process();
+        assert!(matches!(
+            result,
+            Err(ChainstateError::ProcessBlockError(
+                BlockError::CheckBlockFailed(CheckBlockError::CheckTransactionFailed(
+                    CheckBlockTransactionsError::TokensError(
+                        TokensError::IssueAmountNotRepresentable(_, _)
+                    )
+                ))
+            ))
+        ));
+
+        // The whole supply can be issued
+        let tx = make_issuance_tx(max_supply_atoms);
+        tf.make_block_builder().add_transaction(tx).build_and_process().unwrap();
+    });
+}
+
+// Issue a token on a chain where the transaction fees can be paid in tokens
+fn issue_fee_token(
+    rng: &mut (impl Rng + CryptoRng),
+) -> (TestFramework, TokenId, OutPointSourceId, Amount, Amount) {
+    let chain_config =
+        common::chain::config::Builder::test_chain().token_fees_allowed(true).build();
+    let genesis_outpoint_id: OutPointSourceId = chain_config.genesis_block().get_id().into();
+    let genesis_amount = chain_config.genesis_block().utxos()[0].value().coin_amount().unwrap();
+    let token_min_issuance_fee = chain_config.token_min_issuance_fee();
+    let coins_left = (genesis_amount - token_min_issuance_fee).unwrap();
+    let total_funds = Amount::from_atoms(rng.gen_range(2..u128::MAX));
+
+    // Coins must be conserved exactly, so the issuance transaction returns the change
+    let issuance_tx = TransactionBuilder::new()
+        .add_input(
+            TxInput::new(genesis_outpoint_id, 0),
+            InputWitness::NoSignature(None),
+        )
+        .add_output(TxOutput::new(
+            TokenIssuance {
+                token_ticker: random_string(rng, 1..5).as_bytes().to_vec(),
+                amount_to_issue: total_funds,
+                number_of_decimals: rng.gen_range(1..18),
+                metadata_uri: "https://some_site.some".as_bytes().to_vec(),
+            }
+            .into(),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(token_min_issuance_fee),
+            OutputPurpose::Burn,
+        ))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(coins_left),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build();
+    let token_id = token_id(issuance_tx.transaction()).unwrap();
+    let issuance_outpoint_id: OutPointSourceId = issuance_tx.transaction().get_id().into();
+
+    let mut tf = TestFramework::builder(rng).with_chain_config(chain_config).build();
+    tf.make_block_builder()
+        .add_transaction(issuance_tx)
+        .build_and_process()
+        .unwrap()
+        .unwrap();
+
+    (tf, token_id, issuance_outpoint_id, total_funds, coins_left)
+}
+
+fn make_fee_token_tx(
+    issuance_outpoint_id: OutPointSourceId,
+    token_id: TokenId,
+    token_amount: Amount,
+    coin_amount: Amount,
+) -> SignedTransaction {
+    TransactionBuilder::new()
+        .add_input(
+            TxInput::new(issuance_outpoint_id.clone(), 0),
+            InputWitness::NoSignature(None),
+        )
+        .add_input(
+            TxInput::new(issuance_outpoint_id, 2),
+            InputWitness::NoSignature(None),
+        )
+        .add_output(TxOutput::new(
+            TokenData::TokenTransfer(TokenTransfer {
+                token_id,
+                amount: token_amount,
+            })
+            .into(),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(coin_amount),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build()
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn token_fee_test(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let (mut tf, token_id, issuance_outpoint_id, total_funds, coins_left) =
+            issue_fee_token(&mut rng);
+        let fee = Amount::from_atoms(rng.gen_range(1..total_funds.into_atoms()));
+
+        // Coins are transferred as is and the fee is paid in the token
+        let tx = make_fee_token_tx(
+            issuance_outpoint_id,
+            token_id,
+            (total_funds - fee).unwrap(),
+            coins_left,
+        );
+        tf.make_block_builder()
+            .add_transaction(tx)
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn coin_fee_with_token_fees_allowed_test(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let (mut tf, token_id, issuance_outpoint_id, total_funds, coins_left) =
+            issue_fee_token(&mut rng);
+        let fee = Amount::from_atoms(rng.gen_range(1..=coins_left.into_atoms()));
+
+        // The token is conserved and the fee is paid in coins as usual
+        let tx = make_fee_token_tx(
+            issuance_outpoint_id,
+            token_id,
+            total_funds,
+            (coins_left - fee).unwrap(),
+        );
+        tf.make_block_builder()
+            .add_transaction(tx)
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn token_fee_surplus_in_two_currencies_test(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let (mut tf, token_id, issuance_outpoint_id, total_funds, coins_left) =
+            issue_fee_token(&mut rng);
+        let coins_out =
+            (coins_left - Amount::from_atoms(rng.gen_range(1..=coins_left.into_atoms()))).unwrap();
+        let tokens_out =
+            (total_funds - Amount::from_atoms(rng.gen_range(1..total_funds.into_atoms()))).unwrap();
+
+        // The fee is paid in coins, so the token surplus isn't allowed
+        let tx = make_fee_token_tx(issuance_outpoint_id, token_id, tokens_out, coins_out);
+        let result = tf.make_block_builder().add_transaction(tx).build_and_process();
+        assert_eq!(
+            result.unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::SurplusInNonFeeCurrency(total_funds, tokens_out)
+            ))
+        );
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn issue_and_transfer_same_token_in_one_tx(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let mut tf = TestFramework::builder(&mut rng).build();
+        let genesis_outpoint_id: OutPointSourceId = tf.genesis().get_id().into();
+        let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+        let make_issuance = |rng: &mut _| -> OutputValue {
+            TokenIssuance {
+                token_ticker: random_string(rng, 1..5).as_bytes().to_vec(),
+                amount_to_issue: Amount::from_atoms(rng.gen_range(1..u128::MAX)),
+                number_of_decimals: rng.gen_range(1..18),
+                metadata_uri: "https://some_site.some".as_bytes().to_vec(),
+            }
+            .into()
+        };
+
+        // The id of the token issued in a tx only depends on the first input of the tx
+        let first_input = TxInput::new(genesis_outpoint_id, 0);
+        let issued_token_id = token_id(
+            TransactionBuilder::new()
+                .add_input(first_input.clone(), InputWitness::NoSignature(None))
+                .build()
+                .transaction(),
+        )
+        .unwrap();
+
+        let result = tf
+            .make_block_builder()
+            .add_transaction(
+                TransactionBuilder::new()
+                    .add_input(first_input.clone(), InputWitness::NoSignature(None))
+                    .add_output(TxOutput::new(
+                        make_issuance(&mut rng),
+                        OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                    ))
+                    .add_output(TxOutput::new(
+                        OutputValue::Coin(token_min_issuance_fee),
+                        OutputPurpose::Burn,
+                    ))
+                    .add_output(TxOutput::new(
+                        TokenData::TokenTransfer(TokenTransfer {
+                            token_id: issued_token_id,
+                            amount: Amount::from_atoms(rng.gen_range(1..u128::MAX)),
+                        })
+                        .into(),
+                        OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                    ))
+                    .build(),
+            )
+            .build_and_process();
+        assert!(matches!(
+            result,
+            Err(ChainstateError::ProcessBlockError(
+                BlockError::CheckBlockFailed(CheckBlockError::CheckTransactionFailed(
+                    CheckBlockTransactionsError::TokensError(
+                        TokensError::CannotTransferAndIssueSameToken(_, _)
+                    )
+                ))
+            ))
+        ));
+
+        // Issue a token separately, keeping enough coins to pay for another issuance
+        let first_issuance_value = make_issuance(&mut rng);
+        let first_issuance_tx = TransactionBuilder::new()
+            .add_input(first_input, InputWitness::NoSignature(None))
+            .add_output(TxOutput::new(
+                first_issuance_value.clone(),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            ))
+            .add_output(TxOutput::new(
+                OutputValue::Coin(token_min_issuance_fee),
+                OutputPurpose::Burn,
+            ))
+            .add_output(TxOutput::new(
+                OutputValue::Coin(token_min_issuance_fee),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            ))
+            .build();
+        let first_issuance_outpoint_id: OutPointSourceId =
+            first_issuance_tx.transaction().get_id().into();
+        tf.make_block_builder()
+            .add_transaction(first_issuance_tx)
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+        let first_amount = match first_issuance_value {
+            OutputValue::Token(token_data) => match *token_data {
+                TokenData::TokenIssuance(issuance) => issuance.amount_to_issue,
+                _ => unreachable!(),
+            },
+            OutputValue::Coin(_) => unreachable!(),
+        };
+
+        // Transferring the existing token while issuing another one in the same tx is fine
+        tf.make_block_builder()
+            .add_transaction(
+                TransactionBuilder::new()
+                    .add_input(
+                        TxInput::new(first_issuance_outpoint_id.clone(), 0),
+                        InputWitness::NoSignature(None),
+                    )
+                    .add_input(
+                        TxInput::new(first_issuance_outpoint_id, 2),
+                        InputWitness::NoSignature(None),
+                    )
+                    .add_output(TxOutput::new(
+                        make_issuance(&mut rng),
+                        OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                    ))
+                    .add_output(TxOutput::new(
+                        OutputValue::Coin(token_min_issuance_fee),
+                        OutputPurpose::Burn,
+                    ))
+                    .add_output(TxOutput::new(
+                        TokenData::TokenTransfer(TokenTransfer {
+                            token_id: issued_token_id,
+                            amount: first_amount,
+                        })
+                        .into(),
+                        OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                    ))
+                    .build(),
+            )
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+    })
+}
+
+// Issues a token with the given freeze authority, the change output is kept at the index 1 to pay
+// for the token operations
+fn issue_token_with_freeze_authority(
+    tf: &mut TestFramework,
+    rng: &mut (impl Rng + CryptoRng),
+    amount_to_issue: Amount,
+    freeze_authority: Option<Destination>,
+    change_destination: Destination,
+) -> (TokenId, OutPointSourceId) {
+    let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+    let issuance = TokenIssuance {
+        token_ticker: random_string(rng, 1..5).as_bytes().to_vec(),
+        amount_to_issue,
+        number_of_decimals: rng.gen_range(1..18),
+        metadata_uri: "https://some_site.some".as_bytes().to_vec(),
+    };
+    let issuance_value = match freeze_authority {
+        Some(freeze_authority) => TokenIssuanceV2 {
+            issuance,
+            freeze_authority,
+        }
+        .into(),
+        None => issuance.into(),
+    };
+    let issuance_tx = TransactionBuilder::new()
+        .add_input(
+            TxInput::new(
+                OutPointSourceId::BlockReward(tf.genesis().get_id().into()),
+                0,
+            ),
+            InputWitness::NoSignature(None),
+        )
+        .add_output(TxOutput::new(
+            issuance_value,
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(token_min_issuance_fee),
+            OutputPurpose::Transfer(change_destination),
+        ))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(token_min_issuance_fee),
+            OutputPurpose::Burn,
+        ))
+        .build();
+    let token_id = token_id(issuance_tx.transaction()).unwrap();
+    let issuance_outpoint_id = issuance_tx.transaction().get_id().into();
+    tf.make_block_builder()
+        .add_transaction(issuance_tx)
+        .build_and_process()
+        .unwrap()
+        .unwrap();
+    (token_id, issuance_outpoint_id)
+}
+
+fn make_token_operation_tx(
+    input: OutPointSourceId,
+    operation: TokenData,
+    change: Amount,
+) -> SignedTransaction {
+    TransactionBuilder::new()
+        .add_input(TxInput::new(input, 1), InputWitness::NoSignature(None))
+        .add_output(TxOutput::new(operation.into(), OutputPurpose::Burn))
+        .add_output(TxOutput::new(
+            OutputValue::Coin(change),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build()
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn freeze_and_unfreeze_tokens(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let mut tf = TestFramework::builder(&mut rng).build();
+        let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+        let total_funds = Amount::from_atoms(rng.gen_range(1..u128::MAX));
+        let (token_id, issuance_outpoint_id) = issue_token_with_freeze_authority(
+            &mut tf,
+            &mut rng,
+            total_funds,
+            Some(Destination::AnyoneCanSpend),
+            Destination::AnyoneCanSpend,
+        );
+
+        let transfer_tx = TransactionBuilder::new()
+            .add_input(
+                TxInput::new(issuance_outpoint_id.clone(), 0),
+                InputWitness::NoSignature(None),
+            )
+            .add_output(TxOutput::new(
+                TokenTransfer {
+                    token_id,
+                    amount: total_funds,
+                }
+                .into(),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            ))
+            .build();
+
+        // Unfreezing a token that isn't frozen is an error
+        let result = tf
+            .make_block_builder()
+            .add_transaction(make_token_operation_tx(
+                issuance_outpoint_id.clone(),
+                TokenData::TokenUnfreezeV1 { token_id },
+                token_min_issuance_fee,
+            ))
+            .build_and_process();
+        assert_eq!(
+            result.unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::TokensError(TokensError::TokenNotFrozen(token_id))
+            ))
+        );
+
+        // Freeze the token
+        let freeze_tx = make_token_operation_tx(
+            issuance_outpoint_id,
+            TokenData::TokenFreezeV1 { token_id },
+            token_min_issuance_fee,
+        );
+        let freeze_outpoint_id: OutPointSourceId = freeze_tx.transaction().get_id().into();
+        tf.make_block_builder()
+            .add_transaction(freeze_tx)
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+        assert!(tf.chainstate.get_token_aux_data(token_id).unwrap().unwrap().is_frozen());
+
+        // The frozen token can't be transferred
+        let result =
+            tf.make_block_builder().add_transaction(transfer_tx.clone()).build_and_process();
+        assert_eq!(
+            result.unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::TokensError(TokensError::TokenIsFrozen(token_id))
+            ))
+        );
+
+        // Freezing the token again is an error
+        let result = tf
+            .make_block_builder()
+            .add_transaction(make_token_operation_tx(
+                freeze_outpoint_id.clone(),
+                TokenData::TokenFreezeV1 { token_id },
+                token_min_issuance_fee,
+            ))
+            .build_and_process();
+        assert_eq!(
+            result.unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::TokensError(TokensError::TokenAlreadyFrozen(token_id))
+            ))
+        );
+
+        // Unfreeze the token, after which the transfer succeeds
+        tf.make_block_builder()
+            .add_transaction(make_token_operation_tx(
+                freeze_outpoint_id,
+                TokenData::TokenUnfreezeV1 { token_id },
+                token_min_issuance_fee,
+            ))
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+        assert!(!tf.chainstate.get_token_aux_data(token_id).unwrap().unwrap().is_frozen());
+
+        tf.make_block_builder()
+            .add_transaction(transfer_tx)
+            .build_and_process()
+            .unwrap()
+            .unwrap();
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn freeze_tokens_not_authorized(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let script_hash = Destination::ScriptHash(Id::new(H256::random_using(&mut rng)));
+
+        // A token without the authority can't be frozen, and the authority must be spent by the
+        // freeze transaction
+        for freeze_authority in [None, Some(script_hash)] {
+            let mut tf = TestFramework::builder(&mut rng).build();
+            let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+            let amount_to_issue = Amount::from_atoms(rng.gen_range(1..u128::MAX));
+            let (token_id, issuance_outpoint_id) = issue_token_with_freeze_authority(
+                &mut tf,
+                &mut rng,
+                amount_to_issue,
+                freeze_authority,
+                Destination::AnyoneCanSpend,
+            );
+
+            let freeze_tx = make_token_operation_tx(
+                issuance_outpoint_id,
+                TokenData::TokenFreezeV1 { token_id },
+                token_min_issuance_fee,
+            );
+            let freeze_tx_id = freeze_tx.transaction().get_id();
+            let result = tf.make_block_builder().add_transaction(freeze_tx).build_and_process();
+            assert_eq!(
+                result.unwrap_err(),
+                ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                    ConnectTransactionError::TokensError(TokensError::FreezeNotAuthorized(
+                        freeze_tx_id,
+                        token_id
+                    ))
+                ))
+            );
+            assert!(!tf.chainstate.get_token_aux_data(token_id).unwrap().unwrap().is_frozen());
+        }
+    })
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn freeze_tokens_not_signed(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let mut tf = TestFramework::builder(&mut rng).build();
+        let token_min_issuance_fee = tf.chainstate.get_chain_config().token_min_issuance_fee();
+        let (_, public_key) =
+            crypto::key::PrivateKey::new_from_rng(&mut rng, crypto::key::KeyKind::RistrettoSchnorr);
+        let authority = Destination::PublicKey(public_key);
+
+        // The output of the authority is spent without its signature
+        let amount_to_issue = Amount::from_atoms(rng.gen_range(1..u128::MAX));
+        let (token_id, issuance_outpoint_id) = issue_token_with_freeze_authority(
+            &mut tf,
+            &mut rng,
+            amount_to_issue,
+            Some(authority.clone()),
+            authority,
+        );
+
+        let result = tf
+            .make_block_builder()
+            .add_transaction(make_token_operation_tx(
+                issuance_outpoint_id,
+                TokenData::TokenFreezeV1 { token_id },
+                token_min_issuance_fee,
+            ))
+            .build_and_process();
+        assert_eq!(
+            result.unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::SignatureVerificationFailed(
+                    TransactionSigError::SignatureNotFound
+                )
+            ))
+        );
+        assert!(!tf.chainstate.get_token_aux_data(token_id).unwrap().unwrap().is_frozen());
+    })
+}
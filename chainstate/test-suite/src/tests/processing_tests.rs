@@ -1076,31 +1076,39 @@ fn blocks_from_the_future(#[case] seed: Seed) {
             // submit a block a second after the allowed threshold in the future
             let max_future_offset =
                 tf.chainstate.get_chain_config().max_future_block_time_offset().as_secs();
+            let timestamp = BlockTimestamp::from_int_seconds(
+                current_time.load(Ordering::SeqCst) + max_future_offset + 1,
+            );
 
             assert_eq!(
                 tf.make_block_builder()
-                    .with_timestamp(BlockTimestamp::from_int_seconds(
-                        current_time.load(Ordering::SeqCst) + max_future_offset + 1,
-                    ))
+                    .with_timestamp(timestamp)
                     .build_and_process()
                     .unwrap_err(),
                 ChainstateError::ProcessBlockError(BlockError::CheckBlockFailed(
-                    CheckBlockError::BlockFromTheFuture
+                    CheckBlockError::ConsensusVerificationFailed(
+                        ConsensusVerificationError::BlockFromTheFuture(timestamp)
+                    )
                 ))
             );
         }
 
         {
             // submit a block one second before genesis in time
+            let timestamp =
+                BlockTimestamp::from_int_seconds(current_time.load(Ordering::SeqCst) - 1);
+            let median_time =
+                tf.chainstate.calculate_median_time_past(&tf.best_block_id()).unwrap();
+
             assert_eq!(
                 tf.make_block_builder()
-                    .with_timestamp(BlockTimestamp::from_int_seconds(
-                        current_time.load(Ordering::SeqCst) - 1
-                    ))
+                    .with_timestamp(timestamp)
                     .build_and_process()
                     .unwrap_err(),
                 ChainstateError::ProcessBlockError(BlockError::CheckBlockFailed(
-                    CheckBlockError::BlockTimeOrderInvalid
+                    CheckBlockError::ConsensusVerificationFailed(
+                        ConsensusVerificationError::BlockTimeOrderInvalid(timestamp, median_time)
+                    )
                 ))
             );
         }
@@ -28,9 +28,11 @@ use common::{
 };
 use crypto::key::{KeyKind, PrivateKey};
 
+use chainstate::{BlockError, ChainstateError, ConnectTransactionError};
 use chainstate_test_framework::TestFramework;
 use chainstate_test_framework::TransactionBuilder;
 use rstest::rstest;
+use serialization::Encode;
 use test_utils::random::Seed;
 
 #[rstest]
@@ -97,3 +99,49 @@ fn signed_tx(#[case] seed: Seed) {
             .unwrap();
     });
 }
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn witness_size_limit(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = test_utils::random::make_seedable_rng(seed);
+        let max_witness_size = 100;
+        let chain_config = common::chain::config::Builder::test_chain()
+            .max_witness_size(max_witness_size)
+            .build();
+        let mut tf = TestFramework::builder(&mut rng).with_chain_config(chain_config).build();
+        let genesis_id = tf.chainstate.get_chain_config().genesis_block_id();
+
+        let make_tx = |witness: InputWitness| {
+            TransactionBuilder::new()
+                .add_input(
+                    TxInput::new(OutPointSourceId::BlockReward(genesis_id), 0),
+                    witness,
+                )
+                .add_output(TxOutput::new(
+                    OutputValue::Coin(Amount::from_atoms(100)),
+                    OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                ))
+                .build()
+        };
+
+        // The witness is larger than the limit
+        let witness = InputWitness::NoSignature(Some(vec![0; max_witness_size]));
+        let witness_size = witness.encoded_size();
+        assert!(witness_size > max_witness_size);
+        let tx = make_tx(witness);
+        assert_eq!(
+            tf.make_block_builder().add_transaction(tx).build_and_process().unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::StateUpdateFailed(
+                ConnectTransactionError::InputWitnessTooLarge(witness_size, max_witness_size)
+            ))
+        );
+
+        // The witness fits into the limit
+        let witness = InputWitness::NoSignature(Some(vec![0; max_witness_size / 2]));
+        assert!(witness.encoded_size() <= max_witness_size);
+        let tx = make_tx(witness);
+        tf.make_block_builder().add_transaction(tx).build_and_process().unwrap();
+    });
+}
@@ -51,12 +51,18 @@ pub enum ConnectTransactionError {
     MissingMempoolTxsUndo,
     #[error("Trying to take TxUndo for a tx `{0}` with a dependency")]
     TxUndoWithDependency(Id<Transaction>),
+    #[error("Transaction `{0}` spends the same outpoint more than once")]
+    DuplicateInputInTransaction(Id<Transaction>),
     #[error("Attempt to print money (total inputs: `{0:?}` vs total outputs `{1:?}`")]
     AttemptToPrintMoney(Amount, Amount),
     #[error("Fee calculation failed (total inputs: `{0:?}` vs total outputs `{1:?}`")]
     TxFeeTotalCalcFailed(Amount, Amount),
+    #[error("Surplus in a currency other than the fee currency (total inputs: `{0:?}` vs total outputs `{1:?}`")]
+    SurplusInNonFeeCurrency(Amount, Amount),
     #[error("Signature verification failed in transaction")]
     SignatureVerificationFailed(#[from] TransactionSigError),
+    #[error("Input witness size {0} exceeds the maximum {1}")]
+    InputWitnessTooLarge(usize, usize),
     #[error("Error while calculating block height; possibly an overflow")]
     BlockHeightArithmeticError,
     #[error("Error while calculating timestamps; possibly an overflow")]
@@ -178,6 +184,8 @@ pub enum TokensError {
     IssueAmountIsZero(Id<Transaction>, Id<Block>),
     #[error("Too many decimals in issuance transaction {0} in block {1}")]
     IssueErrorTooManyDecimals(Id<Transaction>, Id<Block>),
+    #[error("Amount can't be represented with the declared decimals in issuance transaction {0} in block {1}")]
+    IssueAmountNotRepresentable(Id<Transaction>, Id<Block>),
     #[error("Incorrect metadata URI in issuance transaction {0} in block {1}")]
     IssueErrorIncorrectMetadataURI(Id<Transaction>, Id<Block>),
     #[error("Incorrect icon URI in issuance transaction {0} in block {1}")]
@@ -186,6 +194,8 @@ pub enum TokensError {
     IssueErrorIncorrectMediaURI(Id<Transaction>, Id<Block>),
     #[error("Too many tokens issuance in transaction {0} in block {1}")]
     MultipleTokenIssuanceInTransaction(Id<Transaction>, Id<Block>),
+    #[error("Can't transfer the token issued in the same transaction {0} in block {1}")]
+    CannotTransferAndIssueSameToken(Id<Transaction>, Id<Block>),
     #[error("Coin or token overflow")]
     CoinOrTokenOverflow,
     #[error("Insufficient token issuance fee in transaction {0} in block {1}")]
@@ -206,4 +216,14 @@ pub enum TokensError {
     MediaHashTooShort,
     #[error("The media hash is too long")]
     MediaHashTooLong,
+    #[error("Token {0} is frozen and can't be transferred")]
+    TokenIsFrozen(TokenId),
+    #[error("Token {0} is already frozen")]
+    TokenAlreadyFrozen(TokenId),
+    #[error("Token {0} is not frozen")]
+    TokenNotFrozen(TokenId),
+    #[error("Attempt to freeze or unfreeze non-existent token {0}")]
+    FreezeOfNonexistentToken(TokenId),
+    #[error("Transaction {0} doesn't spend an output of the freeze authority of token {1}")]
+    FreezeNotAuthorized(Id<Transaction>, TokenId),
 }
@@ -39,7 +39,8 @@ fn flush_tokens(
     storage: &mut impl TransactionVerifierStorageMut,
     token_cache: &ConsumedTokenIssuanceCache,
 ) -> Result<(), TransactionVerifierStorageError> {
-    debug_assert_eq!(token_cache.data.len(), token_cache.txid_vs_tokenid.len());
+    // Freezing and unfreezing update the data of tokens without touching the issuance index
+    debug_assert!(token_cache.data.len() >= token_cache.txid_vs_tokenid.len());
 
     token_cache.data.iter().try_for_each(
         |(token_id, aux_data_op)| -> Result<(), TransactionVerifierStorageError> {
@@ -27,26 +27,28 @@ pub mod flush;
 pub mod hierarchy;
 mod optional_tx_index_cache;
 pub mod storage;
+pub mod utxo_lookup;
 
 use std::collections::BTreeMap;
 
 use self::{
     accounting_undo_cache::{AccountingBlockUndoCache, AccountingBlockUndoEntry},
-    amounts_map::AmountsMap,
     cached_operation::CachedInputsOperation,
     config::TransactionVerifierConfig,
     error::{ConnectTransactionError, TokensError},
     optional_tx_index_cache::OptionalTxIndexCache,
     storage::TransactionVerifierStorageRef,
-    token_issuance_cache::{CoinOrTokenId, ConsumedTokenIssuanceCache, TokenIssuanceCache},
+    token_issuance_cache::{ConsumedTokenIssuanceCache, TokenIssuanceCache},
     utils::{
-        calculate_total_outputs, check_transferred_amount, get_input_token_id_and_amount,
-        get_total_fee,
+        calculate_total_outputs, check_duplicate_inputs, check_non_fee_currencies_conserved,
+        check_transferred_amount, get_fee_currency, get_total_fee,
     },
     utxos_undo_cache::{UtxosBlockUndoCache, UtxosBlockUndoEntry},
 };
 use ::utils::{ensure, shallow_clone::ShallowClone};
 
+pub use self::{token_issuance_cache::CoinOrTokenId, utils::summarize_balances};
+
 use chainstate_types::{block_index_ancestor_getter, BlockIndex, GenBlockIndex};
 use common::{
     amount_sum,
@@ -54,7 +56,7 @@ use common::{
         block::{timestamp::BlockTimestamp, BlockRewardTransactable},
         signature::{verify_signature, Signable, Transactable},
         signed_transaction::SignedTransaction,
-        tokens::{get_tokens_issuance_count, OutputValue, TokenId},
+        tokens::{get_tokens_issuance_count, token_freeze_operation, OutputValue, TokenId},
         Block, ChainConfig, GenBlock, OutPointSourceId, OutputPurpose, Transaction, TxInput,
         TxMainChainIndex, TxOutput,
     },
@@ -64,7 +66,8 @@ use pos_accounting::{
     PoSAccountingDelta, PoSAccountingDeltaData, PoSAccountingOperations, PoSAccountingUndo,
     PoSAccountingView,
 };
-use utxo::{ConsumedUtxoCache, Utxo, UtxosCache, UtxosDB, UtxosView};
+use serialization::Encode;
+use utxo::{ConsumedUtxoCache, UtxosCache, UtxosDB, UtxosView};
 
 // TODO: We can move it to mod common, because in chain config we have `token_min_issuance_fee`
 //       that essentially belongs to this type, but return Amount
@@ -264,64 +267,106 @@ where
         }
     }
 
-    fn amount_from_outpoint(
-        &self,
-        tx_id: OutPointSourceId,
-        utxo: Utxo,
-    ) -> Result<(CoinOrTokenId, Amount), ConnectTransactionError> {
-        match tx_id {
-            OutPointSourceId::Transaction(tx_id) => {
-                let issuance_token_id_getter =
-                    || -> Result<Option<TokenId>, ConnectTransactionError> {
-                        // issuance transactions are unique, so we use them to get the token id
-                        self.get_token_id_from_issuance_tx(tx_id)
-                            .map_err(ConnectTransactionError::TransactionVerifierError)
-                    };
-                let (key, amount) =
-                    get_input_token_id_and_amount(utxo.output().value(), issuance_token_id_getter)?;
-                Ok((key, amount))
-            }
-            OutPointSourceId::BlockReward(_) => {
-                let (key, amount) =
-                    get_input_token_id_and_amount(utxo.output().value(), || Ok(None))?;
-                match key {
-                    CoinOrTokenId::Coin => Ok((CoinOrTokenId::Coin, amount)),
-                    CoinOrTokenId::TokenId(tid) => Ok((CoinOrTokenId::TokenId(tid), amount)),
-                }
-            }
-        }
-    }
-
     fn calculate_total_inputs(
         &self,
         inputs: &[TxInput],
     ) -> Result<BTreeMap<CoinOrTokenId, Amount>, ConnectTransactionError> {
-        let iter = inputs.iter().map(|input| {
-            let utxo = self
-                .utxo_cache
-                .utxo(input.outpoint())
-                .ok_or(ConnectTransactionError::MissingOutputOrSpent)?;
-            self.amount_from_outpoint(input.outpoint().tx_id(), utxo)
-        });
-
-        let iter = fallible_iterator::convert(iter);
-
-        let amounts_map = AmountsMap::from_fallible_iter(iter)?;
-
-        Ok(amounts_map.take())
+        utxo_lookup::calculate_total_inputs(&self.utxo_cache, inputs, |tx_id| {
+            // issuance transactions are unique, so we use them to get the token id
+            self.get_token_id_from_issuance_tx(tx_id)
+                .map_err(ConnectTransactionError::TransactionVerifierError)
+        })
     }
 
+    /// Checks the amounts transferred by the transaction and returns the fee along with the
+    /// currency it's paid in
     fn check_transferred_amounts_and_get_fee(
         &self,
         tx: &Transaction,
-    ) -> Result<Fee, ConnectTransactionError> {
+    ) -> Result<(CoinOrTokenId, Fee), ConnectTransactionError> {
         let inputs_total_map = self.calculate_total_inputs(tx.inputs())?;
         let outputs_total_map = calculate_total_outputs(tx.outputs(), None)?;
 
         check_transferred_amount(&inputs_total_map, &outputs_total_map)?;
-        let total_fee = get_total_fee(&inputs_total_map, &outputs_total_map)?;
 
-        Ok(total_fee)
+        self.check_frozen_tokens(&inputs_total_map, &outputs_total_map)?;
+
+        let fee_currency = if self.chain_config.as_ref().token_fees_allowed() {
+            let fee_currency = get_fee_currency(&inputs_total_map, &outputs_total_map);
+            check_non_fee_currencies_conserved(
+                &inputs_total_map,
+                &outputs_total_map,
+                &fee_currency,
+            )?;
+            fee_currency
+        } else {
+            CoinOrTokenId::Coin
+        };
+        let fee = get_total_fee(&inputs_total_map, &outputs_total_map, &fee_currency)?;
+
+        Ok((fee_currency, fee))
+    }
+
+    fn check_frozen_tokens(
+        &self,
+        inputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+        outputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+    ) -> Result<(), ConnectTransactionError> {
+        inputs_total_map
+            .keys()
+            .chain(outputs_total_map.keys())
+            .filter_map(|currency| match currency {
+                CoinOrTokenId::Coin => None,
+                CoinOrTokenId::TokenId(token_id) => Some(token_id),
+            })
+            .try_for_each(|token_id| {
+                let is_frozen = self
+                    .get_token_aux_data(token_id)
+                    .map_err(ConnectTransactionError::TransactionVerifierError)?
+                    .map_or(false, |data| data.is_frozen());
+                ensure!(
+                    !is_frozen,
+                    ConnectTransactionError::TokensError(TokensError::TokenIsFrozen(*token_id))
+                );
+                Ok(())
+            })
+    }
+
+    fn check_token_freeze_authority(
+        &self,
+        tx: &SignedTransaction,
+    ) -> Result<(), ConnectTransactionError> {
+        tx.transaction()
+            .outputs()
+            .iter()
+            .filter_map(|output| token_freeze_operation(output.value()))
+            .try_for_each(|(token_id, _)| {
+                let token_data = self
+                    .get_token_aux_data(&token_id)
+                    .map_err(ConnectTransactionError::TransactionVerifierError)?
+                    .ok_or(TokensError::FreezeOfNonexistentToken(token_id))?;
+                let not_authorized = || {
+                    ConnectTransactionError::TokensError(TokensError::FreezeNotAuthorized(
+                        tx.transaction().get_id(),
+                        token_id,
+                    ))
+                };
+                let authority = token_data.freeze_authority().ok_or_else(not_authorized)?;
+
+                // The authority signs the transaction by spending one of its outputs
+                let input_idx = tx
+                    .transaction()
+                    .inputs()
+                    .iter()
+                    .position(|input| {
+                        self.utxo_cache.utxo(input.outpoint()).map_or(false, |utxo| {
+                            utxo.output().purpose().destination() == Some(authority)
+                        })
+                    })
+                    .ok_or_else(not_authorized)?;
+                verify_signature(authority, tx, input_idx)
+                    .map_err(ConnectTransactionError::SignatureVerificationFailed)
+            })
     }
 
     fn check_issuance_fee_burn(
@@ -458,6 +503,16 @@ where
             None => return Ok(()),
         };
 
+        // Reject oversized witnesses before doing any expensive verification
+        let max_witness_size = self.chain_config.as_ref().max_witness_size();
+        for witness in tx.signatures().unwrap_or_default() {
+            let witness_size = witness.encoded_size();
+            ensure!(
+                witness_size <= max_witness_size,
+                ConnectTransactionError::InputWitnessTooLarge(witness_size, max_witness_size)
+            );
+        }
+
         for (input_idx, input) in inputs.iter().enumerate() {
             let outpoint = input.outpoint();
             let utxo = self
@@ -625,19 +680,37 @@ where
     ) -> Result<Option<Fee>, ConnectTransactionError> {
         let block_id = tx_source.chain_block_index().map(|c| *c.block_id());
 
+        // check that the transaction doesn't spend any outpoint twice
+        check_duplicate_inputs(tx.transaction())?;
+
         // pre-cache token ids to check ensure it's not in the db when issuing
         self.token_issuance_cache
             .precache_token_issuance(|id| self.storage.get_token_aux_data(id), tx.transaction())?;
 
         // check for attempted money printing
-        let fee = Some(self.check_transferred_amounts_and_get_fee(tx.transaction())?);
+        let fee = match self.check_transferred_amounts_and_get_fee(tx.transaction())? {
+            (CoinOrTokenId::Coin, fee) => fee,
+            // The block reward is paid in coins, so the fees paid in tokens can't be collected
+            // by the block producer and are burned
+            (CoinOrTokenId::TokenId(_), _) => Fee(Amount::ZERO),
+        };
+        let fee = Some(fee);
 
         // check token issuance fee
         self.check_issuance_fee_burn(tx.transaction(), &block_id)?;
 
+        // check that the token freeze operations are authorized
+        self.check_token_freeze_authority(tx)?;
+
         // Register tokens if tx has issuance data
         self.token_issuance_cache.register(block_id, tx.transaction())?;
 
+        // Freeze or unfreeze tokens if tx has freeze operations
+        self.token_issuance_cache.register_freeze_operations(
+            |id| self.storage.get_token_aux_data(id),
+            tx.transaction(),
+        )?;
+
         // check timelocks of the outputs and make sure there's no premature spending
         self.check_timelocks(tx_source, tx, median_time_past)?;
 
@@ -837,6 +910,12 @@ where
         self.token_issuance_cache
             .precache_token_issuance(|id| self.storage.get_token_aux_data(id), tx.transaction())?;
 
+        // Undo the freeze operations
+        self.token_issuance_cache.unregister_freeze_operations(
+            |id| self.storage.get_token_aux_data(id),
+            tx.transaction(),
+        )?;
+
         // Remove issued tokens
         self.token_issuance_cache.unregister(tx.transaction())?;
 
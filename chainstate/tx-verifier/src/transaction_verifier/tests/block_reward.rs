@@ -0,0 +1,197 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use common::{
+    chain::{
+        block::{
+            consensus_data::{PoSData, PoWData},
+            BlockReward, ConsensusData,
+        },
+        config::Builder as ConfigBuilder,
+    },
+    primitives::{Compact, H256},
+};
+use rstest::rstest;
+use test_utils::random::Seed;
+
+fn reward_output(rng: &mut (impl Rng + CryptoRng), value: UnsignedIntType) -> TxOutput {
+    let (_, pub_key) = PrivateKey::new_from_rng(rng, KeyKind::RistrettoSchnorr);
+    TxOutput::new(
+        OutputValue::Coin(Amount::from_atoms(value)),
+        OutputPurpose::Transfer(Destination::PublicKey(pub_key)),
+    )
+}
+
+fn make_block(
+    rng: &mut (impl Rng + CryptoRng),
+    consensus_data: ConsensusData,
+    reward: UnsignedIntType,
+) -> WithId<Block> {
+    // The reward is split between several outputs
+    let first = rng.gen_range(0..=reward);
+    let reward = BlockReward::new(vec![
+        reward_output(rng, first),
+        reward_output(rng, reward - first),
+    ]);
+    let block = Block::new(
+        Vec::new(),
+        Id::new(H256::random_using(rng)),
+        BlockTimestamp::from_int_seconds(1),
+        consensus_data,
+        reward,
+    )
+    .unwrap();
+    WithId::new(block)
+}
+
+fn pow_data() -> ConsensusData {
+    ConsensusData::PoW(PoWData::new(Compact(0), 0))
+}
+
+// The reward may claim exactly the subsidy plus the fees, but not more
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn pow_reward(#[case] seed: Seed) {
+    let mut rng = test_utils::random::make_seedable_rng(seed);
+    let chain_config = ConfigBuilder::test_chain().build();
+    let store = mock::MockStore::new();
+    let verifier =
+        TransactionVerifier::new(&store, &chain_config, TransactionVerifierConfig::new(true));
+
+    let subsidy = rng.gen_range(1..1_000_000);
+    let fees = rng.gen_range(1..1_000_000);
+
+    let block = make_block(&mut rng, pow_data(), subsidy + fees);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::from_atoms(fees)),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Ok(())
+    );
+
+    let block = make_block(&mut rng, pow_data(), subsidy + fees + 1);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::from_atoms(fees)),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Err(ConnectTransactionError::AttemptToPrintMoney(
+            Amount::ZERO,
+            Amount::from_atoms(subsidy + fees + 1)
+        ))
+    );
+
+    // Claiming less than allowed is fine
+    let block = make_block(&mut rng, pow_data(), subsidy + fees - 1);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::from_atoms(fees)),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Ok(())
+    );
+}
+
+// Without fees only the subsidy can be claimed
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn zero_fee_reward(#[case] seed: Seed) {
+    let mut rng = test_utils::random::make_seedable_rng(seed);
+    let chain_config = ConfigBuilder::test_chain().build();
+    let store = mock::MockStore::new();
+    let verifier =
+        TransactionVerifier::new(&store, &chain_config, TransactionVerifierConfig::new(true));
+
+    let subsidy = rng.gen_range(1..1_000_000);
+
+    let block = make_block(&mut rng, pow_data(), subsidy);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::ZERO),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Ok(())
+    );
+
+    let block = make_block(&mut rng, pow_data(), subsidy + 1);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::ZERO),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Err(ConnectTransactionError::AttemptToPrintMoney(
+            Amount::ZERO,
+            Amount::from_atoms(subsidy + 1)
+        ))
+    );
+}
+
+// With PoS the kernel inputs are spent by the reward, so their value is allowed on top of
+// the subsidy and the fees
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn pos_reward(#[case] seed: Seed) {
+    let mut rng = test_utils::random::make_seedable_rng(seed);
+    let chain_config = ConfigBuilder::test_chain().build();
+    let store = mock::MockStore::new();
+
+    let staked = rng.gen_range(1..1_000_000);
+    let (outpoint, utxo) = create_utxo(&mut rng, staked);
+    let mut verifier =
+        TransactionVerifier::new(&store, &chain_config, TransactionVerifierConfig::new(true));
+    verifier.utxo_cache.add_utxo(&outpoint, utxo, false).unwrap();
+
+    let pos_data = ConsensusData::PoS(PoSData::new(
+        vec![TxInput::new(outpoint.tx_id(), outpoint.output_index())],
+        Vec::new(),
+        Compact(0),
+    ));
+
+    let subsidy = rng.gen_range(1..1_000_000);
+    let fees = rng.gen_range(0..1_000_000);
+
+    let block = make_block(&mut rng, pos_data.clone(), staked + subsidy + fees);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::from_atoms(fees)),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Ok(())
+    );
+
+    let block = make_block(&mut rng, pos_data, staked + subsidy + fees + 1);
+    assert_eq!(
+        verifier.check_block_reward(
+            &block,
+            Fee(Amount::from_atoms(fees)),
+            Subsidy(Amount::from_atoms(subsidy))
+        ),
+        Err(ConnectTransactionError::AttemptToPrintMoney(
+            Amount::from_atoms(staked),
+            Amount::from_atoms(staked + subsidy + fees + 1)
+        ))
+    );
+}
@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod block_reward;
 mod hierarchy_read;
 mod hierarchy_write;
 mod mock;
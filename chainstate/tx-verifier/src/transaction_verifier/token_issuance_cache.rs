@@ -17,7 +17,9 @@ use std::collections::{btree_map::Entry, BTreeMap};
 
 use common::{
     chain::{
-        tokens::{is_tokens_issuance, token_id, TokenAuxiliaryData, TokenId},
+        tokens::{
+            is_tokens_issuance, token_freeze_operation, token_id, TokenAuxiliaryData, TokenId,
+        },
         Block, Transaction,
     },
     primitives::{Id, Idable, H256},
@@ -166,12 +168,86 @@ impl TokenIssuanceCache {
         Ok(())
     }
 
+    // Freezing and unfreezing a token updates its auxiliary data, so it's applied on top of the existing data.
+    // The undo applies the opposite operation, which is possible because the operations must change the state.
+    pub fn register_freeze_operations<
+        F: Fn(&TokenId) -> Result<Option<TokenAuxiliaryData>, TransactionVerifierStorageError>,
+    >(
+        &mut self,
+        token_data_getter: F,
+        tx: &Transaction,
+    ) -> Result<(), ConnectTransactionError> {
+        tx.outputs()
+            .iter()
+            .filter_map(|output| token_freeze_operation(output.value()))
+            .try_for_each(|(token_id, frozen)| {
+                self.set_frozen(&token_data_getter, token_id, frozen)
+            })
+    }
+
+    pub fn unregister_freeze_operations<
+        F: Fn(&TokenId) -> Result<Option<TokenAuxiliaryData>, TransactionVerifierStorageError>,
+    >(
+        &mut self,
+        token_data_getter: F,
+        tx: &Transaction,
+    ) -> Result<(), ConnectTransactionError> {
+        tx.outputs()
+            .iter()
+            .rev()
+            .filter_map(|output| token_freeze_operation(output.value()))
+            .try_for_each(|(token_id, frozen)| {
+                self.set_frozen(&token_data_getter, token_id, !frozen)
+            })
+    }
+
+    fn set_frozen<
+        F: Fn(&TokenId) -> Result<Option<TokenAuxiliaryData>, TransactionVerifierStorageError>,
+    >(
+        &mut self,
+        token_data_getter: &F,
+        token_id: TokenId,
+        frozen: bool,
+    ) -> Result<(), ConnectTransactionError> {
+        let current_token_data = match self.data.get(&token_id) {
+            Some(CachedAuxDataOp::Write(data) | CachedAuxDataOp::Read(data)) => Some(data.clone()),
+            Some(CachedAuxDataOp::Erase) => None,
+            None => token_data_getter(&token_id)?,
+        };
+        let mut data = current_token_data.ok_or(TokensError::FreezeOfNonexistentToken(token_id))?;
+
+        if data.is_frozen() == frozen {
+            let err = if frozen {
+                TokensError::TokenAlreadyFrozen(token_id)
+            } else {
+                TokensError::TokenNotFrozen(token_id)
+            };
+            return Err(ConnectTransactionError::TokensError(err));
+        }
+
+        data.set_frozen(frozen);
+        self.data.insert(token_id, CachedAuxDataOp::Write(data));
+        Ok(())
+    }
+
     pub fn set_token_aux_data(
         &mut self,
         token_id: &TokenId,
         data: TokenAuxiliaryData,
     ) -> Result<(), TokensError> {
-        self.insert_aux_data(*token_id, CachedAuxDataOp::Write(data))
+        // The data of the same token can be overwritten when it gets frozen or unfrozen
+        let is_update = match self.data.get(token_id) {
+            Some(CachedAuxDataOp::Write(current) | CachedAuxDataOp::Read(current)) => {
+                current.issuance_tx().get_id() == data.issuance_tx().get_id()
+            }
+            Some(CachedAuxDataOp::Erase) | None => false,
+        };
+        if is_update {
+            self.data.insert(*token_id, CachedAuxDataOp::Write(data));
+            Ok(())
+        } else {
+            self.insert_aux_data(*token_id, CachedAuxDataOp::Write(data))
+        }
     }
 
     pub fn del_token_aux_data(&mut self, token_id: &TokenId) -> Result<(), TokensError> {
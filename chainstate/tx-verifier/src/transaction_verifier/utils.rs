@@ -13,14 +13,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use common::{
     chain::{
         tokens::{token_id, OutputValue, TokenData, TokenId},
         Transaction, TxOutput,
     },
-    primitives::Amount,
+    primitives::{Amount, Idable},
 };
 use fallible_iterator::FallibleIterator;
 
@@ -34,12 +34,10 @@ use super::{
 pub fn get_total_fee(
     inputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
     outputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+    fee_currency: &CoinOrTokenId,
 ) -> Result<Fee, ConnectTransactionError> {
-    // TODO: fees should support tokens as well in the future
-    let outputs_total =
-        *outputs_total_map.get(&CoinOrTokenId::Coin).unwrap_or(&Amount::from_atoms(0));
-    let inputs_total =
-        *inputs_total_map.get(&CoinOrTokenId::Coin).unwrap_or(&Amount::from_atoms(0));
+    let outputs_total = *outputs_total_map.get(fee_currency).unwrap_or(&Amount::from_atoms(0));
+    let inputs_total = *inputs_total_map.get(fee_currency).unwrap_or(&Amount::from_atoms(0));
     (inputs_total - outputs_total)
         .map(Fee)
         .ok_or(ConnectTransactionError::TxFeeTotalCalcFailed(
@@ -67,6 +65,57 @@ pub fn check_transferred_amount(
     Ok(())
 }
 
+/// Check that the transaction doesn't spend the same outpoint more than once
+///
+/// This is independent of the double spend check against the UTXO set, which only sees the first
+/// of the duplicated inputs as unspent.
+pub fn check_duplicate_inputs(tx: &Transaction) -> Result<(), ConnectTransactionError> {
+    let mut outpoints = BTreeSet::new();
+    if !tx.inputs().iter().all(|input| outpoints.insert(input.outpoint())) {
+        return Err(ConnectTransactionError::DuplicateInputInTransaction(
+            tx.get_id(),
+        ));
+    }
+    Ok(())
+}
+
+/// The currency the transaction pays the fee in: the first currency with more in inputs than in
+/// outputs, coins if there's no surplus
+pub fn get_fee_currency(
+    inputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+    outputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+) -> CoinOrTokenId {
+    inputs_total_map
+        .iter()
+        .find(|(coin_or_token_id, inputs_total)| {
+            *inputs_total > outputs_total_map.get(*coin_or_token_id).unwrap_or(&Amount::ZERO)
+        })
+        .map_or(CoinOrTokenId::Coin, |(coin_or_token_id, _)| {
+            *coin_or_token_id
+        })
+}
+
+/// Check that every currency except the fee currency has exactly the same amount in inputs and outputs
+pub fn check_non_fee_currencies_conserved(
+    inputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+    outputs_total_map: &BTreeMap<CoinOrTokenId, Amount>,
+    fee_currency: &CoinOrTokenId,
+) -> Result<(), ConnectTransactionError> {
+    let currencies = inputs_total_map.keys().chain(outputs_total_map.keys());
+    for coin_or_token_id in currencies.filter(|id| *id != fee_currency) {
+        let inputs_total = inputs_total_map.get(coin_or_token_id).unwrap_or(&Amount::ZERO);
+        let outputs_total = outputs_total_map.get(coin_or_token_id).unwrap_or(&Amount::ZERO);
+
+        if inputs_total != outputs_total {
+            return Err(ConnectTransactionError::SurplusInNonFeeCurrency(
+                *inputs_total,
+                *outputs_total,
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn calculate_total_outputs(
     outputs: &[TxOutput],
     include_issuance: Option<&Transaction>,
@@ -80,6 +129,17 @@ pub fn calculate_total_outputs(
     Ok(result.take())
 }
 
+/// Returns the total amount of every coin or token in the outputs
+///
+/// The outputs are counted in the same way as the transaction verifier counts the outputs of
+/// a transaction: burn outputs are included, and token issuance outputs are skipped because their
+/// token id can only be calculated from the issuing transaction.
+pub fn summarize_balances(
+    outputs: &[TxOutput],
+) -> Result<BTreeMap<CoinOrTokenId, Amount>, ConnectTransactionError> {
+    calculate_total_outputs(outputs, None)
+}
+
 fn get_output_token_id_and_amount(
     output_value: &OutputValue,
     include_issuance: Option<&Transaction>,
@@ -97,6 +157,16 @@ fn get_output_token_id_and_amount(
                 }
                 None => None,
             },
+            TokenData::TokenIssuanceV2(issuance) => match include_issuance {
+                Some(tx) => {
+                    let token_id = token_id(tx).ok_or(TokensError::TokenIdCantBeCalculated)?;
+                    Some((
+                        CoinOrTokenId::TokenId(token_id),
+                        issuance.issuance.amount_to_issue,
+                    ))
+                }
+                None => None,
+            },
             TokenData::NftIssuance(_) => match include_issuance {
                 Some(tx) => {
                     let token_id = token_id(tx).ok_or(TokensError::TokenIdCantBeCalculated)?;
@@ -104,6 +174,7 @@ fn get_output_token_id_and_amount(
                 }
                 None => None,
             },
+            TokenData::TokenFreezeV1 { .. } | TokenData::TokenUnfreezeV1 { .. } => None,
         },
     })
 }
@@ -125,12 +196,150 @@ pub fn get_input_token_id_and_amount<
                 .ok_or(ConnectTransactionError::TokensError(
                     TokensError::TokenIdCantBeCalculated,
                 ))?,
+            TokenData::TokenIssuanceV2(issuance) => issuance_token_id_getter()?
+                .map(|token_id| {
+                    (
+                        CoinOrTokenId::TokenId(token_id),
+                        issuance.issuance.amount_to_issue,
+                    )
+                })
+                .ok_or(ConnectTransactionError::TokensError(
+                    TokensError::TokenIdCantBeCalculated,
+                ))?,
             TokenData::NftIssuance(_) => issuance_token_id_getter()?
                 // TODO: Find more appropriate way to check NFTs when we add multi-token feature
                 .map(|token_id| (CoinOrTokenId::TokenId(token_id), Amount::from_atoms(1)))
                 .ok_or(ConnectTransactionError::TokensError(
                     TokensError::TokenIdCantBeCalculated,
                 ))?,
+            // Freeze operations don't carry any value
+            TokenData::TokenFreezeV1 { .. } | TokenData::TokenUnfreezeV1 { .. } => {
+                (CoinOrTokenId::Coin, Amount::ZERO)
+            }
         },
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::{
+            tokens::{TokenIssuance, TokenTransfer},
+            Destination, OutPointSourceId, OutputPurpose, TxInput,
+        },
+        primitives::{Id, H256},
+    };
+    use rstest::rstest;
+    use test_utils::random::Seed;
+
+    use super::*;
+
+    fn transfer(value: OutputValue) -> TxOutput {
+        TxOutput::new(value, OutputPurpose::Transfer(Destination::AnyoneCanSpend))
+    }
+
+    fn token(token_id: TokenId, atoms: u128) -> OutputValue {
+        TokenData::TokenTransfer(TokenTransfer {
+            token_id,
+            amount: Amount::from_atoms(atoms),
+        })
+        .into()
+    }
+
+    #[rstest]
+    #[trace]
+    #[case(Seed::from_entropy())]
+    fn summarize_coins_and_tokens(#[case] seed: Seed) {
+        let mut rng = test_utils::random::make_seedable_rng(seed);
+        let token_id1 = TokenId::random_using(&mut rng);
+        let token_id2 = TokenId::random_using(&mut rng);
+
+        let outputs = vec![
+            transfer(OutputValue::Coin(Amount::from_atoms(10))),
+            transfer(token(token_id1, 5)),
+            transfer(OutputValue::Coin(Amount::from_atoms(15))),
+            transfer(token(token_id2, 7)),
+            transfer(token(token_id1, 20)),
+            // Burnt amounts are counted like the verifier does it
+            TxOutput::new(
+                OutputValue::Coin(Amount::from_atoms(100)),
+                OutputPurpose::Burn,
+            ),
+            TxOutput::new(token(token_id2, 100), OutputPurpose::Burn),
+            // The issued token id is unknown without the issuing transaction
+            transfer(
+                TokenData::TokenIssuance(Box::new(TokenIssuance {
+                    token_ticker: b"XXXX".to_vec(),
+                    amount_to_issue: Amount::from_atoms(1000),
+                    number_of_decimals: 2,
+                    metadata_uri: Vec::new(),
+                }))
+                .into(),
+            ),
+        ];
+
+        let expected = [
+            (CoinOrTokenId::Coin, Amount::from_atoms(125)),
+            (CoinOrTokenId::TokenId(token_id1), Amount::from_atoms(25)),
+            (CoinOrTokenId::TokenId(token_id2), Amount::from_atoms(107)),
+        ];
+        assert_eq!(
+            summarize_balances(&outputs).unwrap(),
+            expected.into_iter().collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(
+            summarize_balances(&outputs).unwrap(),
+            calculate_total_outputs(&outputs, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn summarize_overflow() {
+        let outputs = vec![
+            transfer(OutputValue::Coin(Amount::MAX)),
+            transfer(OutputValue::Coin(Amount::from_atoms(1))),
+        ];
+        assert_eq!(
+            summarize_balances(&outputs).unwrap_err(),
+            ConnectTransactionError::TokensError(TokensError::CoinOrTokenOverflow)
+        );
+    }
+
+    #[test]
+    fn summarize_empty() {
+        assert_eq!(summarize_balances(&[]).unwrap(), BTreeMap::new());
+    }
+
+    #[rstest]
+    #[trace]
+    #[case(Seed::from_entropy())]
+    fn duplicate_inputs(#[case] seed: Seed) {
+        let mut rng = test_utils::random::make_seedable_rng(seed);
+        let source_id = OutPointSourceId::Transaction(Id::new(H256::random_using(&mut rng)));
+        let other_source_id = OutPointSourceId::Transaction(Id::new(H256::random_using(&mut rng)));
+        let outputs = vec![transfer(OutputValue::Coin(Amount::from_atoms(1)))];
+
+        // The same output index of different transactions and different outputs of the same
+        // transaction are fine
+        let inputs = vec![
+            TxInput::new(source_id.clone(), 0),
+            TxInput::new(source_id.clone(), 1),
+            TxInput::new(other_source_id, 0),
+        ];
+        let tx = Transaction::new(0, inputs, outputs.clone(), 0).unwrap();
+        assert_eq!(check_duplicate_inputs(&tx), Ok(()));
+
+        let inputs = vec![
+            TxInput::new(source_id.clone(), 0),
+            TxInput::new(source_id.clone(), 1),
+            TxInput::new(source_id, 0),
+        ];
+        let tx = Transaction::new(0, inputs, outputs, 0).unwrap();
+        assert_eq!(
+            check_duplicate_inputs(&tx),
+            Err(ConnectTransactionError::DuplicateInputInTransaction(
+                tx.get_id()
+            ))
+        );
+    }
+}
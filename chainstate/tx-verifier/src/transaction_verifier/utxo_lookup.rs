@@ -0,0 +1,209 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use common::{
+    chain::{tokens::TokenId, OutPoint, OutPointSourceId, Transaction, TxInput, TxOutput},
+    primitives::{Amount, Id},
+};
+use utxo::{UtxosCache, UtxosView};
+
+use super::{
+    amounts_map::AmountsMap, error::ConnectTransactionError, token_issuance_cache::CoinOrTokenId,
+    utils::get_input_token_id_and_amount,
+};
+
+/// Source of the outputs spent by the transaction inputs
+///
+/// The verifier only needs the spent outputs, so the callers can resolve the inputs against
+/// different sets, e.g. the committed UTXO set when connecting a block or the UTXO set extended
+/// with the in-flight transactions in the mempool.
+pub trait UtxoLookup {
+    /// Returns the output if it exists and isn't spent yet
+    fn get_utxo(&self, outpoint: &OutPoint) -> Option<TxOutput>;
+}
+
+impl<P: UtxosView> UtxoLookup for UtxosCache<P> {
+    fn get_utxo(&self, outpoint: &OutPoint) -> Option<TxOutput> {
+        self.utxo(outpoint).map(|utxo| utxo.output().clone())
+    }
+}
+
+/// Returns the output spent by the input
+pub fn get_spent_output<L: UtxoLookup + ?Sized>(
+    lookup: &L,
+    input: &TxInput,
+) -> Result<TxOutput, ConnectTransactionError> {
+    lookup
+        .get_utxo(input.outpoint())
+        .ok_or(ConnectTransactionError::MissingOutputOrSpent)
+}
+
+/// Resolves the inputs with the lookup and returns the total amount of every coin or token
+/// they spend
+///
+/// The id of the token issued by a transaction can only be calculated from the issuing
+/// transaction, it's provided by `issuance_token_id_getter`.
+pub fn calculate_total_inputs<L, F>(
+    lookup: &L,
+    inputs: &[TxInput],
+    issuance_token_id_getter: F,
+) -> Result<BTreeMap<CoinOrTokenId, Amount>, ConnectTransactionError>
+where
+    L: UtxoLookup + ?Sized,
+    F: Fn(Id<Transaction>) -> Result<Option<TokenId>, ConnectTransactionError>,
+{
+    let iter = inputs.iter().map(|input| {
+        let output = get_spent_output(lookup, input)?;
+        match input.outpoint().tx_id() {
+            OutPointSourceId::Transaction(tx_id) => {
+                get_input_token_id_and_amount(output.value(), || issuance_token_id_getter(tx_id))
+            }
+            OutPointSourceId::BlockReward(_) => {
+                get_input_token_id_and_amount(output.value(), || Ok(None))
+            }
+        }
+    });
+
+    let iter = fallible_iterator::convert(iter);
+
+    let amounts_map = AmountsMap::from_fallible_iter(iter)?;
+
+    Ok(amounts_map.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use common::{
+        chain::{
+            tokens::{OutputValue, TokenData, TokenTransfer},
+            Destination, OutputPurpose,
+        },
+        primitives::H256,
+    };
+
+    use super::*;
+
+    /// The outputs are kept in memory and stay in the lookup after they're spent
+    #[derive(Default)]
+    struct InMemoryUtxos {
+        outputs: BTreeMap<OutPoint, TxOutput>,
+        spent: BTreeSet<OutPoint>,
+    }
+
+    impl UtxoLookup for InMemoryUtxos {
+        fn get_utxo(&self, outpoint: &OutPoint) -> Option<TxOutput> {
+            if self.spent.contains(outpoint) {
+                return None;
+            }
+            self.outputs.get(outpoint).cloned()
+        }
+    }
+
+    fn transfer(value: OutputValue) -> TxOutput {
+        TxOutput::new(value, OutputPurpose::Transfer(Destination::AnyoneCanSpend))
+    }
+
+    fn input(tx_index: u64, output_index: u32) -> TxInput {
+        let tx_id = Id::<Transaction>::new(H256::from_low_u64_be(tx_index));
+        TxInput::new(OutPointSourceId::Transaction(tx_id), output_index)
+    }
+
+    fn no_issuance(_: Id<Transaction>) -> Result<Option<TokenId>, ConnectTransactionError> {
+        Ok(None)
+    }
+
+    #[test]
+    fn found_outputs() {
+        let token_id = TokenId::from_low_u64_be(100);
+        let mut utxos = InMemoryUtxos::default();
+        utxos.outputs.insert(
+            input(1, 0).outpoint().clone(),
+            transfer(OutputValue::Coin(Amount::from_atoms(10))),
+        );
+        utxos.outputs.insert(
+            input(1, 1).outpoint().clone(),
+            transfer(
+                TokenData::TokenTransfer(TokenTransfer {
+                    token_id,
+                    amount: Amount::from_atoms(5),
+                })
+                .into(),
+            ),
+        );
+        utxos.outputs.insert(
+            input(2, 0).outpoint().clone(),
+            transfer(OutputValue::Coin(Amount::from_atoms(15))),
+        );
+
+        assert_eq!(
+            get_spent_output(&utxos, &input(2, 0)),
+            Ok(transfer(OutputValue::Coin(Amount::from_atoms(15))))
+        );
+
+        let expected = [
+            (CoinOrTokenId::Coin, Amount::from_atoms(25)),
+            (CoinOrTokenId::TokenId(token_id), Amount::from_atoms(5)),
+        ];
+        assert_eq!(
+            calculate_total_inputs(
+                &utxos,
+                &[input(1, 0), input(1, 1), input(2, 0)],
+                no_issuance
+            ),
+            Ok(expected.into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn missing_output() {
+        let mut utxos = InMemoryUtxos::default();
+        utxos.outputs.insert(
+            input(1, 0).outpoint().clone(),
+            transfer(OutputValue::Coin(Amount::from_atoms(10))),
+        );
+
+        assert_eq!(
+            get_spent_output(&utxos, &input(1, 1)),
+            Err(ConnectTransactionError::MissingOutputOrSpent)
+        );
+        assert_eq!(
+            calculate_total_inputs(&utxos, &[input(1, 0), input(2, 0)], no_issuance),
+            Err(ConnectTransactionError::MissingOutputOrSpent)
+        );
+    }
+
+    #[test]
+    fn spent_output() {
+        let mut utxos = InMemoryUtxos::default();
+        utxos.outputs.insert(
+            input(1, 0).outpoint().clone(),
+            transfer(OutputValue::Coin(Amount::from_atoms(10))),
+        );
+        utxos.spent.insert(input(1, 0).outpoint().clone());
+
+        assert_eq!(
+            get_spent_output(&utxos, &input(1, 0)),
+            Err(ConnectTransactionError::MissingOutputOrSpent)
+        );
+        assert_eq!(
+            calculate_total_inputs(&utxos, &[input(1, 0)], no_issuance),
+            Err(ConnectTransactionError::MissingOutputOrSpent)
+        );
+    }
+}
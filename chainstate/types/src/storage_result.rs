@@ -18,6 +18,10 @@
 pub enum Error {
     #[error("Storage error: {0}")]
     Storage(storage::error::Recoverable),
+    #[error("Unsupported storage version {0}, the latest supported version is {1}")]
+    UnsupportedStorageVersion(u32, u32),
+    #[error("Failed to migrate the data stored by the storage version {0}: {1}")]
+    MigrationFailed(u32, String),
 }
 
 impl From<storage::Error> for Error {
@@ -13,9 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::chain::block::BlockHeader;
 use crate::chain::signature::inputsig::InputWitness;
 use crate::chain::ChainConfig;
-use crate::primitives::Compact;
+use crate::primitives::{Compact, H256};
 use crate::Uint256;
 use crate::{chain::TxInput, primitives::BlockDistance};
 
@@ -107,6 +108,17 @@ impl PoWData {
         self.nonce = nonce;
     }
 
+    /// Returns the hash of the given header with its consensus data replaced by this one.
+    ///
+    /// This is the value that has to meet the target for the nonce to be a valid solution.
+    pub fn header_hash(&self, header: &BlockHeader) -> H256 {
+        let header = BlockHeader {
+            consensus_data: ConsensusData::PoW(self.clone()),
+            ..header.clone()
+        };
+        header.block_id().get()
+    }
+
     pub fn get_block_proof(&self) -> Option<Uint256> {
         // 2**256 / (target + 1) == ~target / (target+1) + 1    (eqn shamelessly stolen from bitcoind)
         let target: Uint256 = self.bits.try_into().ok()?;
@@ -118,3 +130,80 @@ impl PoWData {
         Some(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chain::{block::timestamp::BlockTimestamp, GenBlock, OutPointSourceId},
+        primitives::Id,
+    };
+
+    // Golden vectors pinning the encoding of the consensus data. If any of these fail, the
+    // on-disk and on-wire format of the block header has changed.
+    fn check_golden_vector(data: ConsensusData, expected_hex: &str) {
+        assert_eq!(hex::encode(data.encode()), expected_hex);
+
+        let decoded =
+            ConsensusData::decode(&mut hex::decode(expected_hex).unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn none_encoding() {
+        check_golden_vector(ConsensusData::None, "00");
+    }
+
+    #[test]
+    fn pow_encoding() {
+        check_golden_vector(
+            ConsensusData::PoW(PoWData::new(Compact(0x1d00ffff), 0x0123456789abcdef)),
+            "01ffff001defcdab89674523010000000000000000",
+        );
+    }
+
+    #[test]
+    fn pos_encoding() {
+        let kernel_input = TxInput::new(
+            OutPointSourceId::BlockReward(Id::<GenBlock>::new(H256([0x01; 32]))),
+            2,
+        );
+        let kernel_witness = InputWitness::NoSignature(Some(vec![0xaa, 0xbb]));
+
+        check_golden_vector(
+            ConsensusData::PoS(PoSData::new(
+                vec![kernel_input],
+                vec![kernel_witness],
+                Compact(0x2000ffff),
+            )),
+            "0204010101010101010101010101010101010101010101010101010101010101010101020000000400\
+             0108aabbffff0020",
+        );
+    }
+
+    #[test]
+    fn pow_header_hash() {
+        let header = BlockHeader {
+            version: Default::default(),
+            prev_block_id: Id::new(H256([0x01; 32])),
+            tx_merkle_root: H256([0x02; 32]),
+            witness_merkle_root: H256([0x03; 32]),
+            timestamp: BlockTimestamp::from_int_seconds(1_000_000),
+            consensus_data: ConsensusData::None,
+        };
+        let pow_data = PoWData::new(Compact(0x1d00ffff), 123);
+
+        let mut expected_header = header.clone();
+        expected_header.consensus_data = ConsensusData::PoW(pow_data.clone());
+        assert_eq!(
+            pow_data.header_hash(&header),
+            expected_header.block_id().get()
+        );
+
+        let other_pow_data = PoWData::new(Compact(0x1d00ffff), 124);
+        assert_ne!(
+            pow_data.header_hash(&header),
+            other_pow_data.header_hash(&header)
+        );
+    }
+}
@@ -95,18 +95,22 @@ pub struct Builder {
     max_block_header_size: usize,
     max_block_size_with_standard_txs: usize,
     max_block_size_with_smart_contracts: usize,
+    max_witness_size: usize,
     net_upgrades: NetUpgrades<UpgradeVersion>,
     genesis_block: GenesisBlockInit,
     emission_schedule: EmissionScheduleInit,
     token_min_issuance_fee: Amount,
     token_max_uri_len: usize,
     token_max_dec_count: u8,
+    token_max_supply_atoms: Amount,
     token_max_ticker_len: usize,
     token_max_name_len: usize,
     token_max_description_len: usize,
     token_min_hash_len: usize,
     token_max_hash_len: usize,
     empty_consensus_reward_maturity_distance: BlockDistance,
+    token_fees_allowed: bool,
+    protocol_name: Option<String>,
 }
 
 impl Builder {
@@ -121,6 +125,7 @@ impl Builder {
             max_block_header_size: super::MAX_BLOCK_HEADER_SIZE,
             max_block_size_with_standard_txs: super::MAX_BLOCK_TXS_SIZE,
             max_block_size_with_smart_contracts: super::MAX_BLOCK_CONTRACTS_SIZE,
+            max_witness_size: super::MAX_WITNESS_SIZE,
             max_future_block_time_offset: super::DEFAULT_MAX_FUTURE_BLOCK_TIME_OFFSET,
             target_block_spacing: super::DEFAULT_TARGET_BLOCK_SPACING,
             genesis_block: chain_type.default_genesis_init(),
@@ -129,12 +134,15 @@ impl Builder {
             token_min_issuance_fee: super::TOKEN_MIN_ISSUANCE_FEE,
             token_max_uri_len: super::TOKEN_MAX_URI_LEN,
             token_max_dec_count: super::TOKEN_MAX_DEC_COUNT,
+            token_max_supply_atoms: super::TOKEN_MAX_SUPPLY_ATOMS,
             token_max_ticker_len: super::TOKEN_MAX_TICKER_LEN,
             token_max_name_len: super::TOKEN_MAX_NAME_LEN,
             token_max_description_len: super::TOKEN_MAX_DESCRIPTION_LEN,
             token_min_hash_len: super::TOKEN_MIN_HASH_LEN,
             token_max_hash_len: super::TOKEN_MAX_HASH_LEN,
             empty_consensus_reward_maturity_distance: BlockDistance::new(0),
+            token_fees_allowed: false,
+            protocol_name: None,
         }
     }
 
@@ -156,6 +164,7 @@ impl Builder {
             max_block_header_size,
             max_block_size_with_standard_txs,
             max_block_size_with_smart_contracts,
+            max_witness_size,
             max_future_block_time_offset,
             target_block_spacing,
             genesis_block,
@@ -164,12 +173,15 @@ impl Builder {
             token_min_issuance_fee,
             token_max_uri_len,
             token_max_dec_count,
+            token_max_supply_atoms,
             token_max_ticker_len,
             token_max_name_len,
             token_max_description_len,
             token_min_hash_len,
             token_max_hash_len,
             empty_consensus_reward_maturity_distance,
+            token_fees_allowed,
+            protocol_name,
         } = self;
 
         let emission_schedule = match emission_schedule {
@@ -189,6 +201,9 @@ impl Builder {
         };
         let genesis_block = Arc::new(WithId::new(genesis_block));
 
+        let protocol_name = protocol_name
+            .unwrap_or_else(|| format!("/mintlayer/{:x}", WithId::id(&genesis_block).get()));
+
         ChainConfig {
             chain_type,
             address_prefix,
@@ -198,6 +213,7 @@ impl Builder {
             max_block_header_size,
             max_block_size_with_standard_txs,
             max_block_size_with_smart_contracts,
+            max_witness_size,
             max_future_block_time_offset,
             target_block_spacing,
             genesis_block,
@@ -207,12 +223,15 @@ impl Builder {
             token_min_issuance_fee,
             token_max_uri_len,
             token_max_dec_count,
+            token_max_supply_atoms,
             token_max_ticker_len,
             empty_consensus_reward_maturity_distance,
             token_max_name_len,
             token_max_description_len,
             token_min_hash_len,
             token_max_hash_len,
+            token_fees_allowed,
+            protocol_name,
         }
     }
 }
@@ -239,8 +258,12 @@ impl Builder {
     builder_method!(max_block_header_size: usize);
     builder_method!(max_block_size_with_standard_txs: usize);
     builder_method!(max_block_size_with_smart_contracts: usize);
+    builder_method!(max_witness_size: usize);
     builder_method!(net_upgrades: NetUpgrades<UpgradeVersion>);
     builder_method!(empty_consensus_reward_maturity_distance: BlockDistance);
+    builder_method!(token_max_supply_atoms: Amount);
+    builder_method!(token_fees_allowed: bool);
+    builder_method!(protocol_name: Option<String>);
 
     /// Set the genesis block to be the unit test version
     pub fn genesis_unittest(mut self, premine_destination: Destination) -> Self {
@@ -81,15 +81,19 @@ pub struct ChainConfig {
     max_block_header_size: usize,
     max_block_size_with_standard_txs: usize,
     max_block_size_with_smart_contracts: usize,
+    max_witness_size: usize,
     token_min_issuance_fee: Amount,
     token_max_uri_len: usize,
     token_max_dec_count: u8,
+    token_max_supply_atoms: Amount,
     token_max_ticker_len: usize,
     token_max_name_len: usize,
     token_max_description_len: usize,
     token_min_hash_len: usize,
     token_max_hash_len: usize,
     empty_consensus_reward_maturity_distance: BlockDistance,
+    token_fees_allowed: bool,
+    protocol_name: String,
 }
 
 impl ChainConfig {
@@ -161,6 +165,19 @@ impl ChainConfig {
         self.max_block_size_with_smart_contracts
     }
 
+    /// The maximum total size of a block: the sum of the header, the transactions and the smart
+    /// contracts size limits
+    pub fn max_block_size(&self) -> usize {
+        self.max_block_header_size
+            + self.max_block_size_with_standard_txs
+            + self.max_block_size_with_smart_contracts
+    }
+
+    /// The maximum encoded size of a single input witness
+    pub fn max_witness_size(&self) -> usize {
+        self.max_witness_size
+    }
+
     pub fn token_min_issuance_fee(&self) -> Amount {
         self.token_min_issuance_fee
     }
@@ -173,6 +190,10 @@ impl ChainConfig {
         self.token_max_dec_count
     }
 
+    pub fn token_max_supply_atoms(&self) -> Amount {
+        self.token_max_supply_atoms
+    }
+
     pub fn token_max_ticker_len(&self) -> usize {
         self.token_max_ticker_len
     }
@@ -197,6 +218,22 @@ impl ChainConfig {
         self.empty_consensus_reward_maturity_distance
     }
 
+    /// Whether transactions may pay the fee in a token instead of coins.
+    ///
+    /// If set, each transaction pays the fee in the only currency it has a surplus in and all the
+    /// other currencies must be conserved exactly. Otherwise the fee is paid in coins.
+    pub fn token_fees_allowed(&self) -> bool {
+        self.token_fees_allowed
+    }
+
+    /// The name of the p2p protocol exchanged in the handshake.
+    ///
+    /// Nodes only talk to the peers with the same protocol name. Unless set explicitly,
+    /// it's derived from the genesis block, so chains that share the magic bytes are still separated.
+    pub fn protocol_name(&self) -> &str {
+        &self.protocol_name
+    }
+
     // TODO: this should be part of net-upgrades. There should be no canonical definition of PoW for any chain config
     pub const fn get_proof_of_work_config(&self) -> PoWChainConfig {
         PoWChainConfig::new(self.chain_type)
@@ -213,8 +250,10 @@ impl AsRef<ChainConfig> for ChainConfig {
 const MAX_BLOCK_HEADER_SIZE: usize = 1024;
 const MAX_BLOCK_TXS_SIZE: usize = 524_288;
 const MAX_BLOCK_CONTRACTS_SIZE: usize = 524_288;
+const MAX_WITNESS_SIZE: usize = 1024;
 const TOKEN_MIN_ISSUANCE_FEE: Amount = Amount::from_atoms(10_000_000_000_000);
 const TOKEN_MAX_DEC_COUNT: u8 = 18;
+const TOKEN_MAX_SUPPLY_ATOMS: Amount = Amount::MAX;
 const TOKEN_MAX_TICKER_LEN: usize = 5;
 const TOKEN_MIN_HASH_LEN: usize = 4;
 const TOKEN_MAX_HASH_LEN: usize = 32;
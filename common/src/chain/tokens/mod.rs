@@ -27,13 +27,14 @@ pub use nft::*;
 pub use rpc::*;
 pub use tokens_utils::*;
 
-use super::{Block, Transaction};
+use super::{Block, Destination, Transaction};
 
 /// The data that is created when a token is issued to track it (and to update it with ACL commands)
 #[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
 pub struct TokenAuxiliaryData {
     issuance_tx: Transaction,
     issuance_block_id: Id<Block>,
+    frozen: bool,
 }
 
 impl TokenAuxiliaryData {
@@ -41,6 +42,7 @@ impl TokenAuxiliaryData {
         Self {
             issuance_tx,
             issuance_block_id,
+            frozen: false,
         }
     }
 
@@ -51,6 +53,46 @@ impl TokenAuxiliaryData {
     pub fn issuance_block_id(&self) -> Id<Block> {
         self.issuance_block_id
     }
+
+    /// Whether the token is frozen, in which case it can't be transferred
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// The issuance data of the token, `None` for NFTs
+    pub fn token_issuance(&self) -> Option<&TokenIssuance> {
+        self.issuance_tx.outputs().iter().find_map(|output| match output.value() {
+            OutputValue::Coin(_) => None,
+            OutputValue::Token(token_data) => match &**token_data {
+                TokenData::TokenIssuance(issuance) => Some(&**issuance),
+                TokenData::TokenIssuanceV2(issuance) => Some(&issuance.issuance),
+                TokenData::TokenTransfer(_)
+                | TokenData::NftIssuance(_)
+                | TokenData::TokenFreezeV1 { .. }
+                | TokenData::TokenUnfreezeV1 { .. } => None,
+            },
+        })
+    }
+
+    /// The authority allowed to freeze and unfreeze the token, `None` if the token can't be
+    /// frozen (see [`TokenIssuanceV2::freeze_authority`])
+    pub fn freeze_authority(&self) -> Option<&Destination> {
+        self.issuance_tx.outputs().iter().find_map(|output| match output.value() {
+            OutputValue::Coin(_) => None,
+            OutputValue::Token(token_data) => match &**token_data {
+                TokenData::TokenIssuanceV2(issuance) => Some(&issuance.freeze_authority),
+                TokenData::TokenTransfer(_)
+                | TokenData::TokenIssuance(_)
+                | TokenData::NftIssuance(_)
+                | TokenData::TokenFreezeV1 { .. }
+                | TokenData::TokenUnfreezeV1 { .. } => None,
+            },
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
@@ -95,6 +137,34 @@ pub struct TokenIssuance {
     pub metadata_uri: Vec<u8>,
 }
 
+impl TokenIssuance {
+    /// The amount to issue as a fixed-point number with `number_of_decimals` decimals
+    pub fn display_amount(&self) -> String {
+        self.amount_to_issue.into_fixedpoint_str(self.number_of_decimals)
+    }
+
+    /// Whether the amount to issue is valid for the declared number of decimals and doesn't
+    /// exceed `max_supply_atoms` (see [`is_token_amount_representable`])
+    pub fn is_amount_representable(&self, max_supply_atoms: Amount) -> bool {
+        is_token_amount_representable(
+            self.amount_to_issue,
+            self.number_of_decimals,
+            max_supply_atoms,
+        )
+    }
+}
+
+/// Token issuance with an authority allowed to freeze and unfreeze the token
+///
+/// A separate type keeps the encoding of [`TokenIssuance`] unchanged, the tokens issued without the
+/// authority can't be frozen.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub struct TokenIssuanceV2 {
+    pub issuance: TokenIssuance,
+    /// The authority whose signature is required to freeze and unfreeze the token
+    pub freeze_authority: Destination,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
 pub enum TokenData {
     /// TokenTransfer data to another user. If it is a token, then the token data must also be transferred to the recipient.
@@ -113,6 +183,15 @@ pub enum TokenData {
     //     token_id: TokenId,
     //     amount_to_issue: Amount,
     // },
+    /// Forbid the transfers of the token, must be authorized by the freeze authority of the token
+    #[codec(index = 5)]
+    TokenFreezeV1 { token_id: TokenId },
+    /// Allow the transfers of a frozen token again, must be authorized by the freeze authority of the token
+    #[codec(index = 6)]
+    TokenUnfreezeV1 { token_id: TokenId },
+    /// New token creation with a freeze authority
+    #[codec(index = 7)]
+    TokenIssuanceV2(Box<TokenIssuanceV2>),
 }
 
 impl From<NftIssuance> for TokenData {
@@ -127,6 +206,12 @@ impl From<TokenIssuance> for TokenData {
     }
 }
 
+impl From<TokenIssuanceV2> for TokenData {
+    fn from(d: TokenIssuanceV2) -> Self {
+        Self::TokenIssuanceV2(Box::new(d))
+    }
+}
+
 impl From<TokenTransfer> for OutputValue {
     fn from(d: TokenTransfer) -> Self {
         TokenData::TokenTransfer(d).into()
@@ -144,3 +229,9 @@ impl From<TokenIssuance> for OutputValue {
         TokenData::TokenIssuance(Box::new(d)).into()
     }
 }
+
+impl From<TokenIssuanceV2> for OutputValue {
+    fn from(d: TokenIssuanceV2) -> Self {
+        TokenData::TokenIssuanceV2(Box::new(d)).into()
+    }
+}
@@ -16,9 +16,17 @@
 use super::{OutputValue, TokenData, TokenId};
 use crate::{
     chain::{Transaction, TxOutput},
-    primitives::id::hash_encoded,
+    primitives::{id::hash_encoded, Amount},
 };
 
+/// Calculate the id of a token issued by the given transaction.
+///
+/// The id is the hash of the encoded first input of the issuance transaction, so it can be
+/// computed before the transaction is included in a block. Returns `None` if the transaction
+/// has no inputs.
+///
+/// The derivation is part of the consensus rules and must not change: wallets and other
+/// external tools rely on it to predict token ids.
 pub fn token_id(tx: &Transaction) -> Option<TokenId> {
     Some(hash_encoded(tx.inputs().get(0)?))
 }
@@ -27,8 +35,28 @@ pub fn is_tokens_issuance(output_value: &OutputValue) -> bool {
     match output_value {
         OutputValue::Coin(_) => false,
         OutputValue::Token(token_data) => match **token_data {
-            TokenData::TokenIssuance(_) | TokenData::NftIssuance(_) => true,
-            TokenData::TokenTransfer(_) => false,
+            TokenData::TokenIssuance(_)
+            | TokenData::TokenIssuanceV2(_)
+            | TokenData::NftIssuance(_) => true,
+            TokenData::TokenTransfer(_)
+            | TokenData::TokenFreezeV1 { .. }
+            | TokenData::TokenUnfreezeV1 { .. } => false,
+        },
+    }
+}
+
+/// Returns the id of the token and whether it gets frozen if the output value is a token freeze or
+/// unfreeze operation
+pub fn token_freeze_operation(output_value: &OutputValue) -> Option<(TokenId, bool)> {
+    match output_value {
+        OutputValue::Coin(_) => None,
+        OutputValue::Token(token_data) => match **token_data {
+            TokenData::TokenFreezeV1 { token_id } => Some((token_id, true)),
+            TokenData::TokenUnfreezeV1 { token_id } => Some((token_id, false)),
+            TokenData::TokenTransfer(_)
+            | TokenData::TokenIssuance(_)
+            | TokenData::TokenIssuanceV2(_)
+            | TokenData::NftIssuance(_) => None,
         },
     }
 }
@@ -36,3 +64,106 @@ pub fn is_tokens_issuance(output_value: &OutputValue) -> bool {
 pub fn get_tokens_issuance_count(outputs: &[TxOutput]) -> usize {
     outputs.iter().filter(|&output| is_tokens_issuance(output.value())).count()
 }
+
+/// Check that a token amount is valid for a token with the given number of decimals.
+///
+/// One whole token (10 to the power of `number_of_decimals` atoms) must be representable as an
+/// amount, and the amount must not exceed `max_supply_atoms`.
+pub fn is_token_amount_representable(
+    amount: Amount,
+    number_of_decimals: u8,
+    max_supply_atoms: Amount,
+) -> bool {
+    10u128.checked_pow(number_of_decimals.into()).is_some() && amount <= max_supply_atoms
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        chain::{tokens::TokenIssuance, GenBlock, OutPointSourceId, TxInput},
+        primitives::{Id, H256},
+    };
+
+    fn make_tx(inputs: Vec<TxInput>) -> Transaction {
+        Transaction::new(0, inputs, vec![], 0).unwrap()
+    }
+
+    #[test]
+    fn token_id_no_inputs() {
+        assert_eq!(token_id(&make_tx(vec![])), None);
+    }
+
+    // The derivation must never change, these vectors pin it
+    #[test]
+    fn token_id_golden_vectors() {
+        let vectors = [
+            (
+                TxInput::new(
+                    OutPointSourceId::Transaction(Id::<Transaction>::new(H256([0x01; 32]))),
+                    0,
+                ),
+                "a84a28fda3a3091e675aa6cb010f15526b35b548c309057b19c40ca96666fc7a",
+            ),
+            (
+                TxInput::new(
+                    OutPointSourceId::BlockReward(Id::<GenBlock>::new(H256([0x02; 32]))),
+                    1,
+                ),
+                "c74d871669d25c0468fe31bae8756df7687a8fa52ad224f618228d47b5c2baff",
+            ),
+            (
+                TxInput::new(
+                    OutPointSourceId::Transaction(Id::<Transaction>::new(H256([0xab; 32]))),
+                    7,
+                ),
+                "9144b20fe130574c5766f799bb3fa3208eaae996f04bbeb3fc0ffd96f90d360d",
+            ),
+        ];
+
+        for (input, expected) in vectors {
+            let expected = TokenId::from_str(expected).unwrap();
+            assert_eq!(token_id(&make_tx(vec![input.clone()])), Some(expected));
+
+            // Only the first input is used
+            let other_input = TxInput::new(
+                OutPointSourceId::Transaction(Id::<Transaction>::new(H256([0xff; 32]))),
+                3,
+            );
+            assert_eq!(token_id(&make_tx(vec![input, other_input])), Some(expected));
+        }
+    }
+
+    fn make_issuance(amount_to_issue: u128, number_of_decimals: u8) -> TokenIssuance {
+        TokenIssuance {
+            token_ticker: b"XYZ".to_vec(),
+            amount_to_issue: Amount::from_atoms(amount_to_issue),
+            number_of_decimals,
+            metadata_uri: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn display_amount() {
+        assert_eq!(make_issuance(123_456, 2).display_amount(), "1234.56");
+        assert_eq!(make_issuance(123_400, 4).display_amount(), "12.34");
+        assert_eq!(make_issuance(5, 3).display_amount(), "0.005");
+        assert_eq!(make_issuance(1_000, 0).display_amount(), "1000");
+    }
+
+    #[test]
+    fn amount_representable() {
+        let max_supply_atoms = Amount::from_atoms(100_000);
+
+        let issuance = make_issuance(100_000, 2);
+        assert!(issuance.is_amount_representable(max_supply_atoms));
+        assert!(make_issuance(1, 38).is_amount_representable(Amount::MAX));
+
+        // Exceeds the atom limit
+        assert!(!make_issuance(100_001, 2).is_amount_representable(max_supply_atoms));
+        // A whole token doesn't fit into an amount
+        assert!(!make_issuance(1, 39).is_amount_representable(Amount::MAX));
+    }
+}
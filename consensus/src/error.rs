@@ -17,7 +17,7 @@ use thiserror::Error;
 
 use chainstate_types::PropertyQueryError;
 use common::{
-    chain::{Block, GenBlock},
+    chain::{block::timestamp::BlockTimestamp, Block, GenBlock},
     primitives::Id,
 };
 
@@ -38,4 +38,8 @@ pub enum ConsensusVerificationError {
     PoWError(ConsensusPoWError),
     #[error("Unsupported consensus type")]
     UnsupportedConsensusType,
+    #[error("Block time {0} is lower than the median time {1} of its ancestors")]
+    BlockTimeOrderInvalid(BlockTimestamp, BlockTimestamp),
+    #[error("Block time {0} is too far into the future")]
+    BlockFromTheFuture(BlockTimestamp),
 }
@@ -20,7 +20,7 @@ pub mod pow;
 pub use crate::{
     error::ConsensusVerificationError,
     pow::ConsensusPoWError,
-    validator::{validate_consensus, TransactionIndexHandle},
+    validator::{check_block_timestamp, validate_consensus, TransactionIndexHandle},
 };
 
 mod error;
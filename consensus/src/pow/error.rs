@@ -18,7 +18,7 @@ use thiserror::Error;
 use chainstate_types::PropertyQueryError;
 use common::{
     chain::block::Block,
-    primitives::{BlockHeight, Compact, Id},
+    primitives::{BlockHeight, Compact, Id, H256},
 };
 
 /// A proof of work consensus error.
@@ -26,6 +26,10 @@ use common::{
 pub enum ConsensusPoWError {
     #[error("Invalid Proof of Work for block {0}")]
     InvalidPoW(Id<Block>),
+    #[error("No PoW data in block {0}")]
+    NoPowData(Id<Block>),
+    #[error("Invalid target bits in block {0}: expected `{1:?}`, got `{2:?}`")]
+    InvalidTargetBits(Id<Block>, Compact, Compact),
     #[error("Error while loading previous block {0} of block {1} with error {2}")]
     PrevBlockLoadError(Id<Block>, Id<Block>, PropertyQueryError),
     #[error("Previous block {0} of block {1} not found in database")]
@@ -34,6 +38,8 @@ pub enum ConsensusPoWError {
     AncestorAtHeightNotFound(Id<Block>, BlockHeight, PropertyQueryError),
     #[error("No PoW data for block for block")]
     NoPowDataInPreviousBlock,
+    #[error("Block hash {0:?} doesn't meet the target `{1:?}`")]
+    HashAboveTarget(H256, Compact),
     #[error("Decoding bits of block failed: `{0:?}`")]
     DecodingBitsFailed(Compact),
     #[error("Previous bits conversion failed: `{0:?}`")]
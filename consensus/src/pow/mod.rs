@@ -16,7 +16,7 @@
 pub use self::{
     error::ConsensusPoWError,
     work::mine,
-    work::{check_pow_consensus, check_proof_of_work},
+    work::{calculate_retarget_bits, check_pow_consensus, check_pow_solution, check_proof_of_work},
 };
 
 mod error;
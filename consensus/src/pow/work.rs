@@ -46,6 +46,15 @@ pub fn check_proof_of_work(
         .map_err(|_| ConsensusPoWError::DecodingBitsFailed(block_bits))
 }
 
+/// Checks that the block header hash is a valid solution for the given target bits.
+pub fn check_pow_solution(block_header_hash: H256, bits: Compact) -> Result<(), ConsensusPoWError> {
+    if check_proof_of_work(block_header_hash, bits)? {
+        Ok(())
+    } else {
+        Err(ConsensusPoWError::HashAboveTarget(block_header_hash, bits))
+    }
+}
+
 pub fn check_pow_consensus<H: BlockIndexHandle>(
     chain_config: &ChainConfig,
     header: &BlockHeader,
@@ -54,6 +63,21 @@ pub fn check_pow_consensus<H: BlockIndexHandle>(
 ) -> Result<(), ConsensusPoWError> {
     let work_required =
         calculate_work_required(chain_config, header, pow_status, block_index_handle)?;
+
+    let block_bits = match header.consensus_data() {
+        ConsensusData::PoW(pow_data) => pow_data.bits(),
+        ConsensusData::None | ConsensusData::PoS(_) => {
+            return Err(ConsensusPoWError::NoPowData(header.get_id()))
+        }
+    };
+    if block_bits != work_required {
+        return Err(ConsensusPoWError::InvalidTargetBits(
+            header.get_id(),
+            work_required,
+            block_bits,
+        ));
+    }
+
     if check_proof_of_work(header.block_id().get(), work_required)? {
         Ok(())
     } else {
@@ -100,11 +124,30 @@ fn calculate_work_required<H: BlockIndexHandle>(
     }
 }
 
+/// Calculates the target bits after a difficulty adjustment.
+///
+/// The target of the previous interval is scaled by the ratio of its actual and expected
+/// timespans: faster blocks lower the target (raise the difficulty), slower blocks raise it.
+/// # Arguments
+/// `prev_bits` - the bits of the last block of the previous interval.
+/// `last_block_time` - the timestamp of the last block of the previous interval.
+/// `retarget_block_time` - the timestamp of the first block of the previous interval.
+pub fn calculate_retarget_bits(
+    chain_config: &ChainConfig,
+    prev_bits: Compact,
+    last_block_time: BlockTimestamp,
+    retarget_block_time: BlockTimestamp,
+) -> Result<Compact, ConsensusPoWError> {
+    PoW::new(chain_config).retarget_bits(prev_bits, last_block_time, retarget_block_time)
+}
+
 impl PoW {
     /// The difference (in block time) between the current block and 2016th block before the current one.
+    ///
+    /// Block timestamps are not strictly increasing, a negative difference is clamped to the
+    /// minimal timespan like any other too short one.
     fn actual_timespan(&self, prev_block_blocktime: u64, retarget_blocktime: u64) -> u64 {
-        // TODO: this needs to be fixed because it could suffer from an underflow
-        let actual_timespan = prev_block_blocktime - retarget_blocktime;
+        let actual_timespan = prev_block_blocktime.saturating_sub(retarget_blocktime);
 
         num::clamp(
             actual_timespan,
@@ -167,10 +210,23 @@ impl PoW {
         retarget_block_time: BlockTimestamp,
         prev_block_index: &BlockIndex,
         prev_block_bits: Compact,
+    ) -> Result<Compact, ConsensusPoWError> {
+        self.retarget_bits(
+            prev_block_bits,
+            prev_block_index.block_timestamp(),
+            retarget_block_time,
+        )
+    }
+
+    fn retarget_bits(
+        &self,
+        prev_block_bits: Compact,
+        last_block_time: BlockTimestamp,
+        retarget_block_time: BlockTimestamp,
     ) -> Result<Compact, ConsensusPoWError> {
         // limit adjustment step
         let actual_timespan_of_last_interval = self.actual_timespan(
-            prev_block_index.block_timestamp().as_int_seconds(),
+            last_block_time.as_int_seconds(),
             retarget_block_time.as_int_seconds(),
         );
 
@@ -220,13 +276,97 @@ pub fn mine(block: &mut Block, max_nonce: u128, bits: Compact) -> Result<bool, C
 
 #[cfg(test)]
 mod tests {
-    use crate::pow::work::check_proof_of_work;
+    use crate::pow::{
+        work::{calculate_retarget_bits, check_pow_solution, check_proof_of_work},
+        ConsensusPoWError,
+    };
+    use common::chain::block::timestamp::BlockTimestamp;
     use common::chain::config::create_mainnet;
     use common::primitives::{Compact, H256};
+    use common::Uint256;
     use rstest::rstest;
     use std::str::FromStr;
 
-    //TODO: add `CalculateNextWorkRequired` test cases from Bitcoin
+    // `CalculateNextWorkRequired` test cases from Bitcoin
+    #[rstest]
+    // Blocks were a bit faster than expected, the difficulty is raised
+    #[case(0x1d00ffff, 1262152739, 1261130161, 0x1d00d86a)]
+    // Blocks were much faster than expected, the target is divided by the max retarget factor
+    #[case(0x1c05a3f4, 1279297671, 1279008237, 0x1c0168fd)]
+    // Blocks were much slower than expected, the target is multiplied by the max retarget factor
+    #[case(0x1c387f6f, 1269211443, 1263163443, 0x1d00e1fd)]
+    fn retarget(
+        #[case] prev_bits: u32,
+        #[case] last_block_time: u64,
+        #[case] retarget_block_time: u64,
+        #[case] expected_bits: u32,
+    ) {
+        let cfg = create_mainnet();
+        let new_bits = calculate_retarget_bits(
+            &cfg,
+            Compact(prev_bits),
+            BlockTimestamp::from_int_seconds(last_block_time),
+            BlockTimestamp::from_int_seconds(retarget_block_time),
+        )
+        .unwrap();
+        assert_eq!(new_bits, Compact(expected_bits));
+    }
+
+    #[test]
+    fn retarget_direction() {
+        let cfg = create_mainnet();
+        let target_timespan = cfg.get_proof_of_work_config().target_timespan().as_secs();
+        let prev_bits = Compact(0x1c05a3f4);
+        let start = 1_000_000_000;
+
+        let retarget = |timespan: u64| {
+            let bits = calculate_retarget_bits(
+                &cfg,
+                prev_bits,
+                BlockTimestamp::from_int_seconds(start + timespan),
+                BlockTimestamp::from_int_seconds(start),
+            )
+            .unwrap();
+            Uint256::try_from(bits).unwrap()
+        };
+        let prev_target = Uint256::try_from(prev_bits).unwrap();
+
+        // Exactly on time, the difficulty doesn't change
+        assert_eq!(retarget(target_timespan), prev_target);
+        // Faster blocks result in a lower target, i.e. a higher difficulty
+        assert!(retarget(target_timespan / 2) < prev_target);
+        // Slower blocks result in a higher target, i.e. a lower difficulty
+        assert!(retarget(target_timespan * 2) > prev_target);
+        // A single adjustment is limited by the max retarget factor
+        assert_eq!(retarget(1), retarget(target_timespan / 4));
+        assert_eq!(
+            retarget(target_timespan * 10),
+            retarget(target_timespan * 4)
+        );
+    }
+
+    // The last block of the interval may be older than the first one
+    #[test]
+    fn retarget_non_monotonic_timestamps() {
+        let cfg = create_mainnet();
+        let target_timespan = cfg.get_proof_of_work_config().target_timespan().as_secs();
+        let prev_bits = Compact(0x1c05a3f4);
+        let start = 1_000_000_000;
+
+        let retarget = |last_block_time: u64| {
+            calculate_retarget_bits(
+                &cfg,
+                prev_bits,
+                BlockTimestamp::from_int_seconds(last_block_time),
+                BlockTimestamp::from_int_seconds(start),
+            )
+            .unwrap()
+        };
+
+        // Treated as the shortest allowed timespan
+        assert_eq!(retarget(start - 1), retarget(start + target_timespan / 4));
+        assert_eq!(retarget(0), retarget(start + target_timespan / 4));
+    }
 
     #[rstest]
     #[case(0, "0000000000000000000000000000000000000000000000000000000000000000")]
@@ -257,6 +397,23 @@ mod tests {
         assert!(!check_proof_of_work(hash, bits).unwrap());
     }
 
+    #[test]
+    fn pow_solution() {
+        let bits = Compact(486_604_799);
+        let below_target =
+            H256::from_str("000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd")
+                .unwrap();
+        let above_target =
+            H256::from_str("00000001000000000000000000000000000000000000000000000000000000ff")
+                .unwrap();
+
+        assert_eq!(check_pow_solution(below_target, bits), Ok(()));
+        assert_eq!(
+            check_pow_solution(above_target, bits),
+            Err(ConsensusPoWError::HashAboveTarget(above_target, bits))
+        );
+    }
+
     #[test]
     fn too_easy_target() {
         let cfg = create_mainnet();
@@ -13,8 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub use self::transaction_index_handle::TransactionIndexHandle;
+pub use self::{
+    timestamp::check_block_timestamp, transaction_index_handle::TransactionIndexHandle,
+};
 
+mod timestamp;
 mod transaction_index_handle;
 
 use chainstate_types::BlockIndexHandle;
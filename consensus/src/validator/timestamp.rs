@@ -0,0 +1,117 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use common::chain::block::{timestamp::BlockTimestamp, BlockHeader};
+
+use crate::error::ConsensusVerificationError;
+
+/// Checks that the block time isn't lower than the median time of its ancestors and isn't too far
+/// into the future.
+///
+/// `current_time` is the local time since the epoch, the block time may exceed it by at most
+/// `max_future_drift`.
+pub fn check_block_timestamp(
+    header: &BlockHeader,
+    median_time_past: BlockTimestamp,
+    current_time: Duration,
+    max_future_drift: Duration,
+) -> Result<(), ConsensusVerificationError> {
+    let block_timestamp = header.timestamp();
+    if block_timestamp < median_time_past {
+        return Err(ConsensusVerificationError::BlockTimeOrderInvalid(
+            block_timestamp,
+            median_time_past,
+        ));
+    }
+
+    if block_timestamp.as_duration_since_epoch() > current_time + max_future_drift {
+        return Err(ConsensusVerificationError::BlockFromTheFuture(
+            block_timestamp,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::block::{consensus_data::ConsensusData, Block, BlockReward},
+        primitives::{Id, H256},
+    };
+
+    use super::*;
+
+    const MEDIAN_TIME_PAST: u64 = 1_000;
+    const CURRENT_TIME: Duration = Duration::from_secs(2_000);
+    const MAX_FUTURE_DRIFT: Duration = Duration::from_secs(120);
+
+    fn make_header(timestamp: u64) -> BlockHeader {
+        Block::new(
+            vec![],
+            Id::new(H256::zero()),
+            BlockTimestamp::from_int_seconds(timestamp),
+            ConsensusData::None,
+            BlockReward::new(vec![]),
+        )
+        .unwrap()
+        .header()
+        .clone()
+    }
+
+    fn check(timestamp: u64) -> Result<(), ConsensusVerificationError> {
+        check_block_timestamp(
+            &make_header(timestamp),
+            BlockTimestamp::from_int_seconds(MEDIAN_TIME_PAST),
+            CURRENT_TIME,
+            MAX_FUTURE_DRIFT,
+        )
+    }
+
+    #[test]
+    fn too_early() {
+        assert_eq!(
+            check(MEDIAN_TIME_PAST - 1),
+            Err(ConsensusVerificationError::BlockTimeOrderInvalid(
+                BlockTimestamp::from_int_seconds(MEDIAN_TIME_PAST - 1),
+                BlockTimestamp::from_int_seconds(MEDIAN_TIME_PAST),
+            ))
+        );
+    }
+
+    #[test]
+    fn too_far_in_future() {
+        let timestamp = CURRENT_TIME.as_secs() + MAX_FUTURE_DRIFT.as_secs() + 1;
+        assert_eq!(
+            check(timestamp),
+            Err(ConsensusVerificationError::BlockFromTheFuture(
+                BlockTimestamp::from_int_seconds(timestamp)
+            ))
+        );
+    }
+
+    #[test]
+    fn valid() {
+        // Both bounds are inclusive
+        assert_eq!(check(MEDIAN_TIME_PAST), Ok(()));
+        assert_eq!(check(CURRENT_TIME.as_secs()), Ok(()));
+        assert_eq!(
+            check(CURRENT_TIME.as_secs() + MAX_FUTURE_DRIFT.as_secs()),
+            Ok(())
+        );
+    }
+}
@@ -29,6 +29,8 @@ pub enum Error {
     CipherTextTooShort(usize, usize),
     #[error("Decryption error: {0}")]
     DecryptionError(String),
+    #[error("Invalid key length: {0} != {1}")]
+    InvalidKeyLength(usize, usize),
 }
 
 use self::chacha20poly1305::Chacha20poly1305Key;
@@ -59,6 +61,19 @@ impl SymmetricKey {
         Self { key }
     }
 
+    /// Creates a key of the given kind from the raw key data
+    pub fn from_raw_key(kind: SymmetricKeyKind, key_data: &[u8]) -> Result<Self, Error> {
+        let key = match kind {
+            SymmetricKeyKind::XChacha20Poly1305 => {
+                let key_data = key_data.try_into().map_err(|_| {
+                    Error::InvalidKeyLength(key_data.len(), self::chacha20poly1305::KEY_LEN)
+                })?;
+                SymmetricKeyHolder::XChacha20Poly1305(Chacha20poly1305Key::new_from_array(key_data))
+            }
+        };
+        Ok(Self { key })
+    }
+
     pub fn encrypt<R: Rng + CryptoRng>(
         &self,
         message: &[u8],
@@ -125,6 +140,21 @@ mod test {
         assert_eq!(message, decrypted);
     }
 
+    #[test]
+    fn from_raw_key() {
+        let key_hex = "00a824a1cff88c1acdbb481c75ee60c35e99f1edb0704b5eeb2684c469891a58fa";
+        let key_bin = Vec::from_hex(key_hex).unwrap();
+        let key = SymmetricKey::decode_all(&mut key_bin.as_slice()).unwrap();
+        assert_eq!(
+            SymmetricKey::from_raw_key(SymmetricKeyKind::XChacha20Poly1305, &key_bin[1..]),
+            Ok(key)
+        );
+        assert_eq!(
+            SymmetricKey::from_raw_key(SymmetricKeyKind::XChacha20Poly1305, &key_bin),
+            Err(Error::InvalidKeyLength(33, 32))
+        );
+    }
+
     #[test]
     fn select_text() {
         let message = b"Hello there! Great to see you!".as_slice();
@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use chainstate::ChainstateError;
+use chainstate::{ban_score::BanScore, ChainstateError};
 use subsystem::subsystem::CallError;
 use thiserror::Error;
 
@@ -102,3 +102,52 @@ pub enum TxValidationError {
     #[error("Internal Error.")]
     InternalError,
 }
+
+impl BanScore for Error {
+    fn ban_score(&self) -> u32 {
+        match self {
+            Error::MempoolFull => 0,
+            Error::TxValidationError(err) => err.ban_score(),
+            Error::SubsystemFailure => 0,
+            Error::SendError => 0,
+            Error::RecvError => 0,
+        }
+    }
+}
+
+// Only the transactions that can never be valid are punished. The rejections that depend on the
+// state of the local mempool or chainstate (missing inputs, fees, conflicts) are not the fault of
+// the peer that relayed the transaction.
+impl BanScore for TxValidationError {
+    fn ban_score(&self) -> u32 {
+        match self {
+            TxValidationError::NoInputs => 100,
+            TxValidationError::NoOutputs => 100,
+            TxValidationError::DuplicateInputs => 100,
+            TxValidationError::OutPointNotFound { .. } => 0,
+            TxValidationError::ExceedsMaxBlockSize => 100,
+            TxValidationError::TransactionAlreadyInMempool => 0,
+            TxValidationError::ConflictWithIrreplaceableTransaction => 0,
+            TxValidationError::InputValuesOverflow => 100,
+            TxValidationError::OutputValuesOverflow => 100,
+            TxValidationError::InputsBelowOutputs => 100,
+            TxValidationError::ReplacementFeeLowerThanOriginal { .. } => 0,
+            TxValidationError::TooManyPotentialReplacements => 0,
+            TxValidationError::SpendsNewUnconfirmedOutput => 0,
+            TxValidationError::ConflictsFeeOverflow => 0,
+            TxValidationError::TransactionFeeLowerThanConflictsWithDescendants => 0,
+            TxValidationError::AdditionalFeesUnderflow => 0,
+            TxValidationError::InsufficientFeesToRelay { .. } => 0,
+            TxValidationError::InsufficientFeesToRelayRBF => 0,
+            TxValidationError::RollingFeeThresholdNotMet { .. } => 0,
+            TxValidationError::AncestorFeeOverflow => 0,
+            TxValidationError::AncestorFeeUpdateOverflow => 0,
+            TxValidationError::FeeOverflow => 0,
+            TxValidationError::GetParentError => 0,
+            TxValidationError::DescendantOfExpiredTransaction => 0,
+            TxValidationError::ChainstateError(_) => 0,
+            TxValidationError::CallError(_) => 0,
+            TxValidationError::InternalError => 0,
+        }
+    }
+}
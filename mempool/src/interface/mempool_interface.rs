@@ -16,7 +16,7 @@
 use crate::{error::Error, tx_accumulator::TransactionAccumulator, MempoolEvent};
 use common::{
     chain::{SignedTransaction, Transaction},
-    primitives::Id,
+    primitives::{Amount, Id},
 };
 use std::sync::Arc;
 use subsystem::{CallRequest, ShutdownRequest};
@@ -29,6 +29,10 @@ pub trait MempoolInterface: Send + Sync {
     // Returns `true` if the mempool contains a transaction with the given id, `false` otherwise.
     async fn contains_transaction(&self, tx: &Id<Transaction>) -> Result<bool, Error>;
 
+    // Returns the fee paid by the transaction, its inputs must be known to the chainstate or the
+    // mempool.
+    async fn transaction_fee(&self, tx: &SignedTransaction) -> Result<Amount, Error>;
+
     async fn collect_txs(
         &self,
         tx_accumulator: Box<dyn TransactionAccumulator + Send>,
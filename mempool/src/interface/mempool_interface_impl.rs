@@ -20,7 +20,7 @@ use crate::{
 use chainstate::chainstate_interface::ChainstateInterface;
 use common::{
     chain::{ChainConfig, SignedTransaction, Transaction},
-    primitives::Id,
+    primitives::{Amount, Id},
     time_getter::TimeGetter,
 };
 use std::sync::Arc;
@@ -103,6 +103,10 @@ impl<M: GetMemoryUsage + Sync + Send + 'static> MempoolInterface for MempoolInte
         Ok(self.pool.contains_transaction(tx_id))
     }
 
+    async fn transaction_fee(&self, tx: &SignedTransaction) -> Result<Amount, Error> {
+        self.pool.transaction_fee(tx).await
+    }
+
     async fn collect_txs(
         &self,
         tx_accumulator: Box<dyn TransactionAccumulator + Send>,
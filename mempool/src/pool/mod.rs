@@ -680,6 +680,10 @@ where
         self.store.txs_by_id.contains_key(tx_id)
     }
 
+    pub async fn transaction_fee(&self, tx: &SignedTransaction) -> Result<Amount, Error> {
+        Ok(self.try_get_fee(tx).await?)
+    }
+
     pub fn subscribe_to_events(&mut self, handler: Arc<dyn Fn(MempoolEvent) + Send + Sync>) {
         self.events_controller.subscribe_to_events(handler)
     }
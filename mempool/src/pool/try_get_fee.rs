@@ -33,6 +33,8 @@ where
     M: GetMemoryUsage + Send + Sync,
 {
     // TODO this calculation is already done in ChainState, reuse it
+    // Only the fees paid in coins are supported. The transactions spending tokens, including the
+    // ones paying the fee in a token, are rejected when the input values are read.
     async fn try_get_fee(&self, tx: &SignedTransaction) -> Result<Amount, TxValidationError> {
         let tx_clone = tx.clone();
 
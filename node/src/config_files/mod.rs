@@ -136,12 +136,16 @@ fn p2p_config(config: P2pConfigFile, options: &RunOptions) -> P2pConfigFile {
     let P2pConfigFile {
         bind_addresses,
         added_nodes,
+        preconfigured_peers,
         ban_threshold,
         ban_duration,
         outbound_connection_timeout,
         ping_check_period,
         ping_timeout,
         node_type,
+        disable_peer_discovery,
+        peerdb_encryption_key,
+        min_relay_fee_rate,
     } = config;
 
     let bind_addresses = options.p2p_addr.clone().or(bind_addresses);
@@ -152,16 +156,21 @@ fn p2p_config(config: P2pConfigFile, options: &RunOptions) -> P2pConfigFile {
     let outbound_connection_timeout =
         options.p2p_outbound_connection_timeout.or(outbound_connection_timeout);
     let node_type = options.node_type.or(node_type);
+    let peerdb_encryption_key = options.p2p_peerdb_encryption_key.clone().or(peerdb_encryption_key);
 
     P2pConfigFile {
         bind_addresses,
         added_nodes,
+        preconfigured_peers,
         ban_threshold,
         ban_duration,
         outbound_connection_timeout,
         ping_check_period,
         ping_timeout,
         node_type,
+        disable_peer_discovery,
+        peerdb_encryption_key,
+        min_relay_fee_rate,
     }
 }
 
@@ -15,8 +15,12 @@
 
 use std::{num::NonZeroU64, str::FromStr, time::Duration};
 
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+use common::primitives::Amount;
+use crypto::symkey::{SymmetricKey, SymmetricKeyKind};
+
 use p2p::config::{NodeType, P2pConfig};
 
 /// A node type.
@@ -55,6 +59,8 @@ pub struct P2pConfigFile {
     pub bind_addresses: Option<Vec<String>>,
     /// Optional list of initial node addresses to connect.
     pub added_nodes: Option<Vec<String>>,
+    /// Peers that are always kept connected, they are reconnected when the connection is lost.
+    pub preconfigured_peers: Option<Vec<String>>,
     /// The score threshold after which a peer is banned.
     pub ban_threshold: Option<u32>,
     /// Duration of bans in seconds.
@@ -67,23 +73,87 @@ pub struct P2pConfigFile {
     pub ping_timeout: Option<u64>,
     /// A node type.
     pub node_type: Option<NodeTypeConfigFile>,
+    /// Only connect to the added nodes and the preconfigured peers, don't discover new ones.
+    pub disable_peer_discovery: Option<bool>,
+    /// Hex encoded 32 byte key used to encrypt the peer database.
+    pub peerdb_encryption_key: Option<String>,
+    /// The minimum fee in atoms per 1000 bytes of a relayed transaction.
+    pub min_relay_fee_rate: Option<u128>,
+}
+
+/// Parses the hex encoded peer database encryption key
+fn parse_peerdb_encryption_key(key: &str) -> anyhow::Result<SymmetricKey> {
+    let key_data = hex::decode(key).context("The peer database encryption key is not hex")?;
+    SymmetricKey::from_raw_key(SymmetricKeyKind::XChacha20Poly1305, &key_data)
+        .context("Invalid peer database encryption key")
 }
 
-impl From<P2pConfigFile> for P2pConfig {
-    fn from(c: P2pConfigFile) -> Self {
-        P2pConfig {
+impl TryFrom<P2pConfigFile> for P2pConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(c: P2pConfigFile) -> anyhow::Result<Self> {
+        let peerdb_encryption_key = c
+            .peerdb_encryption_key
+            .as_deref()
+            .map(parse_peerdb_encryption_key)
+            .transpose()?;
+
+        Ok(P2pConfig {
             bind_addresses: c.bind_addresses.clone().unwrap_or_default(),
             added_nodes: c.added_nodes.clone().unwrap_or_default(),
+            preconfigured_peers: c.preconfigured_peers.clone().unwrap_or_default(),
             ban_threshold: c.ban_threshold.into(),
             ban_duration: c.ban_duration.map(Duration::from_secs).into(),
             outbound_connection_timeout: c
                 .outbound_connection_timeout
                 .map(|t| Duration::from_secs(t.into()))
                 .into(),
+            handshake_timeout: Default::default(),
             ping_check_period: c.ping_check_period.map(Duration::from_secs).into(),
             ping_timeout: c.ping_timeout.map(Duration::from_secs).into(),
+            ping_max_retries: Default::default(),
             node_type: c.node_type.map(Into::into).into(),
             allow_discover_private_ips: Default::default(),
-        }
+            observer_mode: Default::default(),
+            max_outbound_announcement_queue: Default::default(),
+            node_kind: Default::default(),
+            health_min_peers: Default::default(),
+            health_max_tip_age: Default::default(),
+            max_send_bytes_per_second: Default::default(),
+            max_addresses: Default::default(),
+            advertise_self: Default::default(),
+            max_active_connections: Default::default(),
+            max_concurrent_dials: Default::default(),
+            stall_grace_period: Default::default(),
+            min_protocol_version: Default::default(),
+            max_announcer_tip_lag: Default::default(),
+            dns_seeds: Default::default(),
+            dns_seed_refresh_interval: Default::default(),
+            send_timeout: Default::default(),
+            max_handshake_size: Default::default(),
+            disconnect_on_protocol_error: Default::default(),
+            max_addr_per_message: Default::default(),
+            max_message_size: Default::default(),
+            max_relay_peers: Default::default(),
+            max_orphan_announcements: Default::default(),
+            peer_selection_recent_period: Default::default(),
+            peer_selection_recent_weight: Default::default(),
+            peer_selection_diverse_weight: Default::default(),
+            peer_selection_failure_penalty: Default::default(),
+            disable_peer_discovery: c.disable_peer_discovery.into(),
+            peerdb_encryption_key: peerdb_encryption_key.into(),
+            announcement_trickle_interval: Default::default(),
+            max_pending_requests: Default::default(),
+            stale_tip_timeout: Default::default(),
+            header_request_timeout: Default::default(),
+            block_request_timeout: Default::default(),
+            reserved_diverse_outbound_slots: Default::default(),
+            max_concurrent_serving_requests: Default::default(),
+            max_concurrent_serving_requests_per_peer: Default::default(),
+            max_orphan_transactions: Default::default(),
+            listen_backlog: Default::default(),
+            max_pending_requests_per_peer: Default::default(),
+            min_relay_fee_rate: c.min_relay_fee_rate.map(Amount::from_atoms).into(),
+        })
     }
 }
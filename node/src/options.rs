@@ -104,6 +104,10 @@ pub struct RunOptions {
     #[clap(long)]
     pub p2p_ping_timeout: Option<u64>,
 
+    /// Hex encoded 32 byte key used to encrypt the peer database.
+    #[clap(long, env = "ML_P2P_PEERDB_ENCRYPTION_KEY", hide_env_values = true)]
+    pub p2p_peerdb_encryption_key: Option<String>,
+
     /// A maximum tip age in seconds.
     ///
     /// The initial block download is finished if the difference between the current time and the
@@ -71,18 +71,22 @@ pub async fn initialize(
     });
 
     // P2P subsystem
+    let p2p_config: p2p::config::P2pConfig = node_config.p2p.try_into()?;
     // TODO: Replace Lmdb with Sqlite backend when it's ready
-    let peerdb_storage = PeerDbStorageImpl::new(storage_lmdb::Lmdb::new(
-        node_config.datadir.join("peerdb-lmdb"),
-        Default::default(),
-        Default::default(),
-        Default::default(),
-    ))?;
+    let peerdb_storage = PeerDbStorageImpl::new_with_encryption_key(
+        storage_lmdb::Lmdb::new(
+            node_config.datadir.join("peerdb-lmdb"),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ),
+        (*p2p_config.peerdb_encryption_key).clone(),
+    )?;
     let p2p = manager.add_subsystem(
         "p2p",
         p2p::make_p2p(
             Arc::clone(&chain_config),
-            Arc::new(node_config.p2p.into()),
+            Arc::new(p2p_config),
             chainstate.clone(),
             mempool.clone(),
             Default::default(),
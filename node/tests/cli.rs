@@ -110,6 +110,7 @@ fn read_config_override_values() {
         p2p_outbound_connection_timeout: Some(p2p_timeout),
         p2p_ping_check_period: Some(p2p_ping_check_period),
         p2p_ping_timeout: Some(p2p_ping_timeout),
+        p2p_peerdb_encryption_key: None,
         max_tip_age: Some(max_tip_age),
         http_rpc_addr: Some(http_rpc_addr),
         http_rpc_enabled: Some(true),
@@ -213,6 +214,7 @@ fn default_run_options() -> RunOptions {
         p2p_outbound_connection_timeout: None,
         p2p_ping_check_period: None,
         p2p_ping_timeout: None,
+        p2p_peerdb_encryption_key: None,
         max_tip_age: None,
         http_rpc_addr: None,
         http_rpc_enabled: None,
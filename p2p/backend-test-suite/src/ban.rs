@@ -15,6 +15,7 @@
 
 use std::{fmt::Debug, sync::Arc};
 
+use common::chain::block::{timestamp::BlockTimestamp, Block};
 use tokio::sync::mpsc;
 
 use p2p::{
@@ -44,6 +45,7 @@ where
     let chain_config = Arc::new(common::chain::config::create_unit_test_config());
     let p2p_config = Arc::new(P2pConfig::default());
     let handle = p2p_test_utils::start_chainstate(Arc::clone(&chain_config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&chain_config), handle.clone()).await;
 
     let (mut conn1, sync1) = N::start(
         T::make_transport(),
@@ -59,6 +61,7 @@ where
         Arc::clone(&p2p_config),
         sync1,
         handle.clone(),
+        mempool,
         rx_sync,
         tx_peer_manager,
     );
@@ -74,16 +77,22 @@ where
 
     let (_address, _peer_info1, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
 
-    // create few blocks so `sync2` has something to send to `sync1`
-    let best_block = TestBlockInfo::from_genesis(chain_config.genesis_block());
-    let blocks = p2p_test_utils::create_n_blocks(Arc::clone(&chain_config), best_block, 3);
+    // a block that is older than its parent is invalid
+    let block = Block::new_with_no_consensus(
+        vec![],
+        chain_config.genesis_block_id(),
+        BlockTimestamp::from_int_seconds(0),
+    )
+    .unwrap();
 
     tokio::spawn(async move {
-        sync1.register_peer(peer_info2.peer_id).await.unwrap();
+        sync1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await.unwrap();
         sync1.run().await
     });
 
-    // spawn `sync2` into background and spam an orphan block on the network
+    // spawn `sync2` into background and announce an invalid block on the network
+    let genesis_id = chain_config.genesis_block_id();
+    let magic_bytes = *chain_config.magic_bytes();
     tokio::spawn(async move {
         let request_id = match sync2.poll_next().await.unwrap() {
             SyncingEvent::Request {
@@ -96,11 +105,15 @@ where
         sync2
             .send_response(
                 request_id,
-                SyncResponse::HeaderListResponse(HeaderListResponse::new(Vec::new())),
+                SyncResponse::HeaderListResponse(HeaderListResponse::new(
+                    magic_bytes,
+                    genesis_id,
+                    Vec::new(),
+                )),
             )
             .unwrap();
 
-        sync2.make_announcement(Announcement::Block(blocks[2].clone())).unwrap();
+        sync2.make_announcement(Announcement::Block(block)).unwrap();
     });
 
     match rx_peer_manager.recv().await {
@@ -124,6 +137,7 @@ where
     let (tx_peer_manager, mut rx_peer_manager) = mpsc::unbounded_channel();
     let chain_config = Arc::new(common::chain::config::create_unit_test_config());
     let handle = p2p_test_utils::start_chainstate(Arc::clone(&chain_config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&chain_config), handle.clone()).await;
 
     let (mut conn1, sync1) = N::start(
         T::make_transport(),
@@ -148,6 +162,7 @@ where
         Arc::new(P2pConfig::default()),
         sync1,
         handle.clone(),
+        mempool,
         rx_p2p_sync,
         tx_peer_manager,
     );
@@ -161,9 +176,10 @@ where
     // register `conn2` to the `SyncManager`, process a block response
     // and verify the `PeerManager` is notified of the protocol violation
     let remote_id = peer_info2.peer_id;
+    let remote_node_kind = peer_info2.node_kind;
 
     tokio::spawn(async move {
-        sync1.register_peer(remote_id).await.unwrap();
+        sync1.register_peer(remote_id, remote_node_kind).await.unwrap();
         let res = sync1.process_block_response(remote_id, vec![blocks[2].clone()]).await;
         sync1.handle_error(remote_id, res).await.unwrap();
     });
@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use common::{
     chain::{
@@ -26,14 +26,15 @@ use common::{
         transaction::Transaction,
         TxInput,
     },
-    primitives::{Id, H256},
+    primitives::{Id, Idable, H256},
 };
 use serialization::Encode;
+use tokio::time::timeout;
 
 use p2p::{
     config::{NodeType, P2pConfig},
     error::{P2pError, PublishError},
-    message::Announcement,
+    message::{Announcement, BlockListRequest, BlockListResponse, SyncRequest, SyncResponse},
     net::{
         default_backend::constants::ANNOUNCEMENT_MAX_SIZE, types::SyncingEvent,
         ConnectivityService, NetworkingService, SyncingMessagingService,
@@ -45,6 +46,9 @@ tests![
     block_announcement,
     block_announcement_no_subscription,
     block_announcement_too_big_message,
+    block_announcement_to_subset,
+    block_announcement_observer,
+    block_headers_announcement,
 ];
 
 async fn block_announcement<T, N, A>()
@@ -130,13 +134,56 @@ where
     let p2p_config = Arc::new(P2pConfig {
         bind_addresses: Vec::new(),
         added_nodes: Vec::new(),
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: NodeType::Inactive.into(),
         allow_discover_private_ips: Default::default(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let (mut conn1, mut sync1) = N::start(
         T::make_transport(),
@@ -231,3 +278,220 @@ where
         )))
     );
 }
+
+async fn block_announcement_to_subset<T, N, A>()
+where
+    T: TestTransportMaker<Transport = N::Transport, Address = N::Address>,
+    N: NetworkingService + Debug,
+    N::SyncingMessagingHandle: SyncingMessagingService<N>,
+    N::ConnectivityHandle: ConnectivityService<N>,
+{
+    let config = Arc::new(common::chain::config::create_mainnet());
+    let (mut conn1, mut sync1) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn2, mut sync2) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn3, mut sync3) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn4, mut sync4) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let (_address, _peer_info, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
+    connect_services::<N>(&mut conn1, &mut conn3).await;
+    connect_services::<N>(&mut conn1, &mut conn4).await;
+
+    sync1
+        .announce_to(
+            &[peer_info2.peer_id],
+            Announcement::Block(
+                Block::new(
+                    vec![],
+                    Id::new(H256([0x01; 32])),
+                    BlockTimestamp::from_int_seconds(1337u64),
+                    ConsensusData::None,
+                    BlockReward::new(Vec::new()),
+                )
+                .unwrap(),
+            ),
+        )
+        .unwrap();
+
+    // Only the selected peer receives the announcement.
+    let block = match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Announcement {
+            peer_id: _,
+            announcement: Announcement::Block(block),
+        } => block,
+        _ => panic!("Unexpected event"),
+    };
+    assert_eq!(block.timestamp().as_int_seconds(), 1337u64);
+
+    assert!(timeout(Duration::from_millis(500), sync3.poll_next()).await.is_err());
+    assert!(timeout(Duration::from_millis(500), sync4.poll_next()).await.is_err());
+}
+
+async fn block_announcement_observer<T, N, A>()
+where
+    T: TestTransportMaker<Transport = N::Transport, Address = N::Address>,
+    N: NetworkingService + Debug,
+    N::SyncingMessagingHandle: SyncingMessagingService<N>,
+    N::ConnectivityHandle: ConnectivityService<N>,
+{
+    let config = Arc::new(common::chain::config::create_mainnet());
+    let (mut conn1, mut sync1) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let observer_config = Arc::new(P2pConfig {
+        observer_mode: true.into(),
+        ..Default::default()
+    });
+    let (mut conn2, mut sync2) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        observer_config,
+    )
+    .await
+    .unwrap();
+
+    let (_address, peer_info1, _peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
+
+    let block = Block::new(
+        vec![],
+        Id::new(H256([0x01; 32])),
+        BlockTimestamp::from_int_seconds(1337u64),
+        ConsensusData::None,
+        BlockReward::new(Vec::new()),
+    )
+    .unwrap();
+
+    // The observer isn't used as a relay target.
+    sync1.make_announcement(Announcement::Block(block.clone())).unwrap();
+    assert!(timeout(Duration::from_millis(500), sync2.poll_next()).await.is_err());
+
+    // But it can still request blocks.
+    sync2
+        .send_request(
+            peer_info1.peer_id,
+            SyncRequest::BlockListRequest(BlockListRequest::new(
+                *config.magic_bytes(),
+                vec![block.get_id()],
+            )),
+        )
+        .unwrap();
+
+    let request_id = match sync1.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id,
+            request: SyncRequest::BlockListRequest(request),
+        } => {
+            assert_eq!(request.block_ids(), &[block.get_id()]);
+            request_id
+        }
+        _ => panic!("Unexpected event"),
+    };
+    sync1
+        .send_response(
+            request_id,
+            SyncResponse::BlockListResponse(BlockListResponse::new(
+                *config.magic_bytes(),
+                vec![block.clone()],
+            )),
+        )
+        .unwrap();
+
+    match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Response {
+            peer_id: _,
+            request_id: _,
+            response: SyncResponse::BlockListResponse(response),
+        } => {
+            assert_eq!(response.blocks(), &[block]);
+        }
+        _ => panic!("Unexpected event"),
+    }
+}
+
+async fn block_headers_announcement<T, N, A>()
+where
+    T: TestTransportMaker<Transport = N::Transport, Address = N::Address>,
+    N: NetworkingService + Debug,
+    N::SyncingMessagingHandle: SyncingMessagingService<N>,
+    N::ConnectivityHandle: ConnectivityService<N>,
+{
+    let config = Arc::new(common::chain::config::create_mainnet());
+    let (mut conn1, mut sync1) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn2, mut sync2) = N::start(
+        T::make_transport(),
+        vec![T::make_address()],
+        Arc::clone(&config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    connect_services::<N>(&mut conn1, &mut conn2).await;
+
+    let headers = (0..3u8)
+        .map(|i| {
+            Block::new(
+                vec![],
+                Id::new(H256([i; 32])),
+                BlockTimestamp::from_int_seconds(1337u64 + i as u64),
+                ConsensusData::None,
+                BlockReward::new(Vec::new()),
+            )
+            .unwrap()
+            .header()
+            .clone()
+        })
+        .collect::<Vec<_>>();
+    sync1.make_announcement(Announcement::Blocks(headers.clone())).unwrap();
+
+    // All headers are received as a single event.
+    let received = match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Announcement {
+            peer_id: _,
+            announcement: Announcement::Blocks(headers),
+        } => headers,
+        _ => panic!("Unexpected event"),
+    };
+    assert_eq!(received, headers);
+}
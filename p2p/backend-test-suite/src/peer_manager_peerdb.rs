@@ -16,19 +16,19 @@
 // Test functions need to be marked async because of the `tests!` macro.
 #![allow(clippy::unused_async)]
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use p2p::{
     config::P2pConfig,
     net::{default_backend::types::PeerId, AsBannableAddress, NetworkingService},
     peer_manager::peerdb::{
         storage::{PeerDbStorageRead, PeerDbTransactional},
-        PeerDb,
+        AddressSource, PeerDb,
     },
     testing_utils::{peerdb_inmemory_store, P2pTestTimeGetter, RandomAddressMaker},
 };
 
-tests![unban_peer,];
+tests![unban_peer, known_addresses,];
 
 async fn unban_peer<T, N, A>()
 where
@@ -41,13 +41,56 @@ where
         Arc::new(P2pConfig {
             bind_addresses: Default::default(),
             added_nodes: Default::default(),
+            preconfigured_peers: Default::default(),
             ban_threshold: Default::default(),
             ban_duration: Duration::from_secs(60).into(),
             outbound_connection_timeout: Default::default(),
+            handshake_timeout: Default::default(),
             ping_check_period: Default::default(),
             ping_timeout: Default::default(),
+            ping_max_retries: Default::default(),
             node_type: Default::default(),
             allow_discover_private_ips: Default::default(),
+            observer_mode: Default::default(),
+            max_outbound_announcement_queue: Default::default(),
+            node_kind: Default::default(),
+            health_min_peers: Default::default(),
+            health_max_tip_age: Default::default(),
+            max_send_bytes_per_second: Default::default(),
+            max_addresses: Default::default(),
+            advertise_self: Default::default(),
+            max_active_connections: Default::default(),
+            max_concurrent_dials: Default::default(),
+            stall_grace_period: Default::default(),
+            min_protocol_version: Default::default(),
+            max_announcer_tip_lag: Default::default(),
+            dns_seeds: Default::default(),
+            dns_seed_refresh_interval: Default::default(),
+            send_timeout: Default::default(),
+            max_handshake_size: Default::default(),
+            disconnect_on_protocol_error: Default::default(),
+            max_addr_per_message: Default::default(),
+            max_message_size: Default::default(),
+            max_relay_peers: Default::default(),
+            max_orphan_announcements: Default::default(),
+            peer_selection_recent_period: Default::default(),
+            peer_selection_recent_weight: Default::default(),
+            peer_selection_diverse_weight: Default::default(),
+            peer_selection_failure_penalty: Default::default(),
+            disable_peer_discovery: Default::default(),
+            peerdb_encryption_key: Default::default(),
+            announcement_trickle_interval: Default::default(),
+            max_pending_requests: Default::default(),
+            stale_tip_timeout: Default::default(),
+            header_request_timeout: Default::default(),
+            block_request_timeout: Default::default(),
+            reserved_diverse_outbound_slots: Default::default(),
+            max_concurrent_serving_requests: Default::default(),
+            max_concurrent_serving_requests_per_peer: Default::default(),
+            max_orphan_transactions: Default::default(),
+            listen_backlog: Default::default(),
+            max_pending_requests_per_peer: Default::default(),
+            min_relay_fee_rate: Default::default(),
         }),
         time_getter.get_time_getter(),
         db_store,
@@ -77,3 +120,52 @@ where
         .unwrap();
     assert_eq!(banned_addresses.len(), 0);
 }
+
+async fn known_addresses<T, N, A>()
+where
+    N: NetworkingService<PeerId = PeerId>,
+    A: RandomAddressMaker<Address = N::Address>,
+{
+    let db_store = peerdb_inmemory_store();
+    let time_getter = P2pTestTimeGetter::new();
+    let added_node = A::new();
+    let mut peerdb = PeerDb::<N, _>::new(
+        Arc::new(P2pConfig {
+            added_nodes: vec![added_node.to_string()],
+            ..Default::default()
+        }),
+        time_getter.get_time_getter(),
+        db_store,
+    )
+    .unwrap();
+
+    let discovered = [A::new(), A::new(), A::new()];
+    for address in &discovered {
+        peerdb.peer_discovered(address, AddressSource::AddressExchange).unwrap();
+    }
+    // Discovering an already known address doesn't change its source
+    peerdb.peer_discovered(&added_node, AddressSource::AddressExchange).unwrap();
+    peerdb.report_outbound_failure(discovered[0].clone());
+    peerdb.ban_peer(&discovered[1]).unwrap();
+
+    let known_addresses = peerdb.known_addresses().collect::<BTreeMap<_, _>>();
+    assert_eq!(known_addresses.len(), 4);
+
+    let state = &known_addresses[&added_node];
+    assert_eq!(state.source, AddressSource::Manual);
+    assert!(state.last_seen.is_some());
+    assert!(!state.banned);
+
+    for address in &discovered {
+        let state = &known_addresses[address];
+        assert_eq!(state.source, AddressSource::AddressExchange);
+        assert_eq!(
+            state.last_seen,
+            Some(time_getter.get_time_getter().get_time())
+        );
+    }
+    assert_eq!(known_addresses[&discovered[0]].failure_count, 1);
+    assert_eq!(known_addresses[&discovered[1]].failure_count, 0);
+    assert!(known_addresses[&discovered[1]].banned);
+    assert!(!known_addresses[&discovered[2]].banned);
+}
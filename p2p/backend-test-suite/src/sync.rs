@@ -82,7 +82,10 @@ where
 
     // connect the two managers together so that they can exchange messages
     let (_address, _peer_info1, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
 
     // ensure that only a header request is received from the remote and
     // as the nodes are tracking the same chain, no further messages are exchanged
@@ -136,7 +139,10 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     let (_address, _peer_info1, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..9 {
@@ -153,16 +159,15 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr2_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr2_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
                 mgr2.handle_mut()
-                    .send_response(
-                        request_id,
-                        SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-                    )
+                    .send_response(request_id, SyncResponse::HeaderListResponse(response))
                     .unwrap()
             }
             SyncingEvent::Request {
@@ -181,7 +186,10 @@ where
                 mgr2.handle_mut()
                     .send_response(
                         request_id,
-                        SyncResponse::BlockListResponse(BlockListResponse::new(blocks)),
+                        SyncResponse::BlockListResponse(BlockListResponse::new(
+                            *config.magic_bytes(),
+                            blocks,
+                        )),
                     )
                     .unwrap();
             }
@@ -228,8 +236,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     let (_address, peer_info, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info.peer_id, peer_info.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..14 {
@@ -248,16 +262,15 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr2_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr2_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
                 mgr2.handle_mut()
-                    .send_response(
-                        request_id,
-                        SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-                    )
+                    .send_response(request_id, SyncResponse::HeaderListResponse(response))
                     .unwrap()
             }
             SyncingEvent::Response {
@@ -277,7 +290,10 @@ where
                     mgr2.handle_mut()
                         .send_request(
                             peer_id,
-                            SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                            SyncRequest::BlockListRequest(BlockListRequest::new(
+                                *config.magic_bytes(),
+                                vec![header],
+                            )),
                         )
                         .unwrap();
                 } else {
@@ -301,7 +317,10 @@ where
                 mgr2.handle_mut()
                     .send_request(
                         peer_id,
-                        SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                        SyncRequest::BlockListRequest(BlockListRequest::new(
+                            *config.magic_bytes(),
+                            vec![header],
+                        )),
                     )
                     .unwrap();
             }
@@ -350,8 +369,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     let (_address, peer_info, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info.peer_id, peer_info.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..24 {
@@ -370,16 +395,15 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr2_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr2_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
                 mgr2.handle_mut()
-                    .send_response(
-                        request_id,
-                        SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-                    )
+                    .send_response(request_id, SyncResponse::HeaderListResponse(response))
                     .unwrap()
             }
             SyncingEvent::Request {
@@ -398,7 +422,10 @@ where
                 mgr2.handle_mut()
                     .send_response(
                         request_id,
-                        SyncResponse::BlockListResponse(BlockListResponse::new(blocks)),
+                        SyncResponse::BlockListResponse(BlockListResponse::new(
+                            *config.magic_bytes(),
+                            blocks,
+                        )),
                     )
                     .unwrap();
             }
@@ -419,7 +446,10 @@ where
                     mgr2.handle_mut()
                         .send_request(
                             peer_id,
-                            SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                            SyncRequest::BlockListRequest(BlockListRequest::new(
+                                *config.magic_bytes(),
+                                vec![header],
+                            )),
                         )
                         .unwrap();
                 }
@@ -439,7 +469,10 @@ where
                 mgr2.handle_mut()
                     .send_request(
                         peer_id,
-                        SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                        SyncRequest::BlockListRequest(BlockListRequest::new(
+                            *config.magic_bytes(),
+                            vec![header],
+                        )),
                     )
                     .unwrap();
             }
@@ -490,8 +523,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     let (_address, peer_info, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info.peer_id, peer_info.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..20 {
@@ -510,16 +549,15 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr2_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr2_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
                 mgr2.handle_mut()
-                    .send_response(
-                        request_id,
-                        SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-                    )
+                    .send_response(request_id, SyncResponse::HeaderListResponse(response))
                     .unwrap()
             }
             SyncingEvent::Request {
@@ -538,7 +576,10 @@ where
                 mgr2.handle_mut()
                     .send_response(
                         request_id,
-                        SyncResponse::BlockListResponse(BlockListResponse::new(blocks)),
+                        SyncResponse::BlockListResponse(BlockListResponse::new(
+                            *config.magic_bytes(),
+                            blocks,
+                        )),
                     )
                     .unwrap();
             }
@@ -559,7 +600,10 @@ where
                     mgr2.handle_mut()
                         .send_request(
                             peer_id,
-                            SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                            SyncRequest::BlockListRequest(BlockListRequest::new(
+                                *config.magic_bytes(),
+                                vec![header],
+                            )),
                         )
                         .unwrap();
                 }
@@ -579,7 +623,10 @@ where
                 mgr2.handle_mut()
                     .send_request(
                         peer_id,
-                        SyncRequest::BlockListRequest(BlockListRequest::new(vec![header])),
+                        SyncRequest::BlockListRequest(BlockListRequest::new(
+                            *config.magic_bytes(),
+                            vec![header],
+                        )),
                     )
                     .unwrap();
             }
@@ -642,10 +689,22 @@ where
     let (_address, peer_info12, peer_info21) = connect_services::<N>(&mut conn1, &mut conn2).await;
     let (_address, peer_info13, peer_info31) = connect_services::<N>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(peer_info21.peer_id).await, Ok(()));
-    assert_eq!(mgr1.register_peer(peer_info31.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info12.peer_id).await, Ok(()));
-    assert_eq!(mgr3.register_peer(peer_info13.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info21.peer_id, peer_info21.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(peer_info31.peer_id, peer_info31.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info12.peer_id, peer_info12.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(peer_info13.peer_id, peer_info13.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..18 {
@@ -667,12 +726,14 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
-                let msg = SyncResponse::HeaderListResponse(HeaderListResponse::new(headers));
+                let msg = SyncResponse::HeaderListResponse(response);
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap()
@@ -687,12 +748,15 @@ where
             } => {
                 assert_eq!(request.block_ids().len(), 1);
                 let id = request.block_ids()[0];
-                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(vec![mgr_handle
-                    .call(move |this| this.get_block(id))
-                    .await
-                    .unwrap()
-                    .unwrap()
-                    .unwrap()]));
+                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(
+                    *config.magic_bytes(),
+                    vec![mgr_handle
+                        .call(move |this| this.get_block(id))
+                        .await
+                        .unwrap()
+                        .unwrap()
+                        .unwrap()],
+                ));
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap();
@@ -773,10 +837,22 @@ where
     let (_address, peer_info12, peer_info21) = connect_services::<N>(&mut conn1, &mut conn2).await;
     let (_address, peer_info13, peer_info31) = connect_services::<N>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(peer_info21.peer_id).await, Ok(()));
-    assert_eq!(mgr1.register_peer(peer_info31.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info12.peer_id).await, Ok(()));
-    assert_eq!(mgr3.register_peer(peer_info13.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info21.peer_id, peer_info21.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(peer_info31.peer_id, peer_info31.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info12.peer_id, peer_info12.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(peer_info13.peer_id, peer_info13.node_kind).await,
+        Ok(())
+    );
 
     let (tx, mut rx) = mpsc::unbounded_channel();
     let handle = tokio::spawn(async move {
@@ -806,12 +882,14 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
-                let msg = SyncResponse::HeaderListResponse(HeaderListResponse::new(headers));
+                let msg = SyncResponse::HeaderListResponse(response);
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap()
@@ -826,12 +904,15 @@ where
             } => {
                 assert_eq!(request.block_ids().len(), 1);
                 let id = request.block_ids()[0];
-                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(vec![mgr_handle
-                    .call(move |this| this.get_block(id))
-                    .await
-                    .unwrap()
-                    .unwrap()
-                    .unwrap()]));
+                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(
+                    *config.magic_bytes(),
+                    vec![mgr_handle
+                        .call(move |this| this.get_block(id))
+                        .await
+                        .unwrap()
+                        .unwrap()
+                        .unwrap()],
+                ));
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap();
@@ -908,10 +989,22 @@ where
     let (_address, peer_info12, peer_info21) = connect_services::<N>(&mut conn1, &mut conn2).await;
     let (_address, peer_info13, peer_info31) = connect_services::<N>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(peer_info21.peer_id).await, Ok(()));
-    assert_eq!(mgr1.register_peer(peer_info31.peer_id).await, Ok(()));
-    assert_eq!(mgr2.register_peer(peer_info12.peer_id).await, Ok(()));
-    assert_eq!(mgr3.register_peer(peer_info13.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info21.peer_id, peer_info21.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(peer_info31.peer_id, peer_info31.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(peer_info12.peer_id, peer_info12.node_kind).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(peer_info13.peer_id, peer_info13.node_kind).await,
+        Ok(())
+    );
 
     let (tx, mut rx) = mpsc::unbounded_channel();
     let mut gethdr_received = HashSet::new();
@@ -943,12 +1036,14 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
-                let msg = SyncResponse::HeaderListResponse(HeaderListResponse::new(headers));
+                let msg = SyncResponse::HeaderListResponse(response);
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap()
@@ -973,12 +1068,15 @@ where
             } => {
                 assert_eq!(request.block_ids().len(), 1);
                 let id = request.block_ids()[0];
-                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(vec![mgr_handle
-                    .call(move |this| this.get_block(id))
-                    .await
-                    .unwrap()
-                    .unwrap()
-                    .unwrap()]));
+                let msg = SyncResponse::BlockListResponse(BlockListResponse::new(
+                    *config.magic_bytes(),
+                    vec![mgr_handle
+                        .call(move |this| this.get_block(id))
+                        .await
+                        .unwrap()
+                        .unwrap()
+                        .unwrap()],
+                ));
 
                 if dest_peer_id == peer_info21.peer_id {
                     mgr2.handle_mut().send_response(request_id, msg).unwrap();
@@ -1032,7 +1130,10 @@ where
     .await;
 
     let (_address, _peer_info1, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
 
     // ensure that only a header request is received from the remote and
     // as the nodes are tracking the same chain, no further messages are exchanged
@@ -1065,7 +1166,10 @@ where
     p2p_test_utils::import_blocks(&mgr2_handle, blocks.clone()).await;
 
     let (_address, _peer_info1, peer_info2) = connect_services::<N>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(peer_info2.peer_id).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(peer_info2.peer_id, peer_info2.node_kind).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..9 {
@@ -1080,16 +1184,15 @@ where
                 request_id,
                 request: SyncRequest::HeaderListRequest(request),
             } => {
-                let headers = mgr2_handle
-                    .call(move |this| this.get_headers(request.into_locator()))
+                let response = mgr2_handle
+                    .call(move |this| {
+                        HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                    })
                     .await
                     .unwrap()
                     .unwrap();
                 mgr2.handle_mut()
-                    .send_response(
-                        request_id,
-                        SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-                    )
+                    .send_response(request_id, SyncResponse::HeaderListResponse(response))
                     .unwrap()
             }
             SyncingEvent::Request {
@@ -1108,7 +1211,10 @@ where
                 mgr2.handle_mut()
                     .send_response(
                         request_id,
-                        SyncResponse::BlockListResponse(BlockListResponse::new(blocks)),
+                        SyncResponse::BlockListResponse(BlockListResponse::new(
+                            *config.magic_bytes(),
+                            blocks,
+                        )),
                     )
                     .unwrap();
             }
@@ -1159,12 +1265,16 @@ where
     .await
     .unwrap();
 
+    let mempool =
+        p2p_test_utils::start_mempool(Arc::clone(&chain_config), chainstate.clone()).await;
+
     (
         BlockSyncManager::<T>::new(
             chain_config,
             p2p_config,
             sync,
             chainstate,
+            mempool,
             rx_p2p_sync,
             tx_peer_manager,
         ),
@@ -1247,15 +1357,15 @@ where
             request_id,
             request: SyncRequest::HeaderListRequest(request),
         } => {
-            let headers = handle
-                .call(move |this| this.get_headers(request.into_locator()))
+            let response = handle
+                .call(move |this| {
+                    HeaderListResponse::from_chainstate(this.as_ref(), request.into_locator())
+                })
                 .await
                 .unwrap()
                 .unwrap();
-            mgr.handle_mut().send_response(
-                request_id,
-                SyncResponse::HeaderListResponse(HeaderListResponse::new(headers)),
-            )
+            mgr.handle_mut()
+                .send_response(request_id, SyncResponse::HeaderListResponse(response))
         }
         _ => panic!("invalid message"),
     }
@@ -1287,7 +1397,7 @@ where
             response,
         } => match response {
             SyncResponse::HeaderListResponse(response) => {
-                mgr.process_header_response(peer_id, response.into_headers()).await?;
+                mgr.process_header_response(peer_id, response).await?;
             }
             SyncResponse::BlockListResponse(response) => {
                 mgr.process_block_response(peer_id, response.into_blocks()).await?;
@@ -0,0 +1,102 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares the streaming block list decoder with decoding the whole `Vec<Block>` at once
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use common::{
+    chain::{
+        block::{consensus_data::ConsensusData, timestamp::BlockTimestamp, BlockReward},
+        signature::inputsig::InputWitness,
+        tokens::OutputValue,
+        Block, Destination, OutPointSourceId, OutputPurpose, SignedTransaction, Transaction,
+        TxInput, TxOutput,
+    },
+    primitives::{Amount, Id, H256},
+};
+use p2p::message::{decode_blocks_from, BlockListResponse};
+use parity_scale_codec::IoReader;
+use serialization::{Decode, Encode};
+
+const MAGIC_BYTES: [u8; 4] = [0x1a, 0x64, 0xe5, 0xf1];
+
+fn make_block(timestamp: u64, tx_count: usize) -> Block {
+    let transactions = (0..tx_count)
+        .map(|i| {
+            let input = TxInput::new(
+                OutPointSourceId::Transaction(Id::new(H256([0x02; 32]))),
+                i as u32,
+            );
+            let output = TxOutput::new(
+                OutputValue::Coin(Amount::from_atoms(i as u128)),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            );
+            let tx = Transaction::new(0, vec![input], vec![output], 0).unwrap();
+            SignedTransaction::new(tx, vec![InputWitness::NoSignature(Some(vec![0x01; 100]))])
+                .unwrap()
+        })
+        .collect();
+    Block::new(
+        transactions,
+        Id::new(H256([0x01; 32])),
+        BlockTimestamp::from_int_seconds(timestamp),
+        ConsensusData::None,
+        BlockReward::new(vec![]),
+    )
+    .unwrap()
+}
+
+fn blocks_decode(c: &mut Criterion) {
+    let blocks = (0..100).map(|i| make_block(i, 100)).collect::<Vec<_>>();
+    let encoded = BlockListResponse::new(MAGIC_BYTES, blocks).encode();
+
+    let mut group = c.benchmark_group("blocks_decode");
+
+    // The naive path keeps the whole list of blocks in memory before the first one is processed
+    group.bench_function("vec_from_slice", |b| {
+        b.iter(|| {
+            let response = <([u8; 4], Vec<Block>)>::decode(&mut encoded.as_slice()).unwrap();
+            response.1.into_iter().for_each(drop)
+        })
+    });
+    group.bench_function("stream_from_slice", |b| {
+        b.iter(|| decode_blocks_from(encoded.as_slice()).for_each(|block| drop(block.unwrap())))
+    });
+
+    // Reading from a stream, the naive path reads the input a few bytes at a time
+    group.bench_function("vec_from_reader", |b| {
+        b.iter_batched(
+            || std::io::Cursor::new(&encoded),
+            |reader| {
+                let response = <([u8; 4], Vec<Block>)>::decode(&mut IoReader(reader)).unwrap();
+                response.1.into_iter().for_each(drop)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("stream_from_reader", |b| {
+        b.iter_batched(
+            || std::io::Cursor::new(&encoded),
+            |reader| decode_blocks_from(reader).for_each(|block| drop(block.unwrap())),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, blocks_decode);
+criterion_main!(benches);
@@ -27,7 +27,7 @@ use common::{
         config::ChainConfig,
         signature::inputsig::InputWitness,
         signed_transaction::SignedTransaction,
-        tokens::OutputValue,
+        tokens::{token_id, OutputValue, TokenId, TokenIssuance},
         transaction::Transaction,
         Destination, GenBlock, GenBlockId, Genesis, OutPointSourceId, OutputPurpose, TxInput,
         TxOutput,
@@ -35,6 +35,7 @@ use common::{
     primitives::{time, Amount, Id, Idable},
 };
 use crypto::random::SliceRandom;
+use mempool::{MempoolHandle, MempoolSubsystemInterface};
 
 pub type ChainstateHandle = subsystem::Handle<Box<dyn ChainstateInterface + 'static>>;
 
@@ -184,10 +185,80 @@ pub async fn start_chainstate(
     handle
 }
 
+pub async fn start_mempool(
+    chain_config: Arc<ChainConfig>,
+    chainstate_handle: ChainstateHandle,
+) -> MempoolHandle {
+    let mempool = mempool::make_mempool(
+        chain_config,
+        chainstate_handle,
+        Default::default(),
+        mempool::SystemUsageEstimator {},
+    );
+    let mut man = subsystem::Manager::new("TODO");
+    let handle = man.add_subsystem_with_custom_eventloop("mempool", move |call, shutdn| {
+        mempool.run(call, shutdn)
+    });
+    tokio::spawn(async move { man.main().await });
+    handle
+}
+
 pub fn create_block(config: Arc<ChainConfig>, parent: TestBlockInfo) -> Block {
     produce_test_block(&config, parent)
 }
 
+/// Creates a block that issues a new fungible token
+///
+/// The first coin output of the parent block is spent to pay the issuance fee.
+pub fn create_token_issuance_block(
+    config: Arc<ChainConfig>,
+    parent: TestBlockInfo,
+    issuance: TokenIssuance,
+) -> (Block, TokenId) {
+    let (outsrc, index, amount) = parent
+        .txns
+        .iter()
+        .flat_map(|(outsrc, outs)| {
+            outs.iter().enumerate().filter_map(move |(index, output)| match output.value() {
+                OutputValue::Coin(amount) => Some((outsrc.clone(), index, *amount)),
+                OutputValue::Token(_) => None,
+            })
+        })
+        .next()
+        .expect("parent block has no coin outputs");
+    let change = (amount - config.token_min_issuance_fee()).expect("not enough coins to issue");
+
+    let tx = Transaction::new(
+        0,
+        vec![TxInput::new(outsrc, index as u32)],
+        vec![
+            TxOutput::new(
+                issuance.into(),
+                OutputPurpose::Transfer(anyonecanspend_address()),
+            ),
+            TxOutput::new(
+                OutputValue::Coin(change),
+                OutputPurpose::Transfer(anyonecanspend_address()),
+            ),
+        ],
+        0,
+    )
+    .expect("not to fail");
+    let token_id = token_id(&tx).expect("token id");
+
+    let block = Block::new(
+        vec![SignedTransaction::new(tx, vec![nosig_random_witness()])
+            .expect("invalid witness count")],
+        parent.id,
+        BlockTimestamp::from_duration_since_epoch(time::get()),
+        ConsensusData::None,
+        BlockReward::new(Vec::new()),
+    )
+    .expect("not to fail");
+
+    (block, token_id)
+}
+
 pub fn create_n_blocks(
     config: Arc<ChainConfig>,
     mut prev: TestBlockInfo,
@@ -13,17 +13,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeSet, time::Duration};
+use std::{collections::BTreeSet, num::NonZeroU64, time::Duration};
 
+use common::primitives::{semver::SemVer, Amount};
+use crypto::symkey::SymmetricKey;
 use utils::make_config_setting;
 
-use crate::net::types::PubSubTopic;
+use crate::{
+    constants::MAX_MESSAGE_SIZE,
+    net::default_backend::transport::DEFAULT_LISTEN_BACKLOG,
+    net::types::{NodeKind, PubSubTopic},
+};
 
 pub const DEFAULT_BIND_PORT: u16 = 3031;
 
 make_config_setting!(BanThreshold, u32, 100);
 make_config_setting!(BanDuration, Duration, Duration::from_secs(60 * 60 * 24));
 make_config_setting!(OutboundConnectionTimeout, Duration, Duration::from_secs(10));
+make_config_setting!(HandshakeTimeout, Duration, Duration::from_secs(10));
 make_config_setting!(
     AnnouncementSubscriptions,
     BTreeSet<PubSubTopic>,
@@ -33,6 +40,55 @@ make_config_setting!(NodeTypeSetting, NodeType, NodeType::Full);
 make_config_setting!(AllowDiscoverPrivateIps, bool, false);
 make_config_setting!(PingCheckPeriod, Duration, Duration::from_secs(60));
 make_config_setting!(PingTimeout, Duration, Duration::from_secs(150));
+make_config_setting!(PingMaxRetries, u32, 0);
+make_config_setting!(ObserverMode, bool, false);
+make_config_setting!(MaxOutboundAnnouncementQueue, usize, 16);
+make_config_setting!(NodeKindSetting, NodeKind, NodeKind::Full);
+make_config_setting!(HealthMinPeers, usize, 3);
+make_config_setting!(HealthMaxTipAge, Duration, Duration::from_secs(60 * 60));
+make_config_setting!(MaxSendBytesPerSecond, Option<NonZeroU64>, None);
+make_config_setting!(MaxAddresses, usize, 2000);
+make_config_setting!(AdvertiseSelf, bool, false);
+make_config_setting!(MaxActiveConnections, usize, 128);
+make_config_setting!(MaxConcurrentDials, usize, 8);
+make_config_setting!(StallGracePeriod, Duration, Duration::ZERO);
+make_config_setting!(MinProtocolVersion, SemVer, SemVer::new(0, 0, 0));
+make_config_setting!(MaxAnnouncerTipLag, u64, 10);
+make_config_setting!(DnsSeeds, Vec<String>, Vec::new());
+make_config_setting!(
+    DnsSeedRefreshInterval,
+    Duration,
+    Duration::from_secs(60 * 60)
+);
+make_config_setting!(SendTimeout, Duration, Duration::from_secs(30));
+make_config_setting!(MaxHandshakeSize, usize, 64 * 1024);
+make_config_setting!(DisconnectOnProtocolError, bool, true);
+make_config_setting!(MaxAddrPerMessage, usize, 1000);
+make_config_setting!(MaxMessageSize, usize, MAX_MESSAGE_SIZE);
+make_config_setting!(MaxRelayPeers, Option<usize>, None);
+make_config_setting!(MaxOrphanAnnouncements, usize, 100);
+make_config_setting!(
+    PeerSelectionRecentPeriod,
+    Duration,
+    Duration::from_secs(60 * 60 * 24)
+);
+make_config_setting!(PeerSelectionRecentWeight, u32, 4);
+make_config_setting!(PeerSelectionDiverseWeight, u32, 2);
+make_config_setting!(PeerSelectionFailurePenalty, u32, 1);
+make_config_setting!(DisablePeerDiscovery, bool, false);
+make_config_setting!(PeerDbEncryptionKey, Option<SymmetricKey>, None);
+make_config_setting!(AnnouncementTrickleInterval, Duration, Duration::ZERO);
+make_config_setting!(MaxPendingRequests, usize, 10_000);
+make_config_setting!(MaxPendingRequestsPerPeer, usize, 100);
+make_config_setting!(StaleTipTimeout, Duration, Duration::from_secs(30 * 60));
+make_config_setting!(HeaderRequestTimeout, Duration, Duration::from_secs(30));
+make_config_setting!(BlockRequestTimeout, Duration, Duration::from_secs(2 * 60));
+make_config_setting!(ReservedDiverseOutboundSlots, usize, 2);
+make_config_setting!(MaxConcurrentServingRequests, usize, 64);
+make_config_setting!(MaxConcurrentServingRequestsPerPeer, usize, 4);
+make_config_setting!(MaxOrphanTransactions, usize, 100);
+make_config_setting!(ListenBacklog, u32, DEFAULT_LISTEN_BACKLOG);
+make_config_setting!(MinRelayFeeRate, Amount, Amount::ZERO);
 
 /// A node type.
 #[derive(Debug, Copy, Clone)]
@@ -66,18 +122,142 @@ pub struct P2pConfig {
     pub bind_addresses: Vec<String>,
     /// Optional list of initial node addresses, could be used to specify boot nodes for example.
     pub added_nodes: Vec<String>,
+    /// Peers that are always kept connected, for example the members of a private network.
+    /// They are dialed as soon as the node starts and reconnected when the connection is lost.
+    pub preconfigured_peers: Vec<String>,
     /// The score threshold after which a peer is banned.
     pub ban_threshold: BanThreshold,
     /// Duration of bans in seconds.
     pub ban_duration: BanDuration,
     /// The outbound connection timeout value in seconds.
     pub outbound_connection_timeout: OutboundConnectionTimeout,
+    /// How long to wait for the handshake to complete (for both inbound and outbound connections).
+    pub handshake_timeout: HandshakeTimeout,
     /// How often send ping requests to peers
     pub ping_check_period: PingCheckPeriod,
     /// When a peer is detected as dead and disconnected
     pub ping_timeout: PingTimeout,
+    /// How many times a ping request is resent after a timeout before the peer is disconnected
+    pub ping_max_retries: PingMaxRetries,
     /// A node type.
     pub node_type: NodeTypeSetting,
     /// Allow announcing and discovering local and private IPs. Should be used for testing only.
     pub allow_discover_private_ips: AllowDiscoverPrivateIps,
+    /// Mark this node as an observer in handshakes, so that peers don't use it as a relay target
+    /// for announcements (but still serve its requests).
+    pub observer_mode: ObserverMode,
+    /// Maximum number of outbound announcements queued per peer. When the queue is full,
+    /// the oldest announcement is dropped.
+    pub max_outbound_announcement_queue: MaxOutboundAnnouncementQueue,
+    /// Whether the node is a full or a pruned node. Reported to peers during the handshake.
+    pub node_kind: NodeKindSetting,
+    /// Minimum number of active peers for the node to be reported as healthy.
+    pub health_min_peers: HealthMinPeers,
+    /// Maximum time since the last received block for the node to be reported as healthy.
+    pub health_max_tip_age: HealthMaxTipAge,
+    /// Maximum total number of bytes per second sent to all peers, unlimited if not set.
+    pub max_send_bytes_per_second: MaxSendBytesPerSecond,
+    /// Maximum number of known addresses stored in the peer database.
+    pub max_addresses: MaxAddresses,
+    /// Include the discovered own addresses in the address list responses and periodically
+    /// announce them to random peers, so that the node becomes discoverable.
+    pub advertise_self: AdvertiseSelf,
+    /// Maximum number of connections the peer manager is allowed to have open.
+    pub max_active_connections: MaxActiveConnections,
+    /// Maximum number of outbound connection attempts made by the heartbeat at the same time.
+    pub max_concurrent_dials: MaxConcurrentDials,
+    /// Once the last ping request has timed out, the peer is only disconnected if nothing was
+    /// received from it during this period. The peer is disconnected right away if zero.
+    pub stall_grace_period: StallGracePeriod,
+    /// Handshakes from peers with an older protocol version are rejected.
+    pub min_protocol_version: MinProtocolVersion,
+    /// Block announcements from peers whose tip is more than this number of blocks behind
+    /// the local tip are ignored.
+    pub max_announcer_tip_lag: MaxAnnouncerTipLag,
+    /// DNS seeds (`host:port`) that are resolved to the addresses of the nodes to connect to.
+    pub dns_seeds: DnsSeeds,
+    /// How often the DNS seeds are resolved while the node doesn't have enough peers.
+    /// The interval grows for a seed after each failed lookup.
+    pub dns_seed_refresh_interval: DnsSeedRefreshInterval,
+    /// Maximum time a single message write to the peer socket may take. The peer is
+    /// disconnected if the write doesn't complete in time.
+    pub send_timeout: SendTimeout,
+    /// Maximum size in bytes of the handshake message received from a new peer. The connection
+    /// is dropped without decoding the message if the peer sends a larger one.
+    pub max_handshake_size: MaxHandshakeSize,
+    /// Disconnect the peer that sends a message that can't be decoded. If disabled, the message
+    /// is dropped and the peer score is slightly increased instead (could be useful while
+    /// the network is being upgraded).
+    pub disconnect_on_protocol_error: DisconnectOnProtocolError,
+    /// Maximum number of addresses sent in a single address list response. Peers that send
+    /// more are penalized and the excess addresses are ignored.
+    pub max_addr_per_message: MaxAddrPerMessage,
+    /// Maximum size in bytes of a message received from a peer after the handshake. The peer
+    /// that sends a larger message is reported as misbehaving and disconnected. Messages larger
+    /// than the protocol limit are always rejected.
+    pub max_message_size: MaxMessageSize,
+    /// Maximum number of peers an announcement is relayed to, all subscribed peers if not set.
+    /// The peers that haven't been relayed to on the topic for the longest time are preferred.
+    pub max_relay_peers: MaxRelayPeers,
+    /// Maximum number of announced blocks with an unknown parent kept until the parent arrives.
+    /// The oldest ones are evicted first, a peer that fills most of the cache is penalized.
+    pub max_orphan_announcements: MaxOrphanAnnouncements,
+    /// Addresses seen or connected to within this period are considered recent when selecting
+    /// the addresses for new outbound connections.
+    pub peer_selection_recent_period: PeerSelectionRecentPeriod,
+    /// How many times a recent address is more likely to be selected for a new outbound connection.
+    pub peer_selection_recent_weight: PeerSelectionRecentWeight,
+    /// How many times the only known address from its address group is more likely to be selected
+    /// for a new outbound connection.
+    pub peer_selection_diverse_weight: PeerSelectionDiverseWeight,
+    /// The selection weight of an address is divided by `1 + penalty * failed attempts`,
+    /// so the addresses that fail to connect are tried less often (but still occasionally).
+    pub peer_selection_failure_penalty: PeerSelectionFailurePenalty,
+    /// Don't learn new addresses from the DNS seeds and the other peers and don't make outbound
+    /// connections to the known addresses, only the preconfigured peers and the peers added by
+    /// the node operator are connected. Useful for closed networks.
+    pub disable_peer_discovery: DisablePeerDiscovery,
+    /// The key used to encrypt the records of the peer database storage (see
+    /// [`crate::peer_manager::peerdb::storage_impl::PeerDbStorageImpl::new_with_encryption_key`]),
+    /// the records are stored unencrypted if not set.
+    pub peerdb_encryption_key: PeerDbEncryptionKey,
+    /// Average delay before the relayed announcements are sent to the outbound peers, the actual
+    /// delay is random (between the half and one and a half of the interval). The announcements
+    /// that accumulate during the delay are sent together. Inbound peers and the outbound peers
+    /// are not delayed if zero. Capped to ten minutes.
+    pub announcement_trickle_interval: AnnouncementTrickleInterval,
+    /// Maximum number of inbound requests of all peers waiting for a response, the requests
+    /// received over the limit are ignored. Limits the memory used to track the requests.
+    pub max_pending_requests: MaxPendingRequests,
+    /// If no new tip is received for this long, the node makes a few short-lived connections to
+    /// new peers to check that it isn't eclipsed and its peers aren't stalled.
+    pub stale_tip_timeout: StaleTipTimeout,
+    /// How long the sync manager waits for a response to a header request before the peer is
+    /// disconnected. Headers are small, so the timeout is short.
+    pub header_request_timeout: HeaderRequestTimeout,
+    /// How long the sync manager waits for a response to a block request before the peer is
+    /// disconnected. Blocks can be large and are sent in chunks, so they get more time.
+    pub block_request_timeout: BlockRequestTimeout,
+    /// Number of outbound connections (out of [`P2pConfig::max_active_connections`]) that are
+    /// only made to the address groups not represented among the other outbound peers. Makes it
+    /// harder to eclipse the node with many addresses from a few networks.
+    pub reserved_diverse_outbound_slots: ReservedDiverseOutboundSlots,
+    /// Maximum number of sync requests of all peers that are answered from the chainstate
+    /// concurrently. The requests over the limit are rejected as rate limited.
+    pub max_concurrent_serving_requests: MaxConcurrentServingRequests,
+    /// Maximum number of sync requests of one peer that are answered from the chainstate
+    /// concurrently. The requests over the limit are rejected as rate limited.
+    pub max_concurrent_serving_requests_per_peer: MaxConcurrentServingRequestsPerPeer,
+    /// Maximum number of relayed transactions with unknown inputs kept until their parents arrive.
+    /// The oldest ones are evicted first, a peer that fills most of the pool is penalized.
+    pub max_orphan_transactions: MaxOrphanTransactions,
+    /// Maximum number of pending incoming TCP connections (the `listen` backlog), the OS may
+    /// adjust the value.
+    pub listen_backlog: ListenBacklog,
+    /// Maximum number of inbound requests of a single peer waiting for a response, checked before
+    /// [`Self::max_pending_requests`] so that a few peers can't take all of the slots.
+    pub max_pending_requests_per_peer: MaxPendingRequestsPerPeer,
+    /// The minimum fee per 1000 bytes of a relayed transaction. The cheaper transactions are
+    /// ignored without punishing the peer that sent them.
+    pub min_relay_fee_rate: MinRelayFeeRate,
 }
@@ -27,3 +27,11 @@ pub const PING_MAX_RETRIES: u32 = 3;
 
 /// Maximum message size
 pub const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Maximum total size of the blocks sent in one block response message.
+///
+/// Larger responses are split into chunks, some space is left for the message headers.
+pub const MAX_BLOCKS_CHUNK_SIZE: usize = MAX_MESSAGE_SIZE - 1024;
+
+/// Maximum number of blocks that can be asked about in one `HasData` request
+pub const MAX_HAS_DATA_IDS: usize = 500;
@@ -23,12 +23,36 @@ use thiserror::Error;
 pub enum ProtocolError {
     #[error("Peer is in different network. Our network {0:?}, their network {1:?}")]
     DifferentNetwork([u8; 4], [u8; 4]),
+    #[error("Peer speaks a different protocol. Our protocol {0}, their protocol {1}")]
+    DifferentProtocolName(String, String),
     #[error("Peer has an unsupported version. Our version {0}, their version {1}")]
     InvalidVersion(SemVer, SemVer),
     #[error("Peer sent an invalid message")]
     InvalidMessage,
     #[error("Peer is unresponsive")]
     Unresponsive,
+    #[error("Peer sent a message of unknown type")]
+    UnknownMessageType,
+    #[error("Peer sent blocks chunk {1} when chunk {0} was expected")]
+    UnexpectedBlocksChunk(u32, u32),
+    #[error("Peer protocol version is too old. Minimum version {0}, their version {1}")]
+    UnsupportedProtocolVersion(SemVer, SemVer),
+    #[error("Peer sent a message of {0} bytes when the limit is {1}")]
+    MessageTooLarge(usize, usize),
+    #[error("Peer sent a message that can't be decoded")]
+    UndecodableMessage,
+    #[error("Peer sent {0} addresses when the limit is {1}")]
+    TooManyAddresses(usize, usize),
+    #[error("Peer announced too many blocks with an unknown parent")]
+    TooManyOrphanAnnouncements,
+    #[error("Peer asked about {0} blocks when the limit is {1}")]
+    TooManyBlockIds(usize, usize),
+    #[error("Peer sent too many transactions with unknown inputs")]
+    TooManyOrphanTransactions,
+    #[error("Peer sent a blocks chunk for a request that isn't pending")]
+    UnsolicitedBlocksChunk,
+    #[error("Peer sent a block response of {0} bytes when the limit is {1}")]
+    BlockResponseTooLarge(usize, usize),
 }
 
 /// Peer state errors (Errors either for an individual peer or for the [`PeerManager`])
@@ -46,6 +70,12 @@ pub enum PeerError {
     TooManyPeers,
     #[error("Connection to address {0} already pending")]
     Pending(String),
+    #[error("Timed out writing to the peer")]
+    WriteTimeout,
+    #[error("Too many pending requests")]
+    RequestManagerFull,
+    #[error("Too many pending requests from the peer")]
+    TooManyPeerRequests,
 }
 
 /// PubSub errors for announcements
@@ -89,6 +119,10 @@ pub enum P2pError {
     DialError(DialError),
     #[error("Connection to other task lost")]
     ChannelClosed,
+    #[error("Networking backend was shut down")]
+    BackendShutdown,
+    #[error("Networking backend terminated unexpectedly")]
+    BackendCrashed,
     #[error("Peer-related error: `{0}`")]
     PeerError(PeerError),
     #[error("SubsystemFailure")]
@@ -101,6 +135,8 @@ pub enum P2pError {
     ConversionError(ConversionError),
     #[error("Noise protocol handshake error")]
     NoiseHandshakeError(String),
+    #[error("Timed out waiting for {0} connected peers")]
+    WaitForPeersTimeout(usize),
     #[error("Other: `{0}`")]
     Other(&'static str),
 }
@@ -148,6 +184,8 @@ impl BanScore for P2pError {
             P2pError::PublishError(err) => err.ban_score(),
             P2pError::DialError(_) => 0,
             P2pError::ChannelClosed => 0,
+            P2pError::BackendShutdown => 0,
+            P2pError::BackendCrashed => 0,
             P2pError::PeerError(_) => 0,
             P2pError::SubsystemFailure => 0,
             P2pError::ChainstateError(_) => 0,
@@ -155,6 +193,7 @@ impl BanScore for P2pError {
             P2pError::ConversionError(err) => err.ban_score(),
             // Could be a noise protocol violation but also a network error, do not ban peer
             P2pError::NoiseHandshakeError(_) => 0,
+            P2pError::WaitForPeersTimeout(_) => 0,
             P2pError::Other(_) => 0,
         }
     }
@@ -164,9 +203,25 @@ impl BanScore for ProtocolError {
     fn ban_score(&self) -> u32 {
         match self {
             ProtocolError::DifferentNetwork(_, _) => 100,
+            ProtocolError::DifferentProtocolName(_, _) => 100,
             ProtocolError::InvalidVersion(_, _) => 100,
             ProtocolError::InvalidMessage => 100,
             ProtocolError::Unresponsive => 100,
+            // The peer might run a newer protocol version, don't ban it
+            ProtocolError::UnknownMessageType => 0,
+            ProtocolError::UnexpectedBlocksChunk(_, _) => 100,
+            // Outdated peers are not malicious
+            ProtocolError::UnsupportedProtocolVersion(_, _) => 0,
+            ProtocolError::MessageTooLarge(_, _) => 100,
+            // Only reported if the peer is not disconnected right away, see
+            // `P2pConfig::disconnect_on_protocol_error`
+            ProtocolError::UndecodableMessage => 10,
+            ProtocolError::TooManyAddresses(_, _) => 20,
+            ProtocolError::TooManyOrphanAnnouncements => 20,
+            ProtocolError::TooManyBlockIds(_, _) => 20,
+            ProtocolError::TooManyOrphanTransactions => 20,
+            ProtocolError::UnsolicitedBlocksChunk => 100,
+            ProtocolError::BlockResponseTooLarge(_, _) => 100,
         }
     }
 }
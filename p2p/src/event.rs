@@ -13,18 +13,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use common::chain::block::Block;
+use common::{chain::block::Block, primitives::BlockHeight};
 
-use crate::{interface::types::ConnectedPeer, net::NetworkingService, utils::oneshot_nofail};
+use crate::{
+    interface::types::{ConnectedPeer, HealthStatus, KnownAddress, SyncProgress},
+    net::{
+        types::{BestBlock, NodeKind},
+        NetworkingService,
+    },
+    utils::oneshot_nofail,
+};
 
 #[derive(Debug)]
 pub enum PeerManagerEvent<T: NetworkingService> {
     /// Try to establish connection with a remote peer
     Connect(T::Address, oneshot_nofail::Sender<crate::Result<()>>),
 
+    /// Try to establish connection with a remote peer that is reachable at any of the addresses
+    ///
+    /// The addresses are tried in order until one of them succeeds.
+    ConnectAny(Vec<T::Address>, oneshot_nofail::Sender<crate::Result<()>>),
+
     /// Disconnect node using peer ID
     Disconnect(T::PeerId, oneshot_nofail::Sender<crate::Result<()>>),
 
+    /// Add the address to the set of persistent peers that the node always keeps connected
+    AddNode(T::Address, oneshot_nofail::Sender<crate::Result<()>>),
+
+    /// Remove the address from the set of persistent peers
+    RemoveNode(T::Address, oneshot_nofail::Sender<crate::Result<()>>),
+
     /// Get the total number of peers local node has a connection with
     GetPeerCount(oneshot_nofail::Sender<usize>),
 
@@ -34,8 +52,31 @@ pub enum PeerManagerEvent<T: NetworkingService> {
     /// Get peer IDs and addresses of connected peers
     GetConnectedPeers(oneshot_nofail::Sender<Vec<ConnectedPeer>>),
 
+    /// Get addresses known to the peer database along with their state
+    GetKnownAddresses(oneshot_nofail::Sender<Vec<KnownAddress>>),
+
+    /// Request the address list from the peer again and merge it into the peer database
+    RefreshPeerAddresses(T::PeerId, oneshot_nofail::Sender<crate::Result<()>>),
+
     /// Adjust peer score
     AdjustPeerScore(T::PeerId, u32, oneshot_nofail::Sender<crate::Result<()>>),
+
+    /// Get a summary of the sync and connectivity state of the node
+    GetHealth(oneshot_nofail::Sender<HealthStatus>),
+
+    /// Get the sync progress of the local node compared to the connected peers
+    GetSyncProgress(oneshot_nofail::Sender<SyncProgress>),
+
+    /// The local node has a new tip
+    NewTipReceived {
+        is_initial_block_download: bool,
+        height: BlockHeight,
+    },
+
+    /// The local node didn't receive a new tip for a long time, make short-lived connections to
+    /// new peers to check that the node isn't eclipsed. The peer manager also handles this by
+    /// itself after [`crate::config::P2pConfig::stale_tip_timeout`].
+    StaleTipDetected,
 }
 
 #[derive(Debug)]
@@ -46,8 +87,8 @@ pub enum SyncEvent {
 
 #[derive(Debug)]
 pub enum SyncControlEvent<T: NetworkingService> {
-    /// Peer connected
-    Connected(T::PeerId),
+    /// Peer connected, along with the best block it reported during the handshake
+    Connected(T::PeerId, NodeKind, Option<BestBlock>),
 
     /// Peer disconnected
     Disconnected(T::PeerId),
@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::types::ConnectedPeer;
+use super::types::{ConnectedPeer, HealthStatus, KnownAddress, SyncProgress};
 
 #[async_trait::async_trait]
 pub trait P2pInterface: Send + Sync {
@@ -26,4 +26,10 @@ pub trait P2pInterface: Send + Sync {
     async fn get_bind_addresses(&self) -> crate::Result<Vec<String>>;
 
     async fn get_connected_peers(&self) -> crate::Result<Vec<ConnectedPeer>>;
+
+    async fn get_known_addresses(&self) -> crate::Result<Vec<KnownAddress>>;
+
+    async fn get_health(&self) -> crate::Result<HealthStatus>;
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress>;
 }
@@ -21,7 +21,10 @@ use crate::{
     P2p,
 };
 
-use super::{p2p_interface::P2pInterface, types::ConnectedPeer};
+use super::{
+    p2p_interface::P2pInterface,
+    types::{ConnectedPeer, HealthStatus, KnownAddress, SyncProgress},
+};
 
 #[async_trait::async_trait]
 impl<T> P2pInterface for P2p<T>
@@ -74,4 +77,28 @@ where
             .map_err(P2pError::from)?;
         rx.await.map_err(P2pError::from)
     }
+
+    async fn get_known_addresses(&self) -> crate::Result<Vec<KnownAddress>> {
+        let (tx, rx) = oneshot_nofail::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetKnownAddresses(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
+
+    async fn get_health(&self) -> crate::Result<HealthStatus> {
+        let (tx, rx) = oneshot_nofail::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetHealth(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress> {
+        let (tx, rx) = oneshot_nofail::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetSyncProgress(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
 }
@@ -15,7 +15,10 @@
 
 use std::ops::{Deref, DerefMut};
 
-use super::{p2p_interface::P2pInterface, types::ConnectedPeer};
+use super::{
+    p2p_interface::P2pInterface,
+    types::{ConnectedPeer, HealthStatus, KnownAddress, SyncProgress},
+};
 
 #[async_trait::async_trait]
 impl<T: Deref<Target = dyn P2pInterface> + DerefMut<Target = dyn P2pInterface> + Send + Sync>
@@ -40,4 +43,16 @@ impl<T: Deref<Target = dyn P2pInterface> + DerefMut<Target = dyn P2pInterface> +
     async fn get_connected_peers(&self) -> crate::Result<Vec<ConnectedPeer>> {
         self.deref().get_connected_peers().await
     }
+
+    async fn get_known_addresses(&self) -> crate::Result<Vec<KnownAddress>> {
+        self.deref().get_known_addresses().await
+    }
+
+    async fn get_health(&self) -> crate::Result<HealthStatus> {
+        self.deref().get_health().await
+    }
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress> {
+        self.deref().get_sync_progress().await
+    }
 }
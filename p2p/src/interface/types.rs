@@ -13,6 +13,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::peer_manager::peerdb::AddressSource;
+
 #[derive(Debug, serde::Serialize)]
 pub struct ConnectedPeer {
     // TODO: Replace String with actual type, once libp2p removed
@@ -24,4 +26,62 @@ pub struct ConnectedPeer {
     pub inbound: bool,
 
     pub ban_score: u32,
+
+    /// Last ping round-trip time in milliseconds
+    pub ping_last: Option<u64>,
+
+    /// Minimum ping round-trip time in milliseconds
+    pub ping_min: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct KnownAddress {
+    // TODO: Replace String with actual type, once libp2p removed
+    pub address: String,
+
+    /// Seconds since UNIX epoch
+    pub last_seen: Option<u64>,
+
+    pub failure_count: u32,
+
+    pub banned: bool,
+
+    pub source: AddressSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum HealthState {
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HealthStatus {
+    pub state: HealthState,
+
+    pub active_peers: usize,
+
+    /// Minimum number of active peers required for the node to be healthy
+    pub min_peers: usize,
+
+    pub initial_block_download: bool,
+
+    /// Seconds since the last new tip, `None` if no blocks were received since the start
+    pub seconds_since_last_block: Option<u64>,
+
+    /// True if there is at least one inbound peer connected
+    pub inbound_reachable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SyncProgress {
+    /// Height of the local best block, `None` if no new tip was received since the start
+    pub current_height: Option<u64>,
+
+    /// Maximum best block height reported by the connected peers during the handshake,
+    /// `None` if no connected peer has reported it
+    pub best_known_peer_height: Option<u64>,
+
+    /// How far along the local node is, `None` if either of the heights is unknown
+    pub percent: Option<u8>,
 }
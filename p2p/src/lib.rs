@@ -78,7 +78,7 @@ where
         chain_config: Arc<ChainConfig>,
         p2p_config: Arc<P2pConfig>,
         chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
-        _mempool_handle: mempool::MempoolHandle,
+        mempool_handle: mempool::MempoolHandle,
         time_getter: TimeGetter,
         peerdb_storage: S,
     ) -> crate::Result<Self> {
@@ -136,6 +136,7 @@ where
                     p2p_config,
                     sync,
                     chainstate_handle,
+                    mempool_handle,
                     rx_p2p_sync,
                     tx_peer_manager,
                 )
@@ -165,7 +166,12 @@ pub async fn make_p2p<S: PeerDbStorage + 'static>(
     peerdb_storage: S,
 ) -> Result<Box<dyn P2pInterface>> {
     let stream_adapter = NoiseEncryptionAdapter::gen_new();
-    let base_transport = net::default_backend::transport::TcpTransportSocket::new();
+    let base_transport = net::default_backend::transport::TcpTransportSocket::with_config(
+        net::default_backend::transport::TcpTransportConfig {
+            listen_backlog: Some(*p2p_config.listen_backlog),
+            ..Default::default()
+        },
+    );
     let transport = NoiseTcpTransport::new(stream_adapter, base_transport);
 
     let p2p = P2p::<DefaultNetworkingService<NoiseTcpTransport>>::new(
@@ -13,23 +13,37 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use chainstate::Locator;
+use std::io;
+
+use chainstate::{chainstate_interface::ChainstateInterface, ChainstateError, Locator};
 use common::{
-    chain::block::{Block, BlockHeader},
+    chain::{
+        block::{Block, BlockHeader},
+        tokens::{TokenId, TokenIssuance},
+        GenBlock, SignedTransaction,
+    },
     primitives::Id,
 };
-use serialization::{Decode, Encode};
+use serialization::{Compact, Decode, Encode, Input};
 
-use crate::types::peer_address::PeerAddress;
+use crate::{net::types::PubSubTopic, types::peer_address::PeerAddress};
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub struct HeaderListRequest {
+    magic_bytes: [u8; 4],
     locator: Locator,
 }
 
 impl HeaderListRequest {
-    pub fn new(locator: Locator) -> Self {
-        HeaderListRequest { locator }
+    pub fn new(magic_bytes: [u8; 4], locator: Locator) -> Self {
+        HeaderListRequest {
+            magic_bytes,
+            locator,
+        }
+    }
+
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        &self.magic_bytes
     }
 
     pub fn locator(&self) -> &Locator {
@@ -43,12 +57,20 @@ impl HeaderListRequest {
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub struct BlockListRequest {
+    magic_bytes: [u8; 4],
     block_ids: Vec<Id<Block>>,
 }
 
 impl BlockListRequest {
-    pub fn new(block_ids: Vec<Id<Block>>) -> Self {
-        Self { block_ids }
+    pub fn new(magic_bytes: [u8; 4], block_ids: Vec<Id<Block>>) -> Self {
+        Self {
+            magic_bytes,
+            block_ids,
+        }
+    }
+
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        &self.magic_bytes
     }
 
     pub fn block_ids(&self) -> &[Id<Block>] {
@@ -73,6 +95,21 @@ pub struct PingRequest {
     pub nonce: u64,
 }
 
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct TokenInfoRequest {
+    /// Magic bytes of the chain the request is made for
+    pub magic_bytes: [u8; 4],
+    pub token_id: TokenId,
+}
+
+/// Asks which of the blocks the peer has, at most [`crate::constants::MAX_HAS_DATA_IDS`] blocks at once
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct HasDataRequest {
+    /// Magic bytes of the chain the request is made for
+    pub magic_bytes: [u8; 4],
+    pub block_ids: Vec<Id<Block>>,
+}
+
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub enum Request {
     #[codec(index = 0)]
@@ -85,12 +122,30 @@ pub enum Request {
     AnnounceAddrRequest(AnnounceAddrRequest),
     #[codec(index = 4)]
     PingRequest(PingRequest),
+    #[codec(index = 5)]
+    TokenInfoRequest(TokenInfoRequest),
+    #[codec(index = 6)]
+    HasData(HasDataRequest),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SyncRequest {
     HeaderListRequest(HeaderListRequest),
     BlockListRequest(BlockListRequest),
+    TokenInfoRequest(TokenInfoRequest),
+    HasData(HasDataRequest),
+}
+
+impl SyncRequest {
+    /// The magic bytes of the chain the request was made for
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        match self {
+            SyncRequest::HeaderListRequest(request) => request.magic_bytes(),
+            SyncRequest::BlockListRequest(request) => request.magic_bytes(),
+            SyncRequest::TokenInfoRequest(request) => &request.magic_bytes,
+            SyncRequest::HasData(request) => &request.magic_bytes,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,12 +157,43 @@ pub enum PeerManagerRequest {
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub struct HeaderListResponse {
+    magic_bytes: [u8; 4],
+    /// The locator entry that was found in the responder's chain, the headers start right after it
+    fork_point: Id<GenBlock>,
     headers: Vec<BlockHeader>,
 }
 
 impl HeaderListResponse {
-    pub fn new(headers: Vec<BlockHeader>) -> Self {
-        Self { headers }
+    pub fn new(magic_bytes: [u8; 4], fork_point: Id<GenBlock>, headers: Vec<BlockHeader>) -> Self {
+        Self {
+            magic_bytes,
+            fork_point,
+            headers,
+        }
+    }
+
+    /// Make a response to the header request with the given locator
+    ///
+    /// If the response contains no headers, the fork point is the tip of the responder's chain.
+    pub fn from_chainstate(
+        chainstate: &dyn ChainstateInterface,
+        locator: Locator,
+    ) -> Result<Self, ChainstateError> {
+        let headers = chainstate.get_headers(locator)?;
+        let fork_point = match headers.first() {
+            Some(header) => *header.prev_block_id(),
+            None => chainstate.get_best_block_id()?,
+        };
+        let magic_bytes = *chainstate.get_chain_config().magic_bytes();
+        Ok(Self::new(magic_bytes, fork_point, headers))
+    }
+
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        &self.magic_bytes
+    }
+
+    pub fn fork_point(&self) -> &Id<GenBlock> {
+        &self.fork_point
     }
 
     pub fn headers(&self) -> &[BlockHeader] {
@@ -119,14 +205,22 @@ impl HeaderListResponse {
     }
 }
 
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+#[derive(Debug, Encode, Clone, PartialEq, Eq)]
 pub struct BlockListResponse {
+    magic_bytes: [u8; 4],
     blocks: Vec<Block>,
 }
 
 impl BlockListResponse {
-    pub fn new(blocks: Vec<Block>) -> Self {
-        Self { blocks }
+    pub fn new(magic_bytes: [u8; 4], blocks: Vec<Block>) -> Self {
+        Self {
+            magic_bytes,
+            blocks,
+        }
+    }
+
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        &self.magic_bytes
     }
 
     pub fn blocks(&self) -> &[Block] {
@@ -138,6 +232,177 @@ impl BlockListResponse {
     }
 }
 
+// The received block responses are decoded block by block with [`BlocksDecoder`]
+impl Decode for BlockListResponse {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, serialization::Error> {
+        let mut decoder = BlocksDecoder::new(BorrowedInput(input));
+        let blocks = decoder.by_ref().collect::<Result<Vec<_>, _>>()?;
+        let magic_bytes = *decoder.magic_bytes().expect("magic bytes are decoded first");
+        Ok(Self::new(magic_bytes, blocks))
+    }
+}
+
+/// The size of the buffer [`decode_blocks_from`] reads the blocks into
+const BLOCKS_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Decodes the encoded [`BlockListResponse`] read from `reader` one block at a time.
+///
+/// The reader is read in large pieces into one buffer that is reused for all blocks, so neither
+/// the encoded response nor the whole list of blocks is kept in memory and every block can be
+/// processed (and dropped) before the next one is decoded. The iterator stops after the first
+/// error.
+pub fn decode_blocks_from<R: io::Read>(reader: R) -> BlocksDecoder<ReadBuffer<R>> {
+    BlocksDecoder::new(ReadBuffer::new(reader, BLOCKS_READ_BUFFER_SIZE))
+}
+
+/// Decodes the blocks of the encoded [`BlockListResponse`] from the input one at a time
+pub struct BlocksDecoder<I> {
+    input: I,
+    /// The magic bytes of the response, `None` until they are decoded
+    magic_bytes: Option<[u8; 4]>,
+    /// The number of blocks that are not decoded yet, `None` until the length prefix is decoded
+    remaining: Option<u32>,
+}
+
+impl<I: Input> BlocksDecoder<I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            magic_bytes: None,
+            remaining: None,
+        }
+    }
+
+    /// The magic bytes of the response, available once the first item is returned
+    pub fn magic_bytes(&self) -> Option<&[u8; 4]> {
+        self.magic_bytes.as_ref()
+    }
+
+    fn decode_prefix(&mut self) -> Result<u32, serialization::Error> {
+        let magic_bytes = <[u8; 4]>::decode(&mut self.input)?;
+        let count = Compact::<u32>::decode(&mut self.input)?;
+        self.magic_bytes = Some(magic_bytes);
+        Ok(count.0)
+    }
+}
+
+impl<I: Input> Iterator for BlocksDecoder<I> {
+    type Item = Result<Block, serialization::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => match self.decode_prefix() {
+                Ok(count) => count,
+                Err(err) => {
+                    self.remaining = Some(0);
+                    return Some(Err(err));
+                }
+            },
+        };
+
+        if remaining == 0 {
+            self.remaining = Some(0);
+            return None;
+        }
+
+        match Block::decode(&mut self.input) {
+            Ok(block) => {
+                self.remaining = Some(remaining - 1);
+                Some(Ok(block))
+            }
+            Err(err) => {
+                self.remaining = Some(0);
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// An [`Input`] that reads from `reader` through a buffer reused for all reads
+pub struct ReadBuffer<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// The buffered bytes that are not consumed yet
+    start: usize,
+    end: usize,
+}
+
+impl<R: io::Read> ReadBuffer<R> {
+    fn new(reader: R, size: usize) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; size],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn fill(&mut self) -> Result<(), serialization::Error> {
+        let read = loop {
+            match self.reader.read(&mut self.buffer) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return Err("Failed to read the input".into()),
+            }
+        };
+        if read == 0 {
+            return Err("Not enough data to fill buffer".into());
+        }
+        self.start = 0;
+        self.end = read;
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Input for ReadBuffer<R> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, serialization::Error> {
+        Ok(None)
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), serialization::Error> {
+        let mut written = 0;
+        while written < into.len() {
+            if self.start == self.end {
+                self.fill()?;
+            }
+            let count = std::cmp::min(self.end - self.start, into.len() - written);
+            into[written..written + count]
+                .copy_from_slice(&self.buffer[self.start..self.start + count]);
+            self.start += count;
+            written += count;
+        }
+        Ok(())
+    }
+}
+
+/// Lets the decoder owning its input decode from a borrowed one
+struct BorrowedInput<'a, I>(&'a mut I);
+
+impl<'a, I: Input> Input for BorrowedInput<'a, I> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, serialization::Error> {
+        self.0.remaining_len()
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), serialization::Error> {
+        self.0.read(into)
+    }
+}
+
+/// A part of the block list response that doesn't fit into one message
+///
+/// All chunks of the response are sent with the same request ID.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct BlocksChunk {
+    /// Magic bytes of the chain the blocks belong to
+    pub magic_bytes: [u8; 4],
+    /// Sequence number of the chunk, starting from zero
+    pub seq: u32,
+    /// Whether this is the last chunk of the response
+    pub last: bool,
+    pub blocks: Vec<Block>,
+}
+
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub struct AnnounceAddrResponse {}
 
@@ -151,6 +416,40 @@ pub struct PingResponse {
     pub nonce: u64,
 }
 
+/// Issuance data of a fungible token
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct TokenInfoResponse {
+    /// Magic bytes of the chain the response is made for
+    pub magic_bytes: [u8; 4],
+    pub token_id: TokenId,
+    /// `None` if the token is unknown to the node (or it's not a fungible token)
+    pub issuance: Option<TokenIssuance>,
+}
+
+/// The reason the request wasn't answered
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseError {
+    /// The responder is serving too many requests of the peer (or of all peers) already
+    #[codec(index = 0)]
+    RateLimited,
+}
+
+/// Whether the peer has each of the blocks of the [`Request::HasData`] request, in the same order
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct DataAvailabilityResponse {
+    /// Magic bytes of the chain the response is made for
+    pub magic_bytes: [u8; 4],
+    pub availability: Vec<bool>,
+}
+
+/// The refusal to answer the request
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// Magic bytes of the chain the response is made for
+    pub magic_bytes: [u8; 4],
+    pub error: ResponseError,
+}
+
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub enum Response {
     #[codec(index = 0)]
@@ -163,12 +462,38 @@ pub enum Response {
     AnnounceAddrResponse(AnnounceAddrResponse),
     #[codec(index = 4)]
     PingResponse(PingResponse),
+    #[codec(index = 5)]
+    BlocksChunk(BlocksChunk),
+    #[codec(index = 6)]
+    TokenInfoResponse(TokenInfoResponse),
+    #[codec(index = 7)]
+    DataAvailability(DataAvailabilityResponse),
+    #[codec(index = 8)]
+    Error(ErrorResponse),
 }
 
 #[derive(Debug, Clone)]
 pub enum SyncResponse {
     HeaderListResponse(HeaderListResponse),
     BlockListResponse(BlockListResponse),
+    BlocksChunk(BlocksChunk),
+    TokenInfoResponse(TokenInfoResponse),
+    DataAvailability(DataAvailabilityResponse),
+    Error(ErrorResponse),
+}
+
+impl SyncResponse {
+    /// The magic bytes of the chain the response was made for
+    pub fn magic_bytes(&self) -> &[u8; 4] {
+        match self {
+            SyncResponse::HeaderListResponse(response) => response.magic_bytes(),
+            SyncResponse::BlockListResponse(response) => response.magic_bytes(),
+            SyncResponse::BlocksChunk(chunk) => &chunk.magic_bytes,
+            SyncResponse::TokenInfoResponse(response) => &response.magic_bytes,
+            SyncResponse::DataAvailability(response) => &response.magic_bytes,
+            SyncResponse::Error(response) => &response.magic_bytes,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -182,6 +507,23 @@ pub enum PeerManagerResponse {
 pub enum Announcement {
     #[codec(index = 0)]
     Block(Block),
+    /// Headers of several blocks announced at once, for example when a node accepts a batch of
+    /// blocks. Only headers are sent to stay under the announcement size limit.
+    #[codec(index = 1)]
+    Blocks(Vec<BlockHeader>),
+    /// A transaction relayed to the mempools of the peers
+    #[codec(index = 2)]
+    Transaction(SignedTransaction),
+}
+
+impl Announcement {
+    /// Returns the topic the announcement is published on
+    pub fn topic(&self) -> PubSubTopic {
+        match self {
+            Announcement::Block(_) | Announcement::Blocks(_) => PubSubTopic::Blocks,
+            Announcement::Transaction(_) => PubSubTopic::Transactions,
+        }
+    }
 }
 
 impl From<PeerManagerRequest> for Request {
@@ -213,6 +555,8 @@ impl From<SyncRequest> for Request {
         match request {
             SyncRequest::HeaderListRequest(request) => Request::HeaderListRequest(request),
             SyncRequest::BlockListRequest(request) => Request::BlockListRequest(request),
+            SyncRequest::TokenInfoRequest(request) => Request::TokenInfoRequest(request),
+            SyncRequest::HasData(request) => Request::HasData(request),
         }
     }
 }
@@ -222,6 +566,83 @@ impl From<SyncResponse> for Response {
         match response {
             SyncResponse::HeaderListResponse(response) => Response::HeaderListResponse(response),
             SyncResponse::BlockListResponse(response) => Response::BlockListResponse(response),
+            SyncResponse::BlocksChunk(chunk) => Response::BlocksChunk(chunk),
+            SyncResponse::TokenInfoResponse(response) => Response::TokenInfoResponse(response),
+            SyncResponse::DataAvailability(response) => Response::DataAvailability(response),
+            SyncResponse::Error(response) => Response::Error(response),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::block::{consensus_data::ConsensusData, timestamp::BlockTimestamp, BlockReward},
+        primitives::H256,
+    };
+    use serialization::DecodeAll;
+
+    use super::*;
+
+    const MAGIC_BYTES: [u8; 4] = [0x1a, 0x64, 0xe5, 0xf1];
+
+    #[test]
+    fn decode_blocks_stream() {
+        let blocks = (0..5)
+            .map(|i| {
+                Block::new(
+                    vec![],
+                    Id::new(H256([0x01; 32])),
+                    BlockTimestamp::from_int_seconds(i),
+                    ConsensusData::None,
+                    BlockReward::new(vec![]),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let encoded = BlockListResponse::new(MAGIC_BYTES, blocks.clone()).encode();
+
+        let decoded = decode_blocks_from(encoded.as_slice()).collect::<Result<Vec<_>, _>>();
+        assert_eq!(decoded, Ok(blocks.clone()));
+
+        // The blocks before the truncated one are still decoded
+        let mut decoder = decode_blocks_from(&encoded[..encoded.len() - 1]);
+        for block in &blocks[..4] {
+            assert_eq!(decoder.next(), Some(Ok(block.clone())));
+        }
+        assert!(matches!(decoder.next(), Some(Err(_))));
+        assert_eq!(decoder.next(), None);
+
+        assert_eq!(decode_blocks_from([].as_slice()).count(), 1);
+        assert_eq!(
+            decode_blocks_from(BlockListResponse::new(MAGIC_BYTES, vec![]).encode().as_slice())
+                .count(),
+            0
+        );
+
+        // The blocks spanning several reads of a small buffer
+        let decoder = BlocksDecoder::new(ReadBuffer::new(encoded.as_slice(), 7));
+        assert_eq!(decoder.collect::<Result<Vec<_>, _>>(), Ok(blocks.clone()));
+    }
+
+    #[test]
+    fn block_list_response_roundtrip() {
+        let block = Block::new(
+            vec![],
+            Id::new(H256([0x01; 32])),
+            BlockTimestamp::from_int_seconds(1),
+            ConsensusData::None,
+            BlockReward::new(vec![]),
+        )
+        .unwrap();
+        for blocks in [vec![], vec![block.clone(), block]] {
+            let response = BlockListResponse::new(MAGIC_BYTES, blocks);
+            let encoded = response.encode();
+            assert_eq!(
+                BlockListResponse::decode_all(&mut encoded.as_slice()),
+                Ok(response)
+            );
+            assert!(BlockListResponse::decode(&mut &encoded[..encoded.len() - 1]).is_err());
         }
     }
 }
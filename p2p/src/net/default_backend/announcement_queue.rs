@@ -0,0 +1,181 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounded queue of outbound announcements
+//!
+//! The backend pushes announcements to the queue and the peer task sends them to the remote
+//! peer when it's ready. If the peer can't keep up, the oldest announcements are dropped
+//! because stale announcements have little value.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use tokio::sync::Notify;
+
+use crate::message::Announcement;
+
+pub struct AnnouncementQueue {
+    queue: Mutex<VecDeque<Announcement>>,
+
+    /// Maximum number of queued announcements
+    capacity: usize,
+
+    /// Used to wake up the peer task when a new announcement is pushed
+    notify: Notify,
+
+    /// Number of announcements dropped because the queue was full
+    dropped: AtomicU64,
+}
+
+impl AnnouncementQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            capacity,
+            notify: Notify::new(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Pushes the announcement to the queue, dropping the oldest one if the queue is full.
+    ///
+    /// Returns `true` if an announcement was dropped.
+    pub fn push(&self, announcement: Announcement) -> bool {
+        let dropped = {
+            let mut queue = self.queue.lock().expect("Announcement queue mutex is poisoned");
+            queue.push_back(announcement);
+            let dropped = queue.len() > self.capacity;
+            if dropped {
+                queue.pop_front();
+            }
+            dropped
+        };
+
+        if dropped {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        self.notify.notify_one();
+
+        dropped
+    }
+
+    /// Removes the oldest announcement from the queue.
+    pub fn pop(&self) -> Option<Announcement> {
+        self.queue.lock().expect("Announcement queue mutex is poisoned").pop_front()
+    }
+
+    /// Waits until an announcement is available and removes it from the queue.
+    ///
+    /// This function is cancel safe.
+    pub async fn pop_wait(&self) -> Announcement {
+        loop {
+            if let Some(announcement) = self.pop() {
+                return announcement;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Removes all queued announcements.
+    pub fn clear(&self) {
+        self.queue.lock().expect("Announcement queue mutex is poisoned").clear()
+    }
+
+    /// Returns the number of queued announcements.
+    pub fn len(&self) -> usize {
+        self.queue.lock().expect("Announcement queue mutex is poisoned").len()
+    }
+
+    /// Returns `true` if there are no queued announcements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of announcements dropped because the queue was full.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::block::{
+            consensus_data::ConsensusData, timestamp::BlockTimestamp, Block, BlockReward,
+        },
+        primitives::{Id, H256},
+    };
+
+    use super::*;
+
+    fn make_announcement(timestamp: u64) -> Announcement {
+        Announcement::Block(
+            Block::new(
+                vec![],
+                Id::new(H256([0x01; 32])),
+                BlockTimestamp::from_int_seconds(timestamp),
+                ConsensusData::None,
+                BlockReward::new(Vec::new()),
+            )
+            .unwrap(),
+        )
+    }
+
+    fn timestamp(announcement: Announcement) -> u64 {
+        match announcement {
+            Announcement::Block(block) => block.timestamp().as_int_seconds(),
+            Announcement::Blocks(headers) => headers[0].timestamp().as_int_seconds(),
+            Announcement::Transaction(_) => panic!("unexpected announcement"),
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_oldest() {
+        let queue = AnnouncementQueue::new(3);
+
+        // Nothing is read from the queue, so it overflows
+        for i in 0..5 {
+            queue.push(make_announcement(i));
+        }
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.dropped_count(), 2);
+
+        // The oldest announcements are dropped, not the newest
+        assert_eq!(timestamp(queue.pop_wait().await), 2);
+        assert_eq!(timestamp(queue.pop_wait().await), 3);
+        assert_eq!(timestamp(queue.pop_wait().await), 4);
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[tokio::test]
+    async fn no_drops_below_capacity() {
+        let queue = AnnouncementQueue::new(3);
+
+        for i in 0..3 {
+            assert!(!queue.push(make_announcement(i)));
+        }
+        assert_eq!(queue.dropped_count(), 0);
+
+        for i in 0..3 {
+            assert_eq!(timestamp(queue.pop_wait().await), i);
+        }
+    }
+}
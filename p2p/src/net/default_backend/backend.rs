@@ -18,48 +18,97 @@
 //! Every connected peer gets unique ID (generated locally from a counter).
 
 use std::{
-    collections::{BTreeSet, HashMap},
-    sync::Arc,
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
-use tokio::{sync::mpsc, time::timeout};
+use tokio::{
+    sync::{mpsc, watch},
+    time::{timeout, Instant},
+};
 
+use chainstate::ChainstateHandle;
 use common::chain::ChainConfig;
-use crypto::random::{make_pseudo_rng, Rng, SliceRandom};
+use crypto::random::{make_pseudo_rng, Rng};
 use logging::log;
 use serialization::{Decode, Encode};
 
 use crate::{
     config::P2pConfig,
+    constants::MAX_BLOCKS_CHUNK_SIZE,
     error::{DialError, P2pError, PeerError, PublishError},
     message::{self, PeerManagerRequest, PeerManagerResponse, SyncRequest, SyncResponse},
     net::{
         default_backend::{
+            announcement_queue::AnnouncementQueue,
             constants::ANNOUNCEMENT_MAX_SIZE,
-            peer, request_manager,
-            transport::{TransportListener, TransportSocket},
+            metrics::MessageCounters,
+            peer,
+            rate_limiter::RateLimiter,
+            relay_targets::{select_relay_targets, trickle_delay},
+            request_manager,
+            transport::{PeerStream, TransportListener, TransportSocket, TransportTiming},
             types::{
-                Command, ConnectivityEvent, Event, Message, PeerEvent, PeerId, RequestId,
-                SyncingEvent,
+                AnnouncementTap, Command, ConnectivityEvent, Event, Message, PeerEvent, PeerId,
+                RequestId, SyncingEvent,
             },
         },
-        types::{PeerInfo, PubSubTopic},
+        types::{BestBlock, ConnectionTiming, PeerInfo, PubSubTopic},
         Announcement,
     },
+    utils::oneshot_nofail,
+};
+
+use super::{
+    peer::PeerRole, tracer::ProtocolTracer, transport::TransportAddress, types::HandshakeNonce,
 };
 
-use super::{peer::PeerRole, transport::TransportAddress, types::HandshakeNonce};
+/// Delay before accepting connections again after a failed accept, doubled after each
+/// consecutive failure
+const ACCEPT_ERROR_MIN_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Maximum delay before accepting connections again after a failed accept
+const ACCEPT_ERROR_MAX_BACKOFF: Duration = Duration::from_secs(1);
 
 /// Active peer data
 struct PeerContext {
     subscriptions: BTreeSet<PubSubTopic>,
 
+    /// The peer is an observer and isn't used as a relay target for announcements
+    observer: bool,
+
     /// Channel used to send messages to the peer's event loop.
     ///
     /// Note that sending may fail unexpectedly if the connection is closed!
     /// Do not propagate ChannelClosed error to the higher level, handle it locally!
     tx: mpsc::UnboundedSender<Event>,
+
+    /// Outbound announcements waiting to be sent to the peer
+    announcement_queue: Arc<AnnouncementQueue>,
+
+    /// When an announcement of each topic was last relayed to the peer
+    last_relayed: BTreeMap<PubSubTopic, Instant>,
+
+    /// Whether the connection was initiated by the local node
+    outbound: bool,
+
+    /// Relayed announcements waiting for the trickle delay to pass (outbound peers only)
+    trickled_announcements: Vec<Announcement>,
+
+    /// When the trickled announcements are sent to the peer, set while there are any
+    trickle_at: Option<Instant>,
+
+    /// The peer will be disconnected once the responses to its pending requests are sent,
+    /// new requests from it are ignored
+    draining: bool,
+
+    /// Number of the peer's sync requests that are being answered from the chainstate
+    serving_requests: usize,
 }
 
 /// Pending peer data (until handshake message is received)
@@ -69,6 +118,14 @@ struct PendingPeerContext<A> {
     peer_role: PeerRole,
 
     tx: mpsc::UnboundedSender<Event>,
+
+    announcement_queue: Arc<AnnouncementQueue>,
+
+    /// Time spent by the transport in establishing the stream
+    transport_timing: TransportTiming,
+
+    /// Time when the peer task was created (used to measure the handshake duration)
+    created_at: Instant,
 }
 
 pub struct Backend<T: TransportSocket> {
@@ -112,6 +169,42 @@ pub struct Backend<T: TransportSocket> {
     /// List of incoming commands to the backend; we put them in a queue
     /// to make receiving commands can run concurrently with other backend operations
     command_queue: FuturesUnordered<BackendTask<T>>,
+
+    /// Local best block that is sent to new peers during the handshake
+    best_block: Option<BestBlock>,
+
+    /// Global rate limiter for the outbound traffic, shared by all peers
+    rate_limiter: Option<Arc<RateLimiter>>,
+
+    /// Set before the backend stops normally, shared with the handles
+    shutdown: Arc<AtomicBool>,
+
+    /// Message counters, updated by the peers and shared with the connectivity handle
+    message_counters: Arc<MessageCounters>,
+
+    /// Requests whose responses are delivered to the waiting callers instead of the syncing events
+    response_waiters: HashMap<RequestId, (PeerId, ResponseSender)>,
+
+    /// Reports the number of connected peers to the connectivity handle
+    peer_count_tx: watch::Sender<usize>,
+
+    /// Chainstate used to answer the sync requests without involving the syncing frontend
+    chainstate_handle: Option<ChainstateHandle>,
+
+    /// Number of the sync requests of all peers that are being answered from the chainstate
+    serving_requests: usize,
+
+    /// Optional sink for the raw bytes of the received announcements
+    announcement_tap: Option<AnnouncementTap>,
+
+    /// Records the protocol steps of all peers, if enabled
+    protocol_tracer: Option<ProtocolTracer>,
+
+    /// Delay after the last failed accept, reset once a connection is accepted
+    accept_backoff: Option<Duration>,
+
+    /// Accepting connections is paused until this time after a failed accept
+    accept_resume_at: Option<Instant>,
 }
 
 impl<T> Backend<T>
@@ -127,7 +220,17 @@ where
         cmd_rx: mpsc::UnboundedReceiver<Command<T>>,
         conn_tx: mpsc::UnboundedSender<ConnectivityEvent<T>>,
         sync_tx: mpsc::UnboundedSender<SyncingEvent>,
+        shutdown: Arc<AtomicBool>,
+        message_counters: Arc<MessageCounters>,
+        peer_count_tx: watch::Sender<usize>,
+        chainstate_handle: Option<ChainstateHandle>,
+        announcement_tap: Option<AnnouncementTap>,
+        protocol_tracer: Option<ProtocolTracer>,
     ) -> Self {
+        let rate_limiter = p2p_config
+            .max_send_bytes_per_second
+            .map(|rate| Arc::new(RateLimiter::new(rate)));
+
         Self {
             transport,
             socket,
@@ -139,8 +242,23 @@ where
             peers: HashMap::new(),
             pending: HashMap::new(),
             peer_chan: mpsc::unbounded_channel(),
-            request_mgr: request_manager::RequestManager::new(),
+            request_mgr: request_manager::RequestManager::new(
+                *p2p_config.max_pending_requests,
+                *p2p_config.max_pending_requests_per_peer,
+            ),
             command_queue: FuturesUnordered::new(),
+            best_block: None,
+            rate_limiter,
+            shutdown,
+            message_counters,
+            response_waiters: HashMap::new(),
+            peer_count_tx,
+            chainstate_handle,
+            serving_requests: 0,
+            announcement_tap,
+            protocol_tracer,
+            accept_backoff: None,
+            accept_resume_at: None,
         }
     }
 
@@ -148,17 +266,19 @@ where
     fn handle_connect_res(
         &mut self,
         address: T::Address,
-        connection_res: crate::Result<T::Stream>,
+        connection_res: crate::Result<(T::Stream, TransportTiming)>,
     ) -> crate::Result<()> {
         match connection_res {
-            Ok(socket) => {
+            Ok((socket, transport_timing)) => {
                 let handshake_nonce = make_pseudo_rng().gen();
+                let peer_id = self.remote_peer_id(&socket);
 
                 self.create_peer(
                     socket,
-                    PeerId::new(),
+                    peer_id,
                     PeerRole::Outbound { handshake_nonce },
                     address,
+                    transport_timing,
                 )
             }
             Err(err) => {
@@ -177,15 +297,60 @@ where
     /// Disconnect remote peer by id. Might fail if the peer is already disconnected.
     fn disconnect_peer(&mut self, peer_id: &PeerId) -> crate::Result<()> {
         self.request_mgr.unregister_peer(peer_id);
+        self.cancel_response_waiters(peer_id);
 
         let peer = self
             .peers
             .remove(peer_id)
             .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        self.update_peer_count();
+
+        // Announcements that are still queued are not sent
+        peer.announcement_queue.clear();
 
         peer.tx.send(Event::Disconnect).map_err(P2pError::from)
     }
 
+    /// Starts draining the peer, it's disconnected right away if there are no pending requests
+    fn drain_peer(&mut self, peer_id: &PeerId) -> crate::Result<()> {
+        self.peers
+            .get_mut(peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .draining = true;
+
+        self.disconnect_if_drained(peer_id)
+    }
+
+    /// Disconnects the draining peer if all of its requests are answered
+    fn disconnect_if_drained(&mut self, peer_id: &PeerId) -> crate::Result<()> {
+        let draining = self.peers.get(peer_id).map_or(false, |peer| peer.draining);
+        if draining && self.request_mgr.pending_requests(peer_id) == 0 {
+            log::debug!("peer {peer_id} is drained, disconnecting");
+            self.disconnect_peer(peer_id)?;
+        }
+        Ok(())
+    }
+
+    fn update_peer_count(&self) {
+        self.peer_count_tx.send_replace(self.peers.len());
+    }
+
+    /// Resolves the pending waiters of the peer's requests with the `PeerDisconnected` error
+    fn cancel_response_waiters(&mut self, peer_id: &PeerId) {
+        let request_ids = self
+            .response_waiters
+            .iter()
+            .filter(|(_, (waiter_peer_id, _))| waiter_peer_id == peer_id)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+
+        for request_id in request_ids {
+            if let Some((_, response_tx)) = self.response_waiters.remove(&request_id) {
+                response_tx.send(Err(P2pError::PeerError(PeerError::PeerDisconnected)));
+            }
+        }
+    }
+
     /// Sends a request to the remote peer. Might fail if the peer is already disconnected.
     fn send_request(
         &mut self,
@@ -220,35 +385,215 @@ where
             .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
             .tx
             .send(Event::SendMessage(response))
-            .map_err(P2pError::from)
+            .map_err(P2pError::from)?;
+
+        // The disconnect event is handled by the peer task after the response is sent
+        self.disconnect_if_drained(&peer_id)
     }
 
-    /// Sends the announcement to all peers.
+    /// Returns the topics the local node is subscribed to (see [`P2pConfig::node_type`])
+    fn subscriptions(&self) -> BTreeSet<PubSubTopic> {
+        (*self.p2p_config.node_type.as_ref()).into()
+    }
+
+    /// Sends the announcement to all peers except observers.
+    ///
+    /// If [`P2pConfig::max_relay_peers`] is set, only that many peers are selected, preferring
+    /// the peers that haven't been relayed to on the topic recently. The announcement is sent to
+    /// the outbound peers after the trickle delay (see
+    /// [`P2pConfig::announcement_trickle_interval`]).
     ///
     /// It is not an error if there are no peers that subscribed to the related topic.
     fn announce_data(&mut self, topic: PubSubTopic, message: Vec<u8>) -> crate::Result<()> {
         let announcement = message::Announcement::decode(&mut &message[..])?;
 
-        // Send the message to peers in pseudorandom order.
-        let mut peers: Vec<_> = self
+        // Send the message to peers in pseudorandom order (the order is only randomized among
+        // the peers with the same last relay time).
+        let candidates = self
             .peers
             .iter()
-            .filter(|(_peer_id, peer)| peer.subscriptions.contains(&topic))
+            .filter(|(_peer_id, peer)| !peer.observer && peer.subscriptions.contains(&topic))
+            .map(|(peer_id, peer)| (*peer_id, peer.last_relayed.get(&topic).copied()))
             .collect();
-        peers.shuffle(&mut make_pseudo_rng());
+        let count = self.p2p_config.max_relay_peers.unwrap_or(usize::MAX);
+
+        let now = Instant::now();
+        let trickle_interval = *self.p2p_config.announcement_trickle_interval;
+        for peer_id in select_relay_targets(candidates, count) {
+            if let Some(peer) = self.peers.get_mut(&peer_id) {
+                if peer.outbound && !trickle_interval.is_zero() {
+                    peer.trickled_announcements.push(announcement.clone());
+                    peer.trickle_at.get_or_insert_with(|| now + trickle_delay(trickle_interval));
+                } else {
+                    Self::queue_announcement(
+                        &self.message_counters,
+                        &peer_id,
+                        peer,
+                        announcement.clone(),
+                    );
+                }
+                peer.last_relayed.insert(topic, now);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns when the trickled announcements are sent to some peer next time
+    fn next_trickle_at(&self) -> Option<Instant> {
+        self.peers.values().filter_map(|peer| peer.trickle_at).min()
+    }
 
-        for (peer_id, peer) in peers {
-            let res = peer.tx.send(Event::SendMessage(Box::new(Message::Announcement {
-                announcement: announcement.clone(),
-            })));
+    /// Sends the trickled announcements whose delay has passed
+    fn send_trickled_announcements(&mut self) {
+        let now = Instant::now();
+        for (peer_id, peer) in self.peers.iter_mut() {
+            if peer.trickle_at.map_or(false, |trickle_at| trickle_at <= now) {
+                peer.trickle_at = None;
+                for announcement in std::mem::take(&mut peer.trickled_announcements) {
+                    Self::queue_announcement(&self.message_counters, peer_id, peer, announcement);
+                }
+            }
+        }
+    }
+
+    /// Sends the announcement to the specified peers only.
+    ///
+    /// Unknown or already disconnected peers are skipped.
+    fn announce_data_to(&mut self, peer_ids: Vec<PeerId>, message: Vec<u8>) -> crate::Result<()> {
+        let announcement = message::Announcement::decode(&mut &message[..])?;
+
+        for peer_id in peer_ids {
+            let peer = match self.peers.get(&peer_id) {
+                Some(peer) => peer,
+                None => {
+                    log::debug!("Skip announcement to unknown peer {peer_id}");
+                    continue;
+                }
+            };
+
+            Self::queue_announcement(&self.message_counters, &peer_id, peer, announcement.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Puts the announcement to the peer's outbound queue, dropping the oldest one if it's full.
+    fn queue_announcement(
+        message_counters: &MessageCounters,
+        peer_id: &PeerId,
+        peer: &PeerContext,
+        announcement: Announcement,
+    ) {
+        if peer.announcement_queue.push(announcement) {
+            message_counters.record_announcement_dropped();
+            log::debug!(
+                "Announcement queue is full for peer {peer_id}, the oldest announcement is dropped (dropped total: {})",
+                peer.announcement_queue.dropped_count()
+            );
+        }
+    }
+
+    /// Answers the sync request from the chainstate if the handle is available.
+    ///
+    /// The requests that can't be answered here (or all of them if there is no chainstate handle)
+    /// are passed to the syncing frontend. So are the requests tagged with the magic bytes of
+    /// another chain, the frontend rejects them. Only the header and block requests count against
+    /// the serving limits (see [`P2pConfig::max_concurrent_serving_requests`]), the requests over
+    /// the limits are rejected as rate limited.
+    fn handle_sync_request(
+        &mut self,
+        peer_id: PeerId,
+        request_id: RequestId,
+        request: SyncRequest,
+    ) -> crate::Result<()> {
+        let same_network = request.magic_bytes() == self.chain_config.magic_bytes();
+        let chainstate_handle = match &self.chainstate_handle {
+            Some(chainstate_handle) if same_network && is_served_from_chainstate(&request) => {
+                chainstate_handle.clone()
+            }
+            _ => {
+                return self
+                    .sync_tx
+                    .send(SyncingEvent::Request {
+                        peer_id,
+                        request_id,
+                        request,
+                    })
+                    .map_err(P2pError::from)
+            }
+        };
+
+        if !self.start_serving(&peer_id) {
+            log::debug!("too many requests are being served, reject request {request_id} from peer {peer_id}");
+            let res = self.send_response(
+                request_id,
+                message::Response::Error(message::ErrorResponse {
+                    magic_bytes: *self.chain_config.magic_bytes(),
+                    error: message::ResponseError::RateLimited,
+                }),
+            );
             if let Err(e) = res {
-                log::error!("Failed to send announcement to peer {peer_id}: {e:?}")
+                log::debug!("Failed to send response to peer {peer_id}: {e}")
             }
+            return Ok(());
         }
 
+        let backend_task: BackendTask<T> = async move {
+            let response = make_sync_response(&chainstate_handle, &request).await;
+
+            boxed_cb(move |this| {
+                this.finish_serving(&peer_id);
+                match response {
+                    Some(response) => {
+                        let res = this.send_response(request_id, response);
+                        if let Err(e) = res {
+                            log::debug!("Failed to send response to peer {peer_id}: {e}")
+                        }
+                        Ok(())
+                    }
+                    None => this
+                        .sync_tx
+                        .send(SyncingEvent::Request {
+                            peer_id,
+                            request_id,
+                            request,
+                        })
+                        .map_err(P2pError::from),
+                }
+            })
+        }
+        .boxed();
+        self.command_queue.push(backend_task);
+
         Ok(())
     }
 
+    /// Counts the request as being answered from the chainstate, returns false if the global or
+    /// the peer's limit of such requests is reached
+    fn start_serving(&mut self, peer_id: &PeerId) -> bool {
+        let peer_serving_requests = self.peers.get(peer_id).map_or(0, |peer| peer.serving_requests);
+        if self.serving_requests >= *self.p2p_config.max_concurrent_serving_requests
+            || peer_serving_requests >= *self.p2p_config.max_concurrent_serving_requests_per_peer
+        {
+            return false;
+        }
+
+        self.serving_requests += 1;
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.serving_requests += 1;
+        }
+        true
+    }
+
+    /// The request counted by [`Self::start_serving`] is answered (or passed to the frontend)
+    fn finish_serving(&mut self, peer_id: &PeerId) {
+        self.serving_requests -= 1;
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.serving_requests -= 1;
+        }
+    }
+
     /// Handle incoming request
     fn handle_incoming_request(
         &mut self,
@@ -258,25 +603,43 @@ where
     ) -> crate::Result<()> {
         log::trace!("request received from peer {peer_id}, request id {request_id}");
 
-        let request_id = self.request_mgr.register_request(&peer_id, &request_id)?;
+        if self.peers.get(&peer_id).map_or(false, |peer| peer.draining) {
+            log::debug!("ignore request {request_id} from draining peer {peer_id}");
+            return Ok(());
+        }
+
+        let request_id = match self.request_mgr.register_request(&peer_id, &request_id) {
+            Ok(request_id) => request_id,
+            Err(P2pError::PeerError(
+                PeerError::RequestManagerFull | PeerError::TooManyPeerRequests,
+            )) => {
+                log::debug!(
+                    "ignore request {request_id} from peer {peer_id}, too many pending requests"
+                );
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
 
         match request {
-            message::Request::HeaderListRequest(request) => self
-                .sync_tx
-                .send(SyncingEvent::Request {
-                    peer_id,
-                    request_id,
-                    request: SyncRequest::HeaderListRequest(request),
-                })
-                .map_err(P2pError::from),
-            message::Request::BlockListRequest(request) => self
-                .sync_tx
-                .send(SyncingEvent::Request {
-                    peer_id,
-                    request_id,
-                    request: SyncRequest::BlockListRequest(request),
-                })
-                .map_err(P2pError::from),
+            message::Request::HeaderListRequest(request) => self.handle_sync_request(
+                peer_id,
+                request_id,
+                SyncRequest::HeaderListRequest(request),
+            ),
+            message::Request::BlockListRequest(request) => self.handle_sync_request(
+                peer_id,
+                request_id,
+                SyncRequest::BlockListRequest(request),
+            ),
+            message::Request::TokenInfoRequest(request) => self.handle_sync_request(
+                peer_id,
+                request_id,
+                SyncRequest::TokenInfoRequest(request),
+            ),
+            message::Request::HasData(request) => {
+                self.handle_sync_request(peer_id, request_id, SyncRequest::HasData(request))
+            }
             message::Request::AddrListRequest(request) => self
                 .conn_tx
                 .send(ConnectivityEvent::Request {
@@ -313,6 +676,14 @@ where
     ) -> crate::Result<()> {
         log::trace!("response received from peer {peer_id}, request id {request_id}");
 
+        if let Entry::Occupied(entry) = self.response_waiters.entry(request_id) {
+            if entry.get().0 == peer_id {
+                let (_, response_tx) = entry.remove();
+                response_tx.send(Ok(response));
+                return Ok(());
+            }
+        }
+
         match response {
             message::Response::HeaderListResponse(response) => self
                 .sync_tx
@@ -330,6 +701,38 @@ where
                     response: SyncResponse::BlockListResponse(response),
                 })
                 .map_err(P2pError::from),
+            message::Response::BlocksChunk(chunk) => self
+                .sync_tx
+                .send(SyncingEvent::Response {
+                    peer_id,
+                    request_id,
+                    response: SyncResponse::BlocksChunk(chunk),
+                })
+                .map_err(P2pError::from),
+            message::Response::TokenInfoResponse(response) => self
+                .sync_tx
+                .send(SyncingEvent::Response {
+                    peer_id,
+                    request_id,
+                    response: SyncResponse::TokenInfoResponse(response),
+                })
+                .map_err(P2pError::from),
+            message::Response::DataAvailability(response) => self
+                .sync_tx
+                .send(SyncingEvent::Response {
+                    peer_id,
+                    request_id,
+                    response: SyncResponse::DataAvailability(response),
+                })
+                .map_err(P2pError::from),
+            message::Response::Error(response) => self
+                .sync_tx
+                .send(SyncingEvent::Response {
+                    peer_id,
+                    request_id,
+                    response: SyncResponse::Error(response),
+                })
+                .map_err(P2pError::from),
             message::Response::AddrListResponse(response) => self
                 .conn_tx
                 .send(ConnectivityEvent::Response {
@@ -362,7 +765,8 @@ where
         peer_id: PeerId,
         announcement: Announcement,
     ) -> crate::Result<()> {
-        let size = announcement.encode().len();
+        let message = announcement.encode();
+        let size = message.len();
         if size > ANNOUNCEMENT_MAX_SIZE {
             self.conn_tx
                 .send(ConnectivityEvent::Misbehaved {
@@ -375,6 +779,13 @@ where
                 .map_err(P2pError::from)?;
         }
 
+        if let Some(announcement_tap) = &self.announcement_tap {
+            if announcement_tap.send((announcement.topic(), message)).is_err() {
+                log::debug!("Announcement tap is closed");
+                self.announcement_tap = None;
+            }
+        }
+
         self.sync_tx
             .send(SyncingEvent::Announcement {
                 peer_id,
@@ -386,6 +797,9 @@ where
     /// Runs the backend events loop.
     pub async fn run(&mut self) -> crate::Result<()> {
         loop {
+            let next_trickle_at = self.next_trickle_at();
+            let accept_resume_at = self.accept_resume_at;
+
             tokio::select! {
                 // Select from the channels in the specified order
                 biased;
@@ -404,16 +818,54 @@ where
                     self.handle_peer_event(peer, event)?;
                 },
                 // Accept a new peer connection.
-                res = self.socket.accept() => {
-                    let (stream, address) = res.map_err(|_| P2pError::Other("accept() failed"))?;
-
-                    self.create_peer(
-                        stream,
-                        PeerId::new(),
-                        PeerRole::Inbound,
-                        address,
-                    )?;
+                res = self.socket.accept_timed(), if accept_resume_at.is_none() => match res {
+                    Ok((stream, address, transport_timing)) => {
+                        self.accept_backoff = None;
+                        let peer_id = self.remote_peer_id(&stream);
+                        self.create_peer(
+                            stream,
+                            peer_id,
+                            PeerRole::Inbound,
+                            address,
+                            transport_timing,
+                        )?;
+                    }
+                    Err(err) => {
+                        // The listener keeps working after a failed accept (for example, if the
+                        // connection was reset while waiting in the backlog or the process ran
+                        // out of file descriptors), so the error is only reported. Accepting is
+                        // paused for a while, because some errors would repeat right away.
+                        let backoff = self.accept_backoff.map_or(
+                            ACCEPT_ERROR_MIN_BACKOFF,
+                            |backoff| std::cmp::min(backoff * 2, ACCEPT_ERROR_MAX_BACKOFF),
+                        );
+                        log::warn!(
+                            "failed to accept an incoming connection: {err}, retry in {backoff:?}"
+                        );
+                        self.message_counters.record_accept_error();
+                        self.accept_backoff = Some(backoff);
+                        self.accept_resume_at = Some(Instant::now() + backoff);
+                    }
+                },
+                // Resume accepting connections after a failed accept
+                _ = tokio::time::sleep_until(accept_resume_at.unwrap_or_else(Instant::now)),
+                    if accept_resume_at.is_some() =>
+                {
+                    self.accept_resume_at = None;
                 }
+                // Send the trickled announcements
+                _ = tokio::time::sleep_until(next_trickle_at.unwrap_or_else(Instant::now)),
+                    if next_trickle_at.is_some() =>
+                {
+                    self.send_trickled_announcements();
+                }
+            }
+
+            // The flag is set before the channels are dropped, so the handles can tell
+            // a normal shutdown from a crash
+            if self.shutdown.load(Ordering::Acquire) {
+                log::info!("networking backend is shut down");
+                return Ok(());
             }
         }
     }
@@ -423,14 +875,34 @@ where
     /// Move the connection to `pending` where it stays until either the connection is closed
     /// or the handshake message is received at which point the peer information is moved from
     /// `pending` to `peers` and the front-end is notified about the peer.
+    /// Returns the ID for a new peer connection.
+    ///
+    /// If the connection is authenticated with the remote static key, the ID is derived from it,
+    /// so a node is known under the same ID across reconnects. A new unique ID is used otherwise,
+    /// and also if the derived ID is already taken (which happens with self-connections and
+    /// duplicate connections, those are detected and dropped later).
+    fn remote_peer_id(&self, socket: &T::Stream) -> PeerId {
+        socket
+            .remote_public_key()
+            .map(|key| PeerId::from_public_key(&key))
+            .filter(|peer_id| {
+                !self.pending.contains_key(peer_id) && !self.peers.contains_key(peer_id)
+            })
+            .unwrap_or_else(PeerId::new)
+    }
+
     fn create_peer(
         &mut self,
         socket: T::Stream,
         remote_peer_id: PeerId,
         peer_role: PeerRole,
         address: T::Address,
+        transport_timing: TransportTiming,
     ) -> crate::Result<()> {
         let (tx, rx) = mpsc::unbounded_channel();
+        let announcement_queue = Arc::new(AnnouncementQueue::new(
+            *self.p2p_config.max_outbound_announcement_queue,
+        ));
 
         let receiver_address = Some(address.as_peer_address());
 
@@ -440,12 +912,19 @@ where
                 address,
                 peer_role,
                 tx,
+                announcement_queue: Arc::clone(&announcement_queue),
+                transport_timing,
+                created_at: Instant::now(),
             },
         );
 
         let tx = self.peer_chan.0.clone();
         let chain_config = Arc::clone(&self.chain_config);
         let p2p_config = Arc::clone(&self.p2p_config);
+        let best_block = self.best_block;
+        let rate_limiter = self.rate_limiter.clone();
+        let message_counters = Arc::clone(&self.message_counters);
+        let protocol_tracer = self.protocol_tracer.clone();
 
         tokio::spawn(async move {
             let mut peer = peer::Peer::<T>::new(
@@ -457,6 +936,11 @@ where
                 receiver_address,
                 tx,
                 rx,
+                announcement_queue,
+                best_block,
+                rate_limiter,
+                message_counters,
+                protocol_tracer,
             );
             let run_res = peer.run().await;
             if let Err(err) = run_res {
@@ -517,12 +1001,18 @@ where
                 version,
                 subscriptions,
                 receiver_address,
+                observer,
+                best_block,
+                node_kind,
                 handshake_nonce,
             } => {
                 let PendingPeerContext {
                     address,
                     peer_role,
                     tx,
+                    announcement_queue,
+                    transport_timing,
+                    created_at,
                 } = match self.pending.remove(&peer_id) {
                     Some(pending) => pending,
                     // Might be removed if self-connection detected
@@ -533,6 +1023,12 @@ where
                     return Ok(());
                 }
 
+                let timing = ConnectionTiming {
+                    tcp_connect: transport_timing.tcp_connect,
+                    noise_handshake: transport_timing.noise_handshake,
+                    handshake: created_at.elapsed(),
+                };
+
                 match peer_role {
                     PeerRole::Outbound { handshake_nonce: _ } => {
                         self.conn_tx
@@ -544,8 +1040,11 @@ where
                                     version,
                                     agent: None,
                                     subscriptions: subscriptions.clone(),
+                                    best_block,
+                                    node_kind,
                                 },
                                 receiver_address,
+                                timing,
                             })
                             .map_err(P2pError::from)?;
                     }
@@ -559,23 +1058,68 @@ where
                                     version,
                                     agent: None,
                                     subscriptions: subscriptions.clone(),
+                                    best_block,
+                                    node_kind,
                                 },
                                 receiver_address,
+                                timing,
                             })
                             .map_err(P2pError::from)?;
                     }
                 }
 
-                self.peers.insert(peer_id, PeerContext { subscriptions, tx });
+                self.peers.insert(
+                    peer_id,
+                    PeerContext {
+                        subscriptions,
+                        observer,
+                        tx,
+                        announcement_queue,
+                        last_relayed: BTreeMap::new(),
+                        outbound: matches!(peer_role, PeerRole::Outbound { .. }),
+                        trickled_announcements: Vec::new(),
+                        trickle_at: None,
+                        draining: false,
+                        serving_requests: 0,
+                    },
+                );
+                self.update_peer_count();
                 let _ = self.request_mgr.register_peer(peer_id);
             }
             PeerEvent::MessageReceived { message } => {
                 self.handle_message(peer_id, message)?;
             }
+            PeerEvent::Misbehaved { error } => {
+                self.conn_tx
+                    .send(ConnectivityEvent::Misbehaved { peer_id, error })
+                    .map_err(P2pError::from)?;
+            }
+            PeerEvent::HandshakeFailed { error } => {
+                // The pending context is removed once the connection is closed
+                if let Some(pending) = self.pending.get(&peer_id) {
+                    self.conn_tx
+                        .send(ConnectivityEvent::ConnectionError {
+                            address: pending.address.clone(),
+                            error,
+                        })
+                        .map_err(P2pError::from)?;
+                }
+            }
             PeerEvent::ConnectionClosed => {
-                self.pending.remove(&peer_id);
+                let pending = self.pending.remove(&peer_id);
                 self.peers.remove(&peer_id);
+                self.update_peer_count();
                 self.request_mgr.unregister_peer(&peer_id);
+                self.cancel_response_waiters(&peer_id);
+
+                // The front-end doesn't know about inbound peers that failed to handshake
+                if let Some(PendingPeerContext {
+                    peer_role: PeerRole::Inbound,
+                    ..
+                }) = pending
+                {
+                    return Ok(());
+                }
 
                 // Probably ConnectionClosed should be only sent if InboundAccepted or OutboundAccepted was sent before.
                 // This can be done by checking self.peers first.
@@ -622,7 +1166,7 @@ where
             Command::Connect { address } => {
                 let connection_fut = timeout(
                     *self.p2p_config.outbound_connection_timeout,
-                    self.transport.connect(address.clone()),
+                    self.transport.connect_timed(address.clone()),
                 );
 
                 async move {
@@ -644,6 +1188,33 @@ where
                 })
             }
             .boxed(),
+            Command::DrainAndDisconnect { peer_id, timeout } => async move {
+                boxed_cb(move |this: &mut Self| {
+                    if let Err(e) = this.drain_peer(&peer_id) {
+                        log::debug!("Failed to drain peer {peer_id}: {e}");
+                        return Ok(());
+                    }
+
+                    // Disconnect the peer anyway once the timeout expires
+                    this.command_queue.push(
+                        async move {
+                            tokio::time::sleep(timeout).await;
+                            boxed_cb(move |this: &mut Self| {
+                                if this.peers.get(&peer_id).map_or(false, |peer| peer.draining) {
+                                    log::debug!("draining peer {peer_id} timed out, disconnecting");
+                                    if let Err(e) = this.disconnect_peer(&peer_id) {
+                                        log::debug!("Failed to disconnect peer {peer_id}: {e}")
+                                    }
+                                }
+                                Ok(())
+                            })
+                        }
+                        .boxed(),
+                    );
+                    Ok(())
+                })
+            }
+            .boxed(),
             Command::SendRequest {
                 peer_id,
                 request_id,
@@ -658,6 +1229,23 @@ where
                 })
             }
             .boxed(),
+            Command::SendRequestAndWait {
+                peer_id,
+                request_id,
+                message,
+                response_tx,
+            } => async move {
+                boxed_cb(move |this| {
+                    match this.send_request(request_id, peer_id, message) {
+                        Ok(()) => {
+                            this.response_waiters.insert(request_id, (peer_id, response_tx));
+                        }
+                        Err(e) => response_tx.send(Err(e)),
+                    }
+                    Ok(())
+                })
+            }
+            .boxed(),
             Command::SendResponse {
                 request_id,
                 message,
@@ -681,6 +1269,37 @@ where
                 })
             }
             .boxed(),
+            Command::AnnounceDataTo { peers, message } => async move {
+                boxed_cb(move |this| {
+                    let res = this.announce_data_to(peers, message);
+                    if let Err(e) = res {
+                        log::error!("Failed to send announce data to peers: {e}")
+                    }
+                    Ok(())
+                })
+            }
+            .boxed(),
+            Command::UpdateBestBlock { best_block } => async move {
+                boxed_cb(move |this| {
+                    this.best_block = Some(best_block);
+                    Ok(())
+                })
+            }
+            .boxed(),
+            Command::GetSubscriptions { response } => async move {
+                boxed_cb(move |this| {
+                    response.send(this.subscriptions());
+                    Ok(())
+                })
+            }
+            .boxed(),
+            Command::Shutdown => async move {
+                boxed_cb(move |this: &mut Self| {
+                    this.shutdown.store(true, Ordering::Release);
+                    Ok(())
+                })
+            }
+            .boxed(),
         };
 
         self.command_queue.push(backend_task);
@@ -689,6 +1308,61 @@ where
     }
 }
 
+type ResponseSender = oneshot_nofail::Sender<crate::Result<message::Response>>;
+
+/// Returns true if the request can be answered by [`make_sync_response`]
+fn is_served_from_chainstate(request: &SyncRequest) -> bool {
+    match request {
+        SyncRequest::HeaderListRequest(_) => true,
+        SyncRequest::BlockListRequest(request) => request.block_ids().len() == 1,
+        SyncRequest::TokenInfoRequest(_) | SyncRequest::HasData(_) => false,
+    }
+}
+
+/// Makes the response to the sync request from the chainstate.
+///
+/// Returns `None` if the request should be handled by the syncing frontend instead: the chainstate
+/// call has failed, the requested block is unknown (the peer is punished for that by the frontend)
+/// or the response doesn't fit into one message.
+async fn make_sync_response(
+    chainstate_handle: &ChainstateHandle,
+    request: &SyncRequest,
+) -> Option<message::Response> {
+    match request {
+        SyncRequest::HeaderListRequest(request) => {
+            let locator = request.locator().clone();
+            let response = chainstate_handle
+                .call(move |this| {
+                    message::HeaderListResponse::from_chainstate(this.as_ref(), locator)
+                })
+                .await
+                .ok()?
+                .ok()?;
+
+            Some(message::Response::HeaderListResponse(response))
+        }
+        SyncRequest::BlockListRequest(request) => {
+            let block_id = match request.block_ids() {
+                [block_id] => *block_id,
+                _ => return None,
+            };
+            let block = chainstate_handle
+                .call(move |this| this.get_block(block_id))
+                .await
+                .ok()?
+                .ok()??;
+            if block.encoded_size() > MAX_BLOCKS_CHUNK_SIZE {
+                return None;
+            }
+
+            Some(message::Response::BlockListResponse(
+                message::BlockListResponse::new(*request.magic_bytes(), vec![block]),
+            ))
+        }
+        SyncRequest::TokenInfoRequest(_) | SyncRequest::HasData(_) => None,
+    }
+}
+
 // Some boilerplate types and a function for blocking tasks handling
 
 type BackendTask<T> = BoxFuture<'static, BackendTaskCallback<T>>;
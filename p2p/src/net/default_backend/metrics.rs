@@ -0,0 +1,111 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Message counters of the backend
+//!
+//! The counters are shared by all peer tasks and updated every time a message is sent to or
+//! received from a remote peer.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    message::{Request, Response},
+    net::default_backend::types::Message,
+};
+
+/// The type of a message used to group the counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Handshake,
+    /// Requests except pings
+    Request,
+    /// Responses except pings
+    Response,
+    Announcement,
+    /// Ping requests and responses
+    Ping,
+}
+
+impl From<&Message> for MessageKind {
+    fn from(message: &Message) -> Self {
+        match message {
+            Message::Handshake(_) => MessageKind::Handshake,
+            Message::Request {
+                request_id: _,
+                request: Request::PingRequest(_),
+            } => MessageKind::Ping,
+            Message::Request {
+                request_id: _,
+                request: _,
+            } => MessageKind::Request,
+            Message::Response {
+                request_id: _,
+                response: Response::PingResponse(_),
+            } => MessageKind::Ping,
+            Message::Response {
+                request_id: _,
+                response: _,
+            } => MessageKind::Response,
+            Message::Announcement { announcement: _ } => MessageKind::Announcement,
+        }
+    }
+}
+
+/// Snapshot of the backend's message counters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackendMetrics {
+    /// The number of messages sent to all peers
+    pub sent: HashMap<MessageKind, u64>,
+
+    /// The number of messages received from all peers
+    pub received: HashMap<MessageKind, u64>,
+
+    /// The number of failed attempts to accept an incoming connection
+    pub accept_errors: u64,
+
+    /// The number of announcements dropped because the outbound queue of the peer was full
+    pub announcements_dropped: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct MessageCounters {
+    metrics: Mutex<BackendMetrics>,
+}
+
+impl MessageCounters {
+    pub fn record_sent(&self, message: &Message) {
+        let mut metrics = self.metrics.lock().expect("Metrics mutex is poisoned");
+        *metrics.sent.entry(message.into()).or_default() += 1;
+    }
+
+    pub fn record_received(&self, message: &Message) {
+        let mut metrics = self.metrics.lock().expect("Metrics mutex is poisoned");
+        *metrics.received.entry(message.into()).or_default() += 1;
+    }
+
+    pub fn record_accept_error(&self) {
+        let mut metrics = self.metrics.lock().expect("Metrics mutex is poisoned");
+        metrics.accept_errors += 1;
+    }
+
+    pub fn record_announcement_dropped(&self) {
+        let mut metrics = self.metrics.lock().expect("Metrics mutex is poisoned");
+        metrics.announcements_dropped += 1;
+    }
+
+    pub fn snapshot(&self) -> BackendMetrics {
+        self.metrics.lock().expect("Metrics mutex is poisoned").clone()
+    }
+}
@@ -13,18 +13,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod announcement_queue;
 pub mod backend;
 pub mod constants;
+pub mod metrics;
 pub mod peer;
+pub mod rate_limiter;
+pub mod relay_targets;
 pub mod request_manager;
+pub mod tracer;
 pub mod transport;
 pub mod types;
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    collections::BTreeSet,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, watch};
 
+use chainstate::ChainstateHandle;
+
+use crypto::key::{signature::SignatureKind, KeyKind, PrivateKey, PublicKey, Signature};
 use logging::log;
 use serialization::Encode;
 
@@ -35,14 +51,21 @@ use crate::{
     net::{
         default_backend::{
             constants::ANNOUNCEMENT_MAX_SIZE,
+            metrics::{BackendMetrics, MessageCounters},
+            tracer::ProtocolTracer,
             transport::{TransportListener, TransportSocket},
-            types::{PeerId, RequestId},
+            types::{AnnouncementTap, PeerId, RequestId},
         },
-        types::{ConnectivityEvent, PubSubTopic, SyncingEvent},
+        types::{BestBlock, ConnectivityEvent, PubSubTopic, SyncingEvent},
         ConnectivityService, NetworkingService, SyncingMessagingService,
     },
+    utils::oneshot_nofail,
 };
 
+/// How many connectivity events are kept for the subscribers that are behind
+/// (see [`ConnectivityHandle::subscribe_events`])
+pub const CONNECTIVITY_EVENTS_CAPACITY: usize = 256;
+
 #[derive(Debug)]
 pub struct DefaultNetworkingService<T: TransportSocket>(PhantomData<T>);
 
@@ -51,28 +74,132 @@ pub struct ConnectivityHandle<S: NetworkingService, T: TransportSocket> {
     /// The local addresses of a network service provider.
     local_addresses: Vec<S::Address>,
 
+    /// Peer ID of the local node
+    local_peer_id: PeerId,
+
     /// TX channel for sending commands to default_backend backend
     cmd_tx: mpsc::UnboundedSender<types::Command<T>>,
 
     /// RX channel for receiving connectivity events from default_backend backend
     conn_rx: mpsc::UnboundedReceiver<types::ConnectivityEvent<T>>,
 
+    /// Set by the backend before it stops normally
+    backend_shutdown: Arc<AtomicBool>,
+
+    /// Message counters updated by the backend
+    message_counters: Arc<MessageCounters>,
+
+    /// The number of connected peers, updated by the backend
+    peer_count_rx: watch::Receiver<usize>,
+
+    /// Identity key used to sign challenges, generated when the handle is created
+    identity_key: PrivateKey,
+
+    /// Copies of the connectivity events for the subscribers (see
+    /// [`ConnectivityHandle::subscribe_events`])
+    events_tx: broadcast::Sender<types::BroadcastConnectivityEvent<T::Address>>,
+
     _marker: PhantomData<fn() -> S>,
 }
 
 impl<S: NetworkingService, T: TransportSocket> ConnectivityHandle<S, T> {
     pub fn new(
         local_addresses: Vec<S::Address>,
+        local_peer_id: PeerId,
         cmd_tx: mpsc::UnboundedSender<types::Command<T>>,
         conn_rx: mpsc::UnboundedReceiver<types::ConnectivityEvent<T>>,
+        backend_shutdown: Arc<AtomicBool>,
+        message_counters: Arc<MessageCounters>,
+        peer_count_rx: watch::Receiver<usize>,
     ) -> Self {
         Self {
             local_addresses,
+            local_peer_id,
             cmd_tx,
             conn_rx,
+            backend_shutdown,
+            message_counters,
+            peer_count_rx,
+            identity_key: PrivateKey::new_from_entropy(KeyKind::Secp256k1Schnorr).0,
+            events_tx: broadcast::channel(CONNECTIVITY_EVENTS_CAPACITY).0,
             _marker: PhantomData,
         }
     }
+
+    /// Returns a receiver of the copies of the connectivity events
+    ///
+    /// Any number of subscribers can observe the events without taking them from
+    /// [`ConnectivityService::poll_next`]. An event is broadcast once it's polled by the handle
+    /// owner. The subscribers that fall more than [`CONNECTIVITY_EVENTS_CAPACITY`] events behind
+    /// get [`broadcast::error::RecvError::Lagged`] and miss the oldest events.
+    pub fn subscribe_events(
+        &self,
+    ) -> broadcast::Receiver<types::BroadcastConnectivityEvent<T::Address>> {
+        self.events_tx.subscribe()
+    }
+
+    /// Returns the current values of the message counters
+    pub fn metrics(&self) -> BackendMetrics {
+        self.message_counters.snapshot()
+    }
+
+    /// Waits until at least `peer_count` peers are connected.
+    ///
+    /// Returns [`P2pError::WaitForPeersTimeout`] if that doesn't happen within `timeout`.
+    pub async fn wait_for_peers(&self, peer_count: usize, timeout: Duration) -> crate::Result<()> {
+        let mut peer_count_rx = self.peer_count_rx.clone();
+        let wait_fut = async {
+            while *peer_count_rx.borrow_and_update() < peer_count {
+                peer_count_rx
+                    .changed()
+                    .await
+                    .map_err(|_| backend_closed_error(&self.backend_shutdown))?;
+            }
+            Ok(())
+        };
+        tokio::time::timeout(timeout, wait_fut)
+            .await
+            .map_err(|_| P2pError::WaitForPeersTimeout(peer_count))?
+    }
+
+    /// Returns the public key of the node identity, used to verify the signed challenges.
+    pub fn identity_public_key(&self) -> PublicKey {
+        PublicKey::from_private_key(&self.identity_key)
+    }
+
+    /// Signs the challenge with the node identity key.
+    ///
+    /// Lets the node prove to an external service that it controls the identity,
+    /// see [`verify_challenge`].
+    pub fn sign_challenge(&self, challenge: &[u8]) -> Signature {
+        self.identity_key
+            .sign_message(challenge)
+            .expect("signing with a secp256k1 key must succeed")
+    }
+
+    /// Returns the topics the local node is currently subscribed to
+    pub async fn subscriptions(&self) -> crate::Result<BTreeSet<PubSubTopic>> {
+        let (response, response_rx) = oneshot_nofail::channel();
+        self.cmd_tx
+            .send(types::Command::GetSubscriptions { response })
+            .map_err(P2pError::from)?;
+        response_rx.await.map_err(|_| backend_closed_error(&self.backend_shutdown))
+    }
+
+    /// Asks the backend to stop.
+    ///
+    /// After the backend is stopped, `poll_next` returns [`P2pError::BackendShutdown`].
+    pub fn shutdown(&self) -> crate::Result<()> {
+        self.cmd_tx.send(types::Command::Shutdown).map_err(P2pError::from)
+    }
+
+    /// Returns the peer ID of the local node.
+    ///
+    /// The ID is derived from the transport's static public key if there is one, so it stays the
+    /// same as long as the node is started with the same keypair. Otherwise a new ID is generated.
+    pub fn local_peer_id(&self) -> PeerId {
+        self.local_peer_id
+    }
 }
 
 pub struct PubSubHandle<S, T>
@@ -101,58 +228,227 @@ where
     /// RX channel for receiving syncing events
     sync_rx: mpsc::UnboundedReceiver<types::SyncingEvent>,
 
+    /// Set by the backend before it stops normally
+    backend_shutdown: Arc<AtomicBool>,
+
     _marker: PhantomData<fn() -> S>,
 }
 
-#[async_trait]
-impl<T: TransportSocket> NetworkingService for DefaultNetworkingService<T> {
-    type Transport = T;
-    type Address = T::Address;
-    type BannableAddress = T::BannableAddress;
-    type PeerId = PeerId;
-    type PeerRequestId = RequestId;
-    type ConnectivityHandle = ConnectivityHandle<Self, T>;
-    type SyncingMessagingHandle = SyncingMessagingHandle<Self, T>;
+impl<S: NetworkingService, T: TransportSocket> SyncingMessagingHandle<S, T> {
+    /// Sends the request to the peer and waits for the response.
+    ///
+    /// The response is returned here and is not reported as a syncing event.
+    /// If the peer disconnects before responding, [`crate::error::PeerError::PeerDisconnected`]
+    /// is returned.
+    pub async fn send_request_and_wait(
+        &self,
+        peer_id: PeerId,
+        request: SyncRequest,
+    ) -> crate::Result<message::Response> {
+        let (response_tx, response_rx) = oneshot_nofail::channel();
+        self.cmd_tx
+            .send(types::Command::SendRequestAndWait {
+                peer_id,
+                request_id: RequestId::new(),
+                message: request.into(),
+                response_tx,
+            })
+            .map_err(P2pError::from)?;
+        response_rx.await.map_err(|_| backend_closed_error(&self.backend_shutdown))?
+    }
+}
 
-    async fn start(
-        transport: Self::Transport,
-        bind_addresses: Vec<Self::Address>,
+/// Checks that the challenge was signed by the node with the given identity public key.
+///
+/// See [`ConnectivityHandle::sign_challenge`].
+pub fn verify_challenge(public_key: &PublicKey, challenge: &[u8], signature: &Signature) -> bool {
+    // `verify_message` panics if the key and the signature kinds don't match
+    matches!(public_key.kind(), KeyKind::Secp256k1Schnorr)
+        && matches!(signature.kind(), SignatureKind::Secp256k1Schnorr)
+        && public_key.verify_message(signature, challenge)
+}
+
+/// Returns the error reported by the handles when the backend's channels are closed.
+///
+/// Channels closed without the shutdown flag set mean that the backend has failed or panicked.
+fn backend_closed_error(backend_shutdown: &AtomicBool) -> P2pError {
+    if backend_shutdown.load(Ordering::Acquire) {
+        P2pError::BackendShutdown
+    } else {
+        P2pError::BackendCrashed
+    }
+}
+
+impl<T: TransportSocket> DefaultNetworkingService<T> {
+    /// Returns the transports this build of the networking backend supports
+    pub fn supported_transports() -> &'static [transport::TransportKind] {
+        transport::SUPPORTED_TRANSPORTS
+    }
+
+    /// Returns the builder that starts the networking service with optional features enabled
+    pub fn builder(
+        transport: T,
+        bind_addresses: Vec<T::Address>,
         chain_config: Arc<common::chain::ChainConfig>,
         p2p_config: Arc<config::P2pConfig>,
-    ) -> crate::Result<(Self::ConnectivityHandle, Self::SyncingMessagingHandle)> {
+    ) -> BackendBuilder<T> {
+        BackendBuilder {
+            transport,
+            bind_addresses,
+            chain_config,
+            p2p_config,
+            chainstate_handle: None,
+            announcement_tap: None,
+            protocol_tracer: None,
+        }
+    }
+
+    /// Starts the networking service, like [`NetworkingService::start`], with an announcement tap.
+    ///
+    /// The tap receives the topic and the encoded bytes of every announcement received from
+    /// the peers, so they don't have to be encoded again.
+    pub async fn start_with_announcement_tap(
+        transport: T,
+        bind_addresses: Vec<T::Address>,
+        chain_config: Arc<common::chain::ChainConfig>,
+        p2p_config: Arc<config::P2pConfig>,
+        announcement_tap: Option<AnnouncementTap>,
+    ) -> crate::Result<(ConnectivityHandle<Self, T>, SyncingMessagingHandle<Self, T>)> {
+        let mut builder = Self::builder(transport, bind_addresses, chain_config, p2p_config);
+        builder.announcement_tap = announcement_tap;
+        builder.start().await
+    }
+
+    /// Starts the networking service, like [`NetworkingService::start`], with a chainstate handle.
+    ///
+    /// The backend answers the header and block requests from the chainstate directly, they are
+    /// passed to the syncing frontend only if the backend can't answer them.
+    pub async fn start_with_chainstate(
+        transport: T,
+        bind_addresses: Vec<T::Address>,
+        chain_config: Arc<common::chain::ChainConfig>,
+        p2p_config: Arc<config::P2pConfig>,
+        chainstate_handle: ChainstateHandle,
+    ) -> crate::Result<(ConnectivityHandle<Self, T>, SyncingMessagingHandle<Self, T>)> {
+        Self::builder(transport, bind_addresses, chain_config, p2p_config)
+            .with_chainstate(chainstate_handle)
+            .start()
+            .await
+    }
+}
+
+/// Starts the networking service with the optional features of the backend
+pub struct BackendBuilder<T: TransportSocket> {
+    transport: T,
+    bind_addresses: Vec<T::Address>,
+    chain_config: Arc<common::chain::ChainConfig>,
+    p2p_config: Arc<config::P2pConfig>,
+    chainstate_handle: Option<ChainstateHandle>,
+    announcement_tap: Option<AnnouncementTap>,
+    protocol_tracer: Option<ProtocolTracer>,
+}
+
+impl<T: TransportSocket> BackendBuilder<T> {
+    /// See [`DefaultNetworkingService::start_with_chainstate`]
+    pub fn with_chainstate(mut self, chainstate_handle: ChainstateHandle) -> Self {
+        self.chainstate_handle = Some(chainstate_handle);
+        self
+    }
+
+    /// See [`DefaultNetworkingService::start_with_announcement_tap`]
+    pub fn with_announcement_tap(mut self, announcement_tap: AnnouncementTap) -> Self {
+        self.announcement_tap = Some(announcement_tap);
+        self
+    }
+
+    /// Records the messages exchanged with all peers, see [`ProtocolTracer`]
+    pub fn with_protocol_tracer(mut self, protocol_tracer: ProtocolTracer) -> Self {
+        self.protocol_tracer = Some(protocol_tracer);
+        self
+    }
+
+    pub async fn start(
+        self,
+    ) -> crate::Result<(
+        ConnectivityHandle<DefaultNetworkingService<T>, T>,
+        SyncingMessagingHandle<DefaultNetworkingService<T>, T>,
+    )> {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
         let (conn_tx, conn_rx) = mpsc::unbounded_channel();
         let (sync_tx, sync_rx) = mpsc::unbounded_channel();
-        let socket = transport.bind(bind_addresses).await?;
+        let backend_shutdown = Arc::new(AtomicBool::new(false));
+        let message_counters = Arc::new(MessageCounters::default());
+        let (peer_count_tx, peer_count_rx) = watch::channel(0);
+        let socket = self.transport.bind(self.bind_addresses).await?;
         let local_addresses = socket.local_addresses().expect("to have bind address available");
+        let local_peer_id = self
+            .transport
+            .local_public_key()
+            .map_or_else(PeerId::new, PeerId::from_public_key);
+
+        let mut backend = backend::Backend::<T>::new(
+            self.transport,
+            socket,
+            self.chain_config,
+            self.p2p_config,
+            cmd_rx,
+            conn_tx,
+            sync_tx,
+            Arc::clone(&backend_shutdown),
+            Arc::clone(&message_counters),
+            peer_count_tx,
+            self.chainstate_handle,
+            self.announcement_tap,
+            self.protocol_tracer,
+        );
 
         tokio::spawn(async move {
-            let mut backend = backend::Backend::<T>::new(
-                transport,
-                socket,
-                chain_config,
-                p2p_config,
-                cmd_rx,
-                conn_tx,
-                sync_tx,
-            );
-
             if let Err(err) = backend.run().await {
                 log::error!("failed to run backend: {err}");
             }
         });
 
         Ok((
-            ConnectivityHandle::new(local_addresses, cmd_tx.clone(), conn_rx),
-            Self::SyncingMessagingHandle {
+            ConnectivityHandle::new(
+                local_addresses,
+                local_peer_id,
+                cmd_tx.clone(),
+                conn_rx,
+                Arc::clone(&backend_shutdown),
+                message_counters,
+                peer_count_rx,
+            ),
+            SyncingMessagingHandle {
                 cmd_tx,
                 sync_rx,
+                backend_shutdown,
                 _marker: Default::default(),
             },
         ))
     }
 }
 
+#[async_trait]
+impl<T: TransportSocket> NetworkingService for DefaultNetworkingService<T> {
+    type Transport = T;
+    type Address = T::Address;
+    type BannableAddress = T::BannableAddress;
+    type PeerId = PeerId;
+    type PeerRequestId = RequestId;
+    type ConnectivityHandle = ConnectivityHandle<Self, T>;
+    type SyncingMessagingHandle = SyncingMessagingHandle<Self, T>;
+
+    async fn start(
+        transport: Self::Transport,
+        bind_addresses: Vec<Self::Address>,
+        chain_config: Arc<common::chain::ChainConfig>,
+        p2p_config: Arc<config::P2pConfig>,
+    ) -> crate::Result<(Self::ConnectivityHandle, Self::SyncingMessagingHandle)> {
+        Self::start_with_announcement_tap(transport, bind_addresses, chain_config, p2p_config, None)
+            .await
+    }
+}
+
 #[async_trait]
 impl<S, T> ConnectivityService<S> for ConnectivityHandle<S, T>
 where
@@ -174,6 +470,14 @@ where
         self.cmd_tx.send(types::Command::Disconnect { peer_id }).map_err(P2pError::from)
     }
 
+    fn drain_and_disconnect(&mut self, peer_id: S::PeerId, timeout: Duration) -> crate::Result<()> {
+        log::debug!("drain and close connection with remote, {peer_id}");
+
+        self.cmd_tx
+            .send(types::Command::DrainAndDisconnect { peer_id, timeout })
+            .map_err(P2pError::from)
+    }
+
     fn send_request(
         &mut self,
         peer_id: S::PeerId,
@@ -208,7 +512,18 @@ where
     }
 
     async fn poll_next(&mut self) -> crate::Result<ConnectivityEvent<S>> {
-        match self.conn_rx.recv().await.ok_or(P2pError::ChannelClosed)? {
+        let event = self
+            .conn_rx
+            .recv()
+            .await
+            .ok_or_else(|| backend_closed_error(&self.backend_shutdown))?;
+
+        // Sending fails only if there are no subscribers
+        if self.events_tx.receiver_count() > 0 {
+            let _ = self.events_tx.send((&event).into());
+        }
+
+        match event {
             types::ConnectivityEvent::Request {
                 peer_id,
                 request_id,
@@ -231,19 +546,23 @@ where
                 address,
                 peer_info,
                 receiver_address,
+                timing,
             } => Ok(ConnectivityEvent::InboundAccepted {
                 address,
                 peer_info,
                 receiver_address,
+                timing,
             }),
             types::ConnectivityEvent::OutboundAccepted {
                 address,
                 peer_info,
                 receiver_address,
+                timing,
             } => Ok(ConnectivityEvent::OutboundAccepted {
                 address,
                 peer_info,
                 receiver_address,
+                timing,
             }),
             types::ConnectivityEvent::ConnectionError { address, error } => {
                 Ok(ConnectivityEvent::ConnectionError { address, error })
@@ -301,17 +620,44 @@ where
             )));
         }
 
-        let topic = match &announcement {
-            message::Announcement::Block(_) => PubSubTopic::Blocks,
-        };
+        self.cmd_tx
+            .send(types::Command::AnnounceData {
+                topic: announcement.topic(),
+                message,
+            })
+            .map_err(P2pError::from)
+    }
 
+    fn announce_to(
+        &mut self,
+        peers: &[S::PeerId],
+        announcement: message::Announcement,
+    ) -> crate::Result<()> {
+        let message = announcement.encode();
+        if message.len() > ANNOUNCEMENT_MAX_SIZE {
+            return Err(P2pError::PublishError(PublishError::MessageTooLarge(
+                message.len(),
+                ANNOUNCEMENT_MAX_SIZE,
+            )));
+        }
+
+        self.cmd_tx
+            .send(types::Command::AnnounceDataTo {
+                peers: peers.to_vec(),
+                message,
+            })
+            .map_err(P2pError::from)
+    }
+
+    fn update_best_block(&mut self, best_block: BestBlock) -> crate::Result<()> {
         self.cmd_tx
-            .send(types::Command::AnnounceData { topic, message })
+            .send(types::Command::UpdateBestBlock { best_block })
             .map_err(P2pError::from)
     }
 
     async fn poll_next(&mut self) -> crate::Result<SyncingEvent<S>> {
-        match self.sync_rx.recv().await.ok_or(P2pError::ChannelClosed)? {
+        let event = self.sync_rx.recv().await;
+        match event.ok_or_else(|| backend_closed_error(&self.backend_shutdown))? {
             types::SyncingEvent::Request {
                 peer_id,
                 request_id,
@@ -344,14 +690,29 @@ where
 #[cfg(test)]
 mod tests {
     use super::{transport::NoiseTcpTransport, *};
-    use crate::error::DialError;
+    use crate::error::{DialError, PeerError};
     use crate::testing_utils::{TestTransportChannel, TestTransportMaker, TestTransportTcp};
     use crate::{
-        net::default_backend::transport::{MpscChannelTransport, TcpTransportSocket},
+        net::{
+            default_backend::{
+                transport::{
+                    BufferedTranscoder, MpscChannelTransport, NoiseEncryptionAdapter,
+                    TcpTransportSocket, WrappedTransportSocket,
+                },
+                types::{HandshakeMessage, Message},
+            },
+            types::NodeKind,
+        },
         testing_utils::TestTransportNoise,
     };
-    use common::primitives::semver::SemVer;
-    use std::fmt::Debug;
+    use common::{
+        chain::block::{
+            consensus_data::ConsensusData, timestamp::BlockTimestamp, Block, BlockReward,
+        },
+        primitives::{semver::SemVer, Id, Idable, H256},
+    };
+    use std::{fmt::Debug, time::Duration};
+    use tokio::io::AsyncReadExt;
 
     async fn connect_to_remote<A, T>()
     where
@@ -386,6 +747,7 @@ mod tests {
             address,
             peer_info,
             receiver_address: _,
+            timing: _,
         }) = conn1.poll_next().await
         {
             assert_eq!(address, conn2.local_addresses()[0]);
@@ -416,6 +778,73 @@ mod tests {
         connect_to_remote::<TestTransportNoise, NoiseTcpTransport>().await;
     }
 
+    #[tokio::test]
+    async fn local_peer_id_from_keypair() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+        let keypair = NoiseEncryptionAdapter::generate_keypair();
+
+        let start = |keypair: &snowstorm::Keypair| {
+            let keypair = snowstorm::Keypair {
+                private: keypair.private.clone(),
+                public: keypair.public.clone(),
+            };
+            DefaultNetworkingService::<NoiseTcpTransport>::start(
+                NoiseTcpTransport::new(
+                    NoiseEncryptionAdapter::new(keypair),
+                    TcpTransportSocket::new(),
+                ),
+                vec![TestTransportNoise::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+            )
+        };
+
+        let (conn1, _) = start(&keypair).await.unwrap();
+        let (conn2, _) = start(&keypair).await.unwrap();
+        assert_eq!(conn1.local_peer_id(), conn2.local_peer_id());
+        assert_eq!(
+            conn1.local_peer_id(),
+            PeerId::from_public_key(&keypair.public)
+        );
+
+        let (conn3, _) = start(&NoiseEncryptionAdapter::generate_keypair()).await.unwrap();
+        assert_ne!(conn1.local_peer_id(), conn3.local_peer_id());
+    }
+
+    #[tokio::test]
+    async fn remote_peer_id_from_keypair() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let start = || {
+            DefaultNetworkingService::<NoiseTcpTransport>::start(
+                TestTransportNoise::make_transport(),
+                vec![TestTransportNoise::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+            )
+        };
+
+        let (mut conn1, _) = start().await.unwrap();
+        let (mut conn2, _) = start().await.unwrap();
+
+        conn1.connect(conn2.local_addresses()[0].clone()).unwrap();
+
+        match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => {
+                assert_eq!(peer_info.peer_id, conn2.local_peer_id());
+            }
+            _ => panic!("invalid event received, expected outbound connection"),
+        }
+        match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::InboundAccepted { peer_info, .. } => {
+                assert_eq!(peer_info.peer_id, conn1.local_peer_id());
+            }
+            _ => panic!("invalid event received, expected incoming connection"),
+        }
+    }
+
     async fn accept_incoming<A, T>()
     where
         A: TestTransportMaker<Transport = T, Address = T::Address>,
@@ -450,6 +879,7 @@ mod tests {
                 address: _,
                 peer_info,
                 receiver_address: _,
+                timing: _,
             } => {
                 assert_eq!(peer_info.network, *config.magic_bytes());
                 assert_eq!(
@@ -510,6 +940,7 @@ mod tests {
                 address: _,
                 peer_info,
                 receiver_address: _,
+                timing: _,
             } => {
                 assert_eq!(conn2.disconnect(peer_info.peer_id), Ok(()));
             }
@@ -517,6 +948,90 @@ mod tests {
         }
     }
 
+    // The pending response is sent before the connection is closed
+    async fn drain_and_disconnect<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, _) = DefaultNetworkingService::<T>::start(
+            A::make_transport(),
+            vec![A::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, _) = DefaultNetworkingService::<T>::start(
+            A::make_transport(),
+            vec![A::make_address()],
+            config,
+            p2p_config,
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0].clone()).unwrap();
+        let peer_id1 = match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::InboundAccepted { peer_info, .. } => peer_info.peer_id,
+            _ => panic!("invalid event received, expected incoming connection"),
+        };
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            _ => panic!("invalid event received, expected outbound connection"),
+        };
+
+        conn1
+            .send_request(
+                peer_id2,
+                PeerManagerRequest::PingRequest(message::PingRequest { nonce: 1 }),
+            )
+            .unwrap();
+        let request_id = match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::Request { request_id, .. } => request_id,
+            _ => panic!("invalid event received, expected request"),
+        };
+
+        conn2.drain_and_disconnect(peer_id1, Duration::from_secs(60)).unwrap();
+        conn2
+            .send_response(
+                request_id,
+                PeerManagerResponse::PingResponse(message::PingResponse { nonce: 1 }),
+            )
+            .unwrap();
+
+        match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::Response {
+                peer_id,
+                response: PeerManagerResponse::PingResponse(message::PingResponse { nonce: 1 }),
+                ..
+            } => assert_eq!(peer_id, peer_id2),
+            _ => panic!("invalid event received, expected response"),
+        }
+        match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::ConnectionClosed { peer_id } => assert_eq!(peer_id, peer_id2),
+            _ => panic!("invalid event received, expected closed connection"),
+        }
+    }
+
+    #[tokio::test]
+    async fn drain_and_disconnect_tcp() {
+        drain_and_disconnect::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn drain_and_disconnect_channels() {
+        drain_and_disconnect::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn drain_and_disconnect_noise() {
+        drain_and_disconnect::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
     #[tokio::test]
     async fn disconnect_tcp() {
         disconnect::<TestTransportTcp, TcpTransportSocket>().await;
@@ -587,6 +1102,7 @@ mod tests {
             address,
             peer_info,
             receiver_address: _,
+            timing: _,
         }) = conn1.poll_next().await
         {
             assert_eq!(address, conn2.local_addresses()[0]);
@@ -616,4 +1132,1089 @@ mod tests {
     async fn self_connect_noise() {
         self_connect::<TestTransportNoise, NoiseTcpTransport>().await;
     }
+
+    async fn handshake_timeout<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket + Debug,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(config::P2pConfig {
+            handshake_timeout: Duration::from_secs(1).into(),
+            ..Default::default()
+        });
+
+        let (mut conn, _) = DefaultNetworkingService::<T>::start(
+            A::make_transport(),
+            vec![A::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        // Connect but never send the handshake message
+        let transport = A::make_transport();
+        let mut stream = transport.connect(conn.local_addresses()[0].clone()).await.unwrap();
+
+        // The connection must be dropped after the timeout
+        let mut buf = [0u8; 1];
+        let read_res = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("connection must be dropped");
+        assert!(std::matches!(read_res, Ok(0) | Err(_)));
+
+        // No event is reported for the inbound connection
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), conn.poll_next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_timeout_tcp() {
+        handshake_timeout::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_timeout_channels() {
+        handshake_timeout::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_timeout_noise() {
+        handshake_timeout::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn connection_timing_channels() {
+        type NoiseChannelTransport =
+            WrappedTransportSocket<NoiseEncryptionAdapter, MpscChannelTransport>;
+        let make_transport = || {
+            NoiseChannelTransport::new(
+                NoiseEncryptionAdapter::gen_new(),
+                MpscChannelTransport::new(),
+            )
+        };
+
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, _) = DefaultNetworkingService::<NoiseChannelTransport>::start(
+            make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, _) = DefaultNetworkingService::<NoiseChannelTransport>::start(
+            make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+
+        match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::InboundAccepted { timing, .. } => {
+                // The channel is already connected when the listener accepts it
+                assert_eq!(timing.tcp_connect, None);
+                assert!(timing.noise_handshake.unwrap() < Duration::from_secs(10));
+                assert!(timing.handshake < Duration::from_secs(10));
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { timing, .. } => {
+                let tcp_connect = timing.tcp_connect.unwrap();
+                let noise_handshake = timing.noise_handshake.unwrap();
+                assert!(tcp_connect + noise_handshake < *p2p_config.outbound_connection_timeout);
+                assert!(timing.handshake < Duration::from_secs(10));
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        // Plain channels are not upgraded
+        let (mut conn3, _) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (conn4, _) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        conn3.connect(conn4.local_addresses()[0]).unwrap();
+
+        match conn3.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { timing, .. } => {
+                assert!(timing.tcp_connect.is_some());
+                assert_eq!(timing.noise_handshake, None);
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn backend_shutdown() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn, mut sync) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            config,
+            p2p_config,
+        )
+        .await
+        .unwrap();
+
+        conn.shutdown().unwrap();
+        assert!(matches!(
+            conn.poll_next().await,
+            Err(P2pError::BackendShutdown)
+        ));
+        assert!(matches!(
+            sync.poll_next().await,
+            Err(P2pError::BackendShutdown)
+        ));
+    }
+
+    #[tokio::test]
+    async fn backend_crash() {
+        let (cmd_tx, _cmd_rx) = mpsc::unbounded_channel();
+        let (conn_tx, conn_rx) = mpsc::unbounded_channel();
+        let mut conn = ConnectivityHandle::<
+            DefaultNetworkingService<MpscChannelTransport>,
+            MpscChannelTransport,
+        >::new(
+            vec![],
+            PeerId::new(),
+            cmd_tx,
+            conn_rx,
+            Arc::new(AtomicBool::new(false)),
+            Default::default(),
+            watch::channel(0).1,
+        );
+
+        // Simulate a backend that panics without setting the shutdown flag
+        let backend = tokio::spawn(async move {
+            let _conn_tx = conn_tx;
+            panic!("backend failure");
+        });
+        assert!(backend.await.unwrap_err().is_panic());
+
+        assert!(matches!(
+            conn.poll_next().await,
+            Err(P2pError::BackendCrashed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn message_counters() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, mut sync1) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, mut sync2) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        assert!(matches!(
+            conn2.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        // Ping the remote peer
+        conn1
+            .send_request(
+                peer_id2,
+                PeerManagerRequest::PingRequest(message::PingRequest { nonce: 1 }),
+            )
+            .unwrap();
+        let request_id = match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::Request { request_id, .. } => request_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        conn2
+            .send_response(
+                request_id,
+                PeerManagerResponse::PingResponse(message::PingResponse { nonce: 1 }),
+            )
+            .unwrap();
+        assert!(matches!(
+            conn1.poll_next().await,
+            Ok(ConnectivityEvent::Response { .. })
+        ));
+
+        // Send a sync request
+        sync1
+            .send_request(
+                peer_id2,
+                SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                    *config.magic_bytes(),
+                    vec![],
+                )),
+            )
+            .unwrap();
+        assert!(matches!(
+            sync2.poll_next().await,
+            Ok(SyncingEvent::Request { .. })
+        ));
+
+        let metrics1 = conn1.metrics();
+        assert_eq!(
+            metrics1.sent,
+            [
+                (metrics::MessageKind::Handshake, 1),
+                (metrics::MessageKind::Ping, 1),
+                (metrics::MessageKind::Request, 1),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(
+            metrics1.received,
+            [(metrics::MessageKind::Handshake, 1), (metrics::MessageKind::Ping, 1),]
+                .into_iter()
+                .collect()
+        );
+
+        let metrics2 = conn2.metrics();
+        assert_eq!(metrics2.sent, metrics1.received);
+        assert_eq!(metrics2.received, metrics1.sent);
+    }
+
+    // The tracer records the handshake and the following frames in the order they were exchanged
+    #[tokio::test]
+    async fn protocol_tracer() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (tracer1, mut trace1) = ProtocolTracer::new();
+        let (mut conn1, mut sync1) = DefaultNetworkingService::<MpscChannelTransport>::builder(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .with_protocol_tracer(tracer1)
+        .start()
+        .await
+        .unwrap();
+        let (tracer2, mut trace2) = ProtocolTracer::new();
+        let (mut conn2, mut sync2) = DefaultNetworkingService::<MpscChannelTransport>::builder(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .with_protocol_tracer(tracer2)
+        .start()
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        let peer_id1 = match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::InboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+
+        sync1
+            .send_request(
+                peer_id2,
+                SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                    *config.magic_bytes(),
+                    vec![],
+                )),
+            )
+            .unwrap();
+        assert!(matches!(
+            sync2.poll_next().await,
+            Ok(SyncingEvent::Request { .. })
+        ));
+
+        let drain = |trace: &mut mpsc::UnboundedReceiver<tracer::ProtocolEvent>| {
+            std::iter::from_fn(|| trace.try_recv().ok()).collect::<Vec<_>>()
+        };
+        let trace1 = drain(&mut trace1);
+        let trace2 = drain(&mut trace2);
+
+        // The outbound peer sends the hello message first
+        let (hello, hello_ack, request) = match trace1.as_slice() {
+            [tracer::ProtocolEvent::HandshakeSent {
+                peer_id: id1,
+                size: hello,
+            }, tracer::ProtocolEvent::HandshakeReceived {
+                peer_id: id2,
+                size: hello_ack,
+            }, tracer::ProtocolEvent::FrameSent {
+                peer_id: id3,
+                kind: metrics::MessageKind::Request,
+                size: request,
+            }] if [*id1, *id2, *id3] == [peer_id2; 3] => (*hello, *hello_ack, *request),
+            trace => panic!("unexpected trace: {trace:?}"),
+        };
+        assert_eq!(
+            trace2,
+            vec![
+                tracer::ProtocolEvent::HandshakeReceived {
+                    peer_id: peer_id1,
+                    size: hello,
+                },
+                tracer::ProtocolEvent::HandshakeSent {
+                    peer_id: peer_id1,
+                    size: hello_ack,
+                },
+                tracer::ProtocolEvent::FrameReceived {
+                    peer_id: peer_id1,
+                    kind: metrics::MessageKind::Request,
+                    size: request,
+                },
+            ]
+        );
+    }
+
+    // The backend reports the topics of the configured node type
+    #[tokio::test]
+    async fn get_subscriptions() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+
+        for (node_type, topics) in [
+            (
+                config::NodeType::Full,
+                vec![PubSubTopic::Blocks, PubSubTopic::Transactions],
+            ),
+            (config::NodeType::BlocksOnly, vec![PubSubTopic::Blocks]),
+            (config::NodeType::Inactive, vec![]),
+        ] {
+            let p2p_config = Arc::new(config::P2pConfig {
+                node_type: node_type.into(),
+                ..Default::default()
+            });
+            let (conn, _sync) = DefaultNetworkingService::<MpscChannelTransport>::start(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                p2p_config,
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                conn.subscriptions().await.unwrap(),
+                topics.into_iter().collect()
+            );
+        }
+    }
+
+    // Every subscriber gets a copy of the event polled by the handle owner, a subscriber that
+    // falls too far behind is notified about the missed events
+    #[tokio::test]
+    async fn subscribe_events() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, _sync1) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (conn2, _sync2) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        let mut events1 = conn1.subscribe_events();
+        let mut events2 = conn1.subscribe_events();
+
+        let address2 = conn2.local_addresses()[0];
+        conn1.connect(address2).unwrap();
+        let peer_info = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted {
+                address, peer_info, ..
+            } => {
+                assert_eq!(address, address2);
+                peer_info
+            }
+            event => panic!("invalid event received: {event:?}"),
+        };
+
+        for events in [&mut events1, &mut events2] {
+            match events.recv().await.unwrap() {
+                types::BroadcastConnectivityEvent::OutboundAccepted {
+                    address,
+                    peer_info: received_peer_info,
+                    ..
+                } => {
+                    assert_eq!(address, address2);
+                    assert_eq!(received_peer_info, peer_info);
+                }
+                event => panic!("invalid event received: {event:?}"),
+            }
+        }
+
+        // Only the events polled after subscribing are received
+        let mut events3 = conn1.subscribe_events();
+        assert_eq!(
+            events3.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        );
+
+        // Lagging subscribers get the standard broadcast error
+        for _ in 0..=CONNECTIVITY_EVENTS_CAPACITY {
+            conn1
+                .events_tx
+                .send(types::BroadcastConnectivityEvent::ConnectionClosed {
+                    peer_id: peer_info.peer_id,
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            events1.recv().await,
+            Err(broadcast::error::RecvError::Lagged(1))
+        );
+    }
+
+    // A pending request to a peer that disconnects is resolved with the `PeerDisconnected` error
+    #[tokio::test]
+    async fn send_request_and_wait_peer_disconnected() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, sync1) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, mut sync2) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        let peer_id1 = match conn2.poll_next().await.unwrap() {
+            ConnectivityEvent::InboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+
+        let request_fut = sync1.send_request_and_wait(
+            peer_id2,
+            SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                *config.magic_bytes(),
+                vec![],
+            )),
+        );
+        let disconnect_fut = async {
+            // The request is received but never answered
+            assert!(matches!(
+                sync2.poll_next().await,
+                Ok(SyncingEvent::Request { .. })
+            ));
+            conn2.disconnect(peer_id1).unwrap();
+        };
+
+        let (res, ()) = tokio::join!(request_fut, disconnect_fut);
+        assert_eq!(res, Err(P2pError::PeerError(PeerError::PeerDisconnected)));
+    }
+
+    // The backend with a chainstate handle answers the sync requests without the frontend
+    #[tokio::test]
+    async fn sync_requests_answered_from_chainstate() {
+        let config = Arc::new(common::chain::config::create_unit_test_config());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let chainstate = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+        let blocks = p2p_test_utils::create_n_blocks(
+            Arc::clone(&config),
+            p2p_test_utils::TestBlockInfo::from_genesis(config.genesis_block()),
+            2,
+        );
+        p2p_test_utils::import_blocks(&chainstate, blocks.clone()).await;
+
+        let (mut conn1, mut sync1) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, mut sync2) =
+            DefaultNetworkingService::<MpscChannelTransport>::start_with_chainstate(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+                chainstate,
+            )
+            .await
+            .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        assert!(matches!(
+            conn2.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        let response = sync1
+            .send_request_and_wait(
+                peer_id2,
+                SyncRequest::HeaderListRequest(message::HeaderListRequest::new(
+                    *config.magic_bytes(),
+                    chainstate::Locator::new(vec![config.genesis_block_id()]),
+                )),
+            )
+            .await
+            .unwrap();
+        let headers = blocks.iter().map(|block| block.header().clone()).collect();
+        assert_eq!(
+            response,
+            message::Response::HeaderListResponse(message::HeaderListResponse::new(
+                *config.magic_bytes(),
+                config.genesis_block_id(),
+                headers
+            ))
+        );
+
+        let response = sync1
+            .send_request_and_wait(
+                peer_id2,
+                SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                    *config.magic_bytes(),
+                    vec![blocks[1].get_id()],
+                )),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            message::Response::BlockListResponse(message::BlockListResponse::new(
+                *config.magic_bytes(),
+                vec![blocks[1].clone()],
+            ))
+        );
+
+        // The request that can't be answered from the chainstate is the first one the frontend sees
+        sync1
+            .send_request(
+                peer_id2,
+                SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                    *config.magic_bytes(),
+                    vec![],
+                )),
+            )
+            .unwrap();
+        match sync2.poll_next().await.unwrap() {
+            SyncingEvent::Request { request, .. } => assert_eq!(
+                request,
+                SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                    *config.magic_bytes(),
+                    vec![]
+                ))
+            ),
+            _ => panic!("invalid event received"),
+        }
+
+        // The request made for another chain is passed to the frontend, which rejects it
+        let request = SyncRequest::HeaderListRequest(message::HeaderListRequest::new(
+            [0xff; 4],
+            chainstate::Locator::new(vec![config.genesis_block_id()]),
+        ));
+        sync1.send_request(peer_id2, request.clone()).unwrap();
+        match sync2.poll_next().await.unwrap() {
+            SyncingEvent::Request {
+                request: received, ..
+            } => assert_eq!(received, request),
+            _ => panic!("invalid event received"),
+        }
+    }
+
+    // The requests of a peer over the serving limit are rejected until the served ones are answered
+    #[tokio::test]
+    async fn sync_requests_rate_limited() {
+        let config = Arc::new(common::chain::config::create_unit_test_config());
+        let p2p_config = Arc::new(config::P2pConfig {
+            max_concurrent_serving_requests_per_peer: 2.into(),
+            ..Default::default()
+        });
+        let chainstate = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+
+        let (mut conn1, mut sync1) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (mut conn2, _sync2) =
+            DefaultNetworkingService::<MpscChannelTransport>::start_with_chainstate(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+                chainstate.clone(),
+            )
+            .await
+            .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        let peer_id2 = match conn1.poll_next().await.unwrap() {
+            ConnectivityEvent::OutboundAccepted { peer_info, .. } => peer_info.peer_id,
+            event => panic!("invalid event received: {event:?}"),
+        };
+        assert!(matches!(
+            conn2.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        // The chainstate is busy, so the served requests stay in flight
+        let (unblock_tx, unblock_rx) = tokio::sync::oneshot::channel::<()>();
+        let _blocked = chainstate.call_async(|_| {
+            Box::pin(async move {
+                let _ = unblock_rx.await;
+            })
+        });
+
+        let make_request = || {
+            SyncRequest::HeaderListRequest(message::HeaderListRequest::new(
+                *config.magic_bytes(),
+                chainstate::Locator::new(vec![config.genesis_block_id()]),
+            ))
+        };
+        let served_ids = (0..2)
+            .map(|_| sync1.send_request(peer_id2, make_request()).unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        let limited_id = sync1.send_request(peer_id2, make_request()).unwrap();
+        match sync1.poll_next().await.unwrap() {
+            SyncingEvent::Response {
+                request_id,
+                response,
+                ..
+            } => {
+                assert_eq!(request_id, limited_id);
+                assert!(matches!(
+                    response,
+                    SyncResponse::Error(message::ErrorResponse {
+                        error: message::ResponseError::RateLimited,
+                        ..
+                    })
+                ));
+            }
+            _ => panic!("invalid event received"),
+        }
+
+        // The served requests are answered once the chainstate is available
+        unblock_tx.send(()).unwrap();
+        let mut answered_ids = std::collections::HashSet::new();
+        for _ in 0..2 {
+            match sync1.poll_next().await.unwrap() {
+                SyncingEvent::Response {
+                    request_id,
+                    response: SyncResponse::HeaderListResponse(_),
+                    ..
+                } => {
+                    answered_ids.insert(request_id);
+                }
+                _ => panic!("invalid event received"),
+            }
+        }
+        assert_eq!(answered_ids, served_ids);
+
+        // The limit is freed
+        let request_id = sync1.send_request(peer_id2, make_request()).unwrap();
+        match sync1.poll_next().await.unwrap() {
+            SyncingEvent::Response {
+                request_id: response_id,
+                response: SyncResponse::HeaderListResponse(_),
+                ..
+            } => assert_eq!(response_id, request_id),
+            _ => panic!("invalid event received"),
+        }
+    }
+
+    #[test]
+    fn default_build_supports_tcp_and_noise() {
+        let transports = DefaultNetworkingService::<TcpTransportSocket>::supported_transports();
+        assert!(transports.contains(&transport::TransportKind::Tcp));
+        assert!(transports.contains(&transport::TransportKind::Noise));
+    }
+
+    // The waiter resolves once the required number of peers is connected
+    #[tokio::test]
+    async fn wait_for_peers() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+        let start = || {
+            DefaultNetworkingService::<MpscChannelTransport>::start(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+            )
+        };
+
+        let (conn1, _sync1) = start().await.unwrap();
+        let short_timeout = Duration::from_millis(100);
+        assert_eq!(
+            conn1.wait_for_peers(1, short_timeout).await,
+            Err(P2pError::WaitForPeersTimeout(1))
+        );
+
+        let mut others = Vec::new();
+        for peer_count in 1..=3 {
+            let (mut conn, sync) = start().await.unwrap();
+            conn.connect(conn1.local_addresses()[0]).unwrap();
+            assert!(matches!(
+                conn.poll_next().await,
+                Ok(ConnectivityEvent::OutboundAccepted { .. })
+            ));
+            others.push((conn, sync));
+
+            conn1.wait_for_peers(peer_count, Duration::from_secs(5)).await.unwrap();
+            assert_eq!(
+                conn1.wait_for_peers(peer_count + 1, short_timeout).await,
+                Err(P2pError::WaitForPeersTimeout(peer_count + 1))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn sign_challenge() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+        let start = || {
+            DefaultNetworkingService::<MpscChannelTransport>::start(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+            )
+        };
+        let (conn1, _sync1) = start().await.unwrap();
+        let (conn2, _sync2) = start().await.unwrap();
+
+        let challenge = b"challenge from the seed server";
+        let signature = conn1.sign_challenge(challenge);
+        assert!(verify_challenge(
+            &conn1.identity_public_key(),
+            challenge,
+            &signature
+        ));
+
+        // Tampered challenge
+        assert!(!verify_challenge(
+            &conn1.identity_public_key(),
+            b"challenge from the seed serveR",
+            &signature
+        ));
+
+        // Signed by another node
+        assert!(!verify_challenge(
+            &conn2.identity_public_key(),
+            challenge,
+            &signature
+        ));
+    }
+
+    // The tap receives the exact bytes of the announcements received from the peers
+    #[tokio::test]
+    async fn announcement_tap() {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (tap_tx, mut tap_rx) = mpsc::unbounded_channel();
+        let (mut conn1, _sync1) =
+            DefaultNetworkingService::<MpscChannelTransport>::start_with_announcement_tap(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(&p2p_config),
+                Some(tap_tx),
+            )
+            .await
+            .unwrap();
+        let (mut conn2, mut sync2) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+        assert!(matches!(
+            conn1.poll_next().await,
+            Ok(ConnectivityEvent::OutboundAccepted { .. })
+        ));
+        assert!(matches!(
+            conn2.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        let announcement = message::Announcement::Block(
+            Block::new(
+                vec![],
+                Id::new(H256([0x01; 32])),
+                BlockTimestamp::from_int_seconds(1),
+                ConsensusData::None,
+                BlockReward::new(Vec::new()),
+            )
+            .unwrap(),
+        );
+        sync2.make_announcement(announcement.clone()).unwrap();
+
+        let (topic, bytes) = tap_rx.recv().await.unwrap();
+        assert_eq!(topic, PubSubTopic::Blocks);
+        assert_eq!(bytes, announcement.encode());
+    }
+
+    // The announcements are relayed to the inbound peers right away, but to the outbound peers
+    // only after the trickle delay, together with those that were made during the delay
+    #[tokio::test]
+    async fn announcement_trickle() {
+        let trickle_interval = Duration::from_millis(1000);
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+        let trickle_config = Arc::new(config::P2pConfig {
+            announcement_trickle_interval: trickle_interval.into(),
+            ..Default::default()
+        });
+
+        let start = |p2p_config: &Arc<config::P2pConfig>| {
+            DefaultNetworkingService::<MpscChannelTransport>::start(
+                TestTransportChannel::make_transport(),
+                vec![TestTransportChannel::make_address()],
+                Arc::clone(&config),
+                Arc::clone(p2p_config),
+            )
+        };
+        let (mut conn1, mut sync1) = start(&trickle_config).await.unwrap();
+        let (mut conn_outbound, mut sync_outbound) = start(&p2p_config).await.unwrap();
+        let (mut conn_inbound, mut sync_inbound) = start(&p2p_config).await.unwrap();
+
+        conn1.connect(conn_outbound.local_addresses()[0]).unwrap();
+        assert!(matches!(
+            conn1.poll_next().await,
+            Ok(ConnectivityEvent::OutboundAccepted { .. })
+        ));
+        assert!(matches!(
+            conn_outbound.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+        conn_inbound.connect(conn1.local_addresses()[0]).unwrap();
+        assert!(matches!(
+            conn_inbound.poll_next().await,
+            Ok(ConnectivityEvent::OutboundAccepted { .. })
+        ));
+        assert!(matches!(
+            conn1.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        let make_announcement = |n: u8| {
+            message::Announcement::Block(
+                Block::new(
+                    vec![],
+                    Id::new(H256([n; 32])),
+                    BlockTimestamp::from_int_seconds(1),
+                    ConsensusData::None,
+                    BlockReward::new(Vec::new()),
+                )
+                .unwrap(),
+            )
+        };
+        let announcements = vec![make_announcement(1), make_announcement(2)];
+
+        let started_at = tokio::time::Instant::now();
+        for announcement in &announcements {
+            sync1.make_announcement(announcement.clone()).unwrap();
+        }
+
+        async fn receive_announcements(
+            sync: &mut impl SyncingMessagingService<DefaultNetworkingService<MpscChannelTransport>>,
+            count: usize,
+        ) -> Vec<message::Announcement> {
+            let mut received = Vec::new();
+            while received.len() < count {
+                match sync.poll_next().await.unwrap() {
+                    SyncingEvent::Announcement { announcement, .. } => received.push(announcement),
+                    event => panic!("unexpected event: {event:?}"),
+                }
+            }
+            received
+        }
+
+        assert_eq!(
+            receive_announcements(&mut sync_inbound, 2).await,
+            announcements
+        );
+        assert!(started_at.elapsed() < trickle_interval / 4);
+
+        assert_eq!(
+            receive_announcements(&mut sync_outbound, 2).await,
+            announcements
+        );
+        let elapsed = started_at.elapsed();
+        assert!(
+            elapsed >= trickle_interval / 2 && elapsed < trickle_interval * 2,
+            "unexpected delay: {elapsed:?}"
+        );
+    }
+
+    // The remote peer stops reading, so the outbound announcement queue overflows. The oldest
+    // queued announcements are dropped and the newest ones are delivered later.
+    #[tokio::test]
+    async fn announcement_queue_overflow() {
+        const QUEUE_SIZE: usize = 3;
+        const ANNOUNCEMENT_COUNT: u64 = 30;
+
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(config::P2pConfig {
+            max_outbound_announcement_queue: QUEUE_SIZE.into(),
+            ..Default::default()
+        });
+
+        let (mut conn, mut sync) = DefaultNetworkingService::<MpscChannelTransport>::start(
+            TestTransportChannel::make_transport(),
+            vec![TestTransportChannel::make_address()],
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+
+        // Handshake with the node over a raw stream
+        let transport = TestTransportChannel::make_transport();
+        let stream = transport.connect(conn.local_addresses()[0]).await.unwrap();
+        let mut remote = BufferedTranscoder::new(stream);
+        remote
+            .send(Message::Handshake(HandshakeMessage::Hello {
+                version: *config.version(),
+                network: *config.magic_bytes(),
+                protocol_name: config.protocol_name().to_owned(),
+                subscriptions: [PubSubTopic::Blocks].into_iter().collect(),
+                receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
+                handshake_nonce: 1,
+            }))
+            .await
+            .unwrap();
+        assert!(matches!(
+            remote.recv().await,
+            Ok(Message::Handshake(HandshakeMessage::HelloAck { .. }))
+        ));
+        assert!(matches!(
+            conn.poll_next().await,
+            Ok(ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        // The announcements are large enough to fill up the channel buffer quickly
+        let make_announcement = |timestamp: u64| {
+            let block = Block::new(
+                vec![],
+                Id::new(H256([0x01; 32])),
+                BlockTimestamp::from_int_seconds(timestamp),
+                ConsensusData::None,
+                BlockReward::new(Vec::new()),
+            )
+            .unwrap();
+            message::Announcement::Blocks(vec![block.header().clone(); 10_000])
+        };
+        for timestamp in 0..ANNOUNCEMENT_COUNT {
+            sync.make_announcement(make_announcement(timestamp)).unwrap();
+        }
+
+        // The remote doesn't read, so the channel buffer fills up and the queue overflows
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while conn.metrics().announcements_dropped == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // The newest announcement is never dropped, so it's received last
+        let mut delivered = Vec::new();
+        while delivered.last() != Some(&(ANNOUNCEMENT_COUNT - 1)) {
+            match remote.recv().await.unwrap() {
+                Message::Announcement {
+                    announcement: message::Announcement::Blocks(headers),
+                } => delivered.push(headers[0].timestamp().as_int_seconds()),
+                message => panic!("unexpected message: {message:?}"),
+            }
+        }
+
+        // The announcements are delivered in order and the newest ones are not dropped
+        assert!(delivered.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            delivered[delivered.len() - QUEUE_SIZE..],
+            ((ANNOUNCEMENT_COUNT - QUEUE_SIZE as u64)..ANNOUNCEMENT_COUNT).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            delivered.len() as u64 + conn.metrics().announcements_dropped,
+            ANNOUNCEMENT_COUNT
+        );
+    }
 }
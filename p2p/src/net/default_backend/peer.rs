@@ -13,29 +13,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use tokio::{sync::mpsc, time::timeout};
 
-use common::chain::ChainConfig;
+use common::{chain::ChainConfig, primitives::semver::SemVer};
 use logging::log;
+use serialization::Encode;
 
 use crate::{
     config::P2pConfig,
-    error::{P2pError, ProtocolError},
+    error::{ConversionError, P2pError, PeerError, ProtocolError},
     net::{
         default_backend::{
             transport::TransportSocket,
             types::{self, Event, PeerEvent, PeerId},
         },
-        types::Role,
+        types::{BestBlock, Role},
     },
     types::peer_address::PeerAddress,
 };
 
-use super::{transport::BufferedTranscoder, types::HandshakeNonce};
-
-const PEER_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+use super::{
+    announcement_queue::AnnouncementQueue, metrics::MessageCounters, rate_limiter::RateLimiter,
+    tracer::ProtocolTracer, transport::BufferedTranscoder, types::HandshakeNonce,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PeerRole {
@@ -75,6 +77,21 @@ pub struct Peer<T: TransportSocket> {
 
     /// RX channel for receiving commands from backend
     rx: mpsc::UnboundedReceiver<Event>,
+
+    /// Outbound announcements queued by backend
+    announcement_queue: Arc<AnnouncementQueue>,
+
+    /// Local best block that is sent to the remote peer during the handshake
+    best_block: Option<BestBlock>,
+
+    /// Rate limiter shared by all peers, if the outbound bandwidth is limited
+    rate_limiter: Option<Arc<RateLimiter>>,
+
+    /// Message counters shared by all peers
+    message_counters: Arc<MessageCounters>,
+
+    /// Protocol tracer shared by all peers, if enabled
+    protocol_tracer: Option<ProtocolTracer>,
 }
 
 impl<T> Peer<T>
@@ -91,6 +108,11 @@ where
         receiver_address: Option<PeerAddress>,
         tx: mpsc::UnboundedSender<(PeerId, PeerEvent)>,
         rx: mpsc::UnboundedReceiver<Event>,
+        announcement_queue: Arc<AnnouncementQueue>,
+        best_block: Option<BestBlock>,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        message_counters: Arc<MessageCounters>,
+        protocol_tracer: Option<ProtocolTracer>,
     ) -> Self {
         let socket = BufferedTranscoder::new(socket);
 
@@ -103,22 +125,98 @@ where
             receiver_address,
             tx,
             rx,
+            announcement_queue,
+            best_block,
+            rate_limiter,
+            message_counters,
+            protocol_tracer,
+        }
+    }
+
+    /// Sends the message to the remote peer.
+    ///
+    /// If the outbound bandwidth is limited, waits until the rate limiter allows sending the message.
+    /// Fails with [`PeerError::WriteTimeout`] if the write takes longer than
+    /// [`P2pConfig::send_timeout`], so that the peer that doesn't read from its socket is
+    /// disconnected.
+    async fn send_message(&mut self, message: types::Message) -> crate::Result<()> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(message.encoded_size()).await;
+        }
+        self.message_counters.record_sent(&message);
+        if let Some(tracer) = &self.protocol_tracer {
+            tracer.record_sent(self.peer_id, &message);
+        }
+        match timeout(*self.p2p_config.send_timeout, self.socket.send(message)).await {
+            Ok(res) => res,
+            Err(_) => {
+                log::debug!("write timeout for peer {}", self.peer_id);
+                Err(P2pError::PeerError(PeerError::WriteTimeout))
+            }
+        }
+    }
+
+    /// Receives the next message from the remote peer
+    ///
+    /// The message size is limited by [`P2pConfig::max_message_size`].
+    async fn recv_message(&mut self) -> crate::Result<types::Message> {
+        let message = self.socket.recv_limited(*self.p2p_config.max_message_size).await?;
+        self.record_received(&message);
+        Ok(message)
+    }
+
+    fn record_received(&self, message: &types::Message) {
+        self.message_counters.record_received(message);
+        if let Some(tracer) = &self.protocol_tracer {
+            tracer.record_received(self.peer_id, message);
+        }
+    }
+
+    /// Receives the handshake message from the remote peer
+    ///
+    /// The message size is limited by [`P2pConfig::max_handshake_size`], the peer that sends
+    /// a larger message is rejected before the message is decoded.
+    async fn recv_handshake_message(&mut self) -> crate::Result<types::Message> {
+        let max_size = *self.p2p_config.max_handshake_size;
+        match self.socket.recv_limited(max_size).await {
+            Ok(message) => {
+                self.record_received(&message);
+                Ok(message)
+            }
+            Err(P2pError::ProtocolError(ProtocolError::MessageTooLarge(size, limit))) => {
+                let error = || P2pError::ProtocolError(ProtocolError::MessageTooLarge(size, limit));
+                self.tx
+                    .send((
+                        self.peer_id,
+                        types::PeerEvent::HandshakeFailed { error: error() },
+                    ))
+                    .map_err(P2pError::from)?;
+                Err(error())
+            }
+            Err(err) => Err(err),
         }
     }
 
     async fn handshake(&mut self) -> crate::Result<()> {
         match self.peer_role {
             PeerRole::Inbound => {
-                let Ok(types::Message::Handshake(types::HandshakeMessage::Hello {
+                let types::Message::Handshake(types::HandshakeMessage::Hello {
                     version,
                     network,
+                    protocol_name,
                     subscriptions,
                     receiver_address,
+                    observer,
+                    best_block,
+                    node_kind,
                     handshake_nonce,
-                })) = self.socket.recv().await
+                }) = self.recv_handshake_message().await?
                 else {
                     return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
                 };
+                self.check_network(network)?;
+                self.check_protocol_name(protocol_name)?;
+                self.check_version(version)?;
 
                 // Send PeerInfoReceived before sending handshake to remote peer!
                 // Backend is expected to receive PeerInfoReceived before outgoing connection has chance to complete handshake,
@@ -131,42 +229,58 @@ where
                             version,
                             subscriptions,
                             receiver_address,
+                            observer,
+                            best_block,
+                            node_kind,
                             handshake_nonce,
                         },
                     ))
                     .map_err(P2pError::from)?;
 
-                self.socket
-                    .send(types::Message::Handshake(
-                        types::HandshakeMessage::HelloAck {
-                            version: *self.chain_config.version(),
-                            network: *self.chain_config.magic_bytes(),
-                            subscriptions: (*self.p2p_config.node_type.as_ref()).into(),
-                            receiver_address: self.receiver_address.clone(),
-                        },
-                    ))
-                    .await?;
-            }
-            PeerRole::Outbound { handshake_nonce } => {
-                self.socket
-                    .send(types::Message::Handshake(types::HandshakeMessage::Hello {
+                self.send_message(types::Message::Handshake(
+                    types::HandshakeMessage::HelloAck {
                         version: *self.chain_config.version(),
                         network: *self.chain_config.magic_bytes(),
+                        protocol_name: self.chain_config.protocol_name().to_owned(),
                         subscriptions: (*self.p2p_config.node_type.as_ref()).into(),
                         receiver_address: self.receiver_address.clone(),
-                        handshake_nonce,
-                    }))
-                    .await?;
+                        observer: *self.p2p_config.observer_mode,
+                        best_block: self.best_block,
+                        node_kind: *self.p2p_config.node_kind,
+                    },
+                ))
+                .await?;
+            }
+            PeerRole::Outbound { handshake_nonce } => {
+                self.send_message(types::Message::Handshake(types::HandshakeMessage::Hello {
+                    version: *self.chain_config.version(),
+                    network: *self.chain_config.magic_bytes(),
+                    protocol_name: self.chain_config.protocol_name().to_owned(),
+                    subscriptions: (*self.p2p_config.node_type.as_ref()).into(),
+                    receiver_address: self.receiver_address.clone(),
+                    observer: *self.p2p_config.observer_mode,
+                    best_block: self.best_block,
+                    node_kind: *self.p2p_config.node_kind,
+                    handshake_nonce,
+                }))
+                .await?;
 
-                let Ok(types::Message::Handshake(types::HandshakeMessage::HelloAck {
+                let types::Message::Handshake(types::HandshakeMessage::HelloAck {
                     version,
                     network,
+                    protocol_name,
                     subscriptions,
                     receiver_address,
-                })) = self.socket.recv().await
+                    observer,
+                    best_block,
+                    node_kind,
+                }) = self.recv_handshake_message().await?
                 else {
                     return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
                 };
+                self.check_network(network)?;
+                self.check_protocol_name(protocol_name)?;
+                self.check_version(version)?;
 
                 self.tx
                     .send((
@@ -176,6 +290,9 @@ where
                             version,
                             subscriptions,
                             receiver_address,
+                            observer,
+                            best_block,
+                            node_kind,
                             handshake_nonce,
                         },
                     ))
@@ -186,9 +303,74 @@ where
         Ok(())
     }
 
+    /// Rejects the remote peer if it belongs to a different network.
+    ///
+    /// The backend is notified before the connection is reported as accepted, so peers from
+    /// other networks are never treated as connected.
+    fn check_network(&self, network: [u8; 4]) -> crate::Result<()> {
+        let magic_bytes = *self.chain_config.magic_bytes();
+        if network == magic_bytes {
+            return Ok(());
+        }
+
+        let error =
+            || P2pError::ProtocolError(ProtocolError::DifferentNetwork(magic_bytes, network));
+        self.tx
+            .send((
+                self.peer_id,
+                types::PeerEvent::HandshakeFailed { error: error() },
+            ))
+            .map_err(P2pError::from)?;
+        Err(error())
+    }
+
+    /// Rejects the remote peer if it speaks a different protocol (for example a custom testnet
+    /// that reuses the magic bytes of another chain).
+    fn check_protocol_name(&self, protocol_name: String) -> crate::Result<()> {
+        if protocol_name == self.chain_config.protocol_name() {
+            return Ok(());
+        }
+
+        let error = || {
+            P2pError::ProtocolError(ProtocolError::DifferentProtocolName(
+                self.chain_config.protocol_name().to_owned(),
+                protocol_name.clone(),
+            ))
+        };
+        self.tx
+            .send((
+                self.peer_id,
+                types::PeerEvent::HandshakeFailed { error: error() },
+            ))
+            .map_err(P2pError::from)?;
+        Err(error())
+    }
+
+    /// Rejects the remote peer if its protocol version is below the configured minimum.
+    fn check_version(&self, version: SemVer) -> crate::Result<()> {
+        let min_version = *self.p2p_config.min_protocol_version;
+        if version >= min_version {
+            return Ok(());
+        }
+
+        let error = || {
+            P2pError::ProtocolError(ProtocolError::UnsupportedProtocolVersion(
+                min_version,
+                version,
+            ))
+        };
+        self.tx
+            .send((
+                self.peer_id,
+                types::PeerEvent::HandshakeFailed { error: error() },
+            ))
+            .map_err(P2pError::from)?;
+        Err(error())
+    }
+
     pub async fn run(&mut self) -> crate::Result<()> {
         // handshake with remote peer and send peer's info to backend
-        let handshake_res = timeout(PEER_HANDSHAKE_TIMEOUT, self.handshake()).await;
+        let handshake_res = timeout(*self.p2p_config.handshake_timeout, self.handshake()).await;
         match handshake_res {
             Ok(Ok(())) => {}
             Ok(Err(err)) => {
@@ -208,9 +390,43 @@ where
 
                 event = self.rx.recv() => match event.ok_or(P2pError::ChannelClosed)? {
                     Event::Disconnect => return Ok(()),
-                    Event::SendMessage(message) => self.socket.send(*message).await?,
+                    Event::SendMessage(message) => self.send_message(*message).await?,
                 },
-                event = self.socket.recv() => match event {
+                announcement = self.announcement_queue.pop_wait() => {
+                    self.send_message(types::Message::Announcement { announcement }).await?;
+                },
+                event = self.recv_message() => match event {
+                    Err(P2pError::ProtocolError(ProtocolError::UnknownMessageType)) => {
+                        log::debug!("peer {} sent a message of unknown type", self.peer_id);
+                        self.tx
+                            .send((
+                                self.peer_id,
+                                types::PeerEvent::Misbehaved {
+                                    error: P2pError::ProtocolError(ProtocolError::UnknownMessageType),
+                                },
+                            ))
+                            .map_err(P2pError::from)?;
+                    }
+                    Err(err @ P2pError::ConversionError(ConversionError::DecodeError(_)))
+                        if !*self.p2p_config.disconnect_on_protocol_error =>
+                    {
+                        log::debug!("peer {} sent an undecodable message: {err}", self.peer_id);
+                        self.tx
+                            .send((
+                                self.peer_id,
+                                types::PeerEvent::Misbehaved {
+                                    error: P2pError::ProtocolError(ProtocolError::UndecodableMessage),
+                                },
+                            ))
+                            .map_err(P2pError::from)?;
+                    }
+                    Err(err @ P2pError::ProtocolError(ProtocolError::MessageTooLarge(_, _))) => {
+                        log::debug!("peer {} sent a message that is too large: {err}", self.peer_id);
+                        self.tx
+                            .send((self.peer_id, types::PeerEvent::Misbehaved { error: err }))
+                            .map_err(P2pError::from)?;
+                        return Ok(());
+                    }
                     Err(err) => {
                         log::info!("peer connection closed, reason {err:?}");
                         return Ok(());
@@ -248,15 +464,19 @@ mod tests {
         net::{
             default_backend::{
                 transport::{
-                    MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket, TransportListener,
+                    MessageEnvelope, MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket,
+                    TransportListener, MESSAGE_VERSION,
                 },
                 types,
             },
-            types::PubSubTopic,
+            types::{NodeKind, PubSubTopic},
         },
     };
+    use std::{collections::BTreeSet, num::NonZeroU64};
+
     use chainstate::Locator;
     use futures::FutureExt;
+    use tokio::io::AsyncWriteExt;
 
     async fn handshake_inbound<A, T>()
     where
@@ -279,6 +499,11 @@ mod tests {
             None,
             tx1,
             rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
         );
 
         let handle = tokio::spawn(async move {
@@ -292,10 +517,14 @@ mod tests {
             .send(types::Message::Handshake(types::HandshakeMessage::Hello {
                 version: *chain_config.version(),
                 network: *chain_config.magic_bytes(),
+                protocol_name: chain_config.protocol_name().to_owned(),
                 subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                     .into_iter()
                     .collect(),
                 receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
                 handshake_nonce: 123,
             }))
             .await
@@ -311,6 +540,9 @@ mod tests {
                     .into_iter()
                     .collect(),
                 receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
                 handshake_nonce: 123,
             }
         );
@@ -352,6 +584,11 @@ mod tests {
             None,
             tx1,
             rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
         );
 
         let handle = tokio::spawn(async move {
@@ -366,10 +603,14 @@ mod tests {
                 types::HandshakeMessage::HelloAck {
                     version: *chain_config.version(),
                     network: *chain_config.magic_bytes(),
+                    protocol_name: chain_config.protocol_name().to_owned(),
                     subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                         .into_iter()
                         .collect(),
                     receiver_address: None,
+                    observer: false,
+                    best_block: None,
+                    node_kind: NodeKind::Full,
                 }
             ))
             .await
@@ -387,6 +628,9 @@ mod tests {
                         .into_iter()
                         .collect(),
                     receiver_address: None,
+                    observer: false,
+                    best_block: None,
+                    node_kind: NodeKind::Full,
                     handshake_nonce: 1,
                 }
             ))
@@ -416,7 +660,7 @@ mod tests {
         let (socket1, socket2) = get_two_connected_sockets::<A, T>().await;
         let chain_config = Arc::new(common::chain::config::create_mainnet());
         let p2p_config = Arc::new(P2pConfig::default());
-        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
         let (_tx2, rx2) = mpsc::unbounded_channel();
         let peer_id3 = PeerId::new();
 
@@ -429,6 +673,11 @@ mod tests {
             None,
             tx1,
             rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
         );
 
         let handle = tokio::spawn(async move { peer.handshake().await });
@@ -439,16 +688,35 @@ mod tests {
             .send(types::Message::Handshake(types::HandshakeMessage::Hello {
                 version: *chain_config.version(),
                 network: [1, 2, 3, 4],
+                protocol_name: chain_config.protocol_name().to_owned(),
                 subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                     .into_iter()
                     .collect(),
                 receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
                 handshake_nonce: 123,
             }))
             .await
             .is_ok());
 
-        assert_eq!(handle.await.unwrap(), Ok(()));
+        let expected_error = || {
+            P2pError::ProtocolError(ProtocolError::DifferentNetwork(
+                *chain_config.magic_bytes(),
+                [1, 2, 3, 4],
+            ))
+        };
+        assert_eq!(handle.await.unwrap(), Err(expected_error()));
+        assert_eq!(
+            rx1.try_recv(),
+            Ok((
+                peer_id3,
+                types::PeerEvent::HandshakeFailed {
+                    error: expected_error()
+                }
+            ))
+        );
     }
 
     #[tokio::test]
@@ -466,6 +734,181 @@ mod tests {
         handshake_different_network::<TestTransportNoise, NoiseTcpTransport>().await;
     }
 
+    // The magic bytes match, but the peer belongs to a different chain
+    async fn handshake_different_protocol_name<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let other_chain_config =
+            common::chain::config::Builder::new(common::chain::config::ChainType::Mainnet)
+                .protocol_name(Some("/mintlayer/custom-testnet".to_owned()))
+                .build();
+        assert_eq!(chain_config.magic_bytes(), other_chain_config.magic_bytes());
+        let p2p_config = Arc::new(P2pConfig::default());
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id3 = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id3,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+
+        let handle = tokio::spawn(async move { peer.handshake().await });
+
+        let mut socket2 = BufferedTranscoder::new(socket2);
+        assert!(socket2
+            .send(types::Message::Handshake(types::HandshakeMessage::Hello {
+                version: *other_chain_config.version(),
+                network: *other_chain_config.magic_bytes(),
+                protocol_name: other_chain_config.protocol_name().to_owned(),
+                subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
+                    .into_iter()
+                    .collect(),
+                receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
+                handshake_nonce: 123,
+            }))
+            .await
+            .is_ok());
+
+        let expected_error = || {
+            P2pError::ProtocolError(ProtocolError::DifferentProtocolName(
+                chain_config.protocol_name().to_owned(),
+                "/mintlayer/custom-testnet".to_owned(),
+            ))
+        };
+        assert_eq!(handle.await.unwrap(), Err(expected_error()));
+        assert_eq!(
+            rx1.try_recv(),
+            Ok((
+                peer_id3,
+                types::PeerEvent::HandshakeFailed {
+                    error: expected_error()
+                }
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_different_protocol_name_tcp() {
+        handshake_different_protocol_name::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_different_protocol_name_channels() {
+        handshake_different_protocol_name::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_different_protocol_name_noise() {
+        handshake_different_protocol_name::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
+    async fn handshake_old_version<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let min_version = *chain_config.version();
+        let old_version = SemVer::new(0, 0, 1);
+        let p2p_config = Arc::new(P2pConfig {
+            min_protocol_version: min_version.into(),
+            ..Default::default()
+        });
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id3 = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id3,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+
+        let handle = tokio::spawn(async move { peer.handshake().await });
+
+        let mut socket2 = BufferedTranscoder::new(socket2);
+        assert!(socket2
+            .send(types::Message::Handshake(types::HandshakeMessage::Hello {
+                version: old_version,
+                network: *chain_config.magic_bytes(),
+                protocol_name: chain_config.protocol_name().to_owned(),
+                subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
+                    .into_iter()
+                    .collect(),
+                receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
+                handshake_nonce: 123,
+            }))
+            .await
+            .is_ok());
+
+        let expected_error = || {
+            P2pError::ProtocolError(ProtocolError::UnsupportedProtocolVersion(
+                min_version,
+                old_version,
+            ))
+        };
+        assert_eq!(handle.await.unwrap(), Err(expected_error()));
+        // The peer info is not reported, so the connection is never accepted
+        assert_eq!(
+            rx1.try_recv(),
+            Ok((
+                peer_id3,
+                types::PeerEvent::HandshakeFailed {
+                    error: expected_error()
+                }
+            ))
+        );
+        assert!(rx1.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn handshake_old_version_tcp() {
+        handshake_old_version::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_old_version_channels() {
+        handshake_old_version::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_old_version_noise() {
+        handshake_old_version::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
     async fn invalid_handshake_message<A, T>()
     where
         A: TestTransportMaker<Transport = T, Address = T::Address>,
@@ -481,12 +924,17 @@ mod tests {
         let mut peer = Peer::<T>::new(
             peer_id2,
             PeerRole::Inbound,
-            chain_config,
+            Arc::clone(&chain_config),
             p2p_config,
             socket1,
             None,
             tx1,
             rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
         );
 
         let handle = tokio::spawn(async move { peer.handshake().await });
@@ -497,6 +945,7 @@ mod tests {
             .send(types::Message::Request {
                 request_id: types::RequestId::new(),
                 request: message::Request::HeaderListRequest(message::HeaderListRequest::new(
+                    *chain_config.magic_bytes(),
                     Locator::new(vec![]),
                 )),
             })
@@ -524,6 +973,531 @@ mod tests {
         invalid_handshake_message::<TestTransportNoise, NoiseTcpTransport>().await;
     }
 
+    // The handshake is rejected by its size, before it's decoded
+    async fn oversized_handshake_message<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(P2pConfig {
+            max_handshake_size: 16.into(),
+            ..Default::default()
+        });
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id2 = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id2,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+
+        let handle = tokio::spawn(async move { peer.handshake().await });
+
+        let make_hello = || {
+            types::Message::Handshake(types::HandshakeMessage::Hello {
+                version: *chain_config.version(),
+                network: *chain_config.magic_bytes(),
+                protocol_name: chain_config.protocol_name().to_owned(),
+                subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
+                    .into_iter()
+                    .collect(),
+                receiver_address: None,
+                observer: false,
+                best_block: None,
+                node_kind: NodeKind::Full,
+                handshake_nonce: 123,
+            })
+        };
+        let frame_size = MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: make_hello().encode(),
+        }
+        .encode()
+        .len();
+
+        let mut socket2 = BufferedTranscoder::new(socket2);
+        socket2.send(make_hello()).await.unwrap();
+
+        let expected_error =
+            || P2pError::ProtocolError(ProtocolError::MessageTooLarge(frame_size, 16));
+        assert_eq!(handle.await.unwrap(), Err(expected_error()));
+        assert_eq!(
+            rx1.try_recv(),
+            Ok((
+                peer_id2,
+                types::PeerEvent::HandshakeFailed {
+                    error: expected_error()
+                }
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_handshake_message_tcp() {
+        oversized_handshake_message::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_handshake_message_channels() {
+        oversized_handshake_message::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_handshake_message_noise() {
+        oversized_handshake_message::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
+    fn make_frame(payload: &[u8]) -> Vec<u8> {
+        let body = MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: payload.to_vec(),
+        }
+        .encode();
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        frame
+    }
+
+    async fn unknown_message_type<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(P2pConfig::default());
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+        tokio::spawn(async move { peer.run().await });
+
+        let hello = types::Message::Handshake(types::HandshakeMessage::Hello {
+            version: *chain_config.version(),
+            network: *chain_config.magic_bytes(),
+            protocol_name: chain_config.protocol_name().to_owned(),
+            subscriptions: BTreeSet::new(),
+            receiver_address: None,
+            observer: false,
+            best_block: None,
+            node_kind: NodeKind::Full,
+            handshake_nonce: 123,
+        });
+        let valid_message = types::Message::Request {
+            request_id: types::RequestId::new(),
+            request: message::Request::PingRequest(message::PingRequest { nonce: 1 }),
+        };
+
+        socket2.write_all(&make_frame(&hello.encode())).await.unwrap();
+        // Unknown top-level message type
+        socket2.write_all(&make_frame(&[0xff, 1, 2, 3])).await.unwrap();
+        // Request with unknown request type
+        let mut unknown_request = vec![1];
+        unknown_request.extend_from_slice(&types::RequestId::new().encode());
+        unknown_request.push(0xff);
+        socket2.write_all(&make_frame(&unknown_request)).await.unwrap();
+        socket2.write_all(&make_frame(&valid_message.encode())).await.unwrap();
+
+        assert!(std::matches!(
+            rx1.recv().await.unwrap(),
+            (_, PeerEvent::PeerInfoReceived { .. })
+        ));
+        for _ in 0..2 {
+            assert_eq!(
+                rx1.recv().await.unwrap(),
+                (
+                    peer_id,
+                    PeerEvent::Misbehaved {
+                        error: P2pError::ProtocolError(ProtocolError::UnknownMessageType),
+                    }
+                )
+            );
+        }
+        // The connection is still usable
+        assert_eq!(
+            rx1.recv().await.unwrap(),
+            (
+                peer_id,
+                PeerEvent::MessageReceived {
+                    message: valid_message
+                }
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_message_type_tcp() {
+        unknown_message_type::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn unknown_message_type_channels() {
+        unknown_message_type::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn unknown_message_type_noise() {
+        unknown_message_type::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
+    // A message of a known type with a truncated body can't be decoded
+    async fn undecodable_message<A, T>(disconnect_on_protocol_error: bool)
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(P2pConfig {
+            disconnect_on_protocol_error: disconnect_on_protocol_error.into(),
+            ..Default::default()
+        });
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+        tokio::spawn(async move { peer.run().await });
+
+        let hello = types::Message::Handshake(types::HandshakeMessage::Hello {
+            version: *chain_config.version(),
+            network: *chain_config.magic_bytes(),
+            protocol_name: chain_config.protocol_name().to_owned(),
+            subscriptions: BTreeSet::new(),
+            receiver_address: None,
+            observer: false,
+            best_block: None,
+            node_kind: NodeKind::Full,
+            handshake_nonce: 123,
+        });
+        let valid_message = types::Message::Request {
+            request_id: types::RequestId::new(),
+            request: message::Request::PingRequest(message::PingRequest { nonce: 1 }),
+        };
+
+        socket2.write_all(&make_frame(&hello.encode())).await.unwrap();
+        // Ping request without the nonce
+        let mut truncated_request = vec![1];
+        truncated_request.extend_from_slice(&types::RequestId::new().encode());
+        truncated_request.push(4);
+        socket2.write_all(&make_frame(&truncated_request)).await.unwrap();
+        socket2.write_all(&make_frame(&valid_message.encode())).await.unwrap();
+
+        assert!(std::matches!(
+            rx1.recv().await.unwrap(),
+            (_, PeerEvent::PeerInfoReceived { .. })
+        ));
+        if disconnect_on_protocol_error {
+            assert_eq!(
+                rx1.recv().await.unwrap(),
+                (peer_id, PeerEvent::ConnectionClosed)
+            );
+        } else {
+            assert_eq!(
+                rx1.recv().await.unwrap(),
+                (
+                    peer_id,
+                    PeerEvent::Misbehaved {
+                        error: P2pError::ProtocolError(ProtocolError::UndecodableMessage),
+                    }
+                )
+            );
+            // The connection is still usable
+            assert_eq!(
+                rx1.recv().await.unwrap(),
+                (
+                    peer_id,
+                    PeerEvent::MessageReceived {
+                        message: valid_message
+                    }
+                )
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_strict_tcp() {
+        undecodable_message::<TestTransportTcp, TcpTransportSocket>(true).await;
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_strict_channels() {
+        undecodable_message::<TestTransportChannel, MpscChannelTransport>(true).await;
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_strict_noise() {
+        undecodable_message::<TestTransportNoise, NoiseTcpTransport>(true).await;
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_lenient_tcp() {
+        undecodable_message::<TestTransportTcp, TcpTransportSocket>(false).await;
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_lenient_channels() {
+        undecodable_message::<TestTransportChannel, MpscChannelTransport>(false).await;
+    }
+
+    #[tokio::test]
+    async fn undecodable_message_lenient_noise() {
+        undecodable_message::<TestTransportNoise, NoiseTcpTransport>(false).await;
+    }
+
+    // The message size is checked before the message is decoded
+    async fn oversized_message<A, T>()
+    where
+        A: TestTransportMaker<Transport = T, Address = T::Address>,
+        T: TransportSocket,
+    {
+        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+
+        let hello = types::Message::Handshake(types::HandshakeMessage::Hello {
+            version: *chain_config.version(),
+            network: *chain_config.magic_bytes(),
+            protocol_name: chain_config.protocol_name().to_owned(),
+            subscriptions: BTreeSet::new(),
+            receiver_address: None,
+            observer: false,
+            best_block: None,
+            node_kind: NodeKind::Full,
+            handshake_nonce: 123,
+        });
+        let message = types::Message::Request {
+            request_id: types::RequestId::new(),
+            request: message::Request::PingRequest(message::PingRequest { nonce: 1 }),
+        };
+        let message_frame = make_frame(&message.encode());
+        let message_size = message_frame.len() - 4;
+
+        let p2p_config = Arc::new(P2pConfig {
+            max_message_size: (message_size - 1).into(),
+            ..Default::default()
+        });
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let peer_id = PeerId::new();
+
+        let mut peer = Peer::<T>::new(
+            peer_id,
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            p2p_config,
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            None,
+            Default::default(),
+            None,
+        );
+        tokio::spawn(async move { peer.run().await });
+
+        socket2.write_all(&make_frame(&hello.encode())).await.unwrap();
+        socket2.write_all(&message_frame).await.unwrap();
+
+        assert!(std::matches!(
+            rx1.recv().await.unwrap(),
+            (_, PeerEvent::PeerInfoReceived { .. })
+        ));
+        assert_eq!(
+            rx1.recv().await.unwrap(),
+            (
+                peer_id,
+                PeerEvent::Misbehaved {
+                    error: P2pError::ProtocolError(ProtocolError::MessageTooLarge(
+                        message_size,
+                        message_size - 1
+                    )),
+                }
+            )
+        );
+        assert_eq!(
+            rx1.recv().await.unwrap(),
+            (peer_id, PeerEvent::ConnectionClosed)
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_message_tcp() {
+        oversized_message::<TestTransportTcp, TcpTransportSocket>().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_message_channels() {
+        oversized_message::<TestTransportChannel, MpscChannelTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_message_noise() {
+        oversized_message::<TestTransportNoise, NoiseTcpTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn send_rate_limited() {
+        let (socket1, socket2) =
+            get_two_connected_sockets::<TestTransportChannel, MpscChannelTransport>().await;
+        let (tx1, _rx1) = mpsc::unbounded_channel();
+        let (_tx2, rx2) = mpsc::unbounded_channel();
+        let chain_config = Arc::new(common::chain::config::create_unit_test_config());
+
+        let make_message = || types::Message::Request {
+            request_id: types::RequestId::new(),
+            request: message::SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                *chain_config.magic_bytes(),
+                vec![],
+            ))
+            .into(),
+        };
+        let message_size = make_message().encoded_size() as u64;
+        // The bucket holds 10 messages and is refilled at the rate of 10 messages per second
+        let rate_limiter = RateLimiter::new(NonZeroU64::new(10 * message_size).unwrap());
+        const MESSAGE_COUNT: usize = 20;
+
+        let mut peer = Peer::<MpscChannelTransport>::new(
+            PeerId::new(),
+            PeerRole::Inbound,
+            Arc::clone(&chain_config),
+            Arc::new(P2pConfig::default()),
+            socket1,
+            None,
+            tx1,
+            rx2,
+            Arc::new(AnnouncementQueue::new(1)),
+            None,
+            Some(Arc::new(rate_limiter)),
+            Default::default(),
+            None,
+        );
+
+        let started_at = std::time::Instant::now();
+        let handle = tokio::spawn(async move {
+            for _ in 0..MESSAGE_COUNT {
+                peer.send_message(make_message()).await.unwrap();
+            }
+        });
+
+        let mut socket2 = BufferedTranscoder::new(socket2);
+        for _ in 0..MESSAGE_COUNT {
+            socket2.recv().await.unwrap();
+        }
+        handle.await.unwrap();
+
+        // The first 10 messages are sent immediately, the remaining ones should take about a second
+        assert!(started_at.elapsed() >= std::time::Duration::from_millis(900));
+    }
+
+    // The peer that doesn't read from its socket is dropped while others keep working
+    #[tokio::test]
+    async fn send_timeout() {
+        let chain_config = Arc::new(common::chain::config::create_unit_test_config());
+        let p2p_config = Arc::new(P2pConfig {
+            send_timeout: std::time::Duration::from_millis(100).into(),
+            ..Default::default()
+        });
+        let make_peer = |socket| {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            let (_tx, rx) = mpsc::unbounded_channel();
+            Peer::<MpscChannelTransport>::new(
+                PeerId::new(),
+                PeerRole::Inbound,
+                Arc::clone(&chain_config),
+                Arc::clone(&p2p_config),
+                socket,
+                None,
+                tx,
+                rx,
+                Arc::new(AnnouncementQueue::new(1)),
+                None,
+                None,
+                Default::default(),
+                None,
+            )
+        };
+        let make_message = || types::Message::Request {
+            request_id: types::RequestId::new(),
+            request: message::SyncRequest::BlockListRequest(message::BlockListRequest::new(
+                *chain_config.magic_bytes(),
+                vec![common::primitives::Id::new(common::primitives::H256::zero()); 1000],
+            ))
+            .into(),
+        };
+
+        let (stalled_socket, _stalled_remote_socket) =
+            get_two_connected_sockets::<TestTransportChannel, MpscChannelTransport>().await;
+        let (socket, remote_socket) =
+            get_two_connected_sockets::<TestTransportChannel, MpscChannelTransport>().await;
+        let mut stalled_peer = make_peer(stalled_socket);
+        let mut peer = make_peer(socket);
+
+        // The remote socket is never read, so the writes block once its buffer is full
+        let mut send_result = Ok(());
+        for _ in 0..1000 {
+            send_result = stalled_peer.send_message(make_message()).await;
+            if send_result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(
+            send_result,
+            Err(P2pError::PeerError(PeerError::WriteTimeout))
+        );
+
+        let mut remote_socket = BufferedTranscoder::new(remote_socket);
+        peer.send_message(make_message()).await.unwrap();
+        assert!(std::matches!(
+            remote_socket.recv().await.unwrap(),
+            types::Message::Request { .. }
+        ));
+    }
+
     pub async fn get_two_connected_sockets<A, T>() -> (T::Stream, T::Stream)
     where
         A: TestTransportMaker<Transport = T, Address = T::Address>,
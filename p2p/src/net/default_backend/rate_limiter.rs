@@ -0,0 +1,113 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Global limit of the outbound traffic
+//!
+//! A token bucket shared by all peer tasks. Every message sent to a remote peer consumes as many
+//! tokens as its encoded size. When the bucket is empty, the sender waits until enough tokens are
+//! refilled, so the backpressure is applied instead of dropping messages.
+
+use std::{num::NonZeroU64, time::Duration};
+
+use tokio::{sync::Mutex, time::Instant};
+
+/// Tokens are counted in billionths of a byte, so a refill after any number of nanoseconds is exact
+const TOKEN_SCALE: i128 = 1_000_000_000;
+
+pub struct RateLimiter {
+    /// Refill rate, also the capacity of the bucket
+    bytes_per_second: NonZeroU64,
+
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Available tokens (scaled by `TOKEN_SCALE`), negative if the last sender borrowed from
+    /// the future
+    tokens: i128,
+
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: NonZeroU64) -> Self {
+        Self {
+            bytes_per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: i128::from(bytes_per_second.get()) * TOKEN_SCALE,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until `bytes` can be sent without exceeding the configured rate
+    ///
+    /// The bucket is locked while waiting, so the concurrent senders are served in order.
+    /// Messages larger than the bucket capacity are allowed, but the following senders have to
+    /// wait longer.
+    pub async fn acquire(&self, bytes: usize) {
+        let rate = i128::from(self.bytes_per_second.get());
+        let capacity = rate * TOKEN_SCALE;
+        let mut bucket = self.bucket.lock().await;
+
+        let now = Instant::now();
+        let elapsed_nanos =
+            i128::try_from(now.duration_since(bucket.last_refill).as_nanos()).unwrap_or(i128::MAX);
+        bucket.tokens =
+            bucket.tokens.saturating_add(elapsed_nanos.saturating_mul(rate)).min(capacity);
+        bucket.last_refill = now;
+
+        let cost = i128::try_from(bytes).unwrap_or(i128::MAX).saturating_mul(TOKEN_SCALE);
+        bucket.tokens = bucket.tokens.saturating_sub(cost);
+        if bucket.tokens < 0 {
+            let wait_nanos = u64::try_from(-bucket.tokens / rate).unwrap_or(u64::MAX);
+            tokio::time::sleep(Duration::from_nanos(wait_nanos)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn burst_within_capacity() {
+        let limiter = RateLimiter::new(NonZeroU64::new(1000).unwrap());
+
+        let started_at = Instant::now();
+        for _ in 0..10 {
+            limiter.acquire(100).await;
+        }
+        assert!(started_at.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn wait_for_refill() {
+        tokio::time::pause();
+        let limiter = RateLimiter::new(NonZeroU64::new(1000).unwrap());
+
+        let started_at = Instant::now();
+        limiter.acquire(1000).await;
+        assert_eq!(started_at.elapsed(), Duration::ZERO);
+
+        // The bucket is empty, half of the rate is refilled in half a second
+        limiter.acquire(500).await;
+        assert_eq!(started_at.elapsed(), Duration::from_millis(500));
+
+        // A message larger than the capacity delays the next sender
+        limiter.acquire(3000).await;
+        assert_eq!(started_at.elapsed(), Duration::from_millis(3500));
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the peers that announcements are relayed to
+//!
+//! The backend remembers when an announcement of each topic was last relayed to each peer.
+//! If the number of relay targets is limited (see
+//! [`crate::config::P2pConfig::max_relay_peers`]), the peers that haven't been relayed to for
+//! the longest time are preferred, so the announcements rotate across all subscribed peers
+//! instead of always reaching the same ones.
+
+use std::time::Duration;
+
+use crypto::random::{make_pseudo_rng, Rng, SliceRandom};
+use tokio::time::Instant;
+
+/// Maximum announcement trickle interval, larger configured values are capped to it
+pub const MAX_TRICKLE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Selects up to `count` relay targets from the candidates
+///
+/// Every candidate comes with the time of the last relay on the topic. The peers that were never
+/// relayed to are selected first, then the least recently relayed ones. Ties are broken randomly.
+pub fn select_relay_targets<P>(mut candidates: Vec<(P, Option<Instant>)>, count: usize) -> Vec<P> {
+    candidates.shuffle(&mut make_pseudo_rng());
+    // The sort is stable, so the peers with the same relay time stay shuffled
+    candidates.sort_by_key(|(_peer, last_relayed)| *last_relayed);
+    candidates.into_iter().take(count).map(|(peer, _last_relayed)| peer).collect()
+}
+
+/// Returns the pseudorandom delay before the trickled announcements are sent to an outbound peer
+///
+/// The delay is picked between a half and one and a half of the interval. The interval is capped
+/// to [`MAX_TRICKLE_INTERVAL`], so an arbitrarily large configured value can't overflow.
+pub fn trickle_delay(interval: Duration) -> Duration {
+    let interval = std::cmp::min(interval, MAX_TRICKLE_INTERVAL);
+    make_pseudo_rng().gen_range(interval / 2..=interval.saturating_mul(3) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn relays_rotate() {
+        let mut last_relayed = (0..4).map(|peer| (peer, None)).collect::<BTreeMap<u32, _>>();
+        let start = Instant::now();
+        let mut select = |now: Instant| {
+            let selected = select_relay_targets(
+                last_relayed.iter().map(|(peer, time)| (*peer, *time)).collect(),
+                2,
+            );
+            for peer in selected.iter() {
+                last_relayed.insert(*peer, Some(now));
+            }
+            selected
+        };
+
+        let mut first = select(start);
+        let mut second = select(start + Duration::from_secs(1));
+        let mut third = select(start + Duration::from_secs(2));
+
+        // Every peer is selected once before any peer is selected again
+        let mut all = first.iter().chain(second.iter()).copied().collect::<Vec<_>>();
+        all.sort();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+
+        // Then the peers that were relayed to first are selected again
+        first.sort();
+        second.sort();
+        third.sort();
+        assert_eq!(third, first);
+        assert_ne!(third, second);
+    }
+
+    #[test]
+    fn fewer_candidates_than_count() {
+        let now = Instant::now();
+        let mut selected = select_relay_targets(vec![(1, Some(now)), (2, None)], 5);
+        assert_eq!(selected, vec![2, 1]);
+
+        selected = select_relay_targets(Vec::<(u32, _)>::new(), 5);
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn trickle_delay_bounds() {
+        let interval = Duration::from_secs(10);
+        for _ in 0..100 {
+            let delay = trickle_delay(interval);
+            assert!(delay >= interval / 2 && delay <= interval * 3 / 2);
+        }
+
+        for interval in [Duration::MAX, Duration::from_secs(u64::MAX / 2)] {
+            let delay = trickle_delay(interval);
+            assert!(delay >= MAX_TRICKLE_INTERVAL / 2 && delay <= MAX_TRICKLE_INTERVAL * 3 / 2);
+        }
+    }
+}
@@ -31,18 +31,29 @@ use crate::{
 };
 use std::collections::{hash_map::Entry, HashMap, HashSet};
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct RequestManager {
     /// Active ephemeral IDs
     ephemerals: HashMap<types::PeerId, HashSet<types::RequestId>>,
 
     /// Ephemeral requests IDs which are mapped to remote peer ID/request ID pair
     ephemeral: HashMap<types::RequestId, (types::PeerId, types::RequestId)>,
+
+    /// Maximum number of ephemeral requests of all peers
+    max_requests: usize,
+
+    /// Maximum number of ephemeral requests of a single peer
+    max_requests_per_peer: usize,
 }
 
 impl RequestManager {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn new(max_requests: usize, max_requests_per_peer: usize) -> Self {
+        Self {
+            ephemerals: HashMap::new(),
+            ephemeral: HashMap::new(),
+            max_requests,
+            max_requests_per_peer,
+        }
     }
 
     /// Register peer to the request manager
@@ -83,12 +94,28 @@ impl RequestManager {
     ///
     /// Use the assigned ephemeral ID to fetch the peer ID and the actual request ID
     /// of the remote node and return all information to the caller.
+    /// The ephemeral ID is kept until the last chunk of a chunked response is sent.
     pub fn make_response(
         &mut self,
         request_id: &types::RequestId,
         response: message::Response,
     ) -> Option<(types::PeerId, Box<types::Message>)> {
-        if let Some((peer_id, request_id)) = self.ephemeral.remove(request_id) {
+        let ids = match &response {
+            message::Response::BlocksChunk(chunk) if !chunk.last => {
+                self.ephemeral.get(request_id).copied()
+            }
+            _ => {
+                let ids = self.ephemeral.remove(request_id);
+                if let Some((peer_id, _)) = &ids {
+                    if let Some(ephemerals) = self.ephemerals.get_mut(peer_id) {
+                        ephemerals.remove(request_id);
+                    }
+                }
+                ids
+            }
+        };
+
+        if let Some((peer_id, request_id)) = ids {
             return Some((
                 peer_id,
                 Box::new(types::Message::Response {
@@ -101,10 +128,17 @@ impl RequestManager {
         None
     }
 
+    /// Returns the number of inbound requests from the peer that are not answered yet
+    pub fn pending_requests(&self, peer_id: &types::PeerId) -> usize {
+        self.ephemerals.get(peer_id).map_or(0, |ephemerals| ephemerals.len())
+    }
+
     /// Register inbound request
     ///
     /// The request ID is stored into a temporary storage holding all pending
-    /// inbound requests.
+    /// inbound requests. Fails with `TooManyPeerRequests` if the pending requests
+    /// of the peer already reach the per-peer limit, and with `RequestManagerFull`
+    /// if the pending requests of all peers already reach the global limit.
     // TODO: Use different type in result so it's not possible to mixup ephemeral and real request ids.
     pub fn register_request(
         &mut self,
@@ -116,6 +150,14 @@ impl RequestManager {
             .get_mut(peer_id)
             .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
 
+        if peer_ephemerals.len() >= self.max_requests_per_peer {
+            return Err(P2pError::PeerError(PeerError::TooManyPeerRequests));
+        }
+
+        if self.ephemeral.len() >= self.max_requests {
+            return Err(P2pError::PeerError(PeerError::RequestManagerFull));
+        }
+
         let ephemeral_id = types::RequestId::new();
 
         peer_ephemerals.insert(ephemeral_id);
@@ -123,3 +165,81 @@ impl RequestManager {
         Ok(ephemeral_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_response() -> message::Response {
+        message::Response::AddrListResponse(message::AddrListResponse { addresses: vec![] })
+    }
+
+    #[test]
+    fn global_limit() {
+        let mut request_mgr = RequestManager::new(4, 4);
+        let peer1 = types::PeerId::new();
+        let peer2 = types::PeerId::new();
+        request_mgr.register_peer(peer1).unwrap();
+        request_mgr.register_peer(peer2).unwrap();
+
+        // Fill the limit with the requests of both peers
+        let mut ephemeral_ids = Vec::new();
+        for peer_id in [peer1, peer2, peer1, peer2] {
+            ephemeral_ids
+                .push(request_mgr.register_request(&peer_id, &types::RequestId::new()).unwrap());
+        }
+        assert_eq!(request_mgr.pending_requests(&peer1), 2);
+        assert_eq!(request_mgr.pending_requests(&peer2), 2);
+
+        for peer_id in [peer1, peer2] {
+            assert_eq!(
+                request_mgr.register_request(&peer_id, &types::RequestId::new()),
+                Err(P2pError::PeerError(PeerError::RequestManagerFull))
+            );
+        }
+
+        // Answering a request frees a slot for any peer
+        let (peer_id, _) = request_mgr.make_response(&ephemeral_ids[0], make_response()).unwrap();
+        assert_eq!(peer_id, peer1);
+        request_mgr.register_request(&peer2, &types::RequestId::new()).unwrap();
+        assert_eq!(
+            request_mgr.register_request(&peer1, &types::RequestId::new()),
+            Err(P2pError::PeerError(PeerError::RequestManagerFull))
+        );
+
+        // Disconnected peers free their slots too
+        request_mgr.unregister_peer(&peer2);
+        request_mgr.register_request(&peer1, &types::RequestId::new()).unwrap();
+        assert_eq!(request_mgr.pending_requests(&peer1), 2);
+    }
+
+    #[test]
+    fn per_peer_limit() {
+        let mut request_mgr = RequestManager::new(4, 2);
+        let peer1 = types::PeerId::new();
+        let peer2 = types::PeerId::new();
+        request_mgr.register_peer(peer1).unwrap();
+        request_mgr.register_peer(peer2).unwrap();
+
+        let ephemeral_id = request_mgr.register_request(&peer1, &types::RequestId::new()).unwrap();
+        request_mgr.register_request(&peer1, &types::RequestId::new()).unwrap();
+        assert_eq!(
+            request_mgr.register_request(&peer1, &types::RequestId::new()),
+            Err(P2pError::PeerError(PeerError::TooManyPeerRequests))
+        );
+
+        // The other peer still has its own slots
+        request_mgr.register_request(&peer2, &types::RequestId::new()).unwrap();
+        request_mgr.register_request(&peer2, &types::RequestId::new()).unwrap();
+
+        // The per-peer limit is checked before the global one
+        assert_eq!(
+            request_mgr.register_request(&peer1, &types::RequestId::new()),
+            Err(P2pError::PeerError(PeerError::TooManyPeerRequests))
+        );
+
+        // Answering a request frees a slot of the peer
+        request_mgr.make_response(&ephemeral_id, make_response()).unwrap();
+        request_mgr.register_request(&peer1, &types::RequestId::new()).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Protocol tracer of the backend
+//!
+//! The tracer is shared by all peer tasks and records every message sent to or received from
+//! a remote peer, in order, so the tests can assert the exact protocol steps.
+
+use serialization::Encode;
+use tokio::sync::mpsc;
+
+use crate::net::default_backend::{
+    metrics::MessageKind,
+    types::{Message, PeerId},
+};
+
+/// A protocol step recorded by the tracer
+///
+/// The size is the encoded size of the message without the frame length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolEvent {
+    HandshakeSent {
+        peer_id: PeerId,
+        size: usize,
+    },
+    HandshakeReceived {
+        peer_id: PeerId,
+        size: usize,
+    },
+    FrameSent {
+        peer_id: PeerId,
+        kind: MessageKind,
+        size: usize,
+    },
+    FrameReceived {
+        peer_id: PeerId,
+        kind: MessageKind,
+        size: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ProtocolTracer {
+    tx: mpsc::UnboundedSender<ProtocolEvent>,
+}
+
+impl ProtocolTracer {
+    /// Creates a tracer and the channel the recorded events are sent to
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ProtocolEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    pub fn record_sent(&self, peer_id: PeerId, message: &Message) {
+        let size = message.encoded_size();
+        let event = match MessageKind::from(message) {
+            MessageKind::Handshake => ProtocolEvent::HandshakeSent { peer_id, size },
+            kind => ProtocolEvent::FrameSent {
+                peer_id,
+                kind,
+                size,
+            },
+        };
+        self.record(event);
+    }
+
+    pub fn record_received(&self, peer_id: PeerId, message: &Message) {
+        let size = message.encoded_size();
+        let event = match MessageKind::from(message) {
+            MessageKind::Handshake => ProtocolEvent::HandshakeReceived { peer_id, size },
+            kind => ProtocolEvent::FrameReceived {
+                peer_id,
+                kind,
+                size,
+            },
+        };
+        self.record(event);
+    }
+
+    fn record(&self, event: ProtocolEvent) {
+        // The test may have stopped reading the trace, that's not an error for the backend
+        let _ = self.tx.send(event);
+    }
+}
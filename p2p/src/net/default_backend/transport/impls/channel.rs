@@ -223,7 +223,10 @@ mod tests {
         let peer_stream = peer_res.unwrap();
 
         let request_id = RequestId::new();
-        let request = SyncRequest::BlockListRequest(BlockListRequest::new(vec![]));
+        let request = SyncRequest::BlockListRequest(BlockListRequest::new(
+            *common::chain::config::create_unit_test_config().magic_bytes(),
+            vec![],
+        ));
         let mut peer_stream = BufferedTranscoder::new(peer_stream);
         peer_stream
             .send(Message::Request {
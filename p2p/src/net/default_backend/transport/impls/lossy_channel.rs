@@ -0,0 +1,357 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{future::BoxFuture, ready, FutureExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::Sleep,
+};
+
+use crypto::random::{make_pseudo_rng, Rng};
+
+use crate::{
+    net::default_backend::transport::{
+        ChannelListener, ChannelStream, MpscChannelTransport, PeerStream, TransportListener,
+        TransportSocket,
+    },
+    Result,
+};
+
+/// A wrapper over [`MpscChannelTransport`] that simulates an unreliable link.
+///
+/// Every write to a stream created by this transport is delayed by `latency` and is silently
+/// lost with `drop_probability`. Writes can also be limited to `max_write_size` bytes
+/// (see [`LossyChannelTransport::with_max_write_size`]) to simulate a slow trickling peer.
+/// Only the outgoing traffic of the "host" is affected, the remote side may use a different
+/// transport settings.
+///
+/// This transport should only be used in tests.
+#[derive(Debug)]
+pub struct LossyChannelTransport {
+    inner: MpscChannelTransport,
+    latency: Duration,
+    drop_probability: f64,
+    max_write_size: usize,
+}
+
+impl LossyChannelTransport {
+    pub fn new(latency: Duration, drop_probability: f64) -> Self {
+        assert!((0.0..=1.0).contains(&drop_probability));
+
+        Self {
+            inner: MpscChannelTransport::new(),
+            latency,
+            drop_probability,
+            max_write_size: usize::MAX,
+        }
+    }
+
+    /// Limits every write to `max_write_size` bytes, so the messages are delivered in pieces
+    pub fn with_max_write_size(mut self, max_write_size: usize) -> Self {
+        assert!(max_write_size > 0);
+
+        self.max_write_size = max_write_size;
+        self
+    }
+}
+
+#[async_trait]
+impl TransportSocket for LossyChannelTransport {
+    type Address = u32;
+    type BannableAddress = u32;
+    type Listener = LossyChannelListener;
+    type Stream = LossyChannelStream;
+
+    async fn bind(&self, addresses: Vec<Self::Address>) -> Result<Self::Listener> {
+        let inner = self.inner.bind(addresses).await?;
+
+        Ok(LossyChannelListener {
+            inner,
+            latency: self.latency,
+            drop_probability: self.drop_probability,
+            max_write_size: self.max_write_size,
+        })
+    }
+
+    fn connect(&self, address: Self::Address) -> BoxFuture<'static, Result<Self::Stream>> {
+        let latency = self.latency;
+        let drop_probability = self.drop_probability;
+        let max_write_size = self.max_write_size;
+
+        self.inner
+            .connect(address)
+            .map(move |res| {
+                res.map(|stream| {
+                    LossyChannelStream::new(stream, latency, drop_probability, max_write_size)
+                })
+            })
+            .boxed()
+    }
+}
+
+pub struct LossyChannelListener {
+    inner: ChannelListener,
+    latency: Duration,
+    drop_probability: f64,
+    max_write_size: usize,
+}
+
+#[async_trait]
+impl TransportListener<LossyChannelStream, u32> for LossyChannelListener {
+    async fn accept(&mut self) -> Result<(LossyChannelStream, u32)> {
+        let (stream, address) = self.inner.accept().await?;
+        Ok((
+            LossyChannelStream::new(
+                stream,
+                self.latency,
+                self.drop_probability,
+                self.max_write_size,
+            ),
+            address,
+        ))
+    }
+
+    fn local_addresses(&self) -> Result<Vec<u32>> {
+        self.inner.local_addresses()
+    }
+}
+
+pub struct LossyChannelStream {
+    inner: ChannelStream,
+    latency: Duration,
+    drop_probability: f64,
+    max_write_size: usize,
+
+    /// Delay of the write that is currently in progress
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl LossyChannelStream {
+    fn new(
+        inner: ChannelStream,
+        latency: Duration,
+        drop_probability: f64,
+        max_write_size: usize,
+    ) -> Self {
+        Self {
+            inner,
+            latency,
+            drop_probability,
+            max_write_size,
+            delay: None,
+        }
+    }
+}
+
+impl AsyncRead for LossyChannelStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for LossyChannelStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let buf = &buf[..std::cmp::min(buf.len(), self.max_write_size)];
+        let latency = self.latency;
+        let delay = self.delay.get_or_insert_with(|| Box::pin(tokio::time::sleep(latency)));
+        ready!(delay.as_mut().poll(cx));
+
+        if make_pseudo_rng().gen_bool(self.drop_probability) {
+            // The data is lost on the way, but the sender doesn't know about it
+            self.delay = None;
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        let res = ready!(Pin::new(&mut self.inner).poll_write(cx, buf));
+        self.delay = None;
+        Poll::Ready(res)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl PeerStream for LossyChannelStream {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::time::{timeout, Instant};
+
+    use super::*;
+    use crate::{
+        config::P2pConfig,
+        message::{BlockListRequest, SyncRequest},
+        net::{
+            default_backend::{
+                transport::BufferedTranscoder,
+                types::{Message, RequestId},
+                DefaultNetworkingService,
+            },
+            types::ConnectivityEvent,
+            ConnectivityService, NetworkingService,
+        },
+    };
+
+    async fn connect(
+        transport1: LossyChannelTransport,
+        transport2: LossyChannelTransport,
+    ) -> (
+        BufferedTranscoder<LossyChannelStream>,
+        BufferedTranscoder<LossyChannelStream>,
+    ) {
+        let mut server = transport1.bind(vec![0]).await.unwrap();
+        let peer_fut = transport2.connect(server.local_addresses().unwrap()[0]);
+
+        let (server_res, peer_res) = tokio::join!(server.accept(), peer_fut);
+        (
+            BufferedTranscoder::new(server_res.unwrap().0),
+            BufferedTranscoder::new(peer_res.unwrap()),
+        )
+    }
+
+    fn make_message() -> Message {
+        make_message_with_id(RequestId::new())
+    }
+
+    fn make_message_with_id(request_id: RequestId) -> Message {
+        Message::Request {
+            request_id,
+            request: SyncRequest::BlockListRequest(BlockListRequest::new(
+                *common::chain::config::create_unit_test_config().magic_bytes(),
+                vec![],
+            ))
+            .into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn latency() {
+        let latency = Duration::from_millis(200);
+        let (mut server_stream, mut peer_stream) = connect(
+            LossyChannelTransport::new(Duration::ZERO, 0.0),
+            LossyChannelTransport::new(latency, 0.0),
+        )
+        .await;
+
+        let started_at = Instant::now();
+        peer_stream.send(make_message()).await.unwrap();
+        server_stream.recv().await.unwrap();
+        assert!(started_at.elapsed() >= latency);
+    }
+
+    #[tokio::test]
+    async fn packet_loss() {
+        let (mut server_stream, mut peer_stream) = connect(
+            LossyChannelTransport::new(Duration::ZERO, 0.0),
+            LossyChannelTransport::new(Duration::ZERO, 1.0),
+        )
+        .await;
+
+        peer_stream.send(make_message()).await.unwrap();
+        assert!(timeout(Duration::from_millis(100), server_stream.recv()).await.is_err());
+    }
+
+    // The messages are delivered one byte at a time and are still decoded correctly
+    #[tokio::test]
+    async fn trickle() {
+        let (mut server_stream, mut peer_stream) = connect(
+            LossyChannelTransport::new(Duration::ZERO, 0.0),
+            LossyChannelTransport::new(Duration::from_millis(1), 0.0).with_max_write_size(1),
+        )
+        .await;
+
+        let request_ids = [RequestId::new(), RequestId::new()];
+        let send_fut = async {
+            for request_id in request_ids {
+                peer_stream.send(make_message_with_id(request_id)).await.unwrap();
+            }
+        };
+        let recv_fut = async {
+            let mut messages = Vec::new();
+            for _ in request_ids {
+                messages.push(server_stream.recv().await.unwrap());
+            }
+            messages
+        };
+        let ((), messages) = timeout(Duration::from_secs(10), async {
+            tokio::join!(send_fut, recv_fut)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            messages,
+            request_ids.into_iter().map(make_message_with_id).collect::<Vec<_>>()
+        );
+    }
+
+    // The outbound handshake doesn't complete in time because of the high latency
+    #[tokio::test]
+    async fn high_latency_handshake_timeout() {
+        let chain_config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config = Arc::new(P2pConfig {
+            handshake_timeout: Duration::from_millis(100).into(),
+            ..Default::default()
+        });
+
+        let (mut conn1, _sync1) = DefaultNetworkingService::<LossyChannelTransport>::start(
+            LossyChannelTransport::new(Duration::from_secs(5), 0.0),
+            vec![0],
+            Arc::clone(&chain_config),
+            Arc::clone(&p2p_config),
+        )
+        .await
+        .unwrap();
+        let (conn2, _sync2) = DefaultNetworkingService::<LossyChannelTransport>::start(
+            LossyChannelTransport::new(Duration::ZERO, 0.0),
+            vec![0],
+            chain_config,
+            p2p_config,
+        )
+        .await
+        .unwrap();
+
+        conn1.connect(conn2.local_addresses()[0]).unwrap();
+
+        match timeout(Duration::from_secs(1), conn1.poll_next()).await {
+            Ok(Ok(ConnectivityEvent::ConnectionClosed { peer_id: _ })) => {}
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+}
@@ -35,4 +35,8 @@ impl<T: PeerStream + 'static> StreamAdapter<T> for IdentityStreamAdapter {
     fn handshake(&self, base: T, _role: Role) -> BoxFuture<'static, crate::Result<Self::Stream>> {
         Box::pin(ready(Ok(base)))
     }
+
+    fn local_public_key(&self) -> Option<&[u8]> {
+        None
+    }
 }
@@ -15,6 +15,7 @@
 
 pub mod identity;
 pub mod noise;
+pub mod noise_upgrade;
 pub mod traits;
 pub mod wrapped_transport;
 
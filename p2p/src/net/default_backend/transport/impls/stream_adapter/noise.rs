@@ -40,13 +40,29 @@ pub struct NoiseEncryptionAdapter {
 }
 
 impl NoiseEncryptionAdapter {
+    /// Creates the adapter from the existing static keypair.
+    ///
+    /// Reusing the same keypair keeps the local node identity stable across restarts.
+    pub fn new(local_key: snowstorm::Keypair) -> Self {
+        Self {
+            local_key: Arc::new(local_key),
+        }
+    }
+
     pub fn gen_new() -> Self {
-        let local_key = Arc::new(
-            snowstorm::Builder::new(NOISE_HANDSHAKE_PARAMS.clone())
-                .generate_keypair()
-                .expect("key generation must succeed"),
-        );
-        Self { local_key }
+        Self::new(Self::generate_keypair())
+    }
+
+    /// Generates a new static keypair that can be persisted and passed to [`Self::new`] later.
+    pub fn generate_keypair() -> snowstorm::Keypair {
+        snowstorm::Builder::new(NOISE_HANDSHAKE_PARAMS.clone())
+            .generate_keypair()
+            .expect("key generation must succeed")
+    }
+
+    /// Returns the static keypair used by the adapter.
+    pub fn local_key(&self) -> &snowstorm::Keypair {
+        &self.local_key
     }
 }
 
@@ -82,6 +98,14 @@ impl<T: PeerStream + 'static> StreamAdapter<T> for NoiseEncryptionAdapter {
             Ok(stream)
         })
     }
+
+    fn local_public_key(&self) -> Option<&[u8]> {
+        Some(&self.local_key.public)
+    }
 }
 
-impl<T: PeerStream> PeerStream for snowstorm::NoiseStream<T> {}
+impl<T: PeerStream> PeerStream for snowstorm::NoiseStream<T> {
+    fn remote_public_key(&self) -> Option<Vec<u8>> {
+        self.get_state().get_remote_static().map(|key| key.to_vec())
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opportunistic upgrade of a plaintext connection to Noise
+//!
+//! Before anything else is sent, both sides write one byte telling whether they can upgrade
+//! the connection. If both can, the Noise handshake is done over the same socket and the
+//! connection is encrypted from then on. Otherwise the connection stays plaintext, unless
+//! plaintext connections are not allowed by the local node, then the connection is refused.
+//!
+//! The offers are exchanged before anything is authenticated, so a man in the middle can make
+//! both sides believe that the other one can't upgrade. That's why plaintext connections should
+//! only be allowed if the connection doesn't need to be private.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    time::timeout,
+};
+
+use crate::{
+    error::P2pError,
+    net::{default_backend::transport::PeerStream, types::Role},
+};
+
+use super::{noise::NoiseEncryptionAdapter, StreamAdapter};
+
+/// How much time is allowed to spend telling the other side whether the upgrade is supported
+const NEGOTIATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sent by a node that can't or doesn't want to upgrade the connection
+const PLAINTEXT: u8 = 0;
+
+/// Sent by a node that can upgrade the connection to Noise
+const UPGRADE_NOISE: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct NoiseUpgradeAdapter {
+    noise: Option<NoiseEncryptionAdapter>,
+    allow_plaintext: bool,
+}
+
+impl NoiseUpgradeAdapter {
+    /// Creates the adapter
+    ///
+    /// # Arguments
+    /// * `noise` - the adapter used to upgrade the connection, the upgrade isn't offered if `None`
+    /// * `allow_plaintext` - whether the connection is kept if it can't be upgraded
+    pub fn new(noise: Option<NoiseEncryptionAdapter>, allow_plaintext: bool) -> Self {
+        Self {
+            noise,
+            allow_plaintext,
+        }
+    }
+}
+
+/// StreamAdapter that upgrades the connection to Noise if both sides support it
+impl<T: PeerStream + 'static> StreamAdapter<T> for NoiseUpgradeAdapter {
+    type Stream = UpgradableStream<T>;
+
+    fn handshake(
+        &self,
+        mut base: T,
+        role: Role,
+    ) -> BoxFuture<'static, crate::Result<Self::Stream>> {
+        let noise = self.noise.clone();
+        let allow_plaintext = self.allow_plaintext;
+        Box::pin(async move {
+            let local_offer = if noise.is_some() {
+                UPGRADE_NOISE
+            } else {
+                PLAINTEXT
+            };
+            let remote_offer = timeout(NEGOTIATION_TIMEOUT, async {
+                base.write_all(&[local_offer]).await?;
+                base.flush().await?;
+                base.read_u8().await
+            })
+            .await
+            .map_err(|_err| {
+                P2pError::NoiseHandshakeError("Upgrade negotiation timeout".to_owned())
+            })?
+            .map_err(|err| P2pError::NoiseHandshakeError(err.to_string()))?;
+
+            match (noise, remote_offer) {
+                (Some(noise), UPGRADE_NOISE) => {
+                    let stream = noise.handshake(base, role).await?;
+                    Ok(UpgradableStream::Noise(Box::new(stream)))
+                }
+                // The offers can be tampered with, so falling back to plaintext must be allowed
+                // explicitly
+                (_, PLAINTEXT | UPGRADE_NOISE) if allow_plaintext => {
+                    Ok(UpgradableStream::Plaintext(base))
+                }
+                (_, PLAINTEXT | UPGRADE_NOISE) => Err(P2pError::NoiseHandshakeError(
+                    "Connection can't be encrypted and plaintext is not allowed".to_owned(),
+                )),
+                (_, offer) => Err(P2pError::NoiseHandshakeError(format!(
+                    "Unexpected upgrade offer {offer}"
+                ))),
+            }
+        })
+    }
+
+    fn local_public_key(&self) -> Option<&[u8]> {
+        self.noise.as_ref().map(|noise| noise.local_key().public.as_slice())
+    }
+}
+
+/// A connection that is either upgraded to Noise or stays plaintext
+pub enum UpgradableStream<T> {
+    Plaintext(T),
+    Noise(Box<snowstorm::NoiseStream<T>>),
+}
+
+impl<T> UpgradableStream<T> {
+    /// Returns true if the connection was upgraded to Noise
+    pub fn is_encrypted(&self) -> bool {
+        match self {
+            UpgradableStream::Plaintext(_) => false,
+            UpgradableStream::Noise(_) => true,
+        }
+    }
+}
+
+impl<T: PeerStream> AsyncRead for UpgradableStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            UpgradableStream::Plaintext(stream) => Pin::new(stream).poll_read(cx, buf),
+            UpgradableStream::Noise(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<T: PeerStream> AsyncWrite for UpgradableStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            UpgradableStream::Plaintext(stream) => Pin::new(stream).poll_write(cx, buf),
+            UpgradableStream::Noise(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            UpgradableStream::Plaintext(stream) => Pin::new(stream).poll_flush(cx),
+            UpgradableStream::Noise(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            UpgradableStream::Plaintext(stream) => Pin::new(stream).poll_shutdown(cx),
+            UpgradableStream::Noise(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+impl<T: PeerStream> PeerStream for UpgradableStream<T> {
+    fn remote_public_key(&self) -> Option<Vec<u8>> {
+        match self {
+            UpgradableStream::Plaintext(_) => None,
+            UpgradableStream::Noise(stream) => stream.remote_public_key(),
+        }
+    }
+}
@@ -26,4 +26,7 @@ pub trait StreamAdapter<T>: Clone + Send + Sync + 'static {
 
     /// Wraps base async stream into AsyncRead/AsyncWrite stream that may implement encryption.
     fn handshake(&self, base: T, role: Role) -> BoxFuture<'static, Result<Self::Stream>>;
+
+    /// Returns the static public key that identifies the local node, if the adapter has one.
+    fn local_public_key(&self) -> Option<&[u8]>;
 }
@@ -34,8 +34,8 @@ use crate::{
         transport::{
             impls::stream_adapter::wrapped_transport::wrapped_listener::MAX_CONCURRENT_HANDSHAKES,
             BufferedTranscoder, ChannelListener, IdentityStreamAdapter, MpscChannelTransport,
-            NoiseEncryptionAdapter, PeerStream, TcpTransportSocket, TransportListener,
-            TransportSocket,
+            NoiseEncryptionAdapter, NoiseUpgradeAdapter, PeerStream, TcpTransportSocket,
+            TransportListener, TransportSocket, UpgradableTcpTransport,
         },
         types::{Message, RequestId},
     },
@@ -112,6 +112,70 @@ async fn test_send_recv() {
     .await;
 }
 
+fn make_upgradable_transport(noise: bool, allow_plaintext: bool) -> UpgradableTcpTransport {
+    let noise = noise.then(NoiseEncryptionAdapter::gen_new);
+    WrappedTransportSocket::new(
+        NoiseUpgradeAdapter::new(noise, allow_plaintext),
+        TcpTransportSocket::new(),
+    )
+}
+
+// Connects the client transport to the server transport over TCP
+async fn connect_upgradable(
+    server: UpgradableTcpTransport,
+    client: UpgradableTcpTransport,
+) -> (
+    crate::Result<<UpgradableTcpTransport as TransportSocket>::Stream>,
+    crate::Result<<UpgradableTcpTransport as TransportSocket>::Stream>,
+) {
+    let mut listener = server.bind(vec![TestTransportTcp::make_address()]).await.unwrap();
+    let client_fut = client.connect(listener.local_addresses().unwrap()[0]);
+
+    let (server_res, client_res) = tokio::join!(listener.accept(), client_fut);
+    (server_res.map(|(stream, _)| stream), client_res)
+}
+
+#[tokio::test]
+async fn noise_upgrade() {
+    // Both sides support the upgrade
+    let (server_res, client_res) = connect_upgradable(
+        make_upgradable_transport(true, false),
+        make_upgradable_transport(true, false),
+    )
+    .await;
+    let mut server_stream = server_res.unwrap();
+    let mut client_stream = client_res.unwrap();
+    assert!(server_stream.is_encrypted());
+    assert!(client_stream.is_encrypted());
+    send_recv(&mut client_stream, &mut server_stream, 65536).await;
+    send_recv(&mut server_stream, &mut client_stream, 70000).await;
+
+    // One side doesn't support the upgrade, both allow plaintext
+    let (server_res, client_res) = connect_upgradable(
+        make_upgradable_transport(false, true),
+        make_upgradable_transport(true, true),
+    )
+    .await;
+    let mut server_stream = server_res.unwrap();
+    let mut client_stream = client_res.unwrap();
+    assert!(!server_stream.is_encrypted());
+    assert!(!client_stream.is_encrypted());
+    send_recv(&mut client_stream, &mut server_stream, 65536).await;
+    send_recv(&mut server_stream, &mut client_stream, 70000).await;
+
+    // The client doesn't allow plaintext and refuses the connection
+    let (server_res, client_res) = connect_upgradable(
+        make_upgradable_transport(false, true),
+        make_upgradable_transport(true, false),
+    )
+    .await;
+    assert!(!server_res.unwrap().is_encrypted());
+    assert!(matches!(
+        client_res,
+        Err(crate::error::P2pError::NoiseHandshakeError(_))
+    ));
+}
+
 pub struct TestTransport {
     transport: MpscChannelTransport,
     port_open: Arc<Mutex<bool>>,
@@ -211,7 +275,10 @@ async fn send_2_reqs() {
     let peer_stream = peer_res.unwrap();
 
     let id_1 = RequestId::new();
-    let request = Request::BlockListRequest(BlockListRequest::new(vec![]));
+    let request = Request::BlockListRequest(BlockListRequest::new(
+        *common::chain::config::create_unit_test_config().magic_bytes(),
+        vec![],
+    ));
     let mut peer_stream = BufferedTranscoder::new(peer_stream);
     peer_stream
         .send(Message::Request {
@@ -18,11 +18,13 @@ use futures::{
     future::BoxFuture,
     stream::{FuturesUnordered, StreamExt},
 };
+use tokio::time::Instant;
 
 use crate::{
     net::{
         default_backend::transport::{
             impls::stream_adapter::traits::StreamAdapter, TransportListener, TransportSocket,
+            TransportTiming,
         },
         types::Role,
     },
@@ -39,7 +41,8 @@ pub struct AdaptedListener<S: StreamAdapter<T::Stream>, T: TransportSocket> {
     stream_adapter: S,
     listener: T::Listener,
     #[allow(clippy::type_complexity)]
-    handshakes: FuturesUnordered<BoxFuture<'static, (Result<S::Stream>, T::Address)>>,
+    handshakes:
+        FuturesUnordered<BoxFuture<'static, (Result<S::Stream>, T::Address, TransportTiming)>>,
 }
 
 impl<S: StreamAdapter<T::Stream>, T: TransportSocket> AdaptedListener<S, T> {
@@ -57,27 +60,39 @@ impl<S: StreamAdapter<T::Stream>, T: TransportSocket> TransportListener<S::Strea
     for AdaptedListener<S, T>
 {
     async fn accept(&mut self) -> Result<(S::Stream, T::Address)> {
+        let (stream, address, _timing) = self.accept_timed().await?;
+        Ok((stream, address))
+    }
+
+    async fn accept_timed(&mut self) -> Result<(S::Stream, T::Address, TransportTiming)>
+    where
+        S::Stream: Send + 'static,
+        T::Address: Send + 'static,
+    {
         loop {
             let accept_new = self.handshakes.len() < MAX_CONCURRENT_HANDSHAKES;
             tokio::select! {
                 // FuturesUnordered will panic if polled while empty
                 handshake_res = self.handshakes.select_next_some(), if !self.handshakes.is_empty() => {
                     match handshake_res {
-                        (Ok(handshake), addr) => return Ok((handshake, addr)),
-                        (Err(err), _) => {
+                        (Ok(handshake), addr, timing) => return Ok((handshake, addr, timing)),
+                        (Err(err), _, _) => {
                             logging::log::warn!("handshake failed: {}", err);
                             continue;
                         },
                     }
                 }
-                accept_res = self.listener.accept(), if accept_new => {
+                accept_res = self.listener.accept_timed(), if accept_new => {
                     match accept_res {
-                        Ok((base, addr)) => {
+                        Ok((base, addr, mut timing)) => {
                             // Store active handshakes because accept must be cancel safe
                             let handshake = self.stream_adapter.handshake(base, Role::Inbound);
-                            // Wrap one more time to store original address
+                            // Wrap one more time to store original address and handshake timing
                             let handshake_with_addr = Box::pin(async move {
-                                (handshake.await, addr)
+                                let started_at = Instant::now();
+                                let res = handshake.await;
+                                timing.add_handshake(started_at.elapsed());
+                                (res, addr, timing)
                             });
                             self.handshakes.push(handshake_with_addr);
                         },
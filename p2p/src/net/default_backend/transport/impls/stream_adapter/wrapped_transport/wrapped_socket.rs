@@ -15,9 +15,12 @@
 
 use async_trait::async_trait;
 use futures::future::BoxFuture;
+use tokio::time::Instant;
 
 use crate::net::{
-    default_backend::transport::{impls::stream_adapter::traits::StreamAdapter, TransportSocket},
+    default_backend::transport::{
+        impls::stream_adapter::traits::StreamAdapter, TransportSocket, TransportTiming,
+    },
     types::Role,
 };
 
@@ -68,4 +71,25 @@ impl<S: StreamAdapter<T::Stream>, T: TransportSocket> TransportSocket
             Ok(stream)
         })
     }
+
+    fn connect_timed(
+        &self,
+        address: Self::Address,
+    ) -> BoxFuture<'static, crate::Result<(Self::Stream, TransportTiming)>> {
+        let base = self.base_transport.connect_timed(address);
+        let stream_adapter = self.stream_adapter.clone();
+        Box::pin(async move {
+            let (base, mut timing) = base.await?;
+            let started_at = Instant::now();
+            let stream = stream_adapter.handshake(base, Role::Outbound).await?;
+            timing.add_handshake(started_at.elapsed());
+            Ok((stream, timing))
+        })
+    }
+
+    fn local_public_key(&self) -> Option<&[u8]> {
+        self.stream_adapter
+            .local_public_key()
+            .or_else(|| self.base_transport.local_public_key())
+    }
 }
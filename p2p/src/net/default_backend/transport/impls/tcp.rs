@@ -13,7 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
@@ -27,10 +30,13 @@ use crate::{
         },
         AsBannableAddress,
     },
-    types::peer_address::PeerAddress,
+    types::{ip_address::canonical_ip, peer_address::PeerAddress},
     Result,
 };
 
+/// The default `listen` backlog, we don't need a lot of pending connections
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 32;
+
 impl TransportAddress for SocketAddr {
     fn as_peer_address(&self) -> PeerAddress {
         (*self).into()
@@ -41,12 +47,56 @@ impl TransportAddress for SocketAddr {
     }
 }
 
+/// Options that are applied to every accepted and dialed TCP socket.
+///
+/// The default values keep the OS defaults.
+#[derive(Debug, Clone, Default)]
+pub struct TcpTransportConfig {
+    /// Disable Nagle's algorithm (`TCP_NODELAY`)
+    pub nodelay: bool,
+    /// Enable TCP keep-alive probes after the connection has been idle for the given time
+    pub keepalive_interval: Option<Duration>,
+    /// Size of the receive buffer (`SO_RCVBUF`)
+    pub recv_buffer_size: Option<usize>,
+    /// Size of the send buffer (`SO_SNDBUF`)
+    pub send_buffer_size: Option<usize>,
+    /// Maximum number of pending incoming connections (the `listen` backlog), the OS may
+    /// adjust the value. [`DEFAULT_LISTEN_BACKLOG`] is used if not set.
+    pub listen_backlog: Option<u32>,
+}
+
+impl TcpTransportConfig {
+    fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+
+        let socket = socket2::SockRef::from(stream);
+        if let Some(keepalive_interval) = self.keepalive_interval {
+            socket
+                .set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(keepalive_interval))?;
+        }
+        if let Some(recv_buffer_size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(recv_buffer_size)?;
+        }
+        if let Some(send_buffer_size) = self.send_buffer_size {
+            socket.set_send_buffer_size(send_buffer_size)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
-pub struct TcpTransportSocket;
+pub struct TcpTransportSocket {
+    config: TcpTransportConfig,
+}
 
 impl TcpTransportSocket {
     pub fn new() -> Self {
-        Self
+        Self::with_config(Default::default())
+    }
+
+    pub fn with_config(config: TcpTransportConfig) -> Self {
+        Self { config }
     }
 }
 
@@ -58,12 +108,14 @@ impl TransportSocket for TcpTransportSocket {
     type Stream = TcpTransportStream;
 
     async fn bind(&self, addresses: Vec<Self::Address>) -> Result<Self::Listener> {
-        TcpTransportListener::new(addresses)
+        TcpTransportListener::new(addresses, self.config.clone())
     }
 
     fn connect(&self, address: Self::Address) -> BoxFuture<'static, crate::Result<Self::Stream>> {
+        let config = self.config.clone();
         Box::pin(async move {
             let stream = TcpStream::connect(address).await?;
+            config.apply(&stream)?;
             Ok(stream)
         })
     }
@@ -71,10 +123,11 @@ impl TransportSocket for TcpTransportSocket {
 
 pub struct TcpTransportListener {
     listeners: Vec<TcpListener>,
+    config: TcpTransportConfig,
 }
 
 impl TcpTransportListener {
-    fn new(addresses: Vec<SocketAddr>) -> Result<Self> {
+    fn new(addresses: Vec<SocketAddr>, config: TcpTransportConfig) -> Result<Self> {
         let addresses = if addresses.is_empty() {
             vec![
                 SocketAddr::new(std::net::Ipv4Addr::UNSPECIFIED.into(), DEFAULT_BIND_PORT),
@@ -112,8 +165,9 @@ impl TcpTransportListener {
 
                 socket.bind(&address.into())?;
 
-                // Set max count of pending TCP connections, we don't need a lot
-                socket.listen(32)?;
+                // Set max count of pending TCP connections
+                let backlog = config.listen_backlog.unwrap_or(DEFAULT_LISTEN_BACKLOG);
+                socket.listen(i32::try_from(backlog).unwrap_or(i32::MAX))?;
 
                 let listener = TcpListener::from_std(socket.into())?;
 
@@ -121,7 +175,7 @@ impl TcpTransportListener {
             })
             .collect::<Result<Vec<_>>>()?;
 
-        Ok(Self { listeners })
+        Ok(Self { listeners, config })
     }
 }
 
@@ -131,6 +185,7 @@ impl TransportListener<TcpTransportStream, SocketAddr> for TcpTransportListener
         let mut tasks: FuturesUnordered<_> =
             self.listeners.iter().map(|listener| listener.accept()).collect();
         let (stream, address) = tasks.select_next_some().await?;
+        self.config.apply(&stream)?;
         Ok((stream, address))
     }
 
@@ -148,7 +203,7 @@ impl AsBannableAddress for SocketAddr {
     type BannableAddress = IpAddr;
 
     fn as_bannable(&self) -> Self::BannableAddress {
-        self.ip()
+        canonical_ip(self.ip())
     }
 }
 
@@ -169,6 +224,25 @@ mod tests {
         types::{Message, RequestId},
     };
 
+    #[test]
+    fn bannable_address_ignores_port() {
+        let address1: SocketAddr = "127.0.0.1:3031".parse().unwrap();
+        let address2: SocketAddr = "127.0.0.1:13031".parse().unwrap();
+        let address3: SocketAddr = "127.0.0.2:3031".parse().unwrap();
+
+        assert_eq!(address1.as_bannable(), address2.as_bannable());
+        assert_ne!(address1.as_bannable(), address3.as_bannable());
+    }
+
+    #[test]
+    fn bannable_address_ipv4_mapped() {
+        let address1: SocketAddr = "1.2.3.4:3031".parse().unwrap();
+        let address2: SocketAddr = "[::ffff:1.2.3.4]:3031".parse().unwrap();
+
+        assert_eq!(address1.as_bannable(), address2.as_bannable());
+        assert_eq!(address2.as_bannable(), "1.2.3.4".parse::<IpAddr>().unwrap());
+    }
+
     #[tokio::test]
     async fn send_recv() {
         let transport = TcpTransportSocket::new();
@@ -180,7 +254,10 @@ mod tests {
         let peer_stream = peer_res.unwrap();
 
         let request_id = RequestId::new();
-        let request = SyncRequest::BlockListRequest(BlockListRequest::new(vec![]));
+        let request = SyncRequest::BlockListRequest(BlockListRequest::new(
+            *common::chain::config::create_unit_test_config().magic_bytes(),
+            vec![],
+        ));
         let mut peer_stream = BufferedTranscoder::new(peer_stream);
         peer_stream
             .send(Message::Request {
@@ -211,7 +288,10 @@ mod tests {
         let peer_stream = peer_res.unwrap();
 
         let id_1 = RequestId::new();
-        let request = SyncRequest::BlockListRequest(BlockListRequest::new(vec![]));
+        let request = SyncRequest::BlockListRequest(BlockListRequest::new(
+            *common::chain::config::create_unit_test_config().magic_bytes(),
+            vec![],
+        ));
         let mut peer_stream = BufferedTranscoder::new(peer_stream);
         peer_stream
             .send(Message::Request {
@@ -246,4 +326,46 @@ mod tests {
             }
         );
     }
+
+    #[tokio::test]
+    async fn listen_backlog() {
+        // The backlog can't be read back, check that the listener works with a small one
+        let transport = TcpTransportSocket::with_config(TcpTransportConfig {
+            listen_backlog: Some(1),
+            ..Default::default()
+        });
+        let mut server = transport.bind(vec![TestTransportTcp::make_address()]).await.unwrap();
+        let peer_fut = transport.connect(server.local_addresses().unwrap()[0]);
+        let (server_res, peer_res) = tokio::join!(server.accept(), peer_fut);
+        assert_eq!(
+            server_res.unwrap().1,
+            peer_res.unwrap().local_addr().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn socket_options() {
+        let transport = TcpTransportSocket::with_config(TcpTransportConfig {
+            nodelay: true,
+            ..Default::default()
+        });
+        let mut server = transport.bind(vec![TestTransportTcp::make_address()]).await.unwrap();
+        let peer_fut = transport.connect(server.local_addresses().unwrap()[0]);
+
+        let (server_res, peer_res) = tokio::join!(server.accept(), peer_fut);
+        let server_stream = server_res.unwrap().0;
+        let peer_stream = peer_res.unwrap();
+
+        assert!(server_stream.nodelay().unwrap());
+        assert!(peer_stream.nodelay().unwrap());
+
+        // The default config doesn't change the OS defaults
+        let transport = TcpTransportSocket::new();
+        let mut server = transport.bind(vec![TestTransportTcp::make_address()]).await.unwrap();
+        let peer_fut = transport.connect(server.local_addresses().unwrap()[0]);
+
+        let (server_res, peer_res) = tokio::join!(server.accept(), peer_fut);
+        assert!(!server_res.unwrap().0.nodelay().unwrap());
+        assert!(!peer_res.unwrap().nodelay().unwrap());
+    }
 }
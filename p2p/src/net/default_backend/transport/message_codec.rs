@@ -16,61 +16,160 @@
 use std::io;
 
 use bytes::{Buf, BytesMut};
-use serialization::{DecodeAll, Encode};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio_util::codec::{Decoder, Encoder};
 
-use crate::{constants::MAX_MESSAGE_SIZE, net::default_backend::types::Message, P2pError, Result};
+use logging::log;
+use serialization::{Decode, DecodeAll, Encode};
 
-struct EncoderDecoder {}
+use crate::{
+    constants::MAX_MESSAGE_SIZE,
+    error::ProtocolError,
+    net::default_backend::types::{Message, RequestId},
+    P2pError, Result,
+};
 
-impl Decoder for EncoderDecoder {
-    type Item = Message;
-    type Error = P2pError;
+/// Version of the messages sent by this node
+pub const MESSAGE_VERSION: u16 = 1;
 
-    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
-        if src.len() < 4 {
-            return Ok(None);
-        }
+/// Every message is wrapped in the envelope before sending
+///
+/// The envelope allows changing the message format in the future. Messages with an unknown
+/// version are skipped, so older nodes can still talk to newer ones.
+#[derive(Debug, Encode, Decode, PartialEq, Eq)]
+pub struct MessageEnvelope {
+    pub version: u16,
+    pub payload: Vec<u8>,
+}
 
-        let (header, remaining_bytes) = src.split_at_mut(4);
+/// Number of the known [`crate::message::Request`] types
+const REQUEST_TYPE_COUNT: u8 = 7;
 
-        // Unwrap is safe here because the header size is 4 bytes
-        let length = u32::from_le_bytes(header.try_into().expect("valid size")) as usize;
+/// Number of the known [`crate::message::Response`] types
+const RESPONSE_TYPE_COUNT: u8 = 9;
 
-        if length > MAX_MESSAGE_SIZE {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Frame of length {length} is too large"),
-            )
-            .into());
-        }
+/// Number of the known [`crate::message::Announcement`] types
+const ANNOUNCEMENT_TYPE_COUNT: u8 = 3;
+
+/// Checks whether the message body can't be decoded because its type (or the type of the wrapped
+/// request, response or announcement) is unknown to this node.
+///
+/// Such messages are likely sent by nodes running a newer protocol version. Because messages are
+/// length-prefixed they can be skipped without tearing down the connection.
+fn is_unknown_message_type(body: &[u8]) -> bool {
+    let mut input = body;
+    let message_type = match u8::decode(&mut input) {
+        Ok(message_type) => message_type,
+        Err(_) => return false,
+    };
 
-        if remaining_bytes.len() < length {
-            src.reserve(4 + length - src.len());
-            return Ok(None);
+    let inner_type_count = match message_type {
+        // Handshake messages are never skipped
+        0 => return false,
+        // Request or response, prefixed with the request id
+        1 | 2 => {
+            if RequestId::decode(&mut input).is_err() {
+                return false;
+            }
+            if message_type == 1 {
+                REQUEST_TYPE_COUNT
+            } else {
+                RESPONSE_TYPE_COUNT
+            }
         }
+        // Announcement
+        3 => ANNOUNCEMENT_TYPE_COUNT,
+        _ => return true,
+    };
+
+    match u8::decode(&mut input) {
+        Ok(inner_type) => inner_type >= inner_type_count,
+        Err(_) => false,
+    }
+}
+
+struct EncoderDecoder {}
+
+impl EncoderDecoder {
+    /// Decodes the next message, frames larger than `max_message_size` are rejected with
+    /// [`ProtocolError::MessageTooLarge`] before their body is read.
+    fn decode_limited(
+        &mut self,
+        src: &mut BytesMut,
+        max_message_size: usize,
+    ) -> Result<Option<Message>> {
+        loop {
+            if src.len() < 4 {
+                return Ok(None);
+            }
+
+            let (header, remaining_bytes) = src.split_at_mut(4);
+
+            // Unwrap is safe here because the header size is 4 bytes
+            let length = u32::from_le_bytes(header.try_into().expect("valid size")) as usize;
+
+            if length > MAX_MESSAGE_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Frame of length {length} is too large"),
+                )
+                .into());
+            }
+
+            if length > max_message_size {
+                return Err(P2pError::ProtocolError(ProtocolError::MessageTooLarge(
+                    length,
+                    max_message_size,
+                )));
+            }
+
+            if remaining_bytes.len() < length {
+                src.reserve(4 + length - src.len());
+                return Ok(None);
+            }
+
+            let (body, _extra_bytes) = remaining_bytes.split_at_mut(length);
 
-        let (body, _extra_bytes) = remaining_bytes.split_at_mut(length);
+            let decode_res = MessageEnvelope::decode_all(&mut &body[..]);
 
-        let decode_res = Message::decode_all(&mut &body[..]);
+            src.advance(4 + length);
 
-        src.advance(4 + length);
+            let envelope = decode_res?;
 
-        match decode_res {
-            Ok(msg) => Ok(Some(msg)),
-            Err(e) => {
-                Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()).into())
+            if envelope.version != MESSAGE_VERSION {
+                log::debug!("skip message with unknown version {}", envelope.version);
+                continue;
             }
+
+            return match Message::decode_all(&mut &envelope.payload[..]) {
+                Ok(msg) => Ok(Some(msg)),
+                Err(_) if is_unknown_message_type(&envelope.payload) => {
+                    Err(P2pError::ProtocolError(ProtocolError::UnknownMessageType))
+                }
+                Err(e) => Err(e.into()),
+            };
         }
     }
 }
 
+impl Decoder for EncoderDecoder {
+    type Item = Message;
+    type Error = P2pError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        self.decode_limited(src, MAX_MESSAGE_SIZE)
+    }
+}
+
 impl Encoder<Message> for EncoderDecoder {
     type Error = P2pError;
 
     fn encode(&mut self, msg: Message, dst: &mut BytesMut) -> Result<()> {
-        let encoded = msg.encode();
+        let encoded = MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: msg.encode(),
+        }
+        .encode();
 
         if encoded.len() > MAX_MESSAGE_SIZE {
             return Err(io::Error::new(
@@ -90,6 +189,28 @@ impl Encoder<Message> for EncoderDecoder {
     }
 }
 
+/// Decodes a single length-prefixed message frame.
+///
+/// The same checks as for the messages received from peers are performed. The input must contain
+/// exactly one complete frame, any failure (including truncated input or trailing bytes) is
+/// returned as an error. Never panics, so it can be used as a fuzzing entry point.
+pub fn decode_message(bytes: &[u8]) -> Result<Message> {
+    let mut buf = BytesMut::from(bytes);
+    let message = EncoderDecoder {}
+        .decode(&mut buf)?
+        .ok_or_else(|| P2pError::from(io::Error::from(io::ErrorKind::UnexpectedEof)))?;
+
+    if !buf.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} unexpected bytes after the message", buf.len()),
+        )
+        .into());
+    }
+
+    Ok(message)
+}
+
 pub struct BufferedTranscoder<S> {
     stream: S,
     buffer: BytesMut,
@@ -118,8 +239,16 @@ impl<S: AsyncWrite + AsyncRead + Unpin> BufferedTranscoder<S> {
     /// has all data. If the buffer has a full frame that can be decoded, return that without
     /// calling the socket first.
     pub async fn recv(&mut self) -> Result<Message> {
+        self.recv_limited(MAX_MESSAGE_SIZE).await
+    }
+
+    /// Read a framed message from socket, rejecting it if it's larger than `max_message_size`
+    ///
+    /// The size is checked as soon as the frame header is received, so the body of an oversized
+    /// message is never buffered.
+    pub async fn recv_limited(&mut self, max_message_size: usize) -> Result<Message> {
         loop {
-            match (EncoderDecoder {}.decode(&mut self.buffer)) {
+            match (EncoderDecoder {}.decode_limited(&mut self.buffer, max_message_size)) {
                 Ok(None) => {
                     if self.stream.read_buf(&mut self.buffer).await? == 0 {
                         return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
@@ -132,3 +261,270 @@ impl<S: AsyncWrite + AsyncRead + Unpin> BufferedTranscoder<S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::{
+            block::{consensus_data::ConsensusData, timestamp::BlockTimestamp, BlockReward},
+            Block, SignedTransaction, Transaction,
+        },
+        primitives::{Id, H256},
+    };
+    use crypto::random::Rng;
+
+    use crate::message::{
+        Announcement, ErrorResponse, HasDataRequest, PingRequest, Request, Response, ResponseError,
+    };
+
+    use super::*;
+
+    fn make_transaction() -> SignedTransaction {
+        SignedTransaction::new(Transaction::new(0, vec![], vec![], 0).unwrap(), vec![]).unwrap()
+    }
+
+    fn make_message(request_id: RequestId) -> Message {
+        Message::Request {
+            request_id,
+            request: Request::PingRequest(PingRequest { nonce: 1 }),
+        }
+    }
+
+    fn make_frame(envelope: MessageEnvelope) -> Vec<u8> {
+        let body = envelope.encode();
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        frame
+    }
+
+    #[test]
+    fn encode_decode() {
+        let request_id = RequestId::new();
+
+        let mut buf = BytesMut::new();
+        EncoderDecoder {}.encode(make_message(request_id), &mut buf).unwrap();
+        assert_eq!(
+            &buf[4..],
+            MessageEnvelope {
+                version: MESSAGE_VERSION,
+                payload: make_message(request_id).encode(),
+            }
+            .encode()
+        );
+
+        assert_eq!(
+            EncoderDecoder {}.decode(&mut buf).unwrap(),
+            Some(make_message(request_id))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn unknown_version_skipped() {
+        let request_id = RequestId::new();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&make_frame(MessageEnvelope {
+            version: 2,
+            payload: vec![0xff, 1, 2, 3],
+        }));
+
+        // Only the message of the unknown version is available, nothing is returned
+        assert_eq!(EncoderDecoder {}.decode(&mut buf).unwrap(), None);
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(&make_frame(MessageEnvelope {
+            version: 2,
+            payload: vec![0xff, 1, 2, 3],
+        }));
+        buf.extend_from_slice(&make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: make_message(request_id).encode(),
+        }));
+
+        // The unknown message is skipped and the next one is decoded
+        assert_eq!(
+            EncoderDecoder {}.decode(&mut buf).unwrap(),
+            Some(make_message(request_id))
+        );
+        assert!(buf.is_empty());
+    }
+
+    // Frames split at arbitrary read boundaries, down to a single byte, are assembled correctly
+    #[test]
+    fn decode_split_frames() {
+        let request_ids = [RequestId::new(), RequestId::new(), RequestId::new()];
+        let mut frames = BytesMut::new();
+        for request_id in request_ids {
+            EncoderDecoder {}.encode(make_message(request_id), &mut frames).unwrap();
+        }
+
+        let mut rng = crypto::random::make_pseudo_rng();
+        for max_read_size in [1, 2, 3, 5, 8, frames.len()] {
+            let mut buf = BytesMut::new();
+            let mut messages = Vec::new();
+            let mut remaining = &frames[..];
+            while !remaining.is_empty() {
+                let read_size = rng.gen_range(1..=std::cmp::min(max_read_size, remaining.len()));
+                buf.extend_from_slice(&remaining[..read_size]);
+                remaining = &remaining[read_size..];
+
+                while let Some(message) = (EncoderDecoder {}).decode(&mut buf).unwrap() {
+                    messages.push(message);
+                }
+            }
+
+            assert_eq!(
+                messages,
+                request_ids.into_iter().map(make_message).collect::<Vec<_>>()
+            );
+            assert!(buf.is_empty());
+        }
+    }
+
+    // The type counts must be updated when new message types are added
+    #[test]
+    fn message_type_counts() {
+        assert_eq!(REQUEST_TYPE_COUNT, 7);
+        assert_eq!(RESPONSE_TYPE_COUNT, 9);
+        assert_eq!(ANNOUNCEMENT_TYPE_COUNT, 3);
+
+        // The last known type of each kind
+        let request = Request::HasData(HasDataRequest {
+            magic_bytes: [0; 4],
+            block_ids: vec![],
+        });
+        assert_eq!(request.encode()[0], REQUEST_TYPE_COUNT - 1);
+        let response = Response::Error(ErrorResponse {
+            magic_bytes: [0; 4],
+            error: ResponseError::RateLimited,
+        });
+        assert_eq!(response.encode()[0], RESPONSE_TYPE_COUNT - 1);
+        assert_eq!(
+            Announcement::Transaction(make_transaction()).encode()[0],
+            ANNOUNCEMENT_TYPE_COUNT - 1
+        );
+    }
+
+    // Every known inner message type is recognized, only the types past the last one are unknown
+    #[test]
+    fn known_message_types() {
+        let request_id = RequestId::new().encode();
+        for (message_type, inner_type_count) in
+            [(1, REQUEST_TYPE_COUNT), (2, RESPONSE_TYPE_COUNT), (3, ANNOUNCEMENT_TYPE_COUNT)]
+        {
+            let mut prefix = vec![message_type];
+            if message_type != 3 {
+                prefix.extend_from_slice(&request_id);
+            }
+
+            for inner_type in 0..=inner_type_count {
+                let mut body = prefix.clone();
+                body.push(inner_type);
+                // The body is truncated, so it can't be decoded
+                assert!(Message::decode_all(&mut &body[..]).is_err());
+                assert_eq!(
+                    is_unknown_message_type(&body),
+                    inner_type == inner_type_count,
+                    "message type {message_type}, inner type {inner_type}"
+                );
+            }
+        }
+
+        let block = Block::new(
+            vec![],
+            Id::new(H256([0x01; 32])),
+            BlockTimestamp::from_int_seconds(1),
+            ConsensusData::None,
+            BlockReward::new(vec![]),
+        )
+        .unwrap();
+        let announcements = [
+            Announcement::Block(block.clone()),
+            Announcement::Blocks(vec![block.header().clone()]),
+            Announcement::Transaction(make_transaction()),
+        ];
+        for announcement in announcements {
+            let message = Message::Announcement { announcement };
+            let frame = make_frame(MessageEnvelope {
+                version: MESSAGE_VERSION,
+                payload: message.encode(),
+            });
+            assert_eq!(decode_message(&frame), Ok(message));
+        }
+    }
+
+    #[test]
+    fn decode_message_valid() {
+        let request_id = RequestId::new();
+        let frame = make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: make_message(request_id).encode(),
+        });
+
+        assert_eq!(decode_message(&frame), Ok(make_message(request_id)));
+    }
+
+    #[test]
+    fn decode_message_truncated() {
+        let frame = make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: make_message(RequestId::new()).encode(),
+        });
+
+        for len in 0..frame.len() {
+            assert!(decode_message(&frame[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn decode_message_oversized() {
+        let mut frame = ((MAX_MESSAGE_SIZE + 1) as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0; 16]);
+        assert!(decode_message(&frame).is_err());
+
+        let mut frame = u32::MAX.to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0xff; 16]);
+        assert!(decode_message(&frame).is_err());
+    }
+
+    #[test]
+    fn decode_message_trailing_bytes() {
+        let mut frame = make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: make_message(RequestId::new()).encode(),
+        });
+        frame.push(0);
+
+        assert!(decode_message(&frame).is_err());
+    }
+
+    #[test]
+    fn decode_message_garbage() {
+        let mut rng = crypto::random::make_pseudo_rng();
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..64);
+            let mut frame = (len as u32).to_le_bytes().to_vec();
+            frame.extend((0..len).map(|_| rng.gen::<u8>()));
+            // Random bytes may accidentally form a valid message, the decoding must not panic
+            let _ = decode_message(&frame);
+
+            let garbage = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+            let _ = decode_message(&garbage);
+        }
+
+        // Valid frame with an invalid payload
+        let frame = make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION,
+            payload: vec![0xff, 0xff, 0xff],
+        });
+        assert!(decode_message(&frame).is_err());
+
+        // Unknown version is skipped, so there's no message to return
+        let frame = make_frame(MessageEnvelope {
+            version: MESSAGE_VERSION + 1,
+            payload: make_message(RequestId::new()).encode(),
+        });
+        assert!(decode_message(&frame).is_err());
+    }
+}
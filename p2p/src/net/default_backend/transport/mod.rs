@@ -17,17 +17,42 @@ mod impls;
 mod message_codec;
 mod traits;
 
-use impls::{channel, stream_adapter, tcp};
+use impls::{channel, lossy_channel, stream_adapter, tcp};
 
 pub use self::{
     channel::{ChannelListener, ChannelStream, MpscChannelTransport},
-    message_codec::BufferedTranscoder,
+    lossy_channel::{LossyChannelListener, LossyChannelStream, LossyChannelTransport},
+    message_codec::{decode_message, BufferedTranscoder, MessageEnvelope, MESSAGE_VERSION},
     stream_adapter::{
-        identity::IdentityStreamAdapter, noise::NoiseEncryptionAdapter,
+        identity::IdentityStreamAdapter,
+        noise::NoiseEncryptionAdapter,
+        noise_upgrade::{NoiseUpgradeAdapter, UpgradableStream},
         wrapped_transport::wrapped_socket::WrappedTransportSocket,
     },
-    tcp::TcpTransportSocket,
-    traits::{PeerStream, TransportAddress, TransportListener, TransportSocket},
+    tcp::{TcpTransportConfig, TcpTransportSocket, DEFAULT_LISTEN_BACKLOG},
+    traits::{PeerStream, TransportAddress, TransportListener, TransportSocket, TransportTiming},
 };
 
 pub type NoiseTcpTransport = WrappedTransportSocket<NoiseEncryptionAdapter, TcpTransportSocket>;
+
+/// Plaintext TCP that is upgraded to Noise when both sides support it
+pub type UpgradableTcpTransport = WrappedTransportSocket<NoiseUpgradeAdapter, TcpTransportSocket>;
+
+/// Kinds of the transports the networking backend can run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportKind {
+    /// Plain TCP
+    Tcp,
+    /// TCP encrypted with the Noise protocol
+    Noise,
+    /// In-process channels (testing only)
+    Channel,
+}
+
+/// Transports that are available in this build
+pub(super) const SUPPORTED_TRANSPORTS: &[TransportKind] = &[
+    TransportKind::Tcp,
+    TransportKind::Noise,
+    #[cfg(feature = "testing_utils")]
+    TransportKind::Channel,
+];
@@ -17,6 +17,8 @@ use async_trait::async_trait;
 
 use crate::Result;
 
+use super::TransportTiming;
+
 /// An abstraction layer over a potential inbound network connection (acceptor in boost terminology).
 // TODO: Replace Stream and Address trait parameters with associated types?
 #[async_trait]
@@ -24,6 +26,18 @@ pub trait TransportListener<Stream, Address>: Send {
     /// Accepts a new inbound connection.
     async fn accept(&mut self) -> Result<(Stream, Address)>;
 
+    /// Same as `accept`, but also reports the time spent in the stream upgrade handshakes.
+    ///
+    /// Must be cancel safe, like `accept`.
+    async fn accept_timed(&mut self) -> Result<(Stream, Address, TransportTiming)>
+    where
+        Stream: Send + 'static,
+        Address: Send + 'static,
+    {
+        let (stream, address) = self.accept().await?;
+        Ok((stream, address, TransportTiming::default()))
+    }
+
     /// Returns the local address of the listener.
     fn local_addresses(&self) -> Result<Vec<Address>>;
 }
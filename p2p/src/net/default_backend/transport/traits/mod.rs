@@ -17,8 +17,10 @@ mod address;
 mod listener;
 mod socket;
 mod stream;
+mod timing;
 
 pub use address::TransportAddress;
 pub use listener::TransportListener;
 pub use socket::TransportSocket;
 pub use stream::PeerStream;
+pub use timing::TransportTiming;
@@ -17,10 +17,11 @@ use std::{fmt::Debug, hash::Hash, str::FromStr};
 
 use async_trait::async_trait;
 use futures::future::BoxFuture;
+use tokio::time::Instant;
 
 use crate::{net::AsBannableAddress, Result};
 
-use super::{listener::TransportListener, stream::PeerStream, TransportAddress};
+use super::{listener::TransportListener, stream::PeerStream, TransportAddress, TransportTiming};
 
 /// An abstraction layer for the transport layer at the highest level, which is responsible for:
 /// 1. Binding to a socket at a specific port, where we listen to connections.
@@ -55,4 +56,30 @@ pub trait TransportSocket: Send + Sync + 'static {
 
     /// Returns a future that opens a connection to the given address.
     fn connect(&self, address: Self::Address) -> BoxFuture<'static, crate::Result<Self::Stream>>;
+
+    /// Same as `connect`, but also reports the time spent in the connection steps.
+    ///
+    /// Transports that wrap another transport should override it to measure their own steps.
+    fn connect_timed(
+        &self,
+        address: Self::Address,
+    ) -> BoxFuture<'static, crate::Result<(Self::Stream, TransportTiming)>> {
+        let connect = self.connect(address);
+        Box::pin(async move {
+            let started_at = Instant::now();
+            let stream = connect.await?;
+            let timing = TransportTiming {
+                tcp_connect: Some(started_at.elapsed()),
+                noise_handshake: None,
+            };
+            Ok((stream, timing))
+        })
+    }
+
+    /// Returns the static public key that identifies the local node.
+    ///
+    /// Transports without a node identity (like plain TCP) return `None`.
+    fn local_public_key(&self) -> Option<&[u8]> {
+        None
+    }
 }
@@ -18,4 +18,9 @@ use tokio::io::{AsyncRead, AsyncWrite};
 
 /// An abstraction layer over some network stream that can be used to send and receive messages.
 #[async_trait]
-pub trait PeerStream: Unpin + Send + AsyncRead + AsyncWrite {}
+pub trait PeerStream: Unpin + Send + AsyncRead + AsyncWrite {
+    /// Returns the static public key of the remote peer if the stream is authenticated with it
+    fn remote_public_key(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+/// Time spent by the transport layer in establishing a stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportTiming {
+    /// Time spent in connecting the base transport (TCP connect for the TCP transport).
+    ///
+    /// Only known for outbound connections, inbound connections are already established
+    /// when they are accepted by the listener.
+    pub tcp_connect: Option<Duration>,
+
+    /// Time spent in the stream upgrade handshakes (the Noise handshake), if there are any
+    pub noise_handshake: Option<Duration>,
+}
+
+impl TransportTiming {
+    /// Adds the duration of one more stream upgrade handshake
+    pub fn add_handshake(&mut self, duration: Duration) {
+        self.noise_handshake = Some(self.noise_handshake.unwrap_or_default() + duration);
+    }
+}
@@ -18,8 +18,11 @@ use std::{
     hash::Hash,
     str::FromStr,
     sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
 };
 
+use tokio::sync::mpsc;
+
 use common::primitives::semver::SemVer;
 use serialization::{Decode, Encode};
 
@@ -28,9 +31,10 @@ use crate::{
     net::{
         self,
         default_backend::transport::TransportSocket,
-        types::{PeerInfo, PubSubTopic},
+        types::{BestBlock, ConnectionTiming, NodeKind, PeerInfo, PubSubTopic},
     },
     types::peer_address::PeerAddress,
+    utils::oneshot_nofail,
 };
 
 #[derive(Debug)]
@@ -41,6 +45,11 @@ pub enum Command<T: TransportSocket> {
     Disconnect {
         peer_id: PeerId,
     },
+    /// Disconnect the peer once the responses to its pending requests are sent
+    DrainAndDisconnect {
+        peer_id: PeerId,
+        timeout: Duration,
+    },
     SendRequest {
         peer_id: PeerId,
         request_id: RequestId,
@@ -51,10 +60,32 @@ pub enum Command<T: TransportSocket> {
         request_id: RequestId,
         message: message::Response,
     },
+    /// Send request and deliver the response to `response_tx` instead of the syncing events
+    SendRequestAndWait {
+        peer_id: PeerId,
+        request_id: RequestId,
+        message: message::Request,
+        response_tx: oneshot_nofail::Sender<crate::Result<message::Response>>,
+    },
     AnnounceData {
         topic: PubSubTopic,
         message: Vec<u8>,
     },
+    /// Send announcement to the specified peers only
+    AnnounceDataTo {
+        peers: Vec<PeerId>,
+        message: Vec<u8>,
+    },
+    /// Update the local best block that is sent to new peers during the handshake
+    UpdateBestBlock {
+        best_block: BestBlock,
+    },
+    /// Get the topics the local node is subscribed to
+    GetSubscriptions {
+        response: oneshot_nofail::Sender<BTreeSet<PubSubTopic>>,
+    },
+    /// Stop the backend after handling the previously sent commands
+    Shutdown,
 }
 
 pub enum SyncingEvent {
@@ -90,11 +121,13 @@ pub enum ConnectivityEvent<T: TransportSocket> {
         address: T::Address,
         peer_info: PeerInfo<PeerId>,
         receiver_address: Option<PeerAddress>,
+        timing: ConnectionTiming,
     },
     OutboundAccepted {
         address: T::Address,
         peer_info: PeerInfo<PeerId>,
         receiver_address: Option<PeerAddress>,
+        timing: ConnectionTiming,
     },
     ConnectionError {
         address: T::Address,
@@ -110,6 +143,105 @@ pub enum ConnectivityEvent<T: TransportSocket> {
     },
 }
 
+/// A copy of a connectivity event delivered to the subscribers of
+/// [`super::ConnectivityHandle::subscribe_events`]
+///
+/// The errors are passed as text because [`error::P2pError`] can't be cloned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastConnectivityEvent<A> {
+    Request {
+        peer_id: PeerId,
+        request_id: RequestId,
+        request: message::PeerManagerRequest,
+    },
+    Response {
+        peer_id: PeerId,
+        request_id: RequestId,
+        response: message::PeerManagerResponse,
+    },
+    InboundAccepted {
+        address: A,
+        peer_info: PeerInfo<PeerId>,
+        receiver_address: Option<PeerAddress>,
+        timing: ConnectionTiming,
+    },
+    OutboundAccepted {
+        address: A,
+        peer_info: PeerInfo<PeerId>,
+        receiver_address: Option<PeerAddress>,
+        timing: ConnectionTiming,
+    },
+    ConnectionError {
+        address: A,
+        error: String,
+    },
+    ConnectionClosed {
+        peer_id: PeerId,
+    },
+    Misbehaved {
+        peer_id: PeerId,
+        error: String,
+    },
+}
+
+impl<T: TransportSocket> From<&ConnectivityEvent<T>> for BroadcastConnectivityEvent<T::Address> {
+    fn from(event: &ConnectivityEvent<T>) -> Self {
+        match event {
+            ConnectivityEvent::Request {
+                peer_id,
+                request_id,
+                request,
+            } => Self::Request {
+                peer_id: *peer_id,
+                request_id: *request_id,
+                request: request.clone(),
+            },
+            ConnectivityEvent::Response {
+                peer_id,
+                request_id,
+                response,
+            } => Self::Response {
+                peer_id: *peer_id,
+                request_id: *request_id,
+                response: response.clone(),
+            },
+            ConnectivityEvent::InboundAccepted {
+                address,
+                peer_info,
+                receiver_address,
+                timing,
+            } => Self::InboundAccepted {
+                address: address.clone(),
+                peer_info: peer_info.clone(),
+                receiver_address: receiver_address.clone(),
+                timing: *timing,
+            },
+            ConnectivityEvent::OutboundAccepted {
+                address,
+                peer_info,
+                receiver_address,
+                timing,
+            } => Self::OutboundAccepted {
+                address: address.clone(),
+                peer_info: peer_info.clone(),
+                receiver_address: receiver_address.clone(),
+                timing: *timing,
+            },
+            ConnectivityEvent::ConnectionError { address, error } => Self::ConnectionError {
+                address: address.clone(),
+                error: error.to_string(),
+            },
+            ConnectivityEvent::ConnectionClosed { peer_id } => {
+                Self::ConnectionClosed { peer_id: *peer_id }
+            }
+            ConnectivityEvent::Misbehaved { peer_id, error } => Self::Misbehaved {
+                peer_id: *peer_id,
+                error: error.to_string(),
+            },
+        }
+    }
+}
+
 // TODO: use two events, one for txs and one for blocks?
 pub enum PubSubEvent<T: TransportSocket> {
     /// Message received from one of the pubsub topics
@@ -156,6 +288,13 @@ impl PeerId {
         let id = NEXT_PEER_ID.fetch_add(1, Ordering::Relaxed);
         Self(id)
     }
+
+    /// Derives the peer ID from the node's static public key (the first bytes of its hash)
+    pub fn from_public_key(public_key: &[u8]) -> Self {
+        let hash = crypto::hash::hash::<crypto::hash::Blake2b32, _>(public_key);
+        let id = u64::from_le_bytes(hash[..8].try_into().expect("hash must be long enough"));
+        Self(id)
+    }
 }
 
 impl std::fmt::Display for PeerId {
@@ -168,6 +307,9 @@ impl std::fmt::Display for PeerId {
 /// Used to detect and drop self connections.
 pub type HandshakeNonce = u64;
 
+/// Receives the topic and the encoded bytes of every announcement received from the peers
+pub type AnnouncementTap = mpsc::UnboundedSender<(PubSubTopic, Vec<u8>)>;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PeerEvent {
     /// Peer information received from remote
@@ -177,14 +319,29 @@ pub enum PeerEvent {
         subscriptions: BTreeSet<PubSubTopic>,
         receiver_address: Option<PeerAddress>,
 
+        /// The remote peer is an observer and shouldn't be used as a relay target
+        observer: bool,
+
+        /// Best block of the remote peer, if reported
+        best_block: Option<BestBlock>,
+
+        /// Whether the remote peer is a full or a pruned node
+        node_kind: NodeKind,
+
         /// For outbound connections that is what we sent.
         /// For inbound connections that is what was received from remote peer.
         handshake_nonce: HandshakeNonce,
     },
 
+    /// Handshake with the remote peer failed and the connection is going to be closed
+    HandshakeFailed { error: error::P2pError },
+
     /// Connection closed to remote
     ConnectionClosed,
 
+    /// Remote peer violated the protocol but the connection is kept open
+    Misbehaved { error: error::P2pError },
+
     /// Message received from remote
     MessageReceived { message: Message },
 }
@@ -203,36 +360,65 @@ pub enum HandshakeMessage {
     Hello {
         version: SemVer,
         network: [u8; 4],
+
+        /// Name of the protocol, must match for the nodes of the same chain
+        protocol_name: String,
+
         subscriptions: BTreeSet<PubSubTopic>,
 
         /// Socket address of the remote peer as seen by this node (addr_you in bitcoin)
         receiver_address: Option<PeerAddress>,
 
+        /// The node is an observer and shouldn't be used as a relay target
+        observer: bool,
+
+        /// Best block of the node, if known
+        best_block: Option<BestBlock>,
+
+        /// Whether the node is a full or a pruned node
+        node_kind: NodeKind,
+
         /// Random nonce that is only used to detect and drop self-connects
         handshake_nonce: HandshakeNonce,
     },
     HelloAck {
         version: SemVer,
         network: [u8; 4],
+
+        /// Name of the protocol, must match for the nodes of the same chain
+        protocol_name: String,
+
         subscriptions: BTreeSet<PubSubTopic>,
 
         /// Socket address of the remote peer as seen by this node (addr_you in bitcoin)
         receiver_address: Option<PeerAddress>,
+
+        /// The node is an observer and shouldn't be used as a relay target
+        observer: bool,
+
+        /// Best block of the node, if known
+        best_block: Option<BestBlock>,
+
+        /// Whether the node is a full or a pruned node
+        node_kind: NodeKind,
     },
 }
 
+// Keep in sync with `is_unknown_message_type` in the message codec when adding new message types.
 #[derive(Debug, Encode, Decode, PartialEq, Eq)]
 pub enum Message {
+    #[codec(index = 0)]
     Handshake(HandshakeMessage),
+    #[codec(index = 1)]
     Request {
         request_id: RequestId,
         request: message::Request,
     },
+    #[codec(index = 2)]
     Response {
         request_id: RequestId,
         response: message::Response,
     },
-    Announcement {
-        announcement: message::Announcement,
-    },
+    #[codec(index = 3)]
+    Announcement { announcement: message::Announcement },
 }
@@ -21,6 +21,7 @@ use std::{
     hash::Hash,
     str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
@@ -116,6 +117,16 @@ where
     /// `peer_id` - Peer ID of the remote node
     fn disconnect(&mut self, peer_id: T::PeerId) -> crate::Result<()>;
 
+    /// Disconnect active connection once the responses to the pending requests are sent
+    ///
+    /// New requests from the peer are ignored. The peer is disconnected anyway if the responses
+    /// aren't sent within `timeout`.
+    ///
+    /// # Arguments
+    /// * `peer_id` - Peer ID of the remote node
+    /// * `timeout` - Maximum time to wait for the pending responses
+    fn drain_and_disconnect(&mut self, peer_id: T::PeerId, timeout: Duration) -> crate::Result<()>;
+
     /// Send PeerManager's request to remote
     ///
     /// # Arguments
@@ -182,6 +193,19 @@ where
     /// Publishes an announcement on the network.
     fn make_announcement(&mut self, announcement: Announcement) -> crate::Result<()>;
 
+    /// Sends an announcement directly to the specified peers only.
+    ///
+    /// Peers that aren't connected are silently skipped. Topic subscriptions are ignored.
+    ///
+    /// # Arguments
+    /// * `peers` - Unique IDs of the peers the announcement is sent to
+    /// * `announcement` - Announcement to be sent
+    fn announce_to(&mut self, peers: &[T::PeerId], announcement: Announcement)
+        -> crate::Result<()>;
+
+    /// Updates the local best block that is reported to new peers during the handshake.
+    fn update_best_block(&mut self, best_block: types::BestBlock) -> crate::Result<()>;
+
     /// Poll syncing-related event from the networking service
     async fn poll_next(&mut self) -> crate::Result<types::SyncingEvent<T>>;
 }
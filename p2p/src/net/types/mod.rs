@@ -16,13 +16,46 @@
 use std::{
     collections::BTreeSet,
     fmt::{Debug, Display},
+    time::Duration,
 };
 
-use common::primitives::semver::SemVer;
+use common::{
+    chain::GenBlock,
+    primitives::{semver::SemVer, BlockHeight, Id},
+    Uint256,
+};
 use serialization::{Decode, Encode};
 
 use crate::{message, types::peer_address::PeerAddress, NetworkingService, P2pError};
 
+/// Best block of a node that is exchanged during the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct BestBlock {
+    pub id: Id<GenBlock>,
+    pub height: BlockHeight,
+    /// The cumulative work (chain trust) of the chain ending at the block
+    pub chain_trust: Uint256,
+}
+
+/// Kind of a node with respect to the blocks it's able to serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum NodeKind {
+    /// The node has all blocks
+    Full,
+    /// The node has discarded blocks below `prune_height`
+    Pruned { prune_height: BlockHeight },
+}
+
+impl NodeKind {
+    /// Returns true if the node still has the block at the given height
+    pub fn has_block(&self, height: BlockHeight) -> bool {
+        match self {
+            NodeKind::Full => true,
+            NodeKind::Pruned { prune_height } => height >= *prune_height,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
     Inbound,
@@ -38,7 +71,7 @@ pub enum Role {
 /// (both are Mintlayer nodes and that both support mandatory protocols). If those checks pass,
 /// the information is passed on to [crate::peer_manager::PeerManager] which decides whether it
 /// wants to keep the connection open or close it and possibly ban the peer from.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PeerInfo<P> {
     /// Unique ID of the peer
     pub peer_id: P,
@@ -54,6 +87,12 @@ pub struct PeerInfo<P> {
 
     /// The announcements list that a peer interested is.
     pub subscriptions: BTreeSet<PubSubTopic>,
+
+    /// Best block of the peer at the time of the handshake, if the peer has reported it
+    pub best_block: Option<BestBlock>,
+
+    /// Whether the peer is a full or a pruned node
+    pub node_kind: NodeKind,
 }
 
 impl<P: Debug> Display for PeerInfo<P> {
@@ -67,11 +106,40 @@ impl<P: Debug> Display for PeerInfo<P> {
             "--> User agent: {}",
             self.agent.as_ref().unwrap_or(&"No user agent".to_string())
         )?;
+        if let Some(best_block) = &self.best_block {
+            writeln!(
+                f,
+                "--> Best block: {} (height {})",
+                best_block.id, best_block.height
+            )?;
+        }
+        match self.node_kind {
+            NodeKind::Full => writeln!(f, "--> Node kind: full")?,
+            NodeKind::Pruned { prune_height } => {
+                writeln!(f, "--> Node kind: pruned (height {prune_height})")?
+            }
+        }
 
         Ok(())
     }
 }
 
+/// Time spent on the different steps of establishing a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionTiming {
+    /// Time spent in connecting the base transport (TCP connect for the TCP transport).
+    ///
+    /// Only known for outbound connections, inbound connections are already established
+    /// when they are accepted by the listener.
+    pub tcp_connect: Option<Duration>,
+
+    /// Time spent in the Noise handshake, `None` if the stream is not upgraded.
+    pub noise_handshake: Option<Duration>,
+
+    /// Time spent in the application handshake (`Hello`/`HelloAck` exchange).
+    pub handshake: Duration,
+}
+
 /// Connectivity-related events received from the network
 #[derive(Debug)]
 pub enum ConnectivityEvent<T: NetworkingService> {
@@ -107,6 +175,9 @@ pub enum ConnectivityEvent<T: NetworkingService> {
 
         /// Socket address of this node as seen by remote peer
         receiver_address: Option<PeerAddress>,
+
+        /// Connection establishment timing
+        timing: ConnectionTiming,
     },
 
     /// Inbound connection received
@@ -119,11 +190,14 @@ pub enum ConnectivityEvent<T: NetworkingService> {
 
         /// Socket address of this node as seen by remote peer
         receiver_address: Option<PeerAddress>,
+
+        /// Connection establishment timing
+        timing: ConnectionTiming,
     },
 
-    /// Outbound connection failed
+    /// Outbound connection failed or the connection was rejected during the handshake
     ConnectionError {
-        /// Address that was dialed
+        /// Address of the remote peer
         address: T::Address,
 
         /// Error that occurred
@@ -0,0 +1,140 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DNS seeds
+//!
+//! The seeds (see [`crate::config::P2pConfig::dns_seeds`]) are resolved to the addresses of
+//! the nodes that can be used to bootstrap the peer database. Every seed is queried at most once
+//! per [`crate::config::P2pConfig::dns_seed_refresh_interval`]. The interval is doubled after
+//! each failed lookup (up to [`MAX_BACKOFF_SHIFT`] times and [`MAX_BACKOFF_INTERVAL`]), so a dead
+//! seed is queried less often.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+/// The refresh interval of a failing seed is doubled at most this number of times
+pub const MAX_BACKOFF_SHIFT: u32 = 6;
+
+/// The backed off refresh interval doesn't grow above this value (unless the configured refresh
+/// interval is already larger)
+pub const MAX_BACKOFF_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a single seed lookup may take
+pub const LOOKUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Resolves a DNS seed to the addresses of the nodes
+#[async_trait]
+pub trait DnsSeedResolver: Send + Sync {
+    /// Returns the addresses of the nodes in the same format as they are stored in the peer database
+    async fn resolve(&self, seed: &str) -> crate::Result<Vec<String>>;
+}
+
+/// Resolver that uses the system DNS resolver
+///
+/// The seed must include the port (for example `seed.mintlayer.org:3031`).
+pub struct DefaultDnsSeedResolver;
+
+#[async_trait]
+impl DnsSeedResolver for DefaultDnsSeedResolver {
+    async fn resolve(&self, seed: &str) -> crate::Result<Vec<String>> {
+        let addresses = tokio::net::lookup_host(seed).await?;
+        Ok(addresses.map(|address| address.to_string()).collect())
+    }
+}
+
+struct SeedState {
+    seed: String,
+    failure_count: u32,
+    next_query_at: Instant,
+    lookup_in_progress: bool,
+}
+
+/// Tracks when each DNS seed should be queried next time
+pub struct DnsSeeds {
+    seeds: Vec<SeedState>,
+    refresh_interval: Duration,
+}
+
+impl DnsSeeds {
+    pub fn new(seeds: &[String], refresh_interval: Duration, now: Instant) -> Self {
+        let seeds = seeds
+            .iter()
+            .map(|seed| SeedState {
+                seed: seed.clone(),
+                failure_count: 0,
+                next_query_at: now,
+                lookup_in_progress: false,
+            })
+            .collect();
+
+        Self {
+            seeds,
+            refresh_interval,
+        }
+    }
+
+    /// Returns the seeds that should be queried now and marks their lookups as started
+    ///
+    /// A seed is not returned again until [`DnsSeeds::lookup_finished`] is called for it.
+    pub fn start_due_lookups(&mut self, now: Instant) -> Vec<String> {
+        self.seeds
+            .iter_mut()
+            .filter(|state| !state.lookup_in_progress && state.next_query_at <= now)
+            .map(|state| {
+                state.lookup_in_progress = true;
+                state.seed.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the number of the lookups that are started but not finished yet
+    pub fn lookups_in_progress(&self) -> usize {
+        self.seeds.iter().filter(|state| state.lookup_in_progress).count()
+    }
+
+    /// Schedules the next query of the seed depending on the lookup result
+    pub fn lookup_finished(&mut self, seed: &str, succeeded: bool, now: Instant) {
+        let state = match self.seeds.iter_mut().find(|state| state.seed == seed) {
+            Some(state) => state,
+            None => return,
+        };
+
+        state.lookup_in_progress = false;
+        if succeeded {
+            state.failure_count = 0;
+        } else {
+            state.failure_count = state.failure_count.saturating_add(1);
+        }
+
+        let backoff_shift = std::cmp::min(state.failure_count, MAX_BACKOFF_SHIFT);
+        let max_interval = std::cmp::max(self.refresh_interval, MAX_BACKOFF_INTERVAL);
+        let interval = std::cmp::min(
+            self.refresh_interval
+                .saturating_mul(2u32.checked_pow(backoff_shift).unwrap_or(u32::MAX)),
+            max_interval,
+        );
+        state.next_query_at = now.checked_add(interval).unwrap_or(now + MAX_BACKOFF_INTERVAL);
+    }
+
+    /// Returns the number of consecutive failed lookups of the seed
+    pub fn failure_count(&self, seed: &str) -> Option<u32> {
+        self.seeds
+            .iter()
+            .find(|state| state.seed == seed)
+            .map(|state| state.failure_count)
+    }
+}
@@ -19,12 +19,13 @@
 //!
 //!
 
+pub mod dns_seed;
 mod global_ip;
 pub mod peer_context;
 pub mod peerdb;
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     sync::Arc,
     time::Duration,
 };
@@ -33,15 +34,20 @@ use crypto::random::{make_pseudo_rng, Rng, SliceRandom};
 use tokio::{sync::mpsc, time::Instant};
 
 use chainstate::ban_score::BanScore;
-use common::{chain::ChainConfig, primitives::semver::SemVer, time_getter::TimeGetter};
+use common::{
+    chain::ChainConfig,
+    primitives::{semver::SemVer, BlockHeight},
+    time_getter::TimeGetter,
+};
 use logging::log;
+use serialization::{Decode, Encode};
 use utils::ensure;
 
 use crate::{
     config::P2pConfig,
     error::{P2pError, PeerError, ProtocolError},
     event::{PeerManagerEvent, SyncControlEvent},
-    interface::types::ConnectedPeer,
+    interface::types::{ConnectedPeer, HealthState, HealthStatus, KnownAddress, SyncProgress},
     message::{
         AddrListRequest, AddrListResponse, AnnounceAddrRequest, AnnounceAddrResponse,
         PeerManagerRequest, PeerManagerResponse, PingRequest, PingResponse,
@@ -58,25 +64,90 @@ use crate::{
 };
 
 use self::{
+    dns_seed::{DefaultDnsSeedResolver, DnsSeedResolver, DnsSeeds},
     global_ip::IsGlobalIp,
     peer_context::{PeerContext, SentPing},
-    peerdb::storage::PeerDbStorage,
+    peerdb::{storage::PeerDbStorage, AddressSource, PeerDbState},
 };
 
-/// Maximum number of connections the [`PeerManager`] is allowed to have open
-const MAX_ACTIVE_CONNECTIONS: usize = 128;
-
 /// Lower bound for how often [`PeerManager::heartbeat()`] is called
 const PEER_MGR_HEARTBEAT_INTERVAL_MIN: Duration = Duration::from_secs(5);
 /// Upper bound for how often [`PeerManager::heartbeat()`] is called
 const PEER_MGR_HEARTBEAT_INTERVAL_MAX: Duration = Duration::from_secs(30);
 
-/// How many addresses are allowed to be sent
-const MAX_ADDRESS_COUNT: usize = 1000;
-
 /// To how many peers re-send received announced address
 const ANNOUNCED_RESEND_COUNT: usize = 2;
 
+/// How often the address list can be requested from the same peer
+const ADDR_LIST_REQUEST_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Average interval between the own address announcements (if `advertise_self` is enabled)
+const SELF_ADVERTISEMENT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// To how many random peers the own addresses are announced
+const SELF_ADVERTISEMENT_PEER_COUNT: usize = 2;
+
+/// How many outbound peers from different address groups must report the same own address
+/// before it's advertised
+const OWN_ADDRESS_MIN_REPORTS: usize = 2;
+
+/// Maximum number of the discovered own addresses
+const MAX_DISCOVERED_OWN_ADDRESSES: usize = 8;
+
+/// Maximum number of the reported own addresses that are not confirmed yet
+const MAX_OWN_ADDRESS_CANDIDATES: usize = 32;
+
+/// Delay before redialing a persistent peer after the first failed reconnection attempt
+const PERSISTENT_RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+
+/// The delay before redialing a persistent peer is doubled after each failure up to this value
+const PERSISTENT_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// How many additional outbound connections are made when the tip is stale
+const STALE_TIP_EXTRA_CONNECTIONS: usize = 2;
+
+/// How long the additional connections made because of a stale tip are kept, which is enough
+/// to receive the best block of the peer and to request its headers
+const STALE_TIP_CONNECTION_LIFETIME: Duration = Duration::from_secs(2 * 60);
+
+/// The result of the inbound connection validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptOutcome {
+    /// The connection is accepted and the peer is now active
+    Accepted,
+    /// The maximum number of active connections is reached
+    RejectedTooManyPeers,
+    /// The address of the peer is banned
+    RejectedBanned,
+    /// The peer belongs to a different network (the magic bytes don't match)
+    RejectedDifferentNetwork,
+}
+
+/// Bookkeeping state of the peer manager that can be carried across a restart
+///
+/// Active connections are not part of the state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct PeerManagerState {
+    /// Known and banned addresses
+    pub peerdb: PeerDbState,
+
+    /// Scores of the connected peers by their IP addresses (the highest score of the peers
+    /// with the same IP address)
+    pub peer_scores: Vec<(String, u32)>,
+}
+
+/// The DNS seed and the result of its lookup
+type DnsSeedResult = (String, crate::Result<Vec<String>>);
+
+/// Reconnection state of a persistent peer that has lost its connection
+struct PersistentReconnect {
+    /// Number of consecutive failed reconnection attempts
+    failure_count: u32,
+
+    /// When the address is dialed next time, `None` while the dial is in progress
+    next_attempt_at: Option<Instant>,
+}
+
 pub struct PeerManager<T, S>
 where
     T: NetworkingService,
@@ -99,6 +170,9 @@ where
     /// Hashmap of pending outbound connections
     pending_connects: HashMap<T::Address, Option<oneshot_nofail::Sender<crate::Result<()>>>>,
 
+    /// Other addresses of the peers that are being dialed, tried if the pending connection fails
+    dial_alternatives: HashMap<T::Address, Vec<T::Address>>,
+
     /// Hashmap of pending disconnect requests
     pending_disconnects: HashMap<T::PeerId, Option<oneshot_nofail::Sender<crate::Result<()>>>>,
 
@@ -115,6 +189,58 @@ where
     /// Used to prevent infinity loops while broadcasting addresses.
     // TODO: Use bloom filter (like it's done in Bitcoin Core).
     announced_addresses: HashMap<T::PeerId, HashSet<T::Address>>,
+
+    /// Whether the local node is in the initial block download state (as of the last new tip)
+    is_initial_block_download: bool,
+
+    /// Last time when the local node received a new tip
+    last_tip_at: Option<Instant>,
+
+    /// Height of the local best block (as of the last new tip)
+    best_block_height: Option<BlockHeight>,
+
+    /// Last time when the local node received a new tip or the stale tip was handled
+    /// (or when the peer manager was started)
+    stale_tip_since: Instant,
+
+    /// Addresses being dialed because of a stale tip, the connections are closed after
+    /// [`STALE_TIP_CONNECTION_LIFETIME`]
+    stale_tip_dials: HashSet<T::Address>,
+
+    /// Addresses being dialed to fill the outbound slots reserved for new address groups
+    diverse_slot_dials: HashSet<T::Address>,
+
+    /// Own addresses discovered from the receiver addresses reported by the outbound peers,
+    /// see [`OWN_ADDRESS_MIN_REPORTS`]
+    discovered_own_addresses: HashSet<T::Address>,
+
+    /// Reported own addresses that are not confirmed yet and the address groups of the peers
+    /// that reported them
+    own_address_reports: HashMap<T::Address, HashSet<Vec<u8>>>,
+
+    /// When the own addresses are announced to random peers next time
+    next_self_advertisement: Instant,
+
+    /// Addresses added by the node operator (including [`P2pConfig::preconfigured_peers`]) that
+    /// the heartbeat always tries to keep connected, independently of the normal peer selection
+    persistent_addresses: BTreeSet<T::Address>,
+
+    /// Persistent peers that are being reconnected
+    persistent_reconnects: BTreeMap<T::Address, PersistentReconnect>,
+
+    /// Peer scores imported with [`PeerManager::import_state`], applied when the peers reconnect
+    /// from the same IP addresses
+    imported_scores: BTreeMap<T::BannableAddress, u32>,
+
+    /// When the DNS seeds are queried next time
+    dns_seeds: DnsSeeds,
+
+    /// Resolver used to query the DNS seeds
+    dns_seed_resolver: Arc<dyn DnsSeedResolver>,
+
+    /// The DNS seed lookups run in separate tasks and send the results back over this channel
+    dns_seed_results_tx: mpsc::UnboundedSender<DnsSeedResult>,
+    dns_seed_results_rx: mpsc::UnboundedReceiver<DnsSeedResult>,
 }
 
 impl<T, S> PeerManager<T, S>
@@ -133,11 +259,21 @@ where
         peerdb_storage: S,
     ) -> crate::Result<Self> {
         let peerdb = peerdb::PeerDb::new(Arc::clone(&p2p_config), time_getter, peerdb_storage)?;
+        let persistent_addresses =
+            peerdb::parse_addresses::<T::Address>(&p2p_config.preconfigured_peers)?
+                .into_iter()
+                .collect();
         let now = tokio::time::Instant::now();
         utils::ensure!(
             !p2p_config.ping_timeout.is_zero(),
             P2pError::Other("ping timeout can't be 0")
         );
+        let dns_seeds = DnsSeeds::new(
+            &p2p_config.dns_seeds,
+            *p2p_config.dns_seed_refresh_interval,
+            now,
+        );
+        let (dns_seed_results_tx, dns_seed_results_rx) = mpsc::unbounded_channel();
         Ok(Self {
             peer_connectivity_handle: handle,
             rx_peer_manager,
@@ -145,14 +281,70 @@ where
             peerdb,
             peers: BTreeMap::new(),
             pending_connects: HashMap::new(),
+            dial_alternatives: HashMap::new(),
             pending_disconnects: HashMap::new(),
             chain_config,
             p2p_config,
             last_heartbeat: now,
             announced_addresses: HashMap::new(),
+            is_initial_block_download: true,
+            last_tip_at: None,
+            best_block_height: None,
+            stale_tip_since: now,
+            stale_tip_dials: HashSet::new(),
+            diverse_slot_dials: HashSet::new(),
+            discovered_own_addresses: HashSet::new(),
+            own_address_reports: HashMap::new(),
+            next_self_advertisement: Self::next_self_advertisement_time(now),
+            persistent_addresses,
+            persistent_reconnects: BTreeMap::new(),
+            imported_scores: BTreeMap::new(),
+            dns_seeds,
+            dns_seed_resolver: Arc::new(DefaultDnsSeedResolver),
+            dns_seed_results_tx,
+            dns_seed_results_rx,
         })
     }
 
+    /// Replace the resolver used to query the DNS seeds
+    pub fn set_dns_seed_resolver(&mut self, resolver: Arc<dyn DnsSeedResolver>) {
+        self.dns_seed_resolver = resolver;
+    }
+
+    /// Returns the state that can be passed to [`PeerManager::import_state`] of a new peer manager
+    pub fn export_state(&self) -> PeerManagerState {
+        let mut peer_scores = BTreeMap::<T::BannableAddress, u32>::new();
+        for peer in self.peers.values().filter(|peer| peer.score > 0) {
+            let score = peer_scores.entry(peer.address.as_bannable()).or_default();
+            *score = std::cmp::max(*score, peer.score);
+        }
+
+        PeerManagerState {
+            peerdb: self.peerdb.export_state(),
+            peer_scores: peer_scores
+                .into_iter()
+                .map(|(address, score)| (address.to_string(), score))
+                .collect(),
+        }
+    }
+
+    /// Restores the state exported from another peer manager
+    ///
+    /// The imported scores are applied once the peers connect to this peer manager.
+    pub fn import_state(&mut self, state: PeerManagerState) -> crate::Result<()> {
+        self.peerdb.import_state(state.peerdb)?;
+
+        self.imported_scores = state
+            .peer_scores
+            .into_iter()
+            .filter_map(|(address, score)| {
+                address.parse::<T::BannableAddress>().ok().map(|address| (address, score))
+            })
+            .collect();
+
+        Ok(())
+    }
+
     /// Verify software version compatibility
     ///
     /// Make sure that local and remote peer have the same software version
@@ -183,6 +375,7 @@ where
     fn handle_outbound_receiver_address(
         &mut self,
         peer_id: T::PeerId,
+        peer_address: &T::Address,
         receiver_address: PeerAddress,
     ) -> crate::Result<()> {
         if !self.is_peer_address_valid(&receiver_address) {
@@ -215,13 +408,86 @@ where
             .filter_map(|address| TransportAddress::from_peer_address(&address))
             .collect::<Vec<_>>();
 
+        let reporter_group = peerdb::address_group(peer_address);
         for address in discovered_own_addresses {
+            self.record_own_address_report(address.clone(), reporter_group.clone());
             self.send_announced_address(peer_id, address)?;
         }
 
         Ok(())
     }
 
+    /// Remember that a peer from the given address group reported the own address.
+    ///
+    /// The address is used once enough peers from different address groups have reported it,
+    /// so that a single peer can't make the node advertise arbitrary addresses.
+    fn record_own_address_report(&mut self, address: T::Address, reporter_group: Vec<u8>) {
+        if self.discovered_own_addresses.contains(&address)
+            || self.discovered_own_addresses.len() >= MAX_DISCOVERED_OWN_ADDRESSES
+        {
+            return;
+        }
+        if !self.own_address_reports.contains_key(&address)
+            && self.own_address_reports.len() >= MAX_OWN_ADDRESS_CANDIDATES
+        {
+            log::debug!("too many unconfirmed own addresses, ignore {address:?}");
+            return;
+        }
+
+        let reporters = self.own_address_reports.entry(address.clone()).or_default();
+        reporters.insert(reporter_group);
+        if reporters.len() >= OWN_ADDRESS_MIN_REPORTS {
+            log::info!("own address {address:?} discovered");
+            self.own_address_reports.remove(&address);
+            self.discovered_own_addresses.insert(address);
+        }
+    }
+
+    /// Returns when the own addresses should be announced next time.
+    ///
+    /// The interval is randomized so that the announcements of different nodes are not
+    /// synchronized and can't be used to link the addresses of the same node.
+    fn next_self_advertisement_time(now: Instant) -> Instant {
+        now + SELF_ADVERTISEMENT_INTERVAL.mul_f64(make_pseudo_rng().gen_range(0.5..1.5))
+    }
+
+    /// Announce the discovered own addresses to a few random peers, if it's time to do so.
+    ///
+    /// The peers are selected from different address groups, so that the announcement
+    /// reaches different parts of the network.
+    fn advertise_own_addresses(&mut self) -> crate::Result<()> {
+        let now = Instant::now();
+        if now < self.next_self_advertisement {
+            return Ok(());
+        }
+        self.next_self_advertisement = Self::next_self_advertisement_time(now);
+
+        let mut peers = self.peers.values().collect::<Vec<_>>();
+        peers.shuffle(&mut make_pseudo_rng());
+        let mut groups = HashSet::new();
+        let peer_ids = peers
+            .into_iter()
+            .filter(|peer| groups.insert(peerdb::address_group(&peer.address)))
+            .map(|peer| peer.info.peer_id)
+            .take(SELF_ADVERTISEMENT_PEER_COUNT)
+            .collect::<Vec<_>>();
+
+        for peer_id in peer_ids {
+            for address in self.discovered_own_addresses.iter() {
+                // The address is announced again even if it was sent to the peer before
+                self.peer_connectivity_handle.send_request(
+                    peer_id,
+                    PeerManagerRequest::AnnounceAddrRequest(AnnounceAddrRequest {
+                        address: address.as_peer_address(),
+                    }),
+                )?;
+                self.announced_addresses.entry(peer_id).or_default().insert(address.clone());
+            }
+        }
+
+        Ok(())
+    }
+
     fn send_announced_address(
         &mut self,
         peer_id: T::PeerId,
@@ -253,6 +519,8 @@ where
         receiver_address: Option<PeerAddress>,
     ) -> crate::Result<()> {
         let peer_id = info.peer_id;
+        let node_kind = info.node_kind;
+        let best_block = info.best_block;
 
         ensure!(
             info.network == *self.chain_config.magic_bytes(),
@@ -278,10 +546,10 @@ where
         );
 
         if let (Some(receiver_address), Role::Outbound) = (receiver_address, role) {
-            self.handle_outbound_receiver_address(peer_id, receiver_address)?;
+            self.handle_outbound_receiver_address(peer_id, &address, receiver_address)?;
         }
 
-        if role == Role::Outbound {
+        if role == Role::Outbound && !*self.p2p_config.disable_peer_discovery {
             self.peer_connectivity_handle.send_request(
                 peer_id,
                 PeerManagerRequest::AddrListRequest(AddrListRequest {}),
@@ -300,15 +568,26 @@ where
                 info,
                 address: address.clone(),
                 role,
-                score: 0,
+                score: self.imported_scores.get(&address.as_bannable()).copied().unwrap_or(0),
                 sent_ping: None,
+                ping_last: None,
+                ping_min: None,
+                addr_list_requested_at: (role == Role::Outbound).then(Instant::now),
+                last_received: Instant::now(),
+                disconnect_at: self
+                    .stale_tip_dials
+                    .remove(&address)
+                    .then(|| Instant::now() + STALE_TIP_CONNECTION_LIFETIME),
+                diverse_slot: self.diverse_slot_dials.remove(&address),
             },
         );
         assert!(old_value.is_none());
 
         self.peerdb.peer_connected(address);
 
-        self.tx_sync.send(SyncControlEvent::Connected(peer_id)).map_err(P2pError::from)
+        self.tx_sync
+            .send(SyncControlEvent::Connected(peer_id, node_kind, best_block))
+            .map_err(P2pError::from)
     }
 
     /// Validate inbound peer connection
@@ -320,12 +599,15 @@ where
     /// This function verifies that neither address the nor the peer ID are on the
     /// list of banned IPs/peer IDs. It also checks that the maximum number of
     /// connections `PeerManager` is configured to have has not been reached.
+    ///
+    /// The expected reasons to refuse the connection are reported as [`AcceptOutcome`] variants,
+    /// errors are returned only if the connection could not be handled at all.
     fn accept_inbound_connection(
         &mut self,
         address: T::Address,
         info: net::types::PeerInfo<T::PeerId>,
         receiver_address: Option<PeerAddress>,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<AcceptOutcome> {
         log::debug!("validate inbound connection, inbound address {address:?}");
 
         ensure!(
@@ -334,20 +616,25 @@ where
         );
 
         let bannable_address = address.as_bannable();
-        ensure!(
-            !self.peerdb.is_address_banned(&bannable_address)?,
-            P2pError::PeerError(PeerError::BannedAddress(address.to_string())),
-        );
+        if self.peerdb.is_address_banned(&bannable_address)? {
+            return Ok(AcceptOutcome::RejectedBanned);
+        }
+
+        if info.network != *self.chain_config.magic_bytes() {
+            return Ok(AcceptOutcome::RejectedDifferentNetwork);
+        }
 
         // if the maximum number of connections is reached, the connection cannot be
         // accepted even if it's valid. The peer is still reported to the PeerDb which
         // knows of all peers and later on if the number of connections falls below
         // the desired threshold, `PeerManager::heartbeat()` may connect to this peer.
-        if self.active_peer_count() >= MAX_ACTIVE_CONNECTIONS {
-            return Err(P2pError::PeerError(PeerError::TooManyPeers));
+        if self.active_peer_count() >= *self.p2p_config.max_active_connections {
+            return Ok(AcceptOutcome::RejectedTooManyPeers);
         }
 
-        self.accept_connection(address, Role::Inbound, info, receiver_address)
+        self.accept_connection(address, Role::Inbound, info, receiver_address)?;
+
+        Ok(AcceptOutcome::Accepted)
     }
 
     /// The connection to a remote peer is reported as closed.
@@ -369,9 +656,21 @@ where
                 response.send(Ok(()));
             }
 
-            self.peerdb.peer_disconnected(peer.address);
+            self.peerdb.peer_disconnected(peer.address.clone());
 
             self.announced_addresses.remove(&peer_id);
+
+            if self.persistent_addresses.contains(&peer.address) {
+                log::debug!("reconnect persistent peer {:?}", peer.address);
+                self.persistent_reconnects.insert(
+                    peer.address,
+                    PersistentReconnect {
+                        failure_count: 0,
+                        next_attempt_at: Some(Instant::now()),
+                    },
+                );
+                self.reconnect_persistent_peers()?;
+            }
         }
 
         Ok(())
@@ -384,7 +683,9 @@ where
     /// Unknown peers are reported as to be disconnected.
     ///
     /// If peer is banned, it is removed from the connected peers
-    /// and its address is marked as banned.
+    /// and its address is marked as banned. Other peers connected from the same
+    /// bannable address (for example, the same IP address but a different port)
+    /// are disconnected too.
     fn adjust_peer_score(&mut self, peer_id: T::PeerId, score: u32) -> crate::Result<()> {
         log::debug!("adjusting score for peer {peer_id}, adjustment {score}");
 
@@ -396,8 +697,18 @@ where
         peer.score = peer.score.saturating_add(score);
 
         if peer.score >= *self.p2p_config.ban_threshold {
+            let bannable_address = peer.address.as_bannable();
             self.peerdb.ban_peer(&peer.address)?;
-            self.disconnect(peer_id, None)?;
+
+            let banned_peers = self
+                .peers
+                .iter()
+                .filter(|(_, peer)| peer.address.as_bannable() == bannable_address)
+                .map(|(peer_id, _)| *peer_id)
+                .collect::<Vec<_>>();
+            for peer_id in banned_peers {
+                self.disconnect(peer_id, None)?;
+            }
         }
 
         Ok(())
@@ -408,15 +719,71 @@ where
     /// The outbound connection was dialed successfully but the remote either did not respond
     /// (at all or in time) or it didn't support the handshaking which forced the connection closed.
     ///
-    /// If the connection was initiated by the user via RPC, inform them that the connection failed.
-    /// Inform the [`crate::peer_manager::peerdb::PeerDb`] about the address failure so it knows to
-    /// update its own records.
+    /// If the peer has other addresses, the next one is dialed and the failure is not reported yet.
+    /// Otherwise, if the connection was initiated by the user via RPC, inform them that the
+    /// connection failed. Inform the [`crate::peer_manager::peerdb::PeerDb`] about the address
+    /// failure so it knows to update its own records.
     fn handle_outbound_error(&mut self, address: T::Address, error: P2pError) -> crate::Result<()> {
-        if let Some(Some(channel)) = self.pending_connects.remove(&address) {
+        let response = self.pending_connects.remove(&address).flatten();
+        let alternatives = self.dial_alternatives.remove(&address).unwrap_or_default();
+
+        self.peerdb.report_outbound_failure(address.clone());
+
+        if !alternatives.is_empty() {
+            log::debug!("failed to connect to {address:?} ({error}), try the next address");
+            return self.connect_any(alternatives, response);
+        }
+
+        if self.persistent_addresses.contains(&address) {
+            let reconnect =
+                self.persistent_reconnects
+                    .entry(address.clone())
+                    .or_insert(PersistentReconnect {
+                        failure_count: 0,
+                        next_attempt_at: None,
+                    });
+            let delay = std::cmp::min(
+                PERSISTENT_RECONNECT_MIN_DELAY
+                    .saturating_mul(2u32.saturating_pow(reconnect.failure_count)),
+                PERSISTENT_RECONNECT_MAX_DELAY,
+            );
+            reconnect.failure_count = reconnect.failure_count.saturating_add(1);
+            reconnect.next_attempt_at = Some(Instant::now() + delay);
+            log::debug!("failed to reconnect persistent peer {address:?}, retry in {delay:?}");
+        }
+
+        if let Some(channel) = response {
             channel.send(Err(error));
         }
+        Ok(())
+    }
+
+    /// Requests the address list from the peer again
+    ///
+    /// The received addresses are merged into the peer database. Requests to the same peer
+    /// are rate limited, if the previous request was sent recently this function does nothing.
+    pub fn refresh_peer_addresses(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+
+        let now = Instant::now();
+        if let Some(requested_at) = peer.addr_list_requested_at {
+            if now.duration_since(requested_at) < ADDR_LIST_REQUEST_MIN_INTERVAL {
+                log::debug!(
+                    "address list was recently requested from peer {peer_id}, skip refresh"
+                );
+                return Ok(());
+            }
+        }
+
+        self.peer_connectivity_handle.send_request(
+            peer_id,
+            PeerManagerRequest::AddrListRequest(AddrListRequest {}),
+        )?;
+        peer.addr_list_requested_at = Some(now);
 
-        self.peerdb.report_outbound_failure(address);
         Ok(())
     }
 
@@ -445,27 +812,128 @@ where
         self.peer_connectivity_handle.connect(address)
     }
 
+    /// Add the address to the set of persistent peers
+    ///
+    /// The connection is established by the next heartbeat and restored as soon as it's lost.
+    /// Failed reconnection attempts are retried with a growing delay.
+    fn add_node(&mut self, address: T::Address) -> crate::Result<()> {
+        ensure!(
+            !self.peerdb.is_address_banned(&address.as_bannable())?,
+            P2pError::PeerError(PeerError::BannedAddress(address.to_string())),
+        );
+
+        log::debug!("add persistent peer address {address:?}");
+        self.persistent_addresses.insert(address);
+        Ok(())
+    }
+
+    /// Remove the address from the set of persistent peers
+    ///
+    /// An existing connection is kept, but it won't be restored once lost.
+    fn remove_node(&mut self, address: T::Address) -> crate::Result<()> {
+        ensure!(
+            self.persistent_addresses.remove(&address),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
+
+        log::debug!("remove persistent peer address {address:?}");
+        self.persistent_reconnects.remove(&address);
+        Ok(())
+    }
+
+    /// Dials the persistent peers that are not connected
+    ///
+    /// Peers that failed to reconnect recently are skipped until their backoff delay expires.
+    /// Every due reconnection attempt is either made or rescheduled, so that
+    /// [`PeerManager::next_persistent_reconnect_at`] never stays in the past.
+    fn reconnect_persistent_peers(&mut self) -> crate::Result<()> {
+        let now = Instant::now();
+        let addresses = self
+            .persistent_addresses
+            .iter()
+            .filter(|address| {
+                self.persistent_reconnects
+                    .get(address)
+                    .and_then(|reconnect| reconnect.next_attempt_at)
+                    .map_or(true, |next_attempt_at| next_attempt_at <= now)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for address in addresses {
+            if self.peerdb.is_address_connected(&address) {
+                // A new reconnection is scheduled once the connection is lost
+                self.persistent_reconnects.remove(&address);
+                continue;
+            }
+
+            let banned = self.peerdb.is_address_banned(&address.as_bannable())?;
+            let dialing = self.pending_connects.contains_key(&address);
+            if let Some(reconnect) = self.persistent_reconnects.get_mut(&address) {
+                // The result of the dial schedules the next attempt, a banned address is
+                // checked again after the maximum delay
+                reconnect.next_attempt_at = if banned && !dialing {
+                    Some(now + PERSISTENT_RECONNECT_MAX_DELAY)
+                } else {
+                    None
+                };
+            }
+            if banned || dialing {
+                continue;
+            }
+
+            self.connect(address, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns when the next persistent peer reconnection attempt is due
+    fn next_persistent_reconnect_at(&self) -> Option<Instant> {
+        self.persistent_reconnects
+            .values()
+            .filter_map(|reconnect| reconnect.next_attempt_at)
+            .min()
+    }
+
     /// Establish an outbound connection
     fn connect(
         &mut self,
         address: T::Address,
         response: Option<oneshot_nofail::Sender<crate::Result<()>>>,
     ) -> crate::Result<()> {
-        log::debug!("try to establish outbound connection to peer at address {address:?}");
+        self.connect_any(vec![address], response)
+    }
+
+    /// Establish an outbound connection to a peer that is reachable at several addresses
+    ///
+    /// The addresses are dialed one at a time in the given order, the next address is only dialed
+    /// if the previous one fails. The response is sent once: an error is reported only if none
+    /// of the addresses could be connected.
+    fn connect_any(
+        &mut self,
+        addresses: Vec<T::Address>,
+        response: Option<oneshot_nofail::Sender<crate::Result<()>>>,
+    ) -> crate::Result<()> {
+        let mut last_error = P2pError::Other("no addresses to connect to");
 
-        let res = self.try_connect(address.clone());
+        for (index, address) in addresses.iter().enumerate() {
+            log::debug!("try to establish outbound connection to peer at address {address:?}");
 
-        match res {
-            Ok(()) => {
-                self.pending_connects.insert(address, response);
-            }
-            Err(e) => {
-                if let Some(response) = response {
-                    response.send(Err(e));
+            match self.try_connect(address.clone()) {
+                Ok(()) => {
+                    self.pending_connects.insert(address.clone(), response);
+                    self.dial_alternatives.insert(address.clone(), addresses[index + 1..].to_vec());
+                    return Ok(());
                 }
+                Err(e) => last_error = e,
             }
         }
 
+        if let Some(response) = response {
+            response.send(Err(last_error));
+        }
+
         Ok(())
     }
 
@@ -511,6 +979,29 @@ where
         Ok(())
     }
 
+    /// Disconnect the peer for a non-hostile reason (for example, to free the slot for another
+    /// peer), letting the node answer the requests the peer has already sent
+    ///
+    /// New requests from the peer are ignored. The peer is disconnected anyway if the pending
+    /// responses aren't sent within `timeout`.
+    pub fn drain_and_disconnect(
+        &mut self,
+        peer_id: T::PeerId,
+        timeout: Duration,
+    ) -> crate::Result<()> {
+        log::debug!("drain and disconnect peer {peer_id}");
+
+        ensure!(
+            self.peers.contains_key(&peer_id),
+            P2pError::PeerError(PeerError::PeerDisconnected),
+        );
+
+        self.peer_connectivity_handle.drain_and_disconnect(peer_id, timeout)?;
+        self.pending_disconnects.insert(peer_id, None);
+
+        Ok(())
+    }
+
     /// Maintains the peer manager state.
     ///
     /// `PeerManager::heartbeat()` is called every time a network/control event is received
@@ -523,6 +1014,8 @@ where
     /// This function maintains the overall connectivity state of peers by culling
     /// low-reputation peers and establishing new connections with peers that have higher
     /// reputation. It also updates peer scores and forgets those peers that are no longer needed.
+    /// The persistent peers (see [`PeerManagerEvent::AddNode`]) are reconnected if they are not
+    /// connected already.
     ///
     /// TODO: IP address diversity check?
     /// TODO: exploratory peer connections?
@@ -533,24 +1026,140 @@ where
     /// establish new connections. After that it updates the peer scores and discards any records
     /// that no longer need to be stored.
     fn heartbeat(&mut self) -> crate::Result<()> {
+        self.reconnect_persistent_peers()?;
+
+        let reserved_slots = std::cmp::min(
+            *self.p2p_config.reserved_diverse_outbound_slots,
+            *self.p2p_config.max_active_connections,
+        );
+        if !*self.p2p_config.disable_peer_discovery {
+            self.fill_reserved_diverse_slots(reserved_slots)?;
+        }
+
         let count = std::cmp::min(
             self.peerdb.available_addresses_count(),
-            MAX_ACTIVE_CONNECTIONS
+            self.p2p_config
+                .max_active_connections
+                .saturating_sub(reserved_slots)
                 .saturating_sub(self.peerdb.available_addresses_count())
                 .saturating_sub(self.pending_connects.len()),
         );
+        // New dials are only made once the earlier ones are resolved
+        let count = std::cmp::min(
+            count,
+            self.p2p_config.max_concurrent_dials.saturating_sub(self.pending_connects.len()),
+        );
 
-        let addresses = self.peerdb.random_known_addresses(count);
+        // Only the persistent peers are connected if the peer discovery is disabled
+        if !*self.p2p_config.disable_peer_discovery {
+            let addresses = self.peerdb.select_new_peers(count);
 
-        for address in addresses {
-            self.connect(address, None)?;
+            for address in addresses {
+                self.connect(address, None)?;
+            }
         }
 
+        if *self.p2p_config.advertise_self {
+            self.advertise_own_addresses()?;
+        }
+
+        if self.stale_tip_since.elapsed() >= *self.p2p_config.stale_tip_timeout {
+            self.stale_tip_detected()?;
+        }
+        self.close_stale_tip_connections()?;
+
         // TODO: update peer scores
 
         Ok(())
     }
 
+    /// Dials the addresses from the groups not represented among the outbound peers to fill the
+    /// free reserved slots
+    ///
+    /// The pending dials count as represented groups too, so every reserved slot ends up with
+    /// a different group.
+    fn fill_reserved_diverse_slots(&mut self, reserved_slots: usize) -> crate::Result<()> {
+        // Forget the dials that have failed
+        let pending_connects = &self.pending_connects;
+        self.diverse_slot_dials.retain(|address| pending_connects.contains_key(address));
+
+        let occupied_slots = self.diverse_slot_dials.len()
+            + self.peers.values().filter(|peer| peer.diverse_slot).count();
+        let count = std::cmp::min(
+            reserved_slots.saturating_sub(occupied_slots),
+            self.p2p_config.max_concurrent_dials.saturating_sub(self.pending_connects.len()),
+        );
+        if count == 0 {
+            return Ok(());
+        }
+
+        let represented_groups = self
+            .peers
+            .values()
+            .filter(|peer| peer.role == Role::Outbound)
+            .map(|peer| &peer.address)
+            .chain(self.pending_connects.keys())
+            .map(peerdb::address_group)
+            .collect::<BTreeSet<_>>();
+        let addresses = self.peerdb.select_new_peers_from_new_groups(count, &represented_groups);
+        for address in addresses {
+            log::debug!("dial {address:?} to fill a reserved diverse outbound slot");
+            self.connect(address.clone(), None)?;
+            if self.pending_connects.contains_key(&address) {
+                self.diverse_slot_dials.insert(address);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts the lookups of the DNS seeds that are due
+    ///
+    /// The seeds are only queried while the node has fewer peers than
+    /// [`P2pConfig::health_min_peers`] and never if the peer discovery is disabled.
+    /// Every lookup runs in a separate task (limited by [`dns_seed::LOOKUP_TIMEOUT`]), the results
+    /// are handled by [`PeerManager::handle_dns_seed_result`].
+    fn query_dns_seeds(&mut self) {
+        if *self.p2p_config.disable_peer_discovery
+            || self.peers.len() >= *self.p2p_config.health_min_peers
+        {
+            return;
+        }
+
+        for seed in self.dns_seeds.start_due_lookups(Instant::now()) {
+            let resolver = Arc::clone(&self.dns_seed_resolver);
+            let results_tx = self.dns_seed_results_tx.clone();
+            tokio::spawn(async move {
+                let result =
+                    tokio::time::timeout(dns_seed::LOOKUP_TIMEOUT, resolver.resolve(&seed))
+                        .await
+                        .unwrap_or(Err(P2pError::Other("DNS seed lookup timed out")));
+                // The peer manager may be already stopped
+                let _ = results_tx.send((seed, result));
+            });
+        }
+    }
+
+    /// Adds the addresses resolved from the DNS seed to the peer database
+    fn handle_dns_seed_result(
+        &mut self,
+        seed: String,
+        result: crate::Result<Vec<String>>,
+    ) -> crate::Result<()> {
+        self.dns_seeds.lookup_finished(&seed, result.is_ok(), Instant::now());
+        match result {
+            Ok(addresses) => {
+                log::debug!("DNS seed {seed} resolved to {} addresses", addresses.len());
+                for address in addresses.iter().filter_map(|a| a.parse::<T::Address>().ok()) {
+                    self.peerdb.peer_discovered(&address, AddressSource::DnsSeed)?;
+                }
+            }
+            Err(err) => log::warn!("failed to resolve DNS seed {seed}: {err}"),
+        }
+
+        Ok(())
+    }
+
     fn handle_incoming_request(
         &mut self,
         peer_id: T::PeerId,
@@ -559,12 +1168,18 @@ where
     ) -> crate::Result<()> {
         match request {
             PeerManagerRequest::AddrListRequest(AddrListRequest {}) => {
-                let addresses = self
-                    .peerdb
-                    .random_known_addresses(MAX_ADDRESS_COUNT)
+                let own_addresses: Vec<T::Address> = if *self.p2p_config.advertise_self {
+                    self.discovered_own_addresses.iter().cloned().collect()
+                } else {
+                    Vec::new()
+                };
+                let max_addr_per_message = *self.p2p_config.max_addr_per_message;
+                let addresses = own_addresses
                     .iter()
+                    .chain(self.peerdb.random_known_addresses(max_addr_per_message).iter())
                     .map(TransportAddress::as_peer_address)
                     .filter(|address| self.is_peer_address_valid(address))
+                    .take(max_addr_per_message)
                     .collect();
 
                 self.peer_connectivity_handle.send_response(
@@ -575,12 +1190,13 @@ where
             PeerManagerRequest::AnnounceAddrRequest(AnnounceAddrRequest { address }) => {
                 // TODO: Rate limit announce address requests to prevent DoS attacks.
                 // For example it's 0.1 req/sec in Bitcoin Core.
-                let is_address_valid = self.is_peer_address_valid(&address);
+                let is_address_valid = !*self.p2p_config.disable_peer_discovery
+                    && self.is_peer_address_valid(&address);
                 if let (true, Some(address)) = (
                     is_address_valid,
                     TransportAddress::from_peer_address(&address),
                 ) {
-                    self.peerdb.peer_discovered(&address)?;
+                    self.peerdb.peer_discovered(&address, AddressSource::AddressExchange)?;
 
                     self.announced_addresses.entry(peer_id).or_default().insert(address.clone());
 
@@ -608,13 +1224,26 @@ where
     ) -> crate::Result<()> {
         // TODO: Check that unsolicited responses are not allowed
         match response {
-            PeerManagerResponse::AddrListResponse(AddrListResponse { addresses }) => {
+            PeerManagerResponse::AddrListResponse(AddrListResponse { mut addresses }) => {
+                let max_addr_per_message = *self.p2p_config.max_addr_per_message;
+                if addresses.len() > max_addr_per_message {
+                    let err =
+                        ProtocolError::TooManyAddresses(addresses.len(), max_addr_per_message);
+                    log::warn!("peer {peer_id} sent too many addresses: {err}");
+                    addresses.truncate(max_addr_per_message);
+                    self.adjust_peer_score(peer_id, err.ban_score())?;
+                }
+
+                if *self.p2p_config.disable_peer_discovery {
+                    return Ok(());
+                }
+
                 for address in addresses {
                     if let (true, Some(address)) = (
                         self.is_peer_address_valid(&address),
                         TransportAddress::from_peer_address(&address),
                     ) {
-                        self.peerdb.peer_discovered(&address)?;
+                        self.peerdb.peer_discovered(&address, AddressSource::AddressExchange)?;
                     }
                 }
                 Ok(())
@@ -622,9 +1251,16 @@ where
             PeerManagerResponse::AnnounceAddrResponse(AnnounceAddrResponse {}) => Ok(()),
             PeerManagerResponse::PingResponse(PingResponse { nonce }) => {
                 if let Some(peer) = self.peers.get_mut(&peer_id) {
-                    if peer.sent_ping.as_ref().map(|sent_ping| sent_ping.nonce) == Some(nonce) {
-                        // Correct reply received, clear pending request.
-                        peer.sent_ping = None;
+                    if let Some(sent_ping) = &peer.sent_ping {
+                        if sent_ping.nonce == nonce {
+                            // Correct reply received, clear pending request.
+                            let ping_time = Instant::now().duration_since(sent_ping.timestamp);
+                            peer.ping_last = Some(ping_time);
+                            peer.ping_min = Some(
+                                peer.ping_min.map_or(ping_time, |ping_min| ping_min.min(ping_time)),
+                            );
+                            peer.sent_ping = None;
+                        }
                     }
                 }
                 Ok(())
@@ -651,7 +1287,12 @@ where
     ) -> crate::Result<()> {
         match result {
             Ok(_) => Ok(()),
-            Err(P2pError::ChannelClosed | P2pError::SubsystemFailure) => {
+            Err(
+                P2pError::ChannelClosed
+                | P2pError::BackendShutdown
+                | P2pError::BackendCrashed
+                | P2pError::SubsystemFailure,
+            ) => {
                 log::error!("connection lost with subsystem wrapper/p2p subsystem");
                 result
             }
@@ -676,9 +1317,21 @@ where
             PeerManagerEvent::Connect(address, response) => {
                 self.connect(address, Some(response))?;
             }
+            PeerManagerEvent::ConnectAny(addresses, response) => {
+                self.connect_any(addresses, Some(response))?;
+            }
             PeerManagerEvent::Disconnect(peer_id, response) => {
                 self.disconnect(peer_id, Some(response))?;
             }
+            PeerManagerEvent::AddNode(address, response) => {
+                response.send(self.add_node(address));
+            }
+            PeerManagerEvent::RemoveNode(address, response) => {
+                response.send(self.remove_node(address));
+            }
+            PeerManagerEvent::RefreshPeerAddresses(peer_id, response) => {
+                response.send(self.refresh_peer_addresses(peer_id));
+            }
             PeerManagerEvent::AdjustPeerScore(peer_id, score, response) => {
                 log::debug!("adjust peer {peer_id} score: {score}");
 
@@ -700,6 +1353,35 @@ where
                 let peers = self.get_connected_peers();
                 response.send(peers);
             }
+            PeerManagerEvent::GetKnownAddresses(response) => {
+                let addresses = self
+                    .peerdb
+                    .known_addresses()
+                    .map(|(address, state)| KnownAddress {
+                        address: address.to_string(),
+                        last_seen: state.last_seen.map(|last_seen| last_seen.as_secs()),
+                        failure_count: state.failure_count,
+                        banned: state.banned,
+                        source: state.source,
+                    })
+                    .collect();
+                response.send(addresses);
+            }
+            PeerManagerEvent::GetHealth(response) => {
+                response.send(self.health());
+            }
+            PeerManagerEvent::GetSyncProgress(response) => {
+                response.send(self.sync_progress());
+            }
+            PeerManagerEvent::NewTipReceived {
+                is_initial_block_download,
+                height,
+            } => {
+                self.new_tip_received(is_initial_block_download, height);
+            }
+            PeerManagerEvent::StaleTipDetected => {
+                self.stale_tip_detected()?;
+            }
         }
 
         Ok(())
@@ -717,6 +1399,7 @@ where
                     request_id,
                     request,
                 } => {
+                    self.message_received(&peer_id);
                     self.handle_incoming_request(peer_id, request_id, request)?;
                 }
                 net::types::ConnectivityEvent::Response {
@@ -724,17 +1407,26 @@ where
                     request_id,
                     response,
                 } => {
+                    self.message_received(&peer_id);
                     self.handle_incoming_response(peer_id, request_id, response)?;
                 }
                 net::types::ConnectivityEvent::InboundAccepted {
                     address,
                     peer_info,
                     receiver_address,
+                    timing,
                 } => {
                     let peer_id = peer_info.peer_id;
+                    log::debug!("inbound connection timing for peer {peer_id}: {timing:?}");
 
                     match self.accept_inbound_connection(address, peer_info, receiver_address) {
-                        Ok(_) => {}
+                        Ok(AcceptOutcome::Accepted) => {}
+                        Ok(outcome) => {
+                            log::debug!(
+                                "inbound connection from peer {peer_id} rejected: {outcome:?}"
+                            );
+                            self.peer_connectivity_handle.disconnect(peer_id)?;
+                        }
                         Err(P2pError::ChannelClosed) => return Err(P2pError::ChannelClosed),
                         Err(P2pError::PeerError(err)) => {
                             log::warn!("peer error for peer {peer_id}: {err}");
@@ -753,8 +1445,10 @@ where
                     address,
                     peer_info,
                     receiver_address,
+                    timing,
                 } => {
                     let peer_id = peer_info.peer_id;
+                    log::debug!("outbound connection timing for peer {peer_id}: {timing:?}");
                     let res = self.accept_connection(
                         address.clone(),
                         Role::Outbound,
@@ -763,6 +1457,8 @@ where
                     );
                     self.handle_result(Some(peer_id), res)?;
 
+                    self.dial_alternatives.remove(&address);
+                    self.persistent_reconnects.remove(&address);
                     match self.pending_connects.remove(&address) {
                         Some(Some(channel)) => {
                             channel.send(Ok(()));
@@ -798,6 +1494,125 @@ where
         self.peers.len()
     }
 
+    /// Records that the local node has a new tip
+    fn new_tip_received(&mut self, is_initial_block_download: bool, height: BlockHeight) {
+        self.is_initial_block_download = is_initial_block_download;
+        self.last_tip_at = Some(Instant::now());
+        self.stale_tip_since = Instant::now();
+        self.best_block_height = Some(height);
+    }
+
+    /// Makes short-lived connections to new addresses from the peer database
+    ///
+    /// The node may be eclipsed or its peers may be stalled if no new tip is received for a long
+    /// time, so the new peers are asked for their best blocks to get a second opinion.
+    fn stale_tip_detected(&mut self) -> crate::Result<()> {
+        self.stale_tip_since = Instant::now();
+
+        if *self.p2p_config.disable_peer_discovery {
+            return Ok(());
+        }
+
+        let addresses = self.peerdb.select_new_peers(STALE_TIP_EXTRA_CONNECTIONS);
+        log::info!(
+            "no new tip received for a long time, making {} additional connections",
+            addresses.len()
+        );
+        for address in addresses {
+            if self.pending_connects.contains_key(&address) {
+                continue;
+            }
+            self.connect(address.clone(), None)?;
+            if self.pending_connects.contains_key(&address) {
+                self.stale_tip_dials.insert(address);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Disconnects the peers connected because of a stale tip once their lifetime ends
+    fn close_stale_tip_connections(&mut self) -> crate::Result<()> {
+        // Forget the dials that have failed
+        let pending_connects = &self.pending_connects;
+        self.stale_tip_dials.retain(|address| pending_connects.contains_key(address));
+
+        let now = Instant::now();
+        let expired_peers = self
+            .peers
+            .iter()
+            .filter(|(_peer_id, peer)| peer.disconnect_at.map_or(false, |at| at <= now))
+            .map(|(peer_id, _peer)| *peer_id)
+            .filter(|peer_id| !self.pending_disconnects.contains_key(peer_id))
+            .collect::<Vec<_>>();
+        for peer_id in expired_peers {
+            log::debug!("close the stale tip connection to peer {peer_id}");
+            self.disconnect(peer_id, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a summary of the sync and connectivity state of the node.
+    ///
+    /// The node is healthy if it has enough active peers, isn't in the initial block download
+    /// state and has received a new tip recently.
+    pub fn health(&self) -> HealthStatus {
+        let active_peers = self.active_peer_count();
+        let min_peers = *self.p2p_config.health_min_peers;
+        let since_last_block = self.last_tip_at.map(|last_tip_at| last_tip_at.elapsed());
+        let inbound_reachable = self.peers.values().any(|peer| peer.role == Role::Inbound);
+
+        let tip_is_recent = since_last_block.map_or(false, |since_last_block| {
+            since_last_block <= *self.p2p_config.health_max_tip_age
+        });
+        let state = if active_peers >= min_peers && !self.is_initial_block_download && tip_is_recent
+        {
+            HealthState::Healthy
+        } else {
+            HealthState::Unhealthy
+        };
+
+        HealthStatus {
+            state,
+            active_peers,
+            min_peers,
+            initial_block_download: self.is_initial_block_download,
+            seconds_since_last_block: since_last_block.map(|duration| duration.as_secs()),
+            inbound_reachable,
+        }
+    }
+
+    /// Returns how far along the local node is compared to the best height advertised by the
+    /// connected peers during the handshake
+    pub fn sync_progress(&self) -> SyncProgress {
+        let current_height = self.best_block_height.map(u64::from);
+        let best_known_peer_height = self
+            .peers
+            .values()
+            .filter_map(|peer| peer.info.best_block.map(|best_block| best_block.height))
+            .max()
+            .map(u64::from);
+
+        let percent = match (current_height, best_known_peer_height) {
+            (Some(current_height), Some(peer_height)) => {
+                if current_height >= peer_height {
+                    Some(100)
+                } else {
+                    // `current_height < peer_height` so the result is always below 100
+                    Some((current_height as u128 * 100 / peer_height as u128) as u8)
+                }
+            }
+            _ => None,
+        };
+
+        SyncProgress {
+            current_height,
+            best_known_peer_height,
+            percent,
+        }
+    }
+
     /// Returns short info about all connected peers
     pub fn get_connected_peers(&self) -> Vec<ConnectedPeer> {
         self.peers.values().map(Into::into).collect()
@@ -821,33 +1636,55 @@ where
         self.peers.get(peer_id).is_some()
     }
 
+    /// Resets the stall timer of the peer
+    fn message_received(&mut self, peer_id: &T::PeerId) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.last_received = Instant::now();
+        }
+    }
+
     /// Sends ping requests and disconnects peers that do not respond in time
     fn ping_check(&mut self) -> crate::Result<()> {
         let now = Instant::now();
         let mut dead_peers = Vec::new();
         for (peer_id, peer) in self.peers.iter_mut() {
             // If a ping has already been sent, wait for a reply first, do not send another ping request!
-            match &peer.sent_ping {
+            let retries = match &peer.sent_ping {
                 Some(sent_ping) => {
-                    if now.duration_since(sent_ping.timestamp) >= *self.p2p_config.ping_timeout {
+                    if now.duration_since(sent_ping.timestamp) < *self.p2p_config.ping_timeout {
+                        log::debug!("ping check: slow peer detected: {peer_id}");
+                        continue;
+                    }
+                    if sent_ping.retries >= *self.p2p_config.ping_max_retries {
+                        // The peer might be busy, give it some time to send anything
+                        let stalled_since = std::cmp::max(
+                            sent_ping.timestamp + *self.p2p_config.ping_timeout,
+                            peer.last_received,
+                        );
+                        if now.duration_since(stalled_since) < *self.p2p_config.stall_grace_period {
+                            log::debug!("ping check: stalled peer detected: {peer_id}");
+                            continue;
+                        }
                         log::info!("ping check: dead peer detected: {peer_id}");
                         dead_peers.push(*peer_id);
-                    } else {
-                        log::debug!("ping check: slow peer detected: {peer_id}");
+                        continue;
                     }
+                    log::debug!("ping check: ping timeout, retrying: {peer_id}");
+                    sent_ping.retries + 1
                 }
-                None => {
-                    let nonce = make_pseudo_rng().gen();
-                    self.peer_connectivity_handle.send_request(
-                        *peer_id,
-                        PeerManagerRequest::PingRequest(PingRequest { nonce }),
-                    )?;
-                    peer.sent_ping = Some(SentPing {
-                        nonce,
-                        timestamp: now,
-                    });
-                }
-            }
+                None => 0,
+            };
+
+            let nonce = make_pseudo_rng().gen();
+            self.peer_connectivity_handle.send_request(
+                *peer_id,
+                PeerManagerRequest::PingRequest(PingRequest { nonce }),
+            )?;
+            peer.sent_ping = Some(SentPing {
+                nonce,
+                timestamp: now,
+                retries,
+            });
         }
 
         for peer_id in dead_peers {
@@ -881,7 +1718,12 @@ where
             tokio::time::interval(Duration::MAX)
         };
 
+        // Don't wait for the first heartbeat to dial the preconfigured peers
+        self.reconnect_persistent_peers()?;
+
         loop {
+            let next_persistent_reconnect_at = self.next_persistent_reconnect_at();
+
             tokio::select! {
                 event = self.rx_peer_manager.recv() => {
                     self.handle_control_event(event.ok_or(P2pError::ChannelClosed)?)?;
@@ -892,6 +1734,17 @@ where
                 _event = ping_check_interval.tick(), if ping_check_enabled => {
                     self.ping_check()?;
                 }
+                _event = tokio::time::sleep_until(
+                    next_persistent_reconnect_at.unwrap_or_else(Instant::now)
+                ), if next_persistent_reconnect_at.is_some() => {
+                    self.reconnect_persistent_peers()?;
+                }
+                dns_seed_result = self.dns_seed_results_rx.recv() => {
+                    // The sender is owned by the peer manager, so the channel is never closed
+                    if let Some((seed, result)) = dns_seed_result {
+                        self.handle_dns_seed_result(seed, result)?;
+                    }
+                }
                 _event = tokio::time::sleep(PEER_MGR_HEARTBEAT_INTERVAL_MAX) => {}
             }
 
@@ -899,6 +1752,7 @@ where
             let now = tokio::time::Instant::now();
             if now.duration_since(self.last_heartbeat) > PEER_MGR_HEARTBEAT_INTERVAL_MIN {
                 self.heartbeat()?;
+                self.query_dns_seeds();
                 self.last_heartbeat = now;
             }
         }
@@ -13,6 +13,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use tokio::time::Instant;
 
 use crate::{
@@ -27,6 +29,9 @@ use crate::{
 pub struct SentPing {
     pub nonce: u64,
     pub timestamp: Instant,
+
+    /// How many times the ping request has been resent after a timeout
+    pub retries: u32,
 }
 
 #[derive(Debug)]
@@ -45,6 +50,27 @@ pub struct PeerContext<T: NetworkingService> {
 
     /// Sent ping details
     pub sent_ping: Option<SentPing>,
+
+    /// Last measured ping round-trip time
+    ///
+    /// It doesn't affect the peer score, which only counts misbehavior and leads to a ban.
+    pub ping_last: Option<Duration>,
+
+    /// Minimum measured ping round-trip time
+    pub ping_min: Option<Duration>,
+
+    /// When the address list was last requested from the peer
+    pub addr_list_requested_at: Option<Instant>,
+
+    /// When the last message was received from the peer
+    pub last_received: Instant,
+
+    /// When the peer is disconnected, set for the short-lived connections
+    pub disconnect_at: Option<Instant>,
+
+    /// Whether the connection occupies one of the outbound slots reserved for new address groups
+    /// (see [`crate::config::P2pConfig::reserved_diverse_outbound_slots`])
+    pub diverse_slot: bool,
 }
 
 impl<T: NetworkingService> From<&PeerContext<T>> for ConnectedPeer {
@@ -54,6 +80,8 @@ impl<T: NetworkingService> From<&PeerContext<T>> for ConnectedPeer {
             address: context.address.to_string(),
             inbound: context.role == Role::Inbound,
             ban_score: context.score,
+            ping_last: context.ping_last.map(|time| time.as_millis() as u64),
+            ping_min: context.ping_min.map(|time| time.as_millis() as u64),
         }
     }
 }
@@ -24,29 +24,125 @@
 //! connection with. Available addresses are discovered through various peer discovery mechanisms and they are
 //! used by [`crate::peer_manager::PeerManager::heartbeat()`] to establish new outbound connections
 //! if the actual number of active connection is less than the desired number of connections.
+//!
+//! The number of known addresses is limited by [`config::P2pConfig::max_addresses`], the least
+//! valuable addresses are evicted when a new one is discovered (see [`PeerDb::peer_discovered`]).
 
 pub mod storage;
 pub mod storage_impl;
 
 use std::{
     collections::{BTreeMap, BTreeSet},
+    str::FromStr,
     sync::Arc,
     time::Duration,
 };
 
 use common::time_getter::TimeGetter;
-use crypto::random::{make_pseudo_rng, SliceRandom};
+use crypto::random::{make_pseudo_rng, Rng};
+use logging::log;
+use serialization::{Decode, Encode};
 
 use crate::{
     config,
     error::{ConversionError, P2pError},
-    net::{AsBannableAddress, NetworkingService},
+    net::{default_backend::transport::TransportAddress, AsBannableAddress, NetworkingService},
+    types::ip_address::canonical_ip,
 };
 
 use self::storage::{
     PeerDbStorage, PeerDbStorageRead, PeerDbStorageWrite, PeerDbTransactionRo, PeerDbTransactionRw,
 };
 
+/// The weight of an address without any preference or penalty (see [`PeerDb::address_weight`])
+///
+/// The weights are integers, the scale keeps the precision when they are divided by the penalty.
+const ADDRESS_WEIGHT_SCALE: u64 = 1_000_000;
+
+/// Removes a random candidate and returns its address, the chance of every candidate to be
+/// selected is proportional to its weight
+fn take_weighted<'a, A>(candidates: &mut Vec<(&'a A, u64)>, rng: &mut impl Rng) -> Option<&'a A> {
+    let total_weight =
+        candidates.iter().map(|(_address, weight)| u128::from(*weight)).sum::<u128>();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut point = rng.gen_range(0..total_weight);
+    let index = candidates
+        .iter()
+        .position(|(_address, weight)| {
+            let weight = u128::from(*weight);
+            if point < weight {
+                return true;
+            }
+            point -= weight;
+            false
+        })
+        .expect("point is less than the total weight");
+    Some(candidates.swap_remove(index).0)
+}
+
+/// The way an address was learned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum AddressSource {
+    /// Specified by the node operator (see [`config::P2pConfig::added_nodes`] and
+    /// [`config::P2pConfig::preconfigured_peers`])
+    Manual,
+    /// Received from other peers (address announcements and address list responses)
+    AddressExchange,
+    /// Loaded from the peer database (the original source isn't persisted)
+    Stored,
+    /// Resolved from a DNS seed (see [`config::P2pConfig::dns_seeds`])
+    DnsSeed,
+}
+
+/// The state of a known address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressState {
+    /// The last time the address was discovered or connected to (as a duration since UNIX epoch)
+    pub last_seen: Option<Duration>,
+
+    /// The number of failed outbound connection attempts since the last successful one
+    pub failure_count: u32,
+
+    /// Whether the address is currently banned
+    pub banned: bool,
+
+    /// How the address was learned
+    pub source: AddressSource,
+}
+
+/// Peer database state that can be carried across a restart
+///
+/// Addresses are stored as strings, the same way as in the peer database storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct PeerDbState {
+    /// Known addresses along with the last time they were seen and the number of failed
+    /// connection attempts
+    pub known_addresses: Vec<(String, Option<Duration>, u32)>,
+
+    /// Banned addresses along with the time when the ban ends (as a duration since UNIX epoch)
+    pub banned_addresses: Vec<(String, Duration)>,
+}
+
+/// Known address data tracked in memory
+struct KnownAddressData {
+    last_seen: Option<Duration>,
+    failure_count: u32,
+    source: AddressSource,
+}
+
+impl KnownAddressData {
+    fn new(last_seen: Option<Duration>, source: AddressSource) -> Self {
+        Self {
+            last_seen,
+            failure_count: 0,
+            source,
+        }
+    }
+}
+
 pub struct PeerDb<T: NetworkingService, S> {
     /// P2P configuration
     p2p_config: Arc<config::P2pConfig>,
@@ -54,8 +150,8 @@ pub struct PeerDb<T: NetworkingService, S> {
     /// Set of currently connected addresses
     connected_addresses: BTreeSet<T::Address>,
 
-    /// Set of all known addresses
-    known_addresses: BTreeSet<T::Address>,
+    /// All known addresses
+    known_addresses: BTreeMap<T::Address, KnownAddressData>,
 
     /// Banned addresses along with the duration of the ban.
     ///
@@ -68,21 +164,26 @@ pub struct PeerDb<T: NetworkingService, S> {
     storage: S,
 }
 
+/// Parses the addresses specified by the node operator
+pub fn parse_addresses<A: FromStr>(addresses: &[String]) -> crate::Result<Vec<A>> {
+    addresses
+        .iter()
+        .map(|addr| {
+            addr.parse::<A>().map_err(|_err| {
+                P2pError::ConversionError(ConversionError::InvalidAddress(addr.clone()))
+            })
+        })
+        .collect()
+}
+
 impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
     pub fn new(
         p2p_config: Arc<config::P2pConfig>,
         time_getter: TimeGetter,
         storage: S,
     ) -> crate::Result<Self> {
-        let added_nodes = p2p_config
-            .added_nodes
-            .iter()
-            .map(|addr| {
-                addr.parse::<T::Address>().map_err(|_err| {
-                    P2pError::ConversionError(ConversionError::InvalidAddress(addr.clone()))
-                })
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        let added_nodes = parse_addresses::<T::Address>(&p2p_config.added_nodes)?;
+        let preconfigured_peers = parse_addresses::<T::Address>(&p2p_config.preconfigured_peers)?;
 
         // Node won't start if DB loading fails!
         let tx = storage.transaction_ro()?;
@@ -90,11 +191,17 @@ impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
         let stored_banned_addresses = tx.get_banned_addresses()?;
         tx.close();
 
-        let stored_known_addresses_iter =
-            stored_known_addresses.iter().filter_map(|address| address.parse().ok());
+        let stored_known_addresses_iter = stored_known_addresses
+            .iter()
+            .filter_map(|address| address.parse().ok())
+            .map(|address| (address, KnownAddressData::new(None, AddressSource::Stored)));
         // TODO: We need to handle added nodes differently from ordinary nodes.
         // There are peers that we want to persistently have, and others that we want to just give a "shot" at connecting at.
-        let known_addresses = stored_known_addresses_iter.chain(added_nodes.into_iter()).collect();
+        let added_nodes_iter = added_nodes
+            .into_iter()
+            .chain(preconfigured_peers.into_iter())
+            .map(|address| (address, KnownAddressData::new(None, AddressSource::Manual)));
+        let known_addresses = stored_known_addresses_iter.chain(added_nodes_iter).collect();
 
         let banned_addresses = stored_banned_addresses
             .iter()
@@ -128,13 +235,33 @@ impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
     /// Result could be shared with remote peers over network.
     pub fn random_known_addresses(&self, count: usize) -> Vec<T::Address> {
         // TODO: Use something more efficient (without iterating over the all addresses first)
-        let all_addresses = self.known_addresses.iter().cloned().collect::<Vec<_>>();
+        let all_addresses = self.known_addresses.keys().cloned().collect::<Vec<_>>();
         all_addresses
             .choose_multiple(&mut make_pseudo_rng(), count)
             .cloned()
             .collect::<Vec<_>>()
     }
 
+    /// Returns all known addresses along with their state.
+    ///
+    /// Unlike [`PeerDb::is_address_banned`], expired bans are not removed here.
+    pub fn known_addresses(&self) -> impl Iterator<Item = (T::Address, AddressState)> + '_ {
+        let now = self.time_getter.get_time();
+        self.known_addresses.iter().map(move |(address, data)| {
+            let banned = self
+                .banned_addresses
+                .get(&address.as_bannable())
+                .map_or(false, |banned_till| now <= *banned_till);
+            let state = AddressState {
+                last_seen: data.last_seen,
+                failure_count: data.failure_count,
+                banned,
+                source: data.source,
+            };
+            (address.clone(), state)
+        })
+    }
+
     /// Checks if the given address is banned.
     pub fn is_address_banned(&mut self, address: &T::BannableAddress) -> crate::Result<bool> {
         if let Some(banned_till) = self.banned_addresses.get(address) {
@@ -154,27 +281,217 @@ impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
     }
 
     /// Get socket address of the next best peer (TODO: in terms of peer score).
-    // TODO: Rewrite this.
     pub fn get_best_peer_addr(&mut self) -> Option<T::Address> {
-        self.random_known_addresses(1).into_iter().next()
+        self.select_new_peers(1).into_iter().next()
+    }
+
+    /// Selects up to `count` not connected addresses for new outbound connections.
+    ///
+    /// The addresses are sampled randomly, weighted by their quality (see
+    /// [`PeerDb::address_weight`]), so the better addresses are tried more often while the worse
+    /// ones still get an occasional attempt and can recover.
+    pub fn select_new_peers(&self, count: usize) -> Vec<T::Address> {
+        self.select_new_peers_with_rng(count, &mut make_pseudo_rng())
+    }
+
+    pub(super) fn select_new_peers_with_rng(
+        &self,
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<T::Address> {
+        let mut candidates = self.new_peer_candidates();
+
+        let mut selected = Vec::new();
+        while selected.len() < count {
+            match take_weighted(&mut candidates, rng) {
+                Some(address) => selected.push(address.clone()),
+                None => break,
+            }
+        }
+        selected
+    }
+
+    /// Selects up to `count` not connected addresses from different address groups that are not
+    /// in `excluded_groups`
+    ///
+    /// The addresses are sampled the same way as in [`PeerDb::select_new_peers`], but only one
+    /// address is selected from each group.
+    pub fn select_new_peers_from_new_groups(
+        &self,
+        count: usize,
+        excluded_groups: &BTreeSet<Vec<u8>>,
+    ) -> Vec<T::Address> {
+        self.select_new_peers_from_new_groups_with_rng(
+            count,
+            excluded_groups,
+            &mut make_pseudo_rng(),
+        )
+    }
+
+    pub(super) fn select_new_peers_from_new_groups_with_rng(
+        &self,
+        count: usize,
+        excluded_groups: &BTreeSet<Vec<u8>>,
+        rng: &mut impl Rng,
+    ) -> Vec<T::Address> {
+        let mut candidates = self
+            .new_peer_candidates()
+            .into_iter()
+            .filter(|(address, _weight)| !excluded_groups.contains(&address_group(*address)))
+            .collect::<Vec<_>>();
+
+        // Once an address is selected, the other addresses of its group are not candidates anymore
+        let mut selected = Vec::new();
+        while selected.len() < count {
+            match take_weighted(&mut candidates, rng) {
+                Some(address) => {
+                    let group = address_group(address);
+                    candidates.retain(|(candidate, _weight)| address_group(*candidate) != group);
+                    selected.push(address.clone());
+                }
+                None => break,
+            }
+        }
+        selected
+    }
+
+    /// Returns the not connected addresses along with their selection weights
+    fn new_peer_candidates(&self) -> Vec<(&T::Address, u64)> {
+        let now = self.time_getter.get_time();
+        let group_sizes = self.address_group_sizes();
+
+        self.known_addresses
+            .iter()
+            .filter(|(address, _data)| !self.connected_addresses.contains(*address))
+            .map(|(address, data)| {
+                let diverse = group_sizes[&address_group(address)] == 1;
+                (address, self.address_weight(data, diverse, now))
+            })
+            .collect()
+    }
+
+    /// Returns the relative chance of the address to be selected for a new outbound connection
+    ///
+    /// Addresses seen within [`config::P2pConfig::peer_selection_recent_period`] and the only
+    /// known addresses from their group are preferred. The weight is reduced with every failed
+    /// connection attempt, but never drops to zero.
+    fn address_weight(&self, data: &KnownAddressData, diverse: bool, now: Duration) -> u64 {
+        let mut weight = ADDRESS_WEIGHT_SCALE;
+        let recent = data.last_seen.map_or(false, |last_seen| {
+            now.saturating_sub(last_seen) <= *self.p2p_config.peer_selection_recent_period
+        });
+        if recent {
+            weight = weight
+                .saturating_mul((*self.p2p_config.peer_selection_recent_weight).max(1).into());
+        }
+        if diverse {
+            weight = weight
+                .saturating_mul((*self.p2p_config.peer_selection_diverse_weight).max(1).into());
+        }
+        let failure_penalty = u64::from(*self.p2p_config.peer_selection_failure_penalty);
+        let divisor = failure_penalty.saturating_mul(data.failure_count.into()).saturating_add(1);
+        std::cmp::max(weight / divisor, 1)
+    }
+
+    /// Returns the number of known addresses in every address group
+    fn address_group_sizes(&self) -> BTreeMap<Vec<u8>, usize> {
+        let mut group_sizes = BTreeMap::<Vec<u8>, usize>::new();
+        for address in self.known_addresses.keys() {
+            *group_sizes.entry(address_group(address)).or_default() += 1;
+        }
+        group_sizes
     }
 
     /// Add new peer addresses
-    pub fn peer_discovered(&mut self, address: &T::Address) -> crate::Result<()> {
-        self.known_addresses.insert(address.clone());
+    ///
+    /// The source of an already known address is not changed.
+    /// If the number of known addresses exceeds the limit, the least valuable ones are evicted
+    /// (see [`PeerDb::addresses_to_evict`]).
+    pub fn peer_discovered(
+        &mut self,
+        address: &T::Address,
+        source: AddressSource,
+    ) -> crate::Result<()> {
+        let now = self.time_getter.get_time();
+        self.known_addresses
+            .entry(address.clone())
+            .or_insert_with(|| KnownAddressData::new(None, source))
+            .last_seen = Some(now);
+
+        let evicted = self.addresses_to_evict(address);
 
         let mut tx = self.storage.transaction_rw()?;
         tx.add_known_address(&address.to_string())?;
+        for evicted_address in evicted.iter() {
+            tx.del_known_address(&evicted_address.to_string())?;
+        }
         tx.commit()?;
+
+        for evicted_address in evicted {
+            log::debug!("evict known address {evicted_address:?}");
+            self.known_addresses.remove(&evicted_address);
+        }
+
         Ok(())
     }
 
+    /// Selects the known addresses that should be removed to respect the `max_addresses` limit.
+    ///
+    /// Currently banned addresses go first, followed by the addresses with the most failed
+    /// connection attempts and the addresses that were not seen for the longest time.
+    /// The only addresses from their address group are kept if possible, so the eviction doesn't
+    /// reduce the diversity of the known addresses.
+    /// Connected, manually added and the just discovered addresses are never evicted.
+    fn addresses_to_evict(&self, new_address: &T::Address) -> Vec<T::Address> {
+        let excess = self.known_addresses.len().saturating_sub(*self.p2p_config.max_addresses);
+        if excess == 0 {
+            return Vec::new();
+        }
+
+        let now = self.time_getter.get_time();
+        let group_sizes = self.address_group_sizes();
+
+        let mut candidates = self
+            .known_addresses
+            .iter()
+            .filter(|(address, data)| {
+                *address != new_address
+                    && data.source != AddressSource::Manual
+                    && !self.connected_addresses.contains(*address)
+            })
+            .map(|(address, data)| {
+                let banned = self
+                    .banned_addresses
+                    .get(&address.as_bannable())
+                    .map_or(false, |banned_till| now <= *banned_till);
+                let diverse = group_sizes[&address_group(address)] == 1;
+                let key = (
+                    !banned,
+                    diverse,
+                    std::cmp::Reverse(data.failure_count),
+                    data.last_seen,
+                );
+                (key, address)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
+
+        candidates
+            .into_iter()
+            .take(excess)
+            .map(|(_, address)| address.clone())
+            .collect()
+    }
+
     /// Report outbound connection failure
     ///
     /// When [`crate::peer_manager::PeerManager::heartbeat()`] has initiated an outbound connection
     /// and the connection is refused, it's reported back to the `PeerDb` so it marks the address as unreachable.
-    pub fn report_outbound_failure(&mut self, _address: T::Address) {
-        // TODO: implement
+    pub fn report_outbound_failure(&mut self, address: T::Address) {
+        // TODO: Remove addresses that fail too often
+        if let Some(data) = self.known_addresses.get_mut(&address) {
+            data.failure_count = data.failure_count.saturating_add(1);
+        }
     }
 
     /// Mark peer as connected
@@ -182,6 +499,11 @@ impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
     /// After `PeerManager` has established either an inbound or an outbound connection,
     /// it informs the `PeerDb` about it.
     pub fn peer_connected(&mut self, address: T::Address) {
+        if let Some(data) = self.known_addresses.get_mut(&address) {
+            data.last_seen = Some(self.time_getter.get_time());
+            data.failure_count = 0;
+        }
+
         let is_inserted = self.connected_addresses.insert(address);
         assert!(is_inserted);
     }
@@ -205,8 +527,83 @@ impl<T: NetworkingService, S: PeerDbStorage> PeerDb<T, S> {
         Ok(())
     }
 
+    /// Returns the known and banned addresses
+    pub fn export_state(&self) -> PeerDbState {
+        PeerDbState {
+            known_addresses: self
+                .known_addresses
+                .iter()
+                .map(|(address, data)| (address.to_string(), data.last_seen, data.failure_count))
+                .collect(),
+            banned_addresses: self
+                .banned_addresses
+                .iter()
+                .map(|(address, banned_till)| (address.to_string(), *banned_till))
+                .collect(),
+        }
+    }
+
+    /// Merges the exported state into the database
+    ///
+    /// Invalid addresses are skipped. The `max_addresses` limit is respected the same way as for
+    /// the discovered addresses.
+    pub fn import_state(&mut self, state: PeerDbState) -> crate::Result<()> {
+        let mut tx = self.storage.transaction_rw()?;
+
+        for (address, last_seen, failure_count) in state.known_addresses {
+            let address = match address.parse::<T::Address>() {
+                Ok(address) => address,
+                Err(_) => {
+                    log::warn!("skip invalid imported address {address}");
+                    continue;
+                }
+            };
+
+            let data = self
+                .known_addresses
+                .entry(address.clone())
+                .or_insert_with(|| KnownAddressData::new(None, AddressSource::Stored));
+            data.last_seen = last_seen;
+            data.failure_count = failure_count;
+            tx.add_known_address(&address.to_string())?;
+
+            for evicted_address in self.addresses_to_evict(&address) {
+                tx.del_known_address(&evicted_address.to_string())?;
+                self.known_addresses.remove(&evicted_address);
+            }
+        }
+
+        for (address, banned_till) in state.banned_addresses {
+            let address = match address.parse::<T::BannableAddress>() {
+                Ok(address) => address,
+                Err(_) => {
+                    log::warn!("skip invalid imported banned address {address}");
+                    continue;
+                }
+            };
+
+            tx.add_banned_address(&address.to_string(), banned_till)?;
+            self.banned_addresses.insert(address, banned_till);
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "testing_utils")]
     pub fn get_storage_mut(&mut self) -> &mut S {
         &mut self.storage
     }
 }
+
+/// Returns the group of the address (/16 for IPv4 and /32 for IPv6 addresses)
+///
+/// IPv4-mapped IPv6 addresses are in the same group as the IPv4 addresses they map to.
+pub(super) fn address_group<A: TransportAddress>(address: &A) -> Vec<u8> {
+    let socket_address = std::net::SocketAddr::from(&address.as_peer_address());
+    match canonical_ip(socket_address.ip()) {
+        std::net::IpAddr::V4(ip) => ip.octets()[..2].to_vec(),
+        std::net::IpAddr::V6(ip) => ip.octets()[..4].to_vec(),
+    }
+}
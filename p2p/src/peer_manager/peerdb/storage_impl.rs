@@ -15,6 +15,16 @@
 
 use std::time::Duration;
 
+use crypto::{
+    hash::{hash, Blake2b32},
+    random::make_true_rng,
+    symkey::SymmetricKey,
+};
+use logging::log;
+use serialization::{Decode, DecodeAll, Encode};
+
+use crate::P2pError;
+
 use super::storage::{
     PeerDbStorage, PeerDbStorageRead, PeerDbStorageWrite, PeerDbTransactionRo, PeerDbTransactionRw,
     PeerDbTransactional,
@@ -28,55 +38,218 @@ storage::decl_schema! {
 
         /// Table for banned addresses
         pub DBBannedAddresses: Map<String, Duration>,
+
+        /// Table for known addresses if the storage is encrypted
+        pub DBEncryptedKnownAddresses: Map<Vec<u8>, Vec<u8>>,
+
+        /// Table for banned addresses if the storage is encrypted
+        pub DBEncryptedBannedAddresses: Map<Vec<u8>, Vec<u8>>,
     }
 }
 
-pub struct PeerDbStoreTxRo<'st, B: storage::Backend>(storage::TransactionRo<'st, B, Schema>);
+/// Encrypts and decrypts the peer db records
+///
+/// The records are stored under a keyed hash of the address, so they can be found by the address,
+/// but the address can't be learned from the key. The record is bound to its key with
+/// the associated data of the cipher, so records can't be swapped.
+struct RecordCipher {
+    key: SymmetricKey,
+    hash_key: Vec<u8>,
+}
+
+impl RecordCipher {
+    fn new(key: SymmetricKey) -> Self {
+        let hash_key = hash::<Blake2b32, _>(
+            [b"peerdb record id".as_slice(), key.encode().as_slice()].concat(),
+        );
+        Self {
+            key,
+            hash_key: hash_key.to_vec(),
+        }
+    }
+
+    fn record_id(&self, address: &str) -> Vec<u8> {
+        hash::<Blake2b32, _>([self.hash_key.as_slice(), address.as_bytes()].concat()).to_vec()
+    }
 
-pub struct PeerDbStoreTxRw<'st, B: storage::Backend>(storage::TransactionRw<'st, B, Schema>);
+    fn encrypt<T: Encode>(&self, record_id: &[u8], record: &T) -> crate::Result<Vec<u8>> {
+        self.key
+            .encrypt(&record.encode(), &mut make_true_rng(), Some(record_id))
+            .map_err(|_err| P2pError::Other("failed to encrypt a peer db record"))
+    }
+
+    fn decrypt<T: Decode>(&self, record_id: &[u8], data: &[u8]) -> crate::Result<T> {
+        let encoded = self
+            .key
+            .decrypt(data, Some(record_id))
+            .map_err(|_err| P2pError::Other("failed to decrypt a peer db record"))?;
+        Ok(T::decode_all(&mut encoded.as_slice())?)
+    }
+}
+
+pub struct PeerDbStoreTxRo<'st, B: storage::Backend>(
+    storage::TransactionRo<'st, B, Schema>,
+    Option<&'st RecordCipher>,
+);
+
+pub struct PeerDbStoreTxRw<'st, B: storage::Backend>(
+    storage::TransactionRw<'st, B, Schema>,
+    Option<&'st RecordCipher>,
+);
 
 impl<'tx, B: storage::Backend + 'tx> PeerDbTransactional<'tx> for PeerDbStorageImpl<B> {
     type TransactionRo = PeerDbStoreTxRo<'tx, B>;
     type TransactionRw = PeerDbStoreTxRw<'tx, B>;
 
     fn transaction_ro<'st: 'tx>(&'st self) -> crate::Result<Self::TransactionRo> {
-        self.0.transaction_ro().map_err(crate::P2pError::from).map(PeerDbStoreTxRo)
+        self.store
+            .transaction_ro()
+            .map_err(P2pError::from)
+            .map(|tx| PeerDbStoreTxRo(tx, self.cipher.as_ref()))
     }
 
     fn transaction_rw<'st: 'tx>(&'st self) -> crate::Result<Self::TransactionRw> {
-        self.0.transaction_rw(None).map_err(crate::P2pError::from).map(PeerDbStoreTxRw)
+        self.store
+            .transaction_rw(None)
+            .map_err(P2pError::from)
+            .map(|tx| PeerDbStoreTxRw(tx, self.cipher.as_ref()))
     }
 }
 
 impl<B: storage::Backend + 'static> PeerDbStorage for PeerDbStorageImpl<B> {}
 
-pub struct PeerDbStorageImpl<T: storage::Backend>(storage::Storage<T, Schema>);
+pub struct PeerDbStorageImpl<T: storage::Backend> {
+    store: storage::Storage<T, Schema>,
+    cipher: Option<RecordCipher>,
+}
 
 impl<B: storage::Backend> PeerDbStorageImpl<B> {
     pub fn new(storage: B) -> crate::Result<Self> {
+        Self::new_with_encryption_key(storage, None)
+    }
+
+    /// Opens the storage, the records are encrypted if the key is set
+    ///
+    /// The records that were stored unencrypted are encrypted with the key. The records that
+    /// can't be decrypted (because the key has changed) are dropped with a warning, so the node
+    /// starts with an empty peer db rather than fails.
+    pub fn new_with_encryption_key(
+        storage: B,
+        encryption_key: Option<SymmetricKey>,
+    ) -> crate::Result<Self> {
         let store = storage::Storage::<_, Schema>::new(storage)?;
-        Ok(Self(store))
+        let cipher = encryption_key.map(RecordCipher::new);
+        if let Some(cipher) = &cipher {
+            Self::encrypt_records(&store, cipher)?;
+        }
+        Ok(Self { store, cipher })
+    }
+
+    fn encrypt_records(
+        store: &storage::Storage<B, Schema>,
+        cipher: &RecordCipher,
+    ) -> crate::Result<()> {
+        let mut tx = store.transaction_rw(None)?;
+
+        let known_addresses =
+            tx.get::<DBKnownAddresses, _>().prefix_iter_decoded(&())?.collect::<Vec<_>>();
+        let banned_addresses =
+            tx.get::<DBBannedAddresses, _>().prefix_iter_decoded(&())?.collect::<Vec<_>>();
+
+        // records encrypted with a different key
+        let undecryptable_known = tx
+            .get::<DBEncryptedKnownAddresses, _>()
+            .prefix_iter_decoded(&())?
+            .filter(|(id, data)| cipher.decrypt::<String>(id, data).is_err())
+            .map(|(id, _data)| id)
+            .collect::<Vec<_>>();
+        let undecryptable_banned = tx
+            .get::<DBEncryptedBannedAddresses, _>()
+            .prefix_iter_decoded(&())?
+            .filter(|(id, data)| cipher.decrypt::<(String, Duration)>(id, data).is_err())
+            .map(|(id, _data)| id)
+            .collect::<Vec<_>>();
+
+        let undecryptable_count = undecryptable_known.len() + undecryptable_banned.len();
+        if undecryptable_count > 0 {
+            log::warn!(
+                "dropped {undecryptable_count} peer db records that can't be decrypted with the key"
+            );
+        }
+        for id in undecryptable_known {
+            tx.get_mut::<DBEncryptedKnownAddresses, _>().del(id)?;
+        }
+        for id in undecryptable_banned {
+            tx.get_mut::<DBEncryptedBannedAddresses, _>().del(id)?;
+        }
+
+        // records stored before the encryption was enabled
+        let mut tx = PeerDbStoreTxRw(tx, Some(cipher));
+        for (address, ()) in known_addresses {
+            tx.add_known_address(&address)?;
+            tx.0.get_mut::<DBKnownAddresses, _>().del(address)?;
+        }
+        for (address, duration) in banned_addresses {
+            tx.add_banned_address(&address, duration)?;
+            tx.0.get_mut::<DBBannedAddresses, _>().del(address)?;
+        }
+        tx.commit()
     }
 }
 
 impl<'st, B: storage::Backend> PeerDbStorageWrite for PeerDbStoreTxRw<'st, B> {
     fn add_known_address(&mut self, address: &str) -> crate::Result<()> {
-        self.0.get_mut::<DBKnownAddresses, _>().put(address, ()).map_err(Into::into)
+        match self.1 {
+            Some(cipher) => {
+                let id = cipher.record_id(address);
+                let record = cipher.encrypt(&id, &address)?;
+                self.0
+                    .get_mut::<DBEncryptedKnownAddresses, _>()
+                    .put(id, record)
+                    .map_err(Into::into)
+            }
+            None => self.0.get_mut::<DBKnownAddresses, _>().put(address, ()).map_err(Into::into),
+        }
     }
 
     fn del_known_address(&mut self, address: &str) -> crate::Result<()> {
-        self.0.get_mut::<DBKnownAddresses, _>().del(address).map_err(Into::into)
+        match self.1 {
+            Some(cipher) => self
+                .0
+                .get_mut::<DBEncryptedKnownAddresses, _>()
+                .del(cipher.record_id(address))
+                .map_err(Into::into),
+            None => self.0.get_mut::<DBKnownAddresses, _>().del(address).map_err(Into::into),
+        }
     }
 
     fn add_banned_address(&mut self, address: &str, duration: Duration) -> crate::Result<()> {
-        self.0
-            .get_mut::<DBBannedAddresses, _>()
-            .put(address, duration)
-            .map_err(Into::into)
+        match self.1 {
+            Some(cipher) => {
+                let id = cipher.record_id(address);
+                let record = cipher.encrypt(&id, &(address, duration))?;
+                self.0
+                    .get_mut::<DBEncryptedBannedAddresses, _>()
+                    .put(id, record)
+                    .map_err(Into::into)
+            }
+            None => self
+                .0
+                .get_mut::<DBBannedAddresses, _>()
+                .put(address, duration)
+                .map_err(Into::into),
+        }
     }
 
     fn del_banned_address(&mut self, address: &str) -> crate::Result<()> {
-        self.0.get_mut::<DBBannedAddresses, _>().del(address).map_err(Into::into)
+        match self.1 {
+            Some(cipher) => self
+                .0
+                .get_mut::<DBEncryptedBannedAddresses, _>()
+                .del(cipher.record_id(address))
+                .map_err(Into::into),
+            None => self.0.get_mut::<DBBannedAddresses, _>().del(address).map_err(Into::into),
+        }
     }
 }
 
@@ -92,15 +265,33 @@ impl<'st, B: storage::Backend> PeerDbTransactionRw for PeerDbStoreTxRw<'st, B> {
 
 impl<'st, B: storage::Backend> PeerDbStorageRead for PeerDbStoreTxRo<'st, B> {
     fn get_known_addresses(&self) -> crate::Result<Vec<String>> {
-        let map = self.0.get::<DBKnownAddresses, _>();
-        let iter = map.prefix_iter_decoded(&())?;
-        Ok(iter.map(|(key, _value)| key).collect::<Vec<_>>())
+        match self.1 {
+            Some(cipher) => {
+                let map = self.0.get::<DBEncryptedKnownAddresses, _>();
+                let iter = map.prefix_iter_decoded(&())?;
+                iter.map(|(id, data)| cipher.decrypt(&id, &data)).collect()
+            }
+            None => {
+                let map = self.0.get::<DBKnownAddresses, _>();
+                let iter = map.prefix_iter_decoded(&())?;
+                Ok(iter.map(|(key, _value)| key).collect::<Vec<_>>())
+            }
+        }
     }
 
     fn get_banned_addresses(&self) -> crate::Result<Vec<(String, Duration)>> {
-        let map = self.0.get::<DBBannedAddresses, _>();
-        let iter = map.prefix_iter_decoded(&())?;
-        Ok(iter.collect::<Vec<_>>())
+        match self.1 {
+            Some(cipher) => {
+                let map = self.0.get::<DBEncryptedBannedAddresses, _>();
+                let iter = map.prefix_iter_decoded(&())?;
+                iter.map(|(id, data)| cipher.decrypt(&id, &data)).collect()
+            }
+            None => {
+                let map = self.0.get::<DBBannedAddresses, _>();
+                let iter = map.prefix_iter_decoded(&())?;
+                Ok(iter.collect::<Vec<_>>())
+            }
+        }
     }
 }
 
@@ -109,3 +300,120 @@ impl<'st, B: storage::Backend> PeerDbTransactionRo for PeerDbStoreTxRo<'st, B> {
         self.0.close()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crypto::symkey::SymmetricKeyKind;
+    use storage_lmdb::Lmdb;
+    use tempdir::TempDir;
+    use test_utils::random::{make_seedable_rng, Seed};
+
+    use super::*;
+
+    const KNOWN_ADDRESS: &str = "1.2.3.4:3031";
+    const BANNED_ADDRESS: &str = "5.6.7.8";
+    const BAN_DURATION: Duration = Duration::from_secs(1000);
+
+    fn open(path: &Path, key: Option<SymmetricKey>) -> PeerDbStorageImpl<Lmdb> {
+        let backend = Lmdb::new(
+            path.to_owned(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        PeerDbStorageImpl::new_with_encryption_key(backend, key).unwrap()
+    }
+
+    fn write_records(storage: &PeerDbStorageImpl<Lmdb>) {
+        let mut tx = storage.transaction_rw().unwrap();
+        tx.add_known_address(KNOWN_ADDRESS).unwrap();
+        tx.add_banned_address(BANNED_ADDRESS, BAN_DURATION).unwrap();
+        tx.commit().unwrap();
+    }
+
+    fn read_records(storage: &PeerDbStorageImpl<Lmdb>) -> (Vec<String>, Vec<(String, Duration)>) {
+        let tx = storage.transaction_ro().unwrap();
+        (
+            tx.get_known_addresses().unwrap(),
+            tx.get_banned_addresses().unwrap(),
+        )
+    }
+
+    fn stored_records() -> (Vec<String>, Vec<(String, Duration)>) {
+        (
+            vec![KNOWN_ADDRESS.to_owned()],
+            vec![(BANNED_ADDRESS.to_owned(), BAN_DURATION)],
+        )
+    }
+
+    // Checks if the address is found in any of the database files
+    fn address_on_disk(path: &Path, address: &str) -> bool {
+        std::fs::read_dir(path).unwrap().any(|entry| {
+            let data = std::fs::read(entry.unwrap().path()).unwrap();
+            data.windows(address.len()).any(|window| window == address.as_bytes())
+        })
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let mut rng = make_seedable_rng(Seed::from_entropy());
+        let key = SymmetricKey::new(SymmetricKeyKind::XChacha20Poly1305, &mut rng);
+        let dir = TempDir::new("peerdb").unwrap();
+
+        write_records(&open(dir.path(), Some(key.clone())));
+        assert_eq!(read_records(&open(dir.path(), Some(key))), stored_records());
+    }
+
+    #[test]
+    fn no_addresses_on_disk() {
+        let mut rng = make_seedable_rng(Seed::from_entropy());
+        let key = SymmetricKey::new(SymmetricKeyKind::XChacha20Poly1305, &mut rng);
+
+        let dir = TempDir::new("peerdb").unwrap();
+        write_records(&open(dir.path(), Some(key)));
+        assert!(!address_on_disk(dir.path(), KNOWN_ADDRESS));
+        assert!(!address_on_disk(dir.path(), BANNED_ADDRESS));
+
+        // the addresses can be found if the storage isn't encrypted
+        let dir = TempDir::new("peerdb").unwrap();
+        write_records(&open(dir.path(), None));
+        assert!(address_on_disk(dir.path(), KNOWN_ADDRESS));
+        assert!(address_on_disk(dir.path(), BANNED_ADDRESS));
+    }
+
+    // The records that can't be decrypted are dropped and the storage starts empty
+    #[test]
+    fn wrong_key() {
+        let mut rng = make_seedable_rng(Seed::from_entropy());
+        let key = SymmetricKey::new(SymmetricKeyKind::XChacha20Poly1305, &mut rng);
+        let wrong_key = SymmetricKey::new(SymmetricKeyKind::XChacha20Poly1305, &mut rng);
+        let dir = TempDir::new("peerdb").unwrap();
+
+        write_records(&open(dir.path(), Some(key.clone())));
+        assert_eq!(
+            read_records(&open(dir.path(), Some(wrong_key))),
+            (Vec::new(), Vec::new())
+        );
+        assert_eq!(
+            read_records(&open(dir.path(), Some(key))),
+            (Vec::new(), Vec::new())
+        );
+    }
+
+    // The records stored before the encryption was enabled are encrypted
+    #[test]
+    fn plaintext_records_encrypted() {
+        let mut rng = make_seedable_rng(Seed::from_entropy());
+        let key = SymmetricKey::new(SymmetricKeyKind::XChacha20Poly1305, &mut rng);
+        let dir = TempDir::new("peerdb").unwrap();
+
+        write_records(&open(dir.path(), None));
+        assert_eq!(read_records(&open(dir.path(), Some(key))), stored_records());
+        assert_eq!(
+            read_records(&open(dir.path(), None)),
+            (Vec::new(), Vec::new())
+        );
+    }
+}
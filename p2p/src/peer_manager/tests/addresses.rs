@@ -0,0 +1,702 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::sync::mpsc;
+
+use chainstate::ban_score::BanScore;
+use common::chain::config;
+use serialization::{Decode, Encode};
+use test_utils::random::{make_seedable_rng, Seed};
+
+use crate::{
+    config::P2pConfig,
+    error::{P2pError, PeerError, ProtocolError},
+    message::{
+        AddrListRequest, AddrListResponse, AnnounceAddrRequest, PeerManagerRequest,
+        PeerManagerResponse, Request, Response,
+    },
+    net::{
+        default_backend::{
+            transport::{TcpTransportSocket, TransportAddress},
+            types::{Command, PeerId, RequestId},
+            DefaultNetworkingService,
+        },
+        types::{NodeKind, PeerInfo, Role},
+        AsBannableAddress,
+    },
+    peer_manager::{
+        dns_seed::{self, DnsSeedResolver, DnsSeeds},
+        peerdb::{address_group, AddressSource, PeerDb},
+        tests::{make_mocked_peer_manager, MockedBackend, MockedPeerManager},
+        PeerManagerState, ADDR_LIST_REQUEST_MIN_INTERVAL, SELF_ADVERTISEMENT_INTERVAL,
+    },
+    testing_utils::{peerdb_inmemory_store, P2pTestTimeGetter},
+};
+
+fn expect_addr_list_request(
+    cmd_rx: &mut mpsc::UnboundedReceiver<Command<TcpTransportSocket>>,
+) -> RequestId {
+    match cmd_rx.try_recv().unwrap() {
+        Command::SendRequest {
+            peer_id: _,
+            request_id,
+            message: Request::AddrListRequest(AddrListRequest {}),
+        } => request_id,
+        event => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn refresh_peer_addresses() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config: Arc<P2pConfig> = Arc::new(Default::default());
+
+    let time_getter = P2pTestTimeGetter::new();
+    let (mut peer_manager, mut backend) = make_mocked_peer_manager(
+        Arc::clone(&chain_config),
+        p2p_config,
+        vec![],
+        time_getter.get_time_getter(),
+    );
+
+    let peer_id = PeerId::new();
+    peer_manager
+        .accept_connection(
+            "123.123.123.123:12345".parse().unwrap(),
+            Role::Inbound,
+            PeerInfo {
+                peer_id,
+                network: *chain_config.magic_bytes(),
+                version: *chain_config.version(),
+                agent: None,
+                subscriptions: Default::default(),
+                best_block: None,
+                node_kind: NodeKind::Full,
+            },
+            None,
+        )
+        .unwrap();
+
+    let address1: SocketAddr = "1.2.3.4:3031".parse().unwrap();
+    let address2: SocketAddr = "5.6.7.8:3031".parse().unwrap();
+
+    // The peer advertises the first address
+    peer_manager.refresh_peer_addresses(peer_id).unwrap();
+    let request_id = expect_addr_list_request(&mut backend.cmd_rx);
+    peer_manager
+        .handle_incoming_response(
+            peer_id,
+            request_id,
+            PeerManagerResponse::AddrListResponse(AddrListResponse {
+                addresses: vec![address1.as_peer_address()],
+            }),
+        )
+        .unwrap();
+    let known = peer_manager.peerdb.known_addresses().map(|(a, _)| a).collect::<Vec<_>>();
+    assert_eq!(known, vec![address1]);
+
+    // Requests are rate limited, repeated calls do nothing
+    peer_manager.refresh_peer_addresses(peer_id).unwrap();
+    peer_manager.refresh_peer_addresses(peer_id).unwrap();
+    assert!(backend.cmd_rx.try_recv().is_err());
+
+    // The peer now advertises another address, the refresh picks it up
+    time_getter.advance_time(ADDR_LIST_REQUEST_MIN_INTERVAL).await;
+    peer_manager.refresh_peer_addresses(peer_id).unwrap();
+    let request_id = expect_addr_list_request(&mut backend.cmd_rx);
+    peer_manager
+        .handle_incoming_response(
+            peer_id,
+            request_id,
+            PeerManagerResponse::AddrListResponse(AddrListResponse {
+                addresses: vec![address2.as_peer_address()],
+            }),
+        )
+        .unwrap();
+    let known = peer_manager.peerdb.known_addresses().map(|(a, _)| a).collect::<Vec<_>>();
+    assert_eq!(known, vec![address1, address2]);
+
+    assert_eq!(
+        peer_manager.refresh_peer_addresses(PeerId::new()),
+        Err(P2pError::PeerError(PeerError::PeerDoesntExist))
+    );
+}
+
+#[tokio::test]
+async fn too_many_addresses_in_response() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        max_addr_per_message: 2.into(),
+        ..Default::default()
+    });
+
+    let (mut peer_manager, mut backend) = make_mocked_peer_manager(
+        Arc::clone(&chain_config),
+        p2p_config,
+        vec![],
+        P2pTestTimeGetter::new().get_time_getter(),
+    );
+
+    let peer_id = PeerId::new();
+    peer_manager
+        .accept_connection(
+            "123.123.123.123:12345".parse().unwrap(),
+            Role::Inbound,
+            PeerInfo {
+                peer_id,
+                network: *chain_config.magic_bytes(),
+                version: *chain_config.version(),
+                agent: None,
+                subscriptions: Default::default(),
+                best_block: None,
+                node_kind: NodeKind::Full,
+            },
+            None,
+        )
+        .unwrap();
+
+    let addresses: Vec<SocketAddr> = vec![
+        "1.2.3.4:3031".parse().unwrap(),
+        "5.6.7.8:3031".parse().unwrap(),
+        "9.10.11.12:3031".parse().unwrap(),
+    ];
+
+    // The peer sends more addresses than allowed, the excess is ignored and the peer is scored
+    peer_manager.refresh_peer_addresses(peer_id).unwrap();
+    let request_id = expect_addr_list_request(&mut backend.cmd_rx);
+    peer_manager
+        .handle_incoming_response(
+            peer_id,
+            request_id,
+            PeerManagerResponse::AddrListResponse(AddrListResponse {
+                addresses: addresses.iter().map(TransportAddress::as_peer_address).collect(),
+            }),
+        )
+        .unwrap();
+    let known = peer_manager.peerdb.known_addresses().map(|(a, _)| a).collect::<Vec<_>>();
+    assert_eq!(known, addresses[..2]);
+    assert_eq!(
+        peer_manager.peers.get(&peer_id).unwrap().score,
+        ProtocolError::TooManyAddresses(3, 2).ban_score()
+    );
+}
+
+#[tokio::test]
+async fn max_known_addresses() {
+    type TestNetworkingService = DefaultNetworkingService<TcpTransportSocket>;
+
+    let p2p_config = Arc::new(P2pConfig {
+        max_addresses: 3.into(),
+        ..Default::default()
+    });
+    let time_getter = P2pTestTimeGetter::new();
+    let mut peerdb = PeerDb::<TestNetworkingService, _>::new(
+        p2p_config,
+        time_getter.get_time_getter(),
+        peerdb_inmemory_store(),
+    )
+    .unwrap();
+
+    let address1: SocketAddr = "1.1.1.1:3031".parse().unwrap();
+    let address2: SocketAddr = "2.2.2.2:3031".parse().unwrap();
+    let address3: SocketAddr = "2.2.3.3:3031".parse().unwrap();
+    let address4: SocketAddr = "3.3.3.3:3031".parse().unwrap();
+    let address5: SocketAddr = "4.4.4.4:3031".parse().unwrap();
+    let known_addresses = |peerdb: &PeerDb<TestNetworkingService, _>| {
+        peerdb.known_addresses().map(|(a, _)| a).collect::<Vec<_>>()
+    };
+
+    for address in [address1, address2, address3] {
+        peerdb.peer_discovered(&address, AddressSource::AddressExchange).unwrap();
+        time_getter.advance_time(Duration::from_secs(1)).await;
+    }
+
+    // The stalest address from the group with more than one address is evicted,
+    // the only address from its group is kept even though it's older
+    peerdb.peer_discovered(&address4, AddressSource::AddressExchange).unwrap();
+    assert_eq!(known_addresses(&peerdb), vec![address1, address3, address4]);
+
+    // All groups have one address now, the address that failed is evicted
+    // even though it was seen recently
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    peerdb.report_outbound_failure(address3);
+    peerdb.peer_discovered(&address5, AddressSource::AddressExchange).unwrap();
+    assert_eq!(known_addresses(&peerdb), vec![address1, address4, address5]);
+
+    // Rediscovering a known address refreshes it, so the next eviction picks another one
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    peerdb.peer_discovered(&address1, AddressSource::AddressExchange).unwrap();
+    peerdb.peer_discovered(&address2, AddressSource::AddressExchange).unwrap();
+    assert_eq!(known_addresses(&peerdb), vec![address1, address2, address5]);
+}
+
+// Recently seen addresses from diverse groups are selected more often than stale addresses
+// that failed to connect, but the latter still get selected sometimes
+#[tokio::test]
+async fn weighted_peer_selection() {
+    type TestNetworkingService = DefaultNetworkingService<TcpTransportSocket>;
+
+    let time_getter = P2pTestTimeGetter::new();
+    let mut peerdb = PeerDb::<TestNetworkingService, _>::new(
+        Arc::new(P2pConfig::default()),
+        time_getter.get_time_getter(),
+        peerdb_inmemory_store(),
+    )
+    .unwrap();
+
+    // Stale addresses from the same group that failed several times
+    let bad_addresses: [SocketAddr; 2] =
+        ["2.2.1.1:3031".parse().unwrap(), "2.2.2.2:3031".parse().unwrap()];
+    for address in bad_addresses {
+        peerdb.peer_discovered(&address, AddressSource::AddressExchange).unwrap();
+        for _ in 0..3 {
+            peerdb.report_outbound_failure(address);
+        }
+    }
+    time_getter.advance_time(Duration::from_secs(2 * 24 * 60 * 60)).await;
+
+    let good_addresses: [SocketAddr; 2] =
+        ["1.1.1.1:3031".parse().unwrap(), "3.3.3.3:3031".parse().unwrap()];
+    for address in good_addresses {
+        peerdb.peer_discovered(&address, AddressSource::AddressExchange).unwrap();
+    }
+
+    let mut rng = make_seedable_rng(Seed::from_u64(1));
+    let (mut good, mut bad) = (0, 0);
+    for _ in 0..1000 {
+        let selected = peerdb.select_new_peers_with_rng(1, &mut rng);
+        assert_eq!(selected.len(), 1);
+        if good_addresses.contains(&selected[0]) {
+            good += 1;
+        } else {
+            assert!(bad_addresses.contains(&selected[0]));
+            bad += 1;
+        }
+    }
+    assert!(good > 10 * bad, "good: {good}, bad: {bad}");
+    assert!(bad > 0);
+}
+
+#[tokio::test]
+async fn advertise_self() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        advertise_self: true.into(),
+        ..Default::default()
+    });
+    let time_getter = P2pTestTimeGetter::new();
+
+    let make_peer_manager = |local_addresses: Vec<SocketAddr>| {
+        make_mocked_peer_manager(
+            Arc::clone(&chain_config),
+            Arc::clone(&p2p_config),
+            local_addresses,
+            time_getter.get_time_getter(),
+        )
+    };
+    let peer_info = |peer_id| PeerInfo {
+        peer_id,
+        network: *chain_config.magic_bytes(),
+        version: *chain_config.version(),
+        agent: None,
+        subscriptions: Default::default(),
+        best_block: None,
+        node_kind: NodeKind::Full,
+    };
+
+    let (mut pm_a, mut backend_a) = make_peer_manager(vec!["0.0.0.0:3031".parse().unwrap()]);
+    let (mut pm_b, mut backend_b) = make_peer_manager(vec![]);
+    let (mut pm_c, _backend_c) = make_peer_manager(vec![]);
+    let peer_id_a = PeerId::new();
+    let peer_id_b = PeerId::new();
+    let peer_id_c = PeerId::new();
+    let peer_id_d = PeerId::new();
+    let address_a: SocketAddr = "1.2.3.4:3031".parse().unwrap();
+    let request_addr_list = |pm: &mut MockedPeerManager, backend: &mut MockedBackend| {
+        pm.handle_incoming_request(
+            peer_id_b,
+            RequestId::new(),
+            PeerManagerRequest::AddrListRequest(AddrListRequest {}),
+        )
+        .unwrap();
+        match backend.cmd_rx.try_recv().unwrap() {
+            Command::SendResponse {
+                request_id: _,
+                message: Response::AddrListResponse(AddrListResponse { addresses }),
+            } => addresses,
+            event => panic!("unexpected event: {event:?}"),
+        }
+    };
+
+    // A connects to B, B reports the address of A as it sees it
+    pm_a.accept_connection(
+        "5.6.7.8:3031".parse().unwrap(),
+        Role::Outbound,
+        peer_info(peer_id_b),
+        Some("1.2.3.4:12345".parse::<SocketAddr>().unwrap().as_peer_address()),
+    )
+    .unwrap();
+    while backend_a.cmd_rx.try_recv().is_ok() {}
+
+    // A single report is not enough to advertise the address
+    assert_eq!(request_addr_list(&mut pm_a, &mut backend_a), vec![]);
+
+    // A connects to D from another address group, D reports the same address
+    pm_a.accept_connection(
+        "13.14.15.16:3031".parse().unwrap(),
+        Role::Outbound,
+        peer_info(peer_id_d),
+        Some("1.2.3.4:23456".parse::<SocketAddr>().unwrap().as_peer_address()),
+    )
+    .unwrap();
+    while backend_a.cmd_rx.try_recv().is_ok() {}
+
+    pm_b.accept_connection(
+        "1.2.3.4:12345".parse().unwrap(),
+        Role::Inbound,
+        peer_info(peer_id_a),
+        None,
+    )
+    .unwrap();
+    pm_b.accept_connection(
+        "9.10.11.12:12345".parse().unwrap(),
+        Role::Inbound,
+        peer_info(peer_id_c),
+        None,
+    )
+    .unwrap();
+    pm_c.accept_connection(
+        "5.6.7.8:3031".parse().unwrap(),
+        Role::Outbound,
+        peer_info(peer_id_b),
+        None,
+    )
+    .unwrap();
+
+    // A includes its own address in the address list responses
+    let addresses = request_addr_list(&mut pm_a, &mut backend_a);
+    assert!(addresses.contains(&address_a.as_peer_address()));
+
+    // A periodically announces its own address again (to both B and D)
+    time_getter.advance_time(SELF_ADVERTISEMENT_INTERVAL * 2).await;
+    pm_a.heartbeat().unwrap();
+    let (request_id, address) = loop {
+        match backend_a.cmd_rx.try_recv().unwrap() {
+            Command::SendRequest {
+                peer_id,
+                request_id,
+                message: Request::AnnounceAddrRequest(AnnounceAddrRequest { address }),
+            } if peer_id == peer_id_b => break (request_id, address),
+            Command::SendRequest {
+                peer_id,
+                request_id: _,
+                message: Request::AnnounceAddrRequest(_),
+            } if peer_id == peer_id_d => {}
+            event => panic!("unexpected event: {event:?}"),
+        }
+    };
+    assert_eq!(address, address_a.as_peer_address());
+
+    // B relays the announcement to C
+    pm_b.handle_incoming_request(
+        peer_id_a,
+        request_id,
+        PeerManagerRequest::AnnounceAddrRequest(AnnounceAddrRequest { address }),
+    )
+    .unwrap();
+    let (request_id, address) = loop {
+        match backend_b.cmd_rx.try_recv().unwrap() {
+            Command::SendRequest {
+                peer_id,
+                request_id,
+                message: Request::AnnounceAddrRequest(AnnounceAddrRequest { address }),
+            } => {
+                assert_eq!(peer_id, peer_id_c);
+                break (request_id, address);
+            }
+            // Ignore the address list request sent on the connection
+            Command::SendRequest {
+                peer_id: _,
+                request_id: _,
+                message: Request::AddrListRequest(AddrListRequest {}),
+            } => {}
+            event => panic!("unexpected event: {event:?}"),
+        }
+    };
+
+    pm_c.handle_incoming_request(
+        peer_id_b,
+        request_id,
+        PeerManagerRequest::AnnounceAddrRequest(AnnounceAddrRequest { address }),
+    )
+    .unwrap();
+    let known = pm_c.peerdb.known_addresses().map(|(a, _)| a).collect::<Vec<_>>();
+    assert_eq!(known, vec![address_a]);
+}
+
+// Known addresses, bans and peer scores survive the export to a new peer manager
+#[tokio::test]
+async fn export_import_state() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig::default());
+    let time_getter = P2pTestTimeGetter::new();
+
+    let make_peer_manager = || {
+        make_mocked_peer_manager(
+            Arc::clone(&chain_config),
+            Arc::clone(&p2p_config),
+            vec![],
+            time_getter.get_time_getter(),
+        )
+    };
+    let peer_info = || PeerInfo {
+        peer_id: PeerId::new(),
+        network: *chain_config.magic_bytes(),
+        version: *chain_config.version(),
+        agent: None,
+        subscriptions: Default::default(),
+        best_block: None,
+        node_kind: NodeKind::Full,
+    };
+    // The source of the imported addresses is not preserved
+    let known_addresses = |pm: &MockedPeerManager| {
+        pm.peerdb
+            .known_addresses()
+            .map(|(address, state)| (address, state.last_seen, state.failure_count, state.banned))
+            .collect::<Vec<_>>()
+    };
+
+    let address1: SocketAddr = "1.1.1.1:3031".parse().unwrap();
+    let address2: SocketAddr = "2.2.2.2:3031".parse().unwrap();
+    let banned_address: SocketAddr = "3.3.3.3:3031".parse().unwrap();
+    let connected_address: SocketAddr = "4.4.4.4:3031".parse().unwrap();
+
+    let (mut pm1, _backend1) = make_peer_manager();
+    for address in [address1, address2, banned_address] {
+        pm1.peerdb.peer_discovered(&address, AddressSource::AddressExchange).unwrap();
+    }
+    pm1.peerdb.report_outbound_failure(address2);
+    pm1.peerdb.ban_peer(&banned_address).unwrap();
+    let peer_info1 = peer_info();
+    let peer_id1 = peer_info1.peer_id;
+    pm1.accept_connection(connected_address, Role::Inbound, peer_info1, None)
+        .unwrap();
+    pm1.adjust_peer_score(peer_id1, 10).unwrap();
+
+    let state = pm1.export_state();
+    let state = PeerManagerState::decode(&mut state.encode().as_slice()).unwrap();
+
+    let (mut pm2, _backend2) = make_peer_manager();
+    pm2.import_state(state).unwrap();
+    assert_eq!(known_addresses(&pm2), known_addresses(&pm1));
+    assert!(pm2.peerdb.is_address_banned(&banned_address.as_bannable()).unwrap());
+
+    // The score is restored once a peer reconnects from the same IP address, even if the port
+    // is different
+    let peer_info2 = peer_info();
+    let peer_id2 = peer_info2.peer_id;
+    pm2.accept_connection(
+        "4.4.4.4:12345".parse().unwrap(),
+        Role::Inbound,
+        peer_info2,
+        None,
+    )
+    .unwrap();
+    assert_eq!(pm2.peers.get(&peer_id2).unwrap().score, 10);
+
+    // Peers from other IP addresses with the same port are not affected
+    let peer_info3 = peer_info();
+    let peer_id3 = peer_info3.peer_id;
+    pm2.accept_connection(
+        "5.5.5.5:3031".parse().unwrap(),
+        Role::Inbound,
+        peer_info3,
+        None,
+    )
+    .unwrap();
+    assert_eq!(pm2.peers.get(&peer_id3).unwrap().score, 0);
+}
+
+struct TestDnsSeedResolver {
+    queried_seeds: std::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl DnsSeedResolver for TestDnsSeedResolver {
+    async fn resolve(&self, seed: &str) -> crate::Result<Vec<String>> {
+        self.queried_seeds.lock().unwrap().push(seed.to_owned());
+        match seed {
+            "good.seed:3031" => Ok(vec!["1.2.3.4:3031".to_owned(), "5.6.7.8:3031".to_owned()]),
+            _ => Err(P2pError::Other("unknown host")),
+        }
+    }
+}
+
+/// Starts the due DNS seed lookups and handles their results
+async fn query_dns_seeds(peer_manager: &mut MockedPeerManager) {
+    peer_manager.query_dns_seeds();
+    while peer_manager.dns_seeds.lookups_in_progress() > 0 {
+        let (seed, result) = peer_manager.dns_seed_results_rx.recv().await.unwrap();
+        peer_manager.handle_dns_seed_result(seed, result).unwrap();
+    }
+}
+
+// The resolved addresses are added to the peerdb and the failing seed is queried less often
+#[tokio::test]
+async fn dns_seed_backoff() {
+    tokio::time::pause();
+    let refresh_interval = Duration::from_secs(60);
+    let p2p_config = Arc::new(P2pConfig {
+        dns_seeds: vec!["good.seed:3031".to_owned(), "bad.seed:3031".to_owned()].into(),
+        dns_seed_refresh_interval: refresh_interval.into(),
+        ..Default::default()
+    });
+    let (mut peer_manager, _backend) = make_mocked_peer_manager(
+        Arc::new(config::create_mainnet()),
+        p2p_config,
+        vec![],
+        P2pTestTimeGetter::new().get_time_getter(),
+    );
+    let resolver = Arc::new(TestDnsSeedResolver {
+        queried_seeds: Default::default(),
+    });
+    peer_manager.set_dns_seed_resolver(Arc::clone(&resolver) as Arc<dyn DnsSeedResolver>);
+    let take_queried_seeds = || {
+        let mut seeds = std::mem::take(&mut *resolver.queried_seeds.lock().unwrap());
+        seeds.sort();
+        seeds
+    };
+
+    query_dns_seeds(&mut peer_manager).await;
+    assert_eq!(
+        take_queried_seeds(),
+        vec!["bad.seed:3031".to_owned(), "good.seed:3031".to_owned()]
+    );
+    let dns_seed_addresses = peer_manager
+        .peerdb
+        .known_addresses()
+        .filter(|(_address, state)| state.source == AddressSource::DnsSeed)
+        .map(|(address, _state)| address)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dns_seed_addresses,
+        vec![
+            "1.2.3.4:3031".parse::<SocketAddr>().unwrap(),
+            "5.6.7.8:3031".parse::<SocketAddr>().unwrap(),
+        ]
+    );
+    assert_eq!(
+        peer_manager.dns_seeds.failure_count("good.seed:3031"),
+        Some(0)
+    );
+    assert_eq!(
+        peer_manager.dns_seeds.failure_count("bad.seed:3031"),
+        Some(1)
+    );
+
+    // Nothing is due before the refresh interval passes
+    tokio::time::advance(refresh_interval / 2).await;
+    query_dns_seeds(&mut peer_manager).await;
+    assert!(take_queried_seeds().is_empty());
+
+    // The failing seed is backed off
+    tokio::time::advance(refresh_interval / 2).await;
+    query_dns_seeds(&mut peer_manager).await;
+    assert_eq!(take_queried_seeds(), vec!["good.seed:3031".to_owned()]);
+
+    tokio::time::advance(refresh_interval).await;
+    query_dns_seeds(&mut peer_manager).await;
+    assert_eq!(
+        take_queried_seeds(),
+        vec!["bad.seed:3031".to_owned(), "good.seed:3031".to_owned()]
+    );
+    assert_eq!(
+        peer_manager.dns_seeds.failure_count("bad.seed:3031"),
+        Some(2)
+    );
+}
+
+// A lookup that never finishes times out and the seed is backed off
+#[tokio::test]
+async fn dns_seed_timeout() {
+    struct HangingResolver;
+
+    #[async_trait::async_trait]
+    impl DnsSeedResolver for HangingResolver {
+        async fn resolve(&self, _seed: &str) -> crate::Result<Vec<String>> {
+            std::future::pending().await
+        }
+    }
+
+    tokio::time::pause();
+    let p2p_config = Arc::new(P2pConfig {
+        dns_seeds: vec!["slow.seed:3031".to_owned()].into(),
+        ..Default::default()
+    });
+    let (mut peer_manager, _backend) = make_mocked_peer_manager(
+        Arc::new(config::create_mainnet()),
+        p2p_config,
+        vec![],
+        P2pTestTimeGetter::new().get_time_getter(),
+    );
+    peer_manager.set_dns_seed_resolver(Arc::new(HangingResolver));
+
+    // The lookup doesn't block the caller and is not started twice
+    peer_manager.query_dns_seeds();
+    peer_manager.query_dns_seeds();
+    assert_eq!(peer_manager.dns_seeds.lookups_in_progress(), 1);
+
+    query_dns_seeds(&mut peer_manager).await;
+    assert_eq!(
+        peer_manager.dns_seeds.failure_count("slow.seed:3031"),
+        Some(1)
+    );
+}
+
+// The backed off refresh interval is capped and doesn't overflow
+#[test]
+fn dns_seed_backoff_limit() {
+    let now = tokio::time::Instant::now();
+    let seeds = vec!["seed:3031".to_owned()];
+    let max_interval = dns_seed::MAX_BACKOFF_INTERVAL;
+
+    for refresh_interval in
+        [Duration::from_secs(60 * 60), Duration::from_secs(u64::MAX / 2), Duration::MAX]
+    {
+        let mut dns_seeds = DnsSeeds::new(&seeds, refresh_interval, now);
+        for _ in 0..10 {
+            dns_seeds.lookup_finished("seed:3031", false, now);
+        }
+        assert_eq!(dns_seeds.failure_count("seed:3031"), Some(10));
+
+        assert!(dns_seeds.start_due_lookups(now + max_interval / 2).is_empty());
+        assert_eq!(dns_seeds.start_due_lookups(now + max_interval), seeds);
+    }
+}
+
+#[test]
+fn ipv4_mapped_address_group() {
+    let address: SocketAddr = "1.2.3.4:3031".parse().unwrap();
+    let mapped: SocketAddr = "[::ffff:1.2.3.4]:3031".parse().unwrap();
+    let same_group: SocketAddr = "[::ffff:1.2.5.6]:3031".parse().unwrap();
+    let other_group: SocketAddr = "1.3.3.4:3031".parse().unwrap();
+
+    assert_eq!(address_group(&address), address_group(&mapped));
+    assert_eq!(address_group(&address), address_group(&same_group));
+    assert_ne!(address_group(&mapped), address_group(&other_group));
+    assert_eq!(address.as_bannable(), mapped.as_bannable());
+}
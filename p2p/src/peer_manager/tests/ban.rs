@@ -27,7 +27,7 @@ use crate::{
 use common::{chain::config, primitives::semver::SemVer};
 
 use crate::{
-    error::{P2pError, PeerError},
+    error::{P2pError, PeerError, ProtocolError},
     net::{
         self,
         default_backend::{
@@ -38,7 +38,7 @@ use crate::{
         types::PubSubTopic,
         AsBannableAddress, ConnectivityService, NetworkingService,
     },
-    peer_manager::tests::make_peer_manager,
+    peer_manager::{tests::make_peer_manager, AcceptOutcome},
 };
 
 // ban peer whose connected to us
@@ -229,6 +229,8 @@ where
             version: SemVer::new(0, 1, 0),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
@@ -245,6 +247,8 @@ where
             version: SemVer::new(1, 1, 1),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
@@ -262,6 +266,8 @@ where
             version: SemVer::new(0, 1, 0),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
@@ -321,10 +327,12 @@ where
             version: SemVer::new(0, 1, 0),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
-    assert_eq!(peer_manager.handle_result(Some(peer_id), res), Ok(()));
+    assert_eq!(res, Ok(AcceptOutcome::RejectedDifferentNetwork));
     assert!(!peer_manager.is_peer_connected(&peer_id));
 
     // invalid version
@@ -336,10 +344,16 @@ where
             version: SemVer::new(1, 1, 1),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
-    assert_eq!(peer_manager.handle_result(Some(peer_id), res), Ok(()));
+    assert!(res.is_err());
+    assert_eq!(
+        peer_manager.handle_result(Some(peer_id), res.map(|_| ())),
+        Ok(())
+    );
     assert!(!peer_manager.is_peer_connected(&peer_id));
 
     // valid connection
@@ -352,11 +366,12 @@ where
             version: SemVer::new(0, 1, 0),
             agent: None,
             subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+            best_block: None,
+            node_kind: net::types::NodeKind::Full,
         },
         None,
     );
-    assert!(res.is_ok());
-    assert_eq!(peer_manager.handle_result(Some(peer_id), res), Ok(()));
+    assert_eq!(res, Ok(AcceptOutcome::Accepted));
     assert!(peer_manager.is_peer_connected(&peer_id));
     assert!(!peer_manager.peerdb.is_address_banned(&address.as_bannable()).unwrap());
 }
@@ -413,22 +428,23 @@ where
     )
     .await;
 
-    let (_address, peer_info, _) = connect_services::<T>(
-        &mut pm1.peer_connectivity_handle,
-        &mut pm2.peer_connectivity_handle,
-    )
-    .await;
+    let addr = pm2.peer_connectivity_handle.local_addresses()[0].clone();
+    pm1.peer_connectivity_handle.connect(addr).unwrap();
 
     // run the first peer manager in the background and poll events from the peer manager
-    // that tries to connect to the first manager
+    // that the first manager tries to connect to
     tokio::spawn(async move { pm1.run().await });
 
+    // the connection is rejected during the handshake and never reported as accepted
     let event = get_connectivity_event::<T>(&mut pm2.peer_connectivity_handle).await;
     match event {
-        Ok(net::types::ConnectivityEvent::ConnectionClosed { peer_id })
-            if peer_id == peer_info.peer_id => {}
+        Ok(net::types::ConnectivityEvent::ConnectionError {
+            address: _,
+            error: P2pError::ProtocolError(ProtocolError::DifferentNetwork(_, _)),
+        }) => {}
         _ => panic!("unexpected event: {event:?}"),
     }
+    assert_eq!(pm2.active_peer_count(), 0);
 }
 
 #[tokio::test]
@@ -457,3 +473,48 @@ async fn inbound_connection_invalid_magic_noise() {
     >()
     .await;
 }
+
+// banning a peer disconnects the other peers that share its bannable address
+#[tokio::test]
+async fn ban_disconnects_peers_with_same_ip() {
+    let config = Arc::new(config::create_mainnet());
+    let mut peer_manager = make_peer_manager::<DefaultNetworkingService<TcpTransportSocket>>(
+        TestTransportTcp::make_transport(),
+        TestTransportTcp::make_address(),
+        Arc::clone(&config),
+    )
+    .await;
+
+    let addresses = ["10.0.0.1:3031", "10.0.0.1:13031", "10.0.0.2:3031"];
+    let peer_ids = addresses.map(|address| {
+        let peer_id = PeerId::new();
+        let res = peer_manager.accept_connection(
+            address.parse().unwrap(),
+            Role::Outbound,
+            net::types::PeerInfo::<PeerId> {
+                peer_id,
+                network: *config.magic_bytes(),
+                version: SemVer::new(0, 1, 0),
+                agent: None,
+                subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
+                    .into_iter()
+                    .collect(),
+                best_block: None,
+                node_kind: net::types::NodeKind::Full,
+            },
+            None,
+        );
+        assert_eq!(peer_manager.handle_result(Some(peer_id), res), Ok(()));
+        assert!(peer_manager.is_peer_connected(&peer_id));
+        peer_id
+    });
+
+    let ban_threshold = *peer_manager.p2p_config.ban_threshold;
+    peer_manager.adjust_peer_score(peer_ids[0], ban_threshold).unwrap();
+
+    let banned_address: std::net::SocketAddr = addresses[1].parse().unwrap();
+    assert!(peer_manager.peerdb.is_address_banned(&banned_address.as_bannable()).unwrap());
+    assert!(peer_manager.pending_disconnects.contains_key(&peer_ids[0]));
+    assert!(peer_manager.pending_disconnects.contains_key(&peer_ids[1]));
+    assert!(!peer_manager.pending_disconnects.contains_key(&peer_ids[2]));
+}
@@ -14,6 +14,7 @@
 // limitations under the License.
 
 use std::{
+    collections::BTreeSet,
     net::SocketAddr,
     sync::Arc,
     time::{Duration, Instant},
@@ -31,7 +32,7 @@ use crate::{
     },
     utils::oneshot_nofail,
 };
-use common::chain::config;
+use common::{chain::config, primitives::BlockHeight};
 
 use crate::{
     error::{DialError, P2pError, ProtocolError},
@@ -40,13 +41,21 @@ use crate::{
         self,
         default_backend::{
             transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
-            types::PeerId,
+            types::{Command, PeerId},
             DefaultNetworkingService,
         },
-        types::{PeerInfo, PubSubTopic},
+        types::{NodeKind, PeerInfo, PubSubTopic},
         ConnectivityService, NetworkingService,
     },
-    peer_manager::{self, tests::make_peer_manager},
+    peer_manager::{
+        peerdb::{address_group, AddressSource},
+        tests::{
+            make_mocked_peer_manager, make_peer_manager, make_peer_manager_with_config,
+            MockedPeerManager,
+        },
+        AcceptOutcome, PersistentReconnect, STALE_TIP_CONNECTION_LIFETIME,
+        STALE_TIP_EXTRA_CONNECTIONS,
+    },
 };
 
 // try to connect to an address that no one listening on and verify it fails
@@ -124,7 +133,7 @@ where
     });
 
     // "discover" the other networking service
-    pm1.peerdb.peer_discovered(&addr).unwrap();
+    pm1.peerdb.peer_discovered(&addr, AddressSource::AddressExchange).unwrap();
     pm1.heartbeat().unwrap();
 
     assert_eq!(pm1.pending_connects.len(), 1);
@@ -207,12 +216,14 @@ where
     )
     .await;
 
-    let (_address, peer_info, _) = connect_services::<T>(
-        &mut pm2.peer_connectivity_handle,
-        &mut pm1.peer_connectivity_handle,
-    )
-    .await;
-    assert_ne!(peer_info.network, *config.magic_bytes());
+    let addr = pm1.peer_connectivity_handle.local_addresses()[0].clone();
+    pm2.peer_connectivity_handle.connect(addr).unwrap();
+
+    // The remote peer rejects the connection during the handshake
+    match get_connectivity_event::<T>(&mut pm2.peer_connectivity_handle).await {
+        Ok(net::types::ConnectivityEvent::ConnectionClosed { peer_id: _ }) => {}
+        event => panic!("unexpected event: {event:?}"),
+    }
 }
 
 #[tokio::test]
@@ -262,7 +273,7 @@ where
     .await;
     assert_eq!(
         pm2.accept_inbound_connection(address, peer_info, None),
-        Ok(())
+        Ok(AcceptOutcome::Accepted)
     );
 }
 
@@ -309,19 +320,20 @@ where
     )
     .await;
 
-    let (address, peer_info, _) = connect_services::<T>(
-        &mut pm1.peer_connectivity_handle,
-        &mut pm2.peer_connectivity_handle,
-    )
-    .await;
-
-    assert_eq!(
-        pm2.accept_inbound_connection(address, peer_info, None),
-        Err(P2pError::ProtocolError(ProtocolError::DifferentNetwork(
-            [1, 2, 3, 4],
-            *config::create_mainnet().magic_bytes(),
-        )))
-    );
+    let addr = pm2.peer_connectivity_handle.local_addresses()[0].clone();
+    pm1.peer_connectivity_handle.connect(addr).unwrap();
+
+    // The connection is rejected before it's reported as accepted
+    match get_connectivity_event::<T>(&mut pm2.peer_connectivity_handle).await {
+        Ok(net::types::ConnectivityEvent::ConnectionError { address: _, error }) => assert_eq!(
+            error,
+            P2pError::ProtocolError(ProtocolError::DifferentNetwork(
+                [1, 2, 3, 4],
+                *config::create_mainnet().magic_bytes(),
+            ))
+        ),
+        event => panic!("unexpected event: {event:?}"),
+    }
 }
 
 #[tokio::test]
@@ -406,6 +418,70 @@ async fn remote_closes_connection_noise() {
         .await;
 }
 
+async fn inbound_connection_limit<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        max_active_connections: 1.into(),
+        ..Default::default()
+    });
+    let mut pm1 = make_peer_manager_with_config::<T>(
+        A::make_transport(),
+        A::make_address(),
+        Arc::clone(&config),
+        p2p_config,
+    )
+    .await;
+    let mut pm2 =
+        make_peer_manager::<T>(A::make_transport(), A::make_address(), Arc::clone(&config)).await;
+    let mut pm3 = make_peer_manager::<T>(A::make_transport(), A::make_address(), config).await;
+
+    let (address, peer_info, _) = connect_services::<T>(
+        &mut pm2.peer_connectivity_handle,
+        &mut pm1.peer_connectivity_handle,
+    )
+    .await;
+    assert_eq!(
+        pm1.accept_inbound_connection(address, peer_info, None),
+        Ok(AcceptOutcome::Accepted)
+    );
+
+    // The limit is reached, the second connection is rejected
+    let (address, peer_info, _) = connect_services::<T>(
+        &mut pm3.peer_connectivity_handle,
+        &mut pm1.peer_connectivity_handle,
+    )
+    .await;
+    assert_eq!(
+        pm1.accept_inbound_connection(address, peer_info, None),
+        Ok(AcceptOutcome::RejectedTooManyPeers)
+    );
+    assert_eq!(pm1.active_peer_count(), 1);
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_tcp() {
+    inbound_connection_limit::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_channels() {
+    inbound_connection_limit::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_noise() {
+    inbound_connection_limit::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
+
 async fn inbound_connection_too_many_peers<A, T>(peers: Vec<(T::Address, PeerInfo<T::PeerId>)>)
 where
     A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
@@ -424,7 +500,7 @@ where
     }
     assert_eq!(
         pm1.active_peer_count(),
-        peer_manager::MAX_ACTIVE_CONNECTIONS
+        *P2pConfig::default().max_active_connections
     );
 
     let (_address, peer_info, _) = connect_services::<T>(
@@ -448,7 +524,7 @@ where
 #[tokio::test]
 async fn inbound_connection_too_many_peers_tcp() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_active_connections)
         .map(|index| {
             (
                 format!("127.0.0.1:{}", index + 10000).parse().expect("valid address"),
@@ -460,6 +536,8 @@ async fn inbound_connection_too_many_peers_tcp() {
                     subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                         .into_iter()
                         .collect(),
+                    best_block: None,
+                    node_kind: NodeKind::Full,
                 },
             )
         })
@@ -475,7 +553,7 @@ async fn inbound_connection_too_many_peers_tcp() {
 #[tokio::test]
 async fn inbound_connection_too_many_peers_channels() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_active_connections)
         .map(|index| {
             (
                 format!("{}", index + 10000).parse().expect("valid address"),
@@ -487,6 +565,8 @@ async fn inbound_connection_too_many_peers_channels() {
                     subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                         .into_iter()
                         .collect(),
+                    best_block: None,
+                    node_kind: NodeKind::Full,
                 },
             )
         })
@@ -502,7 +582,7 @@ async fn inbound_connection_too_many_peers_channels() {
 #[tokio::test]
 async fn inbound_connection_too_many_peers_noise() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_active_connections)
         .map(|index| {
             (
                 format!("127.0.0.1:{}", index + 10000).parse().expect("valid address"),
@@ -514,6 +594,8 @@ async fn inbound_connection_too_many_peers_noise() {
                     subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
                         .into_iter()
                         .collect(),
+                    best_block: None,
+                    node_kind: NodeKind::Full,
                 },
             )
         })
@@ -666,6 +748,79 @@ async fn connection_timeout_rpc_notified_noise() {
     .await;
 }
 
+// the peer is reachable at the second address only, the connection is made after the first fails
+async fn connect_any_second_address<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let time_getter = P2pTestTimeGetter::new();
+    let chain_config = Arc::new(config::create_mainnet());
+
+    let tx1 = run_peer_manager::<T>(
+        A::make_transport(),
+        A::make_address(),
+        Arc::clone(&chain_config),
+        Default::default(),
+        time_getter.get_time_getter(),
+    )
+    .await;
+    let (rtx, rrx) = oneshot_nofail::channel();
+    tx1.send(PeerManagerEvent::GetBindAddresses(rtx)).unwrap();
+    let bind_addresses = timeout(Duration::from_secs(1), rrx).await.unwrap().unwrap();
+    let alive_address = bind_addresses[0].parse::<T::Address>().ok().unwrap();
+
+    let tx2 = run_peer_manager::<T>(
+        A::make_transport(),
+        A::make_address(),
+        chain_config,
+        Default::default(),
+        time_getter.get_time_getter(),
+    )
+    .await;
+
+    // Nobody listens on the first address
+    let (rtx, rrx) = oneshot_nofail::channel();
+    tx2.send(PeerManagerEvent::ConnectAny(
+        vec![A::make_address(), alive_address],
+        rtx,
+    ))
+    .unwrap();
+    assert_eq!(
+        timeout(Duration::from_secs(10), rrx).await.unwrap().unwrap(),
+        Ok(())
+    );
+
+    let (rtx, rrx) = oneshot_nofail::channel();
+    tx2.send(PeerManagerEvent::GetPeerCount(rtx)).unwrap();
+    assert_eq!(
+        timeout(Duration::from_secs(1), rrx).await.unwrap().unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn connect_any_second_address_tcp() {
+    connect_any_second_address::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn connect_any_second_address_channels() {
+    connect_any_second_address::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn connect_any_second_address_noise() {
+    connect_any_second_address::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
+
 // verify that peer connection is made when valid add_node parameter is used
 async fn connection_add_node<A, T>()
 where
@@ -680,13 +835,56 @@ where
     let p2p_config_1 = Arc::new(P2pConfig {
         bind_addresses: Default::default(),
         added_nodes: Default::default(),
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: Default::default(),
         allow_discover_private_ips: Default::default(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let tx1 = run_peer_manager::<T>(
         A::make_transport(),
@@ -707,13 +905,56 @@ where
     let p2p_config_2 = Arc::new(P2pConfig {
         bind_addresses: Default::default(),
         added_nodes: bind_addresses,
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: Default::default(),
         allow_discover_private_ips: Default::default(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let tx1 = run_peer_manager::<T>(
         A::make_transport(),
@@ -774,13 +1015,56 @@ where
     let p2p_config_1 = Arc::new(P2pConfig {
         bind_addresses: Default::default(),
         added_nodes: Default::default(),
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: Default::default(),
         allow_discover_private_ips: true.into(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let tx1 = run_peer_manager::<T>(
         A::make_transport(),
@@ -802,13 +1086,56 @@ where
     let p2p_config_2 = Arc::new(P2pConfig {
         bind_addresses: Default::default(),
         added_nodes: bind_addresses.clone(),
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: Default::default(),
         allow_discover_private_ips: true.into(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let tx2 = run_peer_manager::<T>(
         A::make_transport(),
@@ -823,13 +1150,56 @@ where
     let p2p_config_3 = Arc::new(P2pConfig {
         bind_addresses: Default::default(),
         added_nodes: bind_addresses,
+        preconfigured_peers: Default::default(),
         ban_threshold: Default::default(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: Default::default(),
         allow_discover_private_ips: true.into(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let tx3 = run_peer_manager::<T>(
         A::make_transport(),
@@ -888,3 +1258,571 @@ async fn discovered_node_channel() {
     discovered_node::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>(2)
         .await;
 }
+
+// A node with the peer discovery disabled only connects to its preconfigured peers
+async fn preconfigured_peers_only<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let chain_config = Arc::new(config::create_mainnet());
+
+    let time_getter = P2pTestTimeGetter::new();
+
+    let mut bind_addresses = Vec::new();
+    let mut txs = Vec::new();
+    for _ in 0..2 {
+        let p2p_config = Arc::new(P2pConfig {
+            allow_discover_private_ips: true.into(),
+            ..Default::default()
+        });
+        let tx = run_peer_manager::<T>(
+            A::make_transport(),
+            A::make_address(),
+            Arc::clone(&chain_config),
+            p2p_config,
+            time_getter.get_time_getter(),
+        )
+        .await;
+
+        let (rtx, rrx) = oneshot_nofail::channel();
+        tx.send(PeerManagerEvent::GetBindAddresses(rtx)).unwrap();
+        let addresses = timeout(Duration::from_secs(1), rrx).await.unwrap().unwrap();
+        assert_eq!(addresses.len(), 1);
+        bind_addresses.push(addresses[0].clone());
+        txs.push(tx);
+    }
+
+    // The second peer is known, but it must not be dialed
+    let p2p_config = Arc::new(P2pConfig {
+        added_nodes: vec![bind_addresses[1].clone()],
+        preconfigured_peers: vec![bind_addresses[0].clone()],
+        allow_discover_private_ips: true.into(),
+        disable_peer_discovery: true.into(),
+        ..Default::default()
+    });
+    let tx = run_peer_manager::<T>(
+        A::make_transport(),
+        A::make_address(),
+        Arc::clone(&chain_config),
+        p2p_config,
+        time_getter.get_time_getter(),
+    )
+    .await;
+
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        time_getter.advance_time(Duration::from_millis(1000)).await;
+
+        let connected_peers = get_connected_peers(&tx).await;
+        assert!(
+            connected_peers.len() <= 1,
+            "Unexpected peers: {connected_peers:?}"
+        );
+        assert!(get_connected_peers(&txs[1]).await.is_empty());
+    }
+
+    let connected_peers = get_connected_peers(&tx).await;
+    assert_eq!(connected_peers.len(), 1);
+    assert_eq!(connected_peers[0].address, bind_addresses[0]);
+    assert!(!connected_peers[0].inbound);
+}
+
+#[tokio::test]
+async fn preconfigured_peers_only_tcp() {
+    preconfigured_peers_only::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn preconfigured_peers_only_noise() {
+    preconfigured_peers_only::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
+
+#[tokio::test]
+async fn preconfigured_peers_only_channel() {
+    preconfigured_peers_only::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+// The connection to a persistent peer is restored by the heartbeat after it's lost
+async fn persistent_peer_reconnect<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager::<T>(A::make_transport(), A::make_address(), Arc::clone(&config)).await;
+    let mut pm2 = make_peer_manager::<T>(A::make_transport(), A::make_address(), config).await;
+    let address = pm2.peer_connectivity_handle.local_addresses()[0].clone();
+    tokio::spawn(async move {
+        pm2.run().await.unwrap();
+    });
+
+    let (tx, rx) = oneshot_nofail::channel();
+    pm1.handle_control_event(PeerManagerEvent::AddNode(address.clone(), tx))
+        .unwrap();
+    assert_eq!(rx.await.unwrap(), Ok(()));
+
+    pm1.heartbeat().unwrap();
+    timeout(Duration::from_secs(5), async {
+        while !pm1.peerdb.is_address_connected(&address) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    // Kill the connection
+    let peer_id = *pm1.peers.iter().find(|(_, peer)| peer.address == address).unwrap().0;
+    pm1.disconnect(peer_id, None).unwrap();
+    timeout(Duration::from_secs(5), async {
+        while pm1.peerdb.is_address_connected(&address) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    pm1.heartbeat().unwrap();
+    timeout(Duration::from_secs(5), async {
+        while !pm1.peerdb.is_address_connected(&address) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_tcp() {
+    persistent_peer_reconnect::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_channel() {
+    persistent_peer_reconnect::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_noise() {
+    persistent_peer_reconnect::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
+
+// The persistent peer is redialed as soon as its connection is closed, without waiting for
+// the heartbeat
+async fn persistent_peer_reconnect_on_close<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager::<T>(A::make_transport(), A::make_address(), Arc::clone(&config)).await;
+    let mut pm2 = make_peer_manager::<T>(A::make_transport(), A::make_address(), config).await;
+    let address = pm2.peer_connectivity_handle.local_addresses()[0].clone();
+    tokio::spawn(async move {
+        pm2.run().await.unwrap();
+    });
+
+    let (tx, rx) = oneshot_nofail::channel();
+    pm1.handle_control_event(PeerManagerEvent::AddNode(address.clone(), tx))
+        .unwrap();
+    assert_eq!(rx.await.unwrap(), Ok(()));
+
+    pm1.heartbeat().unwrap();
+    timeout(Duration::from_secs(5), async {
+        while !pm1.peerdb.is_address_connected(&address) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    // Kill the connection, the reconnection attempt is made right away
+    let peer_id = *pm1.peers.iter().find(|(_, peer)| peer.address == address).unwrap().0;
+    pm1.disconnect(peer_id, None).unwrap();
+    timeout(Duration::from_secs(5), async {
+        while pm1.peers.contains_key(&peer_id) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+    assert!(pm1.pending_connects.contains_key(&address));
+
+    // The heartbeat is never called, but the connection is restored quickly
+    timeout(Duration::from_secs(5), async {
+        while !pm1.peerdb.is_address_connected(&address) {
+            let event = pm1.peer_connectivity_handle.poll_next().await;
+            pm1.handle_connectivity_event_result(event).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_on_close_tcp() {
+    persistent_peer_reconnect_on_close::<
+        TestTransportTcp,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_on_close_channel() {
+    persistent_peer_reconnect_on_close::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn persistent_peer_reconnect_on_close_noise() {
+    persistent_peer_reconnect_on_close::<
+        TestTransportNoise,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// The heartbeat doesn't have more outbound dials in flight than allowed
+#[tokio::test]
+async fn max_concurrent_dials() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        max_concurrent_dials: 2.into(),
+        ..Default::default()
+    });
+    let time_getter = P2pTestTimeGetter::new();
+
+    let (mut peer_manager, mut backend) = make_mocked_peer_manager(
+        chain_config,
+        Arc::clone(&p2p_config),
+        vec![],
+        time_getter.get_time_getter(),
+    );
+
+    for i in 1..=20 {
+        let address: SocketAddr = format!("{i}.{i}.{i}.{i}:3031").parse().unwrap();
+        peer_manager
+            .peerdb
+            .peer_discovered(&address, AddressSource::AddressExchange)
+            .unwrap();
+    }
+    let mut dialed_addresses = || {
+        let mut addresses = Vec::new();
+        while let Ok(command) = backend.cmd_rx.try_recv() {
+            match command {
+                Command::Connect { address } => addresses.push(address),
+                command => panic!("unexpected command: {command:?}"),
+            }
+        }
+        addresses
+    };
+
+    peer_manager.heartbeat().unwrap();
+    let addresses = dialed_addresses();
+    assert_eq!(addresses.len(), *p2p_config.max_concurrent_dials);
+    assert_eq!(
+        peer_manager.pending_connects.len(),
+        *p2p_config.max_concurrent_dials
+    );
+
+    // Nothing new is dialed while the earlier dials are in flight
+    peer_manager.heartbeat().unwrap();
+    assert!(dialed_addresses().is_empty());
+
+    // More dials are made as the earlier ones are resolved
+    for address in addresses {
+        peer_manager
+            .handle_outbound_error(
+                address,
+                P2pError::DialError(DialError::ConnectionRefusedOrTimedOut),
+            )
+            .unwrap();
+        peer_manager.heartbeat().unwrap();
+        assert!(dialed_addresses().len() <= 1);
+        assert!(peer_manager.pending_connects.len() <= *p2p_config.max_concurrent_dials);
+    }
+}
+
+// Extra short-lived connections are made if no new tip is received for too long
+#[tokio::test]
+async fn stale_tip_extra_connections() {
+    tokio::time::pause();
+    let stale_tip_timeout = Duration::from_secs(60);
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        // Don't make the normal outbound connections
+        max_active_connections: 0.into(),
+        stale_tip_timeout: stale_tip_timeout.into(),
+        ..Default::default()
+    });
+    let time_getter = P2pTestTimeGetter::new();
+
+    let (mut peer_manager, mut backend) = make_mocked_peer_manager(
+        Arc::clone(&chain_config),
+        Arc::clone(&p2p_config),
+        vec![],
+        time_getter.get_time_getter(),
+    );
+
+    for i in 1..=20 {
+        let address: SocketAddr = format!("{i}.{i}.{i}.{i}:3031").parse().unwrap();
+        peer_manager
+            .peerdb
+            .peer_discovered(&address, AddressSource::AddressExchange)
+            .unwrap();
+    }
+    let mut take_commands = || {
+        let mut commands = Vec::new();
+        while let Ok(command) = backend.cmd_rx.try_recv() {
+            commands.push(command);
+        }
+        commands
+    };
+
+    peer_manager.heartbeat().unwrap();
+    assert!(take_commands().is_empty());
+
+    // A new tip restarts the timeout
+    tokio::time::advance(stale_tip_timeout / 2).await;
+    peer_manager.new_tip_received(false, BlockHeight::new(1));
+    tokio::time::advance(stale_tip_timeout * 3 / 4).await;
+    peer_manager.heartbeat().unwrap();
+    assert!(take_commands().is_empty());
+
+    // The timeout passes without a new tip
+    tokio::time::advance(stale_tip_timeout / 2).await;
+    peer_manager.heartbeat().unwrap();
+    let addresses = take_commands()
+        .into_iter()
+        .map(|command| match command {
+            Command::Connect { address } => address,
+            command => panic!("unexpected command: {command:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(addresses.len(), STALE_TIP_EXTRA_CONNECTIONS);
+
+    // No more connections are made until the timeout passes again
+    peer_manager.heartbeat().unwrap();
+    assert!(take_commands().is_empty());
+
+    // The extra connection is closed after its lifetime
+    let peer_id = PeerId::new();
+    peer_manager
+        .accept_connection(
+            addresses[0],
+            Role::Outbound,
+            PeerInfo {
+                peer_id,
+                network: *chain_config.magic_bytes(),
+                version: common::primitives::semver::SemVer::new(0, 1, 0),
+                agent: None,
+                subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions]
+                    .into_iter()
+                    .collect(),
+                best_block: None,
+                node_kind: NodeKind::Full,
+            },
+            None,
+        )
+        .unwrap();
+    take_commands();
+    tokio::time::advance(STALE_TIP_CONNECTION_LIFETIME).await;
+    peer_manager.heartbeat().unwrap();
+    let disconnected = take_commands()
+        .into_iter()
+        .filter_map(|command| match command {
+            Command::Disconnect { peer_id } => Some(peer_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(disconnected, vec![peer_id]);
+}
+
+// Failed reconnections of a persistent peer are retried with a growing delay
+#[tokio::test]
+async fn persistent_peer_reconnect_backoff() {
+    let config = Arc::new(config::create_mainnet());
+    let mut pm = make_peer_manager::<DefaultNetworkingService<TcpTransportSocket>>(
+        TestTransportTcp::make_transport(),
+        TestTransportTcp::make_address(),
+        config,
+    )
+    .await;
+    let address: std::net::SocketAddr = "10.0.0.1:3031".parse().unwrap();
+    pm.add_node(address).unwrap();
+
+    let mut previous_delay = Duration::ZERO;
+    for failure_count in 1..=3 {
+        let started_at = tokio::time::Instant::now();
+        pm.handle_outbound_error(
+            address,
+            P2pError::DialError(DialError::ConnectionRefusedOrTimedOut),
+        )
+        .unwrap();
+
+        let reconnect = &pm.persistent_reconnects[&address];
+        assert_eq!(reconnect.failure_count, failure_count);
+        let delay = reconnect.next_attempt_at.unwrap() - started_at;
+        assert!(delay > previous_delay);
+        previous_delay = delay;
+
+        // The address isn't dialed again until the delay expires
+        pm.heartbeat().unwrap();
+        assert!(!pm.pending_connects.contains_key(&address));
+    }
+}
+
+// The due reconnection attempts of the persistent peers that can't be dialed right now are
+// rescheduled, so the event loop doesn't wake up again immediately
+#[tokio::test]
+async fn persistent_peer_skipped_reconnect() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig::default());
+    let (mut pm, _backend) = make_mocked_peer_manager(
+        chain_config,
+        p2p_config,
+        vec![],
+        P2pTestTimeGetter::new().get_time_getter(),
+    );
+    let address: SocketAddr = "10.0.0.1:3031".parse().unwrap();
+    pm.add_node(address).unwrap();
+    let make_due = |pm: &mut MockedPeerManager| {
+        pm.persistent_reconnects.insert(
+            address,
+            PersistentReconnect {
+                failure_count: 1,
+                next_attempt_at: Some(tokio::time::Instant::now()),
+            },
+        );
+    };
+
+    // The dial is in progress, its result schedules the next attempt
+    make_due(&mut pm);
+    pm.pending_connects.insert(address, None);
+    pm.reconnect_persistent_peers().unwrap();
+    assert_eq!(pm.next_persistent_reconnect_at(), None);
+    pm.pending_connects.remove(&address);
+
+    // The banned address is checked again later
+    make_due(&mut pm);
+    pm.peerdb.ban_peer(&address).unwrap();
+    let now = tokio::time::Instant::now();
+    pm.reconnect_persistent_peers().unwrap();
+    assert!(pm.next_persistent_reconnect_at().unwrap() > now);
+    assert!(!pm.pending_connects.contains_key(&address));
+}
+
+// The reserved outbound slots are only filled with the address groups that aren't represented
+// among the outbound peers
+#[tokio::test]
+async fn reserved_diverse_outbound_slots() {
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        reserved_diverse_outbound_slots: 2.into(),
+        max_concurrent_dials: 2.into(),
+        ..Default::default()
+    });
+    let time_getter = P2pTestTimeGetter::new();
+
+    let (mut peer_manager, mut backend) = make_mocked_peer_manager(
+        Arc::clone(&chain_config),
+        Arc::clone(&p2p_config),
+        vec![],
+        time_getter.get_time_getter(),
+    );
+
+    // Most of the addresses are from the same group
+    let majority_group = address_group(&"1.1.0.0:3031".parse::<SocketAddr>().unwrap());
+    for i in 1..=50 {
+        let address: SocketAddr = format!("1.1.{i}.{i}:3031").parse().unwrap();
+        peer_manager
+            .peerdb
+            .peer_discovered(&address, AddressSource::AddressExchange)
+            .unwrap();
+    }
+    let minority_addresses = ["2.2.0.1:3031", "3.3.0.1:3031", "4.4.0.1:3031"]
+        .into_iter()
+        .map(|address| address.parse::<SocketAddr>().unwrap())
+        .collect::<Vec<_>>();
+    for address in minority_addresses.iter() {
+        peer_manager
+            .peerdb
+            .peer_discovered(address, AddressSource::AddressExchange)
+            .unwrap();
+    }
+    let make_peer_info = |peer_id| PeerInfo {
+        peer_id,
+        network: *chain_config.magic_bytes(),
+        version: common::primitives::semver::SemVer::new(0, 1, 0),
+        agent: None,
+        subscriptions: [PubSubTopic::Blocks, PubSubTopic::Transactions].into_iter().collect(),
+        best_block: None,
+        node_kind: NodeKind::Full,
+    };
+
+    // The node is already connected to the majority group
+    for i in 1..=3 {
+        let address: SocketAddr = format!("1.1.{i}.{i}:3031").parse().unwrap();
+        peer_manager
+            .accept_connection(address, Role::Outbound, make_peer_info(PeerId::new()), None)
+            .unwrap();
+    }
+    let mut dialed_addresses = || {
+        let mut addresses = Vec::new();
+        while let Ok(command) = backend.cmd_rx.try_recv() {
+            if let Command::Connect { address } = command {
+                addresses.push(address);
+            }
+        }
+        addresses
+    };
+    dialed_addresses();
+
+    // The reserved slots take all the allowed dials and go to different minority groups
+    peer_manager.heartbeat().unwrap();
+    let addresses = dialed_addresses();
+    assert_eq!(addresses.len(), *p2p_config.reserved_diverse_outbound_slots);
+    let groups = addresses.iter().map(address_group).collect::<BTreeSet<_>>();
+    assert_eq!(groups.len(), addresses.len());
+    assert!(!groups.contains(&majority_group));
+    assert!(addresses.iter().all(|address| minority_addresses.contains(address)));
+
+    // The connected peers keep the slots occupied
+    for address in addresses {
+        peer_manager
+            .accept_connection(address, Role::Outbound, make_peer_info(PeerId::new()), None)
+            .unwrap();
+    }
+    assert_eq!(
+        peer_manager.peers.values().filter(|peer| peer.diverse_slot).count(),
+        *p2p_config.reserved_diverse_outbound_slots
+    );
+    peer_manager.heartbeat().unwrap();
+    assert!(peer_manager.diverse_slot_dials.is_empty());
+}
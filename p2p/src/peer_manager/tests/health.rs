@@ -0,0 +1,201 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use common::{
+    chain::config,
+    primitives::{semver::SemVer, BlockHeight},
+    Uint256,
+};
+
+use crate::{
+    config::P2pConfig,
+    interface::types::{HealthState, SyncProgress},
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            types::PeerId,
+            DefaultNetworkingService,
+        },
+        types::{BestBlock, NodeKind, PeerInfo, PubSubTopic, Role},
+        ConnectivityService, NetworkingService,
+    },
+    peer_manager::tests::{make_peer_manager, make_peer_manager_custom},
+    testing_utils::{
+        connect_services, RandomAddressMaker, TestChannelAddressMaker, TestTcpAddressMaker,
+        TestTransportChannel, TestTransportMaker, TestTransportNoise, TestTransportTcp,
+    },
+};
+
+async fn health<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    let chain_config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        health_min_peers: 1.into(),
+        ..Default::default()
+    });
+
+    let mut pm1 = make_peer_manager::<T>(
+        A::make_transport(),
+        A::make_address(),
+        Arc::clone(&chain_config),
+    )
+    .await;
+    let (mut pm2, _tx) = make_peer_manager_custom::<T>(
+        A::make_transport(),
+        A::make_address(),
+        chain_config,
+        p2p_config,
+        Default::default(),
+    )
+    .await;
+
+    let health = pm2.health();
+    assert_eq!(health.state, HealthState::Unhealthy);
+    assert_eq!(health.active_peers, 0);
+    assert_eq!(health.min_peers, 1);
+    assert!(health.initial_block_download);
+    assert_eq!(health.seconds_since_last_block, None);
+    assert!(!health.inbound_reachable);
+
+    let (address, peer_info, _) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    pm2.accept_inbound_connection(address, peer_info, None).unwrap();
+    // No blocks have been received yet
+    assert_eq!(pm2.health().state, HealthState::Unhealthy);
+
+    pm2.new_tip_received(false, BlockHeight::new(1));
+    let health = pm2.health();
+    assert_eq!(health.state, HealthState::Healthy);
+    assert_eq!(health.active_peers, 1);
+    assert!(!health.initial_block_download);
+    assert_eq!(health.seconds_since_last_block, Some(0));
+    assert!(health.inbound_reachable);
+}
+
+#[tokio::test]
+async fn health_tcp() {
+    health::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>().await;
+}
+
+#[tokio::test]
+async fn health_channel() {
+    health::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>().await;
+}
+
+#[tokio::test]
+async fn health_noise() {
+    health::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>().await;
+}
+
+async fn sync_progress<A, S, B>(peer_ids: [S::PeerId; 2])
+where
+    A: TestTransportMaker<Transport = S::Transport, Address = S::Address>,
+    S: NetworkingService + 'static + std::fmt::Debug,
+    S::ConnectivityHandle: ConnectivityService<S>,
+    B: RandomAddressMaker<Address = S::Address>,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm =
+        make_peer_manager::<S>(A::make_transport(), A::make_address(), Arc::clone(&config)).await;
+
+    // Nothing is known yet
+    assert_eq!(
+        pm.sync_progress(),
+        SyncProgress {
+            current_height: None,
+            best_known_peer_height: None,
+            percent: None,
+        }
+    );
+
+    for (peer_id, height) in peer_ids.into_iter().zip([400, 1000]) {
+        let res = pm.accept_connection(
+            B::new(),
+            Role::Outbound,
+            PeerInfo::<S::PeerId> {
+                peer_id,
+                network: *config.magic_bytes(),
+                version: SemVer::new(0, 1, 0),
+                agent: None,
+                subscriptions: [PubSubTopic::Blocks].into_iter().collect(),
+                best_block: Some(BestBlock {
+                    id: config.genesis_block_id(),
+                    height: BlockHeight::new(height),
+                    chain_trust: Uint256::from_u64(height),
+                }),
+                node_kind: NodeKind::Full,
+            },
+            None,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    // The local height is unknown until the first new tip
+    assert_eq!(pm.sync_progress().best_known_peer_height, Some(1000));
+    assert_eq!(pm.sync_progress().percent, None);
+
+    pm.new_tip_received(true, BlockHeight::new(250));
+    assert_eq!(
+        pm.sync_progress(),
+        SyncProgress {
+            current_height: Some(250),
+            best_known_peer_height: Some(1000),
+            percent: Some(25),
+        }
+    );
+
+    // The local node is ahead of its peers
+    pm.new_tip_received(false, BlockHeight::new(1200));
+    assert_eq!(pm.sync_progress().percent, Some(100));
+}
+
+#[tokio::test]
+async fn sync_progress_tcp() {
+    sync_progress::<
+        TestTransportTcp,
+        DefaultNetworkingService<TcpTransportSocket>,
+        TestTcpAddressMaker,
+    >([PeerId::new(), PeerId::new()])
+    .await;
+}
+
+#[tokio::test]
+async fn sync_progress_channel() {
+    sync_progress::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+        TestChannelAddressMaker,
+    >([PeerId::new(), PeerId::new()])
+    .await;
+}
+
+#[tokio::test]
+async fn sync_progress_noise() {
+    sync_progress::<
+        TestTransportNoise,
+        DefaultNetworkingService<NoiseTcpTransport>,
+        TestTcpAddressMaker,
+    >([PeerId::new(), PeerId::new()])
+    .await;
+}
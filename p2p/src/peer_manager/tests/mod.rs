@@ -13,26 +13,97 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod addresses;
 mod ban;
 mod connections;
+mod health;
 mod ping;
 
-use std::{sync::Arc, time::Duration};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
-use common::time_getter::TimeGetter;
-use tokio::{sync::mpsc::UnboundedSender, time::timeout};
+use common::{chain::ChainConfig, time_getter::TimeGetter};
+use storage::inmemory::InMemory;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedSender},
+        watch,
+    },
+    time::timeout,
+};
 
 use crate::{
-    event::PeerManagerEvent,
+    event::{PeerManagerEvent, SyncControlEvent},
     interface::types::ConnectedPeer,
-    net::{ConnectivityService, NetworkingService},
+    net::{
+        default_backend::{
+            transport::TcpTransportSocket,
+            types::{Command, ConnectivityEvent, PeerId},
+            ConnectivityHandle, DefaultNetworkingService,
+        },
+        ConnectivityService, NetworkingService,
+    },
     peer_manager::PeerManager,
     testing_utils::peerdb_inmemory_store,
     utils::oneshot_nofail,
     P2pConfig,
 };
 
-use super::peerdb::storage::PeerDbStorage;
+use super::peerdb::{storage::PeerDbStorage, storage_impl::PeerDbStorageImpl};
+
+type TestNetworkingService = DefaultNetworkingService<TcpTransportSocket>;
+
+/// Peer manager created by [`make_mocked_peer_manager`]
+type MockedPeerManager = PeerManager<TestNetworkingService, PeerDbStorageImpl<InMemory>>;
+
+/// The channels that play the part of the backend for [`make_mocked_peer_manager`]
+struct MockedBackend {
+    cmd_rx: mpsc::UnboundedReceiver<Command<TcpTransportSocket>>,
+    conn_tx: mpsc::UnboundedSender<ConnectivityEvent<TcpTransportSocket>>,
+    peer_tx: mpsc::UnboundedSender<PeerManagerEvent<TestNetworkingService>>,
+    _sync_rx: mpsc::UnboundedReceiver<SyncControlEvent<TestNetworkingService>>,
+}
+
+/// Makes a peer manager without a networking backend, the test sends the connectivity events and
+/// receives the backend commands through the returned channels
+fn make_mocked_peer_manager(
+    chain_config: Arc<ChainConfig>,
+    p2p_config: Arc<P2pConfig>,
+    local_addresses: Vec<SocketAddr>,
+    time_getter: TimeGetter,
+) -> (MockedPeerManager, MockedBackend) {
+    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+    let (conn_tx, conn_rx) = mpsc::unbounded_channel();
+    let (peer_tx, peer_rx) = mpsc::unbounded_channel();
+    let (sync_tx, sync_rx) = mpsc::unbounded_channel();
+    let connectivity_handle = ConnectivityHandle::<TestNetworkingService, TcpTransportSocket>::new(
+        local_addresses,
+        PeerId::new(),
+        cmd_tx,
+        conn_rx,
+        Default::default(),
+        Default::default(),
+        watch::channel(0).1,
+    );
+
+    let peer_manager = PeerManager::new(
+        chain_config,
+        p2p_config,
+        connectivity_handle,
+        peer_rx,
+        sync_tx,
+        time_getter,
+        peerdb_inmemory_store(),
+    )
+    .unwrap();
+
+    let backend = MockedBackend {
+        cmd_rx,
+        conn_tx,
+        peer_tx,
+        _sync_rx: sync_rx,
+    };
+    (peer_manager, backend)
+}
 
 async fn make_peer_manager_custom<T>(
     transport: T::Transport,
@@ -75,16 +146,16 @@ where
     (peer_manager, tx)
 }
 
-async fn make_peer_manager<T>(
+async fn make_peer_manager_with_config<T>(
     transport: T::Transport,
     addr: T::Address,
     chain_config: Arc<common::chain::ChainConfig>,
+    p2p_config: Arc<P2pConfig>,
 ) -> PeerManager<T, impl PeerDbStorage>
 where
     T: NetworkingService + 'static,
     T::ConnectivityHandle: ConnectivityService<T>,
 {
-    let p2p_config = Arc::new(P2pConfig::default());
     let (peer_manager, _tx) = make_peer_manager_custom::<T>(
         transport,
         addr,
@@ -96,6 +167,24 @@ where
     peer_manager
 }
 
+async fn make_peer_manager<T>(
+    transport: T::Transport,
+    addr: T::Address,
+    chain_config: Arc<common::chain::ChainConfig>,
+) -> PeerManager<T, impl PeerDbStorage>
+where
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+{
+    make_peer_manager_with_config::<T>(
+        transport,
+        addr,
+        chain_config,
+        Arc::new(P2pConfig::default()),
+    )
+    .await
+}
+
 async fn run_peer_manager<T>(
     transport: T::Transport,
     addr: T::Address,
@@ -13,7 +13,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::{mpsc, watch};
 
 use common::chain::config;
 
@@ -24,12 +26,15 @@ use crate::{
     net::{
         default_backend::{
             transport::TcpTransportSocket,
-            types::{Command, ConnectivityEvent, PeerId},
+            types::{Command, ConnectivityEvent, PeerId, RequestId},
             ConnectivityHandle, DefaultNetworkingService,
         },
-        types::PeerInfo,
+        types::{ConnectionTiming, NodeKind, PeerInfo},
+    },
+    peer_manager::{
+        tests::{get_connected_peers, make_mocked_peer_manager, MockedBackend},
+        PeerManager,
     },
-    peer_manager::PeerManager,
     testing_utils::{peerdb_inmemory_store, P2pTestTimeGetter},
 };
 
@@ -50,8 +55,12 @@ async fn ping_timeout() {
     let (sync_tx, _sync_rx) = tokio::sync::mpsc::unbounded_channel();
     let connectivity_handle = ConnectivityHandle::<TestNetworkingService, TcpTransportSocket>::new(
         vec![],
+        PeerId::new(),
         cmd_tx,
         conn_rx,
+        Default::default(),
+        Default::default(),
+        watch::channel(0).1,
     );
 
     let mut peer_manager = PeerManager::new(
@@ -79,8 +88,15 @@ async fn ping_timeout() {
                 version: *chain_config.version(),
                 agent: None,
                 subscriptions: Default::default(),
+                best_block: None,
+                node_kind: NodeKind::Full,
             },
             receiver_address: None,
+            timing: ConnectionTiming {
+                tcp_connect: None,
+                noise_handshake: None,
+                handshake: Duration::ZERO,
+            },
         })
         .unwrap();
 
@@ -130,3 +146,162 @@ async fn ping_timeout() {
         _ => panic!("unexpected event: {event:?}"),
     }
 }
+
+/// Starts the peer manager with a mocked backend and connects one inbound peer
+fn start_peer_manager(p2p_config: P2pConfig) -> (MockedBackend, P2pTestTimeGetter) {
+    let chain_config = Arc::new(config::create_mainnet());
+    let time_getter = P2pTestTimeGetter::new();
+    let (mut peer_manager, backend) = make_mocked_peer_manager(
+        Arc::clone(&chain_config),
+        Arc::new(p2p_config),
+        vec![],
+        time_getter.get_time_getter(),
+    );
+
+    tokio::spawn(async move {
+        let _ = peer_manager.run().await;
+    });
+
+    backend
+        .conn_tx
+        .send(ConnectivityEvent::InboundAccepted {
+            address: "123.123.123.123:12345".parse().unwrap(),
+            peer_info: PeerInfo {
+                peer_id: PeerId::new(),
+                network: *chain_config.magic_bytes(),
+                version: *chain_config.version(),
+                agent: None,
+                subscriptions: Default::default(),
+                best_block: None,
+                node_kind: NodeKind::Full,
+            },
+            receiver_address: None,
+            timing: ConnectionTiming {
+                tcp_connect: None,
+                noise_handshake: None,
+                handshake: Duration::ZERO,
+            },
+        })
+        .unwrap();
+
+    (backend, time_getter)
+}
+
+async fn expect_ping_request(
+    cmd_rx: &mut mpsc::UnboundedReceiver<Command<TcpTransportSocket>>,
+) -> (PeerId, RequestId, u64) {
+    let event = cmd_rx.recv().await.unwrap();
+    match event {
+        Command::SendRequest {
+            peer_id,
+            request_id,
+            message: Request::PingRequest(PingRequest { nonce }),
+        } => (peer_id, request_id, nonce),
+        _ => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn ping_rtt() {
+    let p2p_config = P2pConfig {
+        ping_check_period: Duration::from_secs(1).into(),
+        ping_timeout: Duration::from_secs(5).into(),
+        ..Default::default()
+    };
+    let (mut backend, time_getter) = start_peer_manager(p2p_config);
+
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    let (peer_id, request_id, nonce) = expect_ping_request(&mut backend.cmd_rx).await;
+
+    let peers = get_connected_peers(&backend.peer_tx).await;
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].ping_last, None);
+    assert_eq!(peers[0].ping_min, None);
+
+    time_getter.advance_time(Duration::from_millis(300)).await;
+    backend
+        .conn_tx
+        .send(ConnectivityEvent::Response {
+            peer_id,
+            request_id,
+            response: PeerManagerResponse::PingResponse(PingResponse { nonce }),
+        })
+        .unwrap();
+
+    // Connectivity and peer manager events are handled concurrently, wait until the response is processed
+    let peer = loop {
+        let mut peers = get_connected_peers(&backend.peer_tx).await;
+        if peers[0].ping_last.is_some() {
+            break peers.remove(0);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert!(peer.ping_last.unwrap() >= 300);
+    assert_eq!(peer.ping_min, peer.ping_last);
+}
+
+#[tokio::test]
+async fn ping_max_retries() {
+    let p2p_config = P2pConfig {
+        ping_check_period: Duration::from_secs(1).into(),
+        ping_timeout: Duration::from_secs(1).into(),
+        ping_max_retries: 2.into(),
+        ..Default::default()
+    };
+    let (mut backend, time_getter) = start_peer_manager(p2p_config);
+
+    // The first ping request and two retries
+    for _ in 0..3 {
+        time_getter.advance_time(Duration::from_secs(1)).await;
+        expect_ping_request(&mut backend.cmd_rx).await;
+    }
+
+    // No response after the last retry, the peer should be disconnected
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    let event = backend.cmd_rx.recv().await.unwrap();
+    match event {
+        Command::Disconnect { peer_id } => {
+            backend.conn_tx.send(ConnectivityEvent::ConnectionClosed { peer_id }).unwrap();
+        }
+        _ => panic!("unexpected event: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn stall_grace_period() {
+    let p2p_config = P2pConfig {
+        ping_check_period: Duration::from_secs(1).into(),
+        ping_timeout: Duration::from_secs(1).into(),
+        stall_grace_period: Duration::from_secs(5).into(),
+        ..Default::default()
+    };
+    let (mut backend, time_getter) = start_peer_manager(p2p_config);
+
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    let (peer_id, request_id, nonce) = expect_ping_request(&mut backend.cmd_rx).await;
+
+    // The ping times out, but the response is sent within the grace period
+    time_getter.advance_time(Duration::from_secs(3)).await;
+    backend
+        .conn_tx
+        .send(ConnectivityEvent::Response {
+            peer_id,
+            request_id,
+            response: PeerManagerResponse::PingResponse(PingResponse { nonce }),
+        })
+        .unwrap();
+
+    // The peer is not disconnected and is pinged again
+    time_getter.advance_time(Duration::from_secs(1)).await;
+    expect_ping_request(&mut backend.cmd_rx).await;
+
+    // No response at all, the peer is disconnected after the grace period
+    time_getter.advance_time(Duration::from_secs(6)).await;
+    let event = backend.cmd_rx.recv().await.unwrap();
+    match event {
+        Command::Disconnect { peer_id } => {
+            backend.conn_tx.send(ConnectivityEvent::ConnectionClosed { peer_id }).unwrap();
+        }
+        _ => panic!("unexpected event: {event:?}"),
+    }
+}
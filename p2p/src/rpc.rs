@@ -13,7 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{error::P2pError, interface::types::ConnectedPeer};
+use crate::{
+    error::P2pError,
+    interface::types::{ConnectedPeer, HealthStatus, KnownAddress, SyncProgress},
+};
 use subsystem::subsystem::CallError;
 
 #[rpc::rpc(server, namespace = "p2p")]
@@ -37,6 +40,18 @@ trait P2pRpc {
     /// Get details of connected peers
     #[method(name = "get_connected_peers")]
     async fn get_connected_peers(&self) -> rpc::Result<Vec<ConnectedPeer>>;
+
+    /// Get addresses known to the peer database
+    #[method(name = "get_known_addresses")]
+    async fn get_known_addresses(&self) -> rpc::Result<Vec<KnownAddress>>;
+
+    /// Get a summary of the sync and connectivity state of the node
+    #[method(name = "get_health")]
+    async fn get_health(&self) -> rpc::Result<HealthStatus>;
+
+    /// Get the sync progress of the node compared to the connected peers
+    #[method(name = "get_sync_progress")]
+    async fn get_sync_progress(&self) -> rpc::Result<SyncProgress>;
 }
 
 #[async_trait::async_trait]
@@ -65,6 +80,21 @@ impl P2pRpcServer for super::P2pHandle {
         let res = self.call_async(|this| Box::pin(this.get_connected_peers())).await;
         handle_error(res)
     }
+
+    async fn get_known_addresses(&self) -> rpc::Result<Vec<KnownAddress>> {
+        let res = self.call_async(|this| Box::pin(this.get_known_addresses())).await;
+        handle_error(res)
+    }
+
+    async fn get_health(&self) -> rpc::Result<HealthStatus> {
+        let res = self.call_async(|this| Box::pin(this.get_health())).await;
+        handle_error(res)
+    }
+
+    async fn get_sync_progress(&self) -> rpc::Result<SyncProgress> {
+        let res = self.call_async(|this| Box::pin(this.get_sync_progress())).await;
+        handle_error(res)
+    }
 }
 
 fn handle_error<T>(e: Result<Result<T, P2pError>, CallError>) -> rpc::Result<T> {
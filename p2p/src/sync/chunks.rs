@@ -0,0 +1,305 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splitting of the block responses that don't fit into one message
+//!
+//! The responder sends such responses as a sequence of [`BlocksChunk`] messages with the same
+//! request ID, the requester collects the chunks and processes the blocks after the last one.
+
+use std::{collections::HashMap, hash::Hash};
+
+use common::chain::Block;
+use serialization::Encode;
+
+use crate::{
+    error::{P2pError, ProtocolError},
+    message::BlocksChunk,
+};
+
+/// Splits the blocks into chunks, so the total encoded size of the blocks in a chunk doesn't
+/// exceed `max_size`.
+///
+/// A block that is larger than `max_size` is sent in a chunk of its own.
+pub fn split_into_chunks(
+    magic_bytes: [u8; 4],
+    blocks: Vec<Block>,
+    max_size: usize,
+) -> Vec<BlocksChunk> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut current_size = 0;
+
+    for block in blocks {
+        let block_size = block.encoded_size();
+        if !current.is_empty() && current_size + block_size > max_size {
+            chunks.push(std::mem::take(&mut current));
+            current_size = 0;
+        }
+        current_size += block_size;
+        current.push(block);
+    }
+    chunks.push(current);
+
+    let chunk_count = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(seq, blocks)| BlocksChunk {
+            magic_bytes,
+            seq: seq as u32,
+            last: seq + 1 == chunk_count,
+            blocks,
+        })
+        .collect()
+}
+
+struct PendingResponse<P> {
+    peer_id: P,
+    next_seq: u32,
+    size: usize,
+    blocks: Vec<Block>,
+}
+
+/// Collects the chunks of block responses, keyed by the request ID
+///
+/// Only one response per peer is buffered and its total size is limited, the caller is
+/// expected to check that the request ID belongs to a pending block request.
+pub struct ChunkAssembler<P, R> {
+    pending: HashMap<R, PendingResponse<P>>,
+    max_response_size: usize,
+}
+
+impl<P: Eq, R: Eq + Hash> ChunkAssembler<P, R> {
+    pub fn new(max_response_size: usize) -> Self {
+        Self {
+            pending: HashMap::new(),
+            max_response_size,
+        }
+    }
+
+    /// Adds the received chunk and returns all blocks of the response once the last chunk is
+    /// received.
+    ///
+    /// Chunks must be received in order and all chunks except the last one must be non-empty,
+    /// a missing or out of order chunk or a response larger than the limit discards the response.
+    pub fn push(
+        &mut self,
+        peer_id: P,
+        request_id: R,
+        chunk: BlocksChunk,
+    ) -> crate::Result<Option<Vec<Block>>> {
+        let mut pending = match self.pending.remove(&request_id) {
+            Some(pending) => pending,
+            None => {
+                // A new response replaces the incomplete one from the same peer
+                self.pending.retain(|_, pending| pending.peer_id != peer_id);
+                PendingResponse {
+                    peer_id,
+                    next_seq: 0,
+                    size: 0,
+                    blocks: Vec::new(),
+                }
+            }
+        };
+
+        if chunk.seq != pending.next_seq {
+            return Err(P2pError::ProtocolError(
+                ProtocolError::UnexpectedBlocksChunk(pending.next_seq, chunk.seq),
+            ));
+        }
+        if chunk.blocks.is_empty() && !chunk.last {
+            return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
+        }
+
+        pending.size = chunk.blocks.iter().fold(pending.size, |size, block| {
+            size.saturating_add(block_size(block))
+        });
+        if pending.size > self.max_response_size {
+            return Err(P2pError::ProtocolError(
+                ProtocolError::BlockResponseTooLarge(pending.size, self.max_response_size),
+            ));
+        }
+
+        pending.blocks.extend(chunk.blocks);
+        if chunk.last {
+            return Ok(Some(pending.blocks));
+        }
+
+        pending.next_seq += 1;
+        self.pending.insert(request_id, pending);
+        Ok(None)
+    }
+
+    /// Discards incomplete responses from the disconnected peer
+    pub fn unregister_peer(&mut self, peer_id: &P) {
+        self.pending.retain(|_, pending| pending.peer_id != *peer_id);
+    }
+}
+
+/// The block size as counted by the [`common::chain::ChainConfig::max_block_size`] limit
+fn block_size(block: &Block) -> usize {
+    let block_size = block.block_size();
+    block_size.size_from_header()
+        + block_size.size_from_txs()
+        + block_size.size_from_smart_contracts()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        chain::block::{consensus_data::ConsensusData, timestamp::BlockTimestamp, BlockReward},
+        primitives::{Id, H256},
+    };
+
+    use super::*;
+
+    const MAGIC_BYTES: [u8; 4] = [0x1a, 0x64, 0xe5, 0xf1];
+
+    fn make_blocks(count: usize) -> Vec<Block> {
+        (0..count)
+            .map(|i| {
+                Block::new(
+                    vec![],
+                    Id::new(H256([0x01; 32])),
+                    BlockTimestamp::from_int_seconds(i as u64),
+                    ConsensusData::None,
+                    BlockReward::new(vec![]),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn split_and_reassemble() {
+        let blocks = make_blocks(10);
+        let block_size = blocks[0].encoded_size();
+
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks.clone(), block_size * 3);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|chunk| chunk.blocks.len() <= 3));
+        assert_eq!(
+            chunks.iter().map(|chunk| (chunk.seq, chunk.last)).collect::<Vec<_>>(),
+            vec![(0, false), (1, false), (2, false), (3, true)]
+        );
+
+        let mut assembler = ChunkAssembler::new(usize::MAX);
+        let (last, chunks) = chunks.split_last().unwrap();
+        for chunk in chunks {
+            assert_eq!(assembler.push(1, 1, chunk.clone()), Ok(None));
+        }
+        assert_eq!(assembler.push(1, 1, last.clone()), Ok(Some(blocks)));
+        assert!(assembler.pending.is_empty());
+    }
+
+    #[test]
+    fn single_chunk() {
+        let blocks = make_blocks(2);
+
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks.clone(), usize::MAX);
+        assert_eq!(
+            chunks,
+            vec![BlocksChunk {
+                magic_bytes: MAGIC_BYTES,
+                seq: 0,
+                last: true,
+                blocks: blocks.clone(),
+            }]
+        );
+
+        // A block larger than the limit is still sent
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks, 1);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.blocks.len() == 1));
+    }
+
+    #[test]
+    fn missing_middle_chunk() {
+        let blocks = make_blocks(3);
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks.clone(), blocks[0].encoded_size());
+        assert_eq!(chunks.len(), 3);
+
+        let mut assembler = ChunkAssembler::new(usize::MAX);
+        assert_eq!(assembler.push(1, 1, chunks[0].clone()), Ok(None));
+        assert_eq!(
+            assembler.push(1, 1, chunks[2].clone()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::UnexpectedBlocksChunk(1, 2)
+            ))
+        );
+        assert!(assembler.pending.is_empty());
+
+        // Chunks of another request are not affected
+        assert_eq!(assembler.push(1, 2, chunks[0].clone()), Ok(None));
+        assert_eq!(
+            assembler.push(2, 3, chunks[1].clone()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::UnexpectedBlocksChunk(0, 1)
+            ))
+        );
+        assembler.unregister_peer(&1);
+        assert!(assembler.pending.is_empty());
+    }
+
+    #[test]
+    fn response_size_limit() {
+        let blocks = make_blocks(3);
+        let size = blocks.iter().map(block_size).sum::<usize>();
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks.clone(), blocks[0].encoded_size());
+
+        let mut assembler = ChunkAssembler::new(size);
+        for chunk in &chunks[..2] {
+            assert_eq!(assembler.push(1, 1, chunk.clone()), Ok(None));
+        }
+        assert_eq!(assembler.push(1, 1, chunks[2].clone()), Ok(Some(blocks)));
+
+        let mut assembler = ChunkAssembler::new(size - 1);
+        for chunk in &chunks[..2] {
+            assert_eq!(assembler.push(1, 1, chunk.clone()), Ok(None));
+        }
+        assert_eq!(
+            assembler.push(1, 1, chunks[2].clone()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::BlockResponseTooLarge(size, size - 1)
+            ))
+        );
+        assert!(assembler.pending.is_empty());
+    }
+
+    #[test]
+    fn one_response_per_peer() {
+        let blocks = make_blocks(2);
+        let chunks = split_into_chunks(MAGIC_BYTES, blocks.clone(), blocks[0].encoded_size());
+
+        let mut assembler = ChunkAssembler::new(usize::MAX);
+        assert_eq!(assembler.push(1, 1, chunks[0].clone()), Ok(None));
+        assert_eq!(assembler.push(1, 2, chunks[0].clone()), Ok(None));
+        assert_eq!(assembler.pending.len(), 1);
+        assert!(assembler.pending.contains_key(&2));
+
+        // Empty chunks can't be used to keep the response pending forever
+        let empty = BlocksChunk {
+            magic_bytes: MAGIC_BYTES,
+            seq: 1,
+            last: false,
+            blocks: vec![],
+        };
+        assert_eq!(
+            assembler.push(1, 2, empty),
+            Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+        );
+        assert!(assembler.pending.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of the recently verified block headers
+//!
+//! The same block is usually announced by several peers, the cache allows to skip verifying it
+//! again and to punish the peers that announce a block that is already known to be invalid.
+
+use std::collections::{HashMap, VecDeque};
+
+use common::{chain::Block, primitives::Id};
+
+/// The result of the header verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderStatus {
+    Valid,
+    Invalid { ban_score: u32 },
+}
+
+/// Bounded cache of the header verification results, the oldest entries are evicted first
+pub struct HeaderCache {
+    capacity: usize,
+    order: VecDeque<Id<Block>>,
+    statuses: HashMap<Id<Block>, HeaderStatus>,
+}
+
+impl HeaderCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            statuses: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached verification result of the header, if any
+    pub fn get(&self, id: &Id<Block>) -> Option<HeaderStatus> {
+        self.statuses.get(id).copied()
+    }
+
+    /// Stores the verification result of the header, evicting the oldest entry if the cache is full
+    pub fn insert(&mut self, id: Id<Block>, status: HeaderStatus) {
+        if self.statuses.insert(id, status).is_some() {
+            return;
+        }
+
+        self.order.push_back(id);
+        if self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.statuses.remove(&evicted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::primitives::H256;
+
+    use super::*;
+
+    #[test]
+    fn oldest_evicted() {
+        let mut cache = HeaderCache::new(2);
+        let ids = (0..3u64).map(|i| Id::new(H256::from_low_u64_be(i))).collect::<Vec<_>>();
+
+        cache.insert(ids[0], HeaderStatus::Valid);
+        cache.insert(ids[1], HeaderStatus::Invalid { ban_score: 100 });
+        cache.insert(ids[2], HeaderStatus::Valid);
+
+        assert_eq!(cache.get(&ids[0]), None);
+        assert_eq!(
+            cache.get(&ids[1]),
+            Some(HeaderStatus::Invalid { ban_score: 100 })
+        );
+        assert_eq!(cache.get(&ids[2]), Some(HeaderStatus::Valid));
+    }
+}
@@ -18,36 +18,77 @@
 
 pub mod peer;
 
+mod chunks;
+mod header_cache;
+mod orphan_announcements;
+mod orphan_transactions;
 mod request;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time::Instant};
 use void::Void;
 
-use chainstate::{ban_score::BanScore, chainstate_interface, BlockError, ChainstateError, Locator};
+use chainstate::{
+    ban_score::BanScore, chainstate_interface, check_block_size, BlockError, ChainstateError,
+    CheckBlockError, ConsensusVerificationError, Locator,
+};
 use common::{
     chain::{
         block::{Block, BlockHeader},
         config::ChainConfig,
+        tokens::TokenId,
+        GenBlock, SignedTransaction, Transaction,
     },
-    primitives::{Id, Idable},
+    primitives::{Amount, BlockHeight, Id, Idable},
 };
 use logging::log;
+use mempool::error::{Error as MempoolError, TxValidationError};
+use serialization::Encode;
 use utils::{ensure, tap_error_log::LogError};
 
 use crate::{
     config::P2pConfig,
+    constants::MAX_HAS_DATA_IDS,
     error::{P2pError, PeerError, ProtocolError},
     event::{PeerManagerEvent, SyncControlEvent},
     message::{self, Announcement, SyncRequest},
-    net::{types::SyncingEvent, NetworkingService, SyncingMessagingService},
+    net::{
+        types::{BestBlock, NodeKind, SyncingEvent},
+        NetworkingService, SyncingMessagingService,
+    },
     utils::oneshot_nofail,
 };
 
+use self::{
+    chunks::ChunkAssembler,
+    header_cache::{HeaderCache, HeaderStatus},
+    orphan_announcements::OrphanAnnouncementCache,
+    orphan_transactions::OrphanTransactionPool,
+};
+
 // TODO: from config? global constant?
 const HEADER_LIMIT: usize = 2000;
 
+/// How many verification results of the announced blocks are cached
+const HEADER_CACHE_SIZE: usize = 1000;
+
+/// How many times the block is submitted to the chainstate if processing fails for a transient reason
+const PROCESS_BLOCK_MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry of the block processing, doubled after each failed attempt
+const PROCESS_BLOCK_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// How often the pending header and block requests are checked for timeouts
+const REQUEST_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delay before the header or block request that the peer rejected as rate limited is sent again
+const RATE_LIMITED_RETRY_DELAY: Duration = Duration::from_secs(5);
+
 // TODO: add more tests
 // TODO: cache locator and invalidate it when `NewTip` event is received
 
@@ -64,7 +105,7 @@ pub struct BlockSyncManager<T: NetworkingService> {
     chain_config: Arc<ChainConfig>,
 
     /// The p2p configuration.
-    _p2p_config: Arc<P2pConfig>,
+    p2p_config: Arc<P2pConfig>,
 
     /// Handle for sending/receiving syncing events
     peer_sync_handle: T::SyncingMessagingHandle,
@@ -80,6 +121,24 @@ pub struct BlockSyncManager<T: NetworkingService> {
 
     /// Subsystem handle to Chainstate
     chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
+
+    /// Subsystem handle to Mempool
+    mempool_handle: mempool::MempoolHandle,
+
+    /// Announced blocks whose parent is not known yet
+    orphan_announcements: OrphanAnnouncementCache<T::PeerId>,
+
+    /// Relayed transactions whose inputs are not known yet
+    orphan_transactions: OrphanTransactionPool<T::PeerId>,
+
+    /// Incomplete chunked block responses
+    block_chunks: ChunkAssembler<T::PeerId, T::PeerRequestId>,
+
+    /// The last block announced to the peers, used to avoid announcing the same block twice
+    last_announced_block: Option<Id<Block>>,
+
+    /// Verification results of the recently announced blocks
+    header_cache: HeaderCache,
 }
 
 /// Syncing manager
@@ -95,17 +154,30 @@ where
         p2p_config: Arc<P2pConfig>,
         handle: T::SyncingMessagingHandle,
         chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
+        mempool_handle: mempool::MempoolHandle,
         rx_sync: mpsc::UnboundedReceiver<SyncControlEvent<T>>,
         tx_peer_manager: mpsc::UnboundedSender<PeerManagerEvent<T>>,
     ) -> Self {
+        let orphan_announcements =
+            OrphanAnnouncementCache::new(*p2p_config.max_orphan_announcements);
+        let orphan_transactions = OrphanTransactionPool::new(*p2p_config.max_orphan_transactions);
+        // Only one block is requested at a time
+        let max_block_response_size = chain_config.max_block_size();
+
         Self {
             chain_config,
-            _p2p_config: p2p_config,
+            p2p_config,
             peer_sync_handle: handle,
             rx_sync,
             tx_peer_manager,
             chainstate_handle,
+            mempool_handle,
             peers: Default::default(),
+            orphan_announcements,
+            orphan_transactions,
+            block_chunks: ChunkAssembler::new(max_block_response_size),
+            last_announced_block: None,
+            header_cache: HeaderCache::new(HEADER_CACHE_SIZE),
         }
     }
 
@@ -118,7 +190,11 @@ where
     }
 
     /// Register peer to the `SyncManager`
-    pub async fn register_peer(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+    pub async fn register_peer(
+        &mut self,
+        peer_id: T::PeerId,
+        node_kind: NodeKind,
+    ) -> crate::Result<()> {
         ensure!(
             !self.peers.contains_key(&peer_id),
             P2pError::PeerError(PeerError::PeerAlreadyExists),
@@ -128,19 +204,61 @@ where
 
         self.send_request(
             peer_id,
-            SyncRequest::HeaderListRequest(message::HeaderListRequest::new(locator.clone())),
+            SyncRequest::HeaderListRequest(message::HeaderListRequest::new(
+                *self.chain_config.magic_bytes(),
+                locator.clone(),
+            )),
         )
         .map(|_| {
-            self.peers.insert(
-                peer_id,
-                peer::PeerContext::new_with_locator(peer_id, locator),
-            );
+            let mut peer = peer::PeerContext::new_with_locator(peer_id, locator);
+            peer.set_node_kind(node_kind);
+            self.peers.insert(peer_id, peer);
         })
     }
 
+    /// Set the height of the best block that the peer reported during the handshake
+    pub fn set_peer_tip_height(&mut self, peer_id: T::PeerId, height: BlockHeight) {
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.set_tip_height(height);
+        }
+    }
+
+    /// Set the height and the chain trust of the best block that the peer reported during
+    /// the handshake
+    ///
+    /// The reported chain trust can't be verified unless the block is known to the local node,
+    /// so only then it's recorded. The peer is punished if it doesn't match the local one.
+    pub async fn set_peer_best_block(
+        &mut self,
+        peer_id: T::PeerId,
+        best_block: BestBlock,
+    ) -> crate::Result<()> {
+        let block_id = best_block.id;
+        let block_index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&block_id))
+            .await??;
+
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        peer.set_tip_height(best_block.height);
+        if let Some(block_index) = block_index {
+            ensure!(
+                *block_index.chain_trust() == best_block.chain_trust,
+                P2pError::ProtocolError(ProtocolError::InvalidMessage),
+            );
+            peer.set_chain_trust(best_block.chain_trust);
+        }
+        Ok(())
+    }
+
     /// Unregister peer from the `SyncManager`
     pub fn unregister_peer(&mut self, peer_id: T::PeerId) {
         self.peers.remove(&peer_id);
+        self.block_chunks.unregister_peer(&peer_id);
+        self.orphan_transactions.remove_peer(&peer_id);
     }
 
     /// Process header request
@@ -153,8 +271,25 @@ where
         log::debug!("send header response to peer {peer_id}, request_id: {request_id:?}");
 
         // TODO: check if remote has already asked for these headers?
-        let headers = self.chainstate_handle.call(move |this| this.get_headers(locator)).await??;
-        self.send_header_response(request_id, headers)
+        let (response, best_height) = self
+            .chainstate_handle
+            .call(move |this| -> Result<_, ChainstateError> {
+                Ok((
+                    message::HeaderListResponse::from_chainstate(this.as_ref(), locator)?,
+                    this.get_best_block_height()?,
+                ))
+            })
+            .await??;
+
+        // The peer is syncing from the local node until it asks for headers and gets none
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.set_syncing(!response.headers().is_empty());
+            if response.headers().is_empty() {
+                peer.set_tip_height(best_height);
+            }
+        }
+
+        self.send_header_response(request_id, response)
     }
 
     /// Process block request
@@ -190,6 +325,87 @@ where
         }
     }
 
+    /// Process token info request
+    ///
+    /// Only the issuance data of fungible tokens is returned.
+    pub async fn process_token_info_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::PeerRequestId,
+        token_id: TokenId,
+    ) -> crate::Result<()> {
+        log::debug!("send token info response to peer {peer_id}, request_id: {request_id:?}");
+
+        let issuance = self
+            .chainstate_handle
+            .call(move |this| this.get_token_aux_data(token_id))
+            .await??
+            .and_then(|data| data.token_issuance().cloned());
+
+        self.send_token_info_response(request_id, token_id, issuance)
+    }
+
+    /// Tells the peer which of the requested blocks the local node has
+    pub async fn process_has_data_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::PeerRequestId,
+        block_ids: Vec<Id<Block>>,
+    ) -> crate::Result<()> {
+        ensure!(
+            block_ids.len() <= MAX_HAS_DATA_IDS,
+            P2pError::ProtocolError(ProtocolError::TooManyBlockIds(
+                block_ids.len(),
+                MAX_HAS_DATA_IDS,
+            )),
+        );
+
+        log::debug!(
+            "send data availability response to peer {peer_id}, request_id: {request_id:?}"
+        );
+
+        let availability = self
+            .chainstate_handle
+            .call(move |this| -> Result<_, ChainstateError> {
+                block_ids
+                    .iter()
+                    .map(|id| this.get_block_index(id).map(|index| index.is_some()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .await??;
+
+        self.send_data_availability_response(request_id, availability)
+    }
+
+    /// Remember which of the asked blocks the peer doesn't have
+    ///
+    /// The response must answer the pending `HasData` request, one flag per requested block.
+    pub fn process_data_availability_response(
+        &mut self,
+        peer_id: T::PeerId,
+        availability: Vec<bool>,
+    ) -> crate::Result<()> {
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        let block_ids = peer
+            .take_has_data_request()
+            .ok_or(P2pError::ProtocolError(ProtocolError::InvalidMessage))?;
+        ensure!(
+            block_ids.len() == availability.len(),
+            P2pError::ProtocolError(ProtocolError::InvalidMessage),
+        );
+
+        let missing_blocks = block_ids
+            .into_iter()
+            .zip(availability)
+            .filter_map(|(id, available)| (!available).then_some(id))
+            .collect::<BTreeSet<_>>();
+        peer.set_missing_blocks(missing_blocks);
+        Ok(())
+    }
+
     /// Validate incoming header response
     async fn validate_header_response(
         &mut self,
@@ -251,10 +467,46 @@ where
     pub async fn process_header_response(
         &mut self,
         peer_id: T::PeerId,
-        headers: Vec<BlockHeader>,
+        response: message::HeaderListResponse,
     ) -> crate::Result<()> {
-        match self.validate_header_response(&peer_id, headers).await {
-            Ok(Some(header)) => self.send_block_request(peer_id, header.get_id()),
+        // the reported fork point must be known to the local node and the headers must
+        // descend from it
+        self.check_fork_point(&peer_id, response.fork_point()).await?;
+        if let Some(header) = response.headers().first() {
+            ensure!(
+                header.prev_block_id() == response.fork_point(),
+                P2pError::ProtocolError(ProtocolError::InvalidMessage),
+            );
+        }
+
+        match self.validate_header_response(&peer_id, response.into_headers()).await {
+            Ok(Some(header)) => {
+                let height = self.block_height(&header).await?;
+                match self.select_block_download_peer(peer_id, header.get_id(), height) {
+                    Some(download_peer) if download_peer == peer_id => {
+                        self.send_block_request(peer_id, header.get_id())
+                    }
+                    Some(download_peer) => {
+                        log::debug!(
+                            "peer {peer_id} is pruned, download block {} from peer {download_peer}",
+                            header.get_id()
+                        );
+                        self.hand_over_work(peer_id, download_peer)?;
+                        self.send_block_request(download_peer, header.get_id())
+                    }
+                    None => {
+                        log::debug!(
+                            "peer {peer_id} is pruned and no other peer can serve block {}",
+                            header.get_id()
+                        );
+                        self.peers
+                            .get_mut(&peer_id)
+                            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+                            .set_state(peer::PeerSyncState::Idle);
+                        Ok(())
+                    }
+                }
+            }
             Ok(None) => {
                 self.peers
                     .get_mut(&peer_id)
@@ -266,16 +518,107 @@ where
         }
     }
 
+    /// Check that the fork point reported in a header response is known to the local node
+    ///
+    /// The fork point must be either one of the locator entries sent to the peer or a block
+    /// that is present in the local chain.
+    async fn check_fork_point(
+        &self,
+        peer_id: &T::PeerId,
+        fork_point: &Id<GenBlock>,
+    ) -> crate::Result<()> {
+        let peer =
+            self.peers.get(peer_id).ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+
+        let in_locator = match peer.state() {
+            peer::PeerSyncState::UploadingHeaders(locator) => {
+                *fork_point == self.chain_config.genesis_block_id()
+                    || locator.iter().any(|id| id == fork_point)
+            }
+            _ => false,
+        };
+        if in_locator {
+            return Ok(());
+        }
+
+        let fork_point = *fork_point;
+        let block_index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&fork_point))
+            .await??;
+        ensure!(
+            block_index.is_some(),
+            P2pError::ProtocolError(ProtocolError::InvalidMessage),
+        );
+        Ok(())
+    }
+
+    /// Get the height of the block that the header belongs to
+    ///
+    /// The parent of the header must be known to the local node.
+    async fn block_height(&self, header: &BlockHeader) -> crate::Result<BlockHeight> {
+        let prev_block_id = *header.prev_block_id();
+        let prev_block_index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&prev_block_id))
+            .await??
+            .ok_or(P2pError::ProtocolError(ProtocolError::InvalidMessage))?;
+        Ok(prev_block_index.block_height().next_height())
+    }
+
+    /// Select a peer to download the block at the given height from
+    ///
+    /// The peer that sent the headers is preferred. Pruned peers don't have the blocks below
+    /// their prune height, so an idle peer that has the block is selected instead. Among those,
+    /// the peer whose chain has the most work is chosen, a higher chain with less work doesn't
+    /// win. Only the work that was verified by the local node is taken into account (see
+    /// [`peer::PeerContext::chain_trust`]). Peers that reported they don't have the block in a `DataAvailability` response
+    /// aren't selected either.
+    fn select_block_download_peer(
+        &self,
+        peer_id: T::PeerId,
+        block_id: Id<Block>,
+        height: BlockHeight,
+    ) -> Option<T::PeerId> {
+        if self.peers.get(&peer_id).map_or(false, |peer| peer.has_block(&block_id, height)) {
+            return Some(peer_id);
+        }
+
+        self.peers
+            .iter()
+            .filter(|(id, peer)| {
+                **id != peer_id
+                    && peer.state() == &peer::PeerSyncState::Idle
+                    && peer.has_block(&block_id, height)
+            })
+            .max_by_key(|(_, peer)| peer.chain_trust())
+            .map(|(id, _)| *id)
+    }
+
+    /// Move the blocks that still need to be downloaded from one peer to another
+    fn hand_over_work(&mut self, from: T::PeerId, to: T::PeerId) -> crate::Result<()> {
+        let work = self
+            .peers
+            .get_mut(&from)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .take_work();
+        self.peers
+            .get_mut(&to)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .register_header_response(&work);
+        Ok(())
+    }
+
     /// Validate incoming block response
     async fn validate_block_response(
         &mut self,
         peer_id: &T::PeerId,
         blocks: Vec<Block>,
     ) -> crate::Result<Option<BlockHeader>> {
-        let peer = self
-            .peers
-            .get_mut(peer_id)
-            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        ensure!(
+            self.peers.contains_key(peer_id),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
 
         let block = blocks.into_iter().next().expect("block to exist");
         let header = block.header().clone();
@@ -285,11 +628,7 @@ where
             .call(move |this| this.preliminary_block_check(block))
             .await?
         {
-            Ok(block) => {
-                self.chainstate_handle
-                    .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
-                    .await?
-            }
+            Ok(block) => self.process_block(block).await?,
             Err(err) => Err(err),
         };
 
@@ -299,9 +638,53 @@ where
             Err(err) => return Err(P2pError::ChainstateError(err)),
         }
 
+        // the peer has proven that its chain has at least as much work as the block
+        let block_id: Id<GenBlock> = header.get_id().into();
+        let block_index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&block_id))
+            .await??;
+
+        let peer = self
+            .peers
+            .get_mut(peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        if let Some(block_index) = block_index {
+            peer.set_chain_trust(*block_index.chain_trust());
+        }
         peer.register_block_response(&header)
     }
 
+    /// Submits the block received from a peer to the chainstate
+    ///
+    /// Processing is retried with a growing delay (up to [`PROCESS_BLOCK_MAX_ATTEMPTS`] times)
+    /// if it fails for a transient reason, like temporarily unavailable storage, so such a block
+    /// isn't lost. Validation errors are returned right away.
+    async fn process_block(&self, block: Block) -> crate::Result<Result<(), ChainstateError>> {
+        let mut delay = PROCESS_BLOCK_RETRY_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            let block = block.clone();
+            let result = self
+                .chainstate_handle
+                .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
+                .await?;
+
+            match result {
+                Err(err) if is_transient_error(&err) && attempt < PROCESS_BLOCK_MAX_ATTEMPTS => {
+                    log::warn!(
+                        "failed to process block (attempt {attempt}), retry in {delay:?}: {err}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return Ok(result.map(|_| ())),
+            }
+        }
+    }
+
     /// Process block response
     pub async fn process_block_response(
         &mut self,
@@ -325,18 +708,72 @@ where
         }
     }
 
+    pub async fn process_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::PeerRequestId,
+        request: message::SyncRequest,
+    ) -> crate::Result<()> {
+        let result = self.check_magic_bytes(request.magic_bytes());
+        if result.is_err() {
+            self.handle_error(peer_id, result).await?;
+            return self.disconnect_peer(peer_id);
+        }
+
+        match request {
+            message::SyncRequest::HeaderListRequest(request) => {
+                log::debug!("process header request (id {request_id:?}) from peer {peer_id}");
+                log::trace!("locator: {:#?}", request.locator());
+
+                let result =
+                    self.process_header_request(peer_id, request_id, request.into_locator()).await;
+                self.handle_error(peer_id, result).await?;
+            }
+            message::SyncRequest::BlockListRequest(request) => {
+                log::debug!("process block request (id {request_id:?}) from peer {peer_id}");
+                log::trace!("requested block ids: {:#?}", request.block_ids());
+
+                let result =
+                    self.process_block_request(peer_id, request_id, request.into_block_ids()).await;
+                self.handle_error(peer_id, result).await?;
+            }
+            message::SyncRequest::TokenInfoRequest(request) => {
+                log::debug!("process token info request (id {request_id:?}) from peer {peer_id}");
+
+                let result =
+                    self.process_token_info_request(peer_id, request_id, request.token_id).await;
+                self.handle_error(peer_id, result).await?;
+            }
+            message::SyncRequest::HasData(request) => {
+                log::debug!("process has data request (id {request_id:?}) from peer {peer_id}");
+
+                let result =
+                    self.process_has_data_request(peer_id, request_id, request.block_ids).await;
+                self.handle_error(peer_id, result).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn process_response(
         &mut self,
         peer_id: T::PeerId,
         request_id: T::PeerRequestId,
         response: message::SyncResponse,
     ) -> crate::Result<()> {
+        let result = self.check_magic_bytes(response.magic_bytes());
+        if result.is_err() {
+            self.handle_error(peer_id, result).await?;
+            return self.disconnect_peer(peer_id);
+        }
+
         match response {
             message::SyncResponse::HeaderListResponse(response) => {
                 log::debug!("process header response (id {request_id:?}) from peer {peer_id}");
                 log::trace!("received headers: {:#?}", response.headers());
 
-                let result = self.process_header_response(peer_id, response.into_headers()).await;
+                let result = self.process_header_response(peer_id, response).await;
                 self.handle_error(peer_id, result).await?;
             }
             message::SyncResponse::BlockListResponse(response) => {
@@ -350,24 +787,157 @@ where
                 let result = self.process_block_response(peer_id, response.into_blocks()).await;
                 self.handle_error(peer_id, result).await?;
             }
+            message::SyncResponse::BlocksChunk(chunk) => {
+                log::debug!(
+                    "process blocks chunk {} (id {request_id:?}) from peer {peer_id}",
+                    chunk.seq
+                );
+
+                let result = match self.process_blocks_chunk(peer_id, request_id, chunk) {
+                    Ok(Some(blocks)) => self.process_block_response(peer_id, blocks).await,
+                    Ok(None) => Ok(()),
+                    Err(err) => Err(err),
+                };
+                self.handle_error(peer_id, result).await?;
+            }
+            message::SyncResponse::TokenInfoResponse(response) => {
+                // The token info is only requested by the wallets, not by the block sync
+                log::debug!(
+                    "ignore token info response for token {} (id {request_id:?}) from peer {peer_id}",
+                    response.token_id
+                );
+            }
+            message::SyncResponse::DataAvailability(response) => {
+                log::debug!(
+                    "process data availability response (id {request_id:?}) from peer {peer_id}"
+                );
+
+                let result =
+                    self.process_data_availability_response(peer_id, response.availability);
+                self.handle_error(peer_id, result).await?;
+            }
+            message::SyncResponse::Error(response) => {
+                log::debug!(
+                    "peer {peer_id} didn't answer the request (id {request_id:?}): {:?}",
+                    response.error
+                );
+
+                let result = self.process_error_response(peer_id, request_id, response.error);
+                self.handle_error(peer_id, result).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles the refusal of the peer to answer the request
+    ///
+    /// A rate limited header or block request doesn't count as timed out, it's dropped and sent
+    /// again after [`RATE_LIMITED_RETRY_DELAY`] by [`Self::check_request_timeouts`].
+    pub fn process_error_response(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::PeerRequestId,
+        error: message::ResponseError,
+    ) -> crate::Result<()> {
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+
+        match error {
+            message::ResponseError::RateLimited => {
+                // Other requests (token info or block availability) aren't retried
+                if peer.is_pending_request(request_id) {
+                    peer.postpone_request(Instant::now() + RATE_LIMITED_RETRY_DELAY);
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Buffers the chunk of a block response, the blocks are returned once the last chunk
+    /// is received
+    ///
+    /// Chunks are only accepted for the block request that is pending for the peer.
+    fn process_blocks_chunk(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::PeerRequestId,
+        chunk: message::BlocksChunk,
+    ) -> crate::Result<Option<Vec<Block>>> {
+        let pending_request_id = self
+            .peers
+            .get(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .block_request_id();
+        ensure!(
+            pending_request_id == Some(request_id),
+            P2pError::ProtocolError(ProtocolError::UnsolicitedBlocksChunk),
+        );
+
+        self.block_chunks.push(peer_id, request_id, chunk)
+    }
+
+    /// Checks that the sync message was made for the same chain as the local one
+    ///
+    /// The peers that remained connected across a chain upgrade may send the messages in
+    /// an incompatible format, such peers are banned and disconnected.
+    fn check_magic_bytes(&self, magic_bytes: &[u8; 4]) -> crate::Result<()> {
+        let expected = self.chain_config.magic_bytes();
+        ensure!(
+            magic_bytes == expected,
+            P2pError::ProtocolError(ProtocolError::DifferentNetwork(*expected, *magic_bytes)),
+        );
+        Ok(())
+    }
+
     pub async fn process_announcement(
         &mut self,
         peer_id: T::PeerId,
         announcement: Announcement,
     ) -> crate::Result<()> {
+        if self.is_peer_behind(&peer_id).await? {
+            log::debug!("ignore announcement from peer {peer_id} that is behind the local tip");
+            return Ok(());
+        }
+
         // TODO: Discuss if we should announce blocks or headers, because announcing
         // blocks seems wasteful, in the sense that it's possible for peers to get
         // blocks again, and again, wasting their bandwidth.
         match announcement {
             Announcement::Block(block) => self.process_block_announcement(peer_id, block).await,
+            Announcement::Blocks(headers) => {
+                self.process_headers_announcement(peer_id, headers).await
+            }
+            Announcement::Transaction(tx) => {
+                self.process_transaction_announcement(peer_id, tx).await
+            }
         }
     }
 
+    /// Returns true if the peer is still syncing from the local node or its tip is too far
+    /// behind the local one, so its announcements aren't worth processing
+    async fn is_peer_behind(&self, peer_id: &T::PeerId) -> crate::Result<bool> {
+        let (syncing, tip_height) = match self.peers.get(peer_id) {
+            Some(peer) => (peer.is_syncing(), peer.tip_height()),
+            None => return Ok(false),
+        };
+        if syncing {
+            return Ok(true);
+        }
+
+        let tip_height = match tip_height {
+            Some(height) => height,
+            None => return Ok(false),
+        };
+        let best_height =
+            self.chainstate_handle.call(|this| this.get_best_block_height()).await??;
+        let max_lag = *self.p2p_config.max_announcer_tip_lag;
+        Ok(u64::from(tip_height).saturating_add(max_lag) < u64::from(best_height))
+    }
+
     // TODO: refactor this
     pub async fn handle_error(
         &mut self,
@@ -441,6 +1011,9 @@ where
         log::info!("Starting SyncManager");
 
         let mut block_rx = self.subscribe_to_chainstate_events().await?;
+        self.update_best_block().await?;
+
+        let mut request_timeout_check = tokio::time::interval(REQUEST_TIMEOUT_CHECK_INTERVAL);
 
         loop {
             tokio::select! {
@@ -449,29 +1022,8 @@ where
                         peer_id,
                         request_id,
                         request,
-                    } => match request {
-                        message::SyncRequest::HeaderListRequest(request) => {
-                            log::debug!("process header request (id {request_id:?}) from peer {peer_id}");
-                            log::trace!("locator: {:#?}", request.locator());
-
-                            let result = self.process_header_request(
-                                peer_id,
-                                request_id,
-                                request.into_locator(),
-                            ).await;
-                            self.handle_error(peer_id, result).await?;
-                        }
-                        message::SyncRequest::BlockListRequest(request) => {
-                            log::debug!("process block request (id {request_id:?}) from peer {peer_id}");
-                            log::trace!("requested block ids: {:#?}", request.block_ids());
-
-                            let result = self.process_block_request(
-                                peer_id,
-                                request_id,
-                                request.into_block_ids(),
-                            ).await;
-                            self.handle_error(peer_id, result).await?;
-                        }
+                    } => {
+                        self.process_request(peer_id, request_id, request).await?;
                     },
                     SyncingEvent::Response {
                         peer_id,
@@ -485,9 +1037,15 @@ where
                     }
                 },
                 event = self.rx_sync.recv() => match event.ok_or(P2pError::ChannelClosed)? {
-                    SyncControlEvent::Connected(peer_id) => {
+                    SyncControlEvent::Connected(peer_id, node_kind, best_block) => {
                         log::debug!("register peer {peer_id} to sync manager");
-                        let result = self.register_peer(peer_id).await;
+                        let result = self.register_peer(peer_id, node_kind).await;
+                        let result = match (result, best_block) {
+                            (Ok(()), Some(best_block)) => {
+                                self.set_peer_best_block(peer_id, best_block).await
+                            }
+                            (result, _) => result,
+                        };
                         self.handle_error(peer_id, result).await?;
                     }
                     SyncControlEvent::Disconnected(peer_id) => {
@@ -495,20 +1053,125 @@ where
                         self.unregister_peer(peer_id)
                     }
                 },
-                block_id = block_rx.recv(), if !self.chainstate_handle.call(|c| c.is_initial_block_download()).await?? => {
+                block_id = block_rx.recv() => {
                     let block_id = block_id.ok_or(P2pError::ChannelClosed)?;
+                    self.update_best_block().await?;
+
+                    let (is_initial_block_download, height) = self
+                        .chainstate_handle
+                        .call(|c| -> Result<_, ChainstateError> {
+                            Ok((c.is_initial_block_download()?, c.get_best_block_height()?))
+                        })
+                        .await??;
+                    self.tx_peer_manager
+                        .send(PeerManagerEvent::NewTipReceived { is_initial_block_download, height })
+                        .map_err(P2pError::from)?;
 
-                    match self.chainstate_handle.call(move |this| this.get_block(block_id)).await?? {
-                        Some(block) => {
-                            let _ = self.peer_sync_handle.make_announcement(Announcement::Block(block)).log_err();
-                        }
-                        None => log::error!("CRITICAL: best block not available"),
+                    if !is_initial_block_download {
+                        self.announce_block(block_id).await?;
                     }
+
+                    self.on_chainstate_reorg(block_id).await?;
+                }
+                _ = request_timeout_check.tick() => {
+                    self.check_request_timeouts(Instant::now())?;
                 }
             }
         }
     }
 
+    /// Disconnects the peers that didn't answer the header or block request in time and sends
+    /// again the requests that were rejected as rate limited
+    ///
+    /// The timeout depends on the request type, see [`P2pConfig::header_request_timeout`] and
+    /// [`P2pConfig::block_request_timeout`].
+    pub fn check_request_timeouts(&mut self, now: Instant) -> crate::Result<()> {
+        let timed_out_peers = self
+            .peers
+            .iter_mut()
+            .filter_map(|(peer_id, peer)| peer.take_request_timeout(now).then_some(*peer_id))
+            .collect::<Vec<_>>();
+
+        for peer_id in timed_out_peers {
+            log::info!("request to peer {peer_id} timed out, disconnecting");
+            self.disconnect_peer(peer_id)?;
+        }
+
+        let retried_peers = self
+            .peers
+            .iter_mut()
+            .filter_map(|(peer_id, peer)| peer.take_request_retry(now).then_some(*peer_id))
+            .collect::<Vec<_>>();
+
+        for peer_id in retried_peers {
+            let state = self.peers.get(&peer_id).map(|peer| peer.state().clone());
+            log::debug!("sending the rate limited request to peer {peer_id} again");
+            match state {
+                Some(peer::PeerSyncState::UploadingBlocks(block_id)) => {
+                    self.send_block_request(peer_id, block_id)?;
+                }
+                Some(peer::PeerSyncState::UploadingHeaders(locator)) => {
+                    self.send_header_request(peer_id, locator)?;
+                }
+                Some(peer::PeerSyncState::Idle | peer::PeerSyncState::Unknown) | None => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Asks the peer manager to disconnect the peer
+    fn disconnect_peer(&self, peer_id: T::PeerId) -> crate::Result<()> {
+        // The peer may be already disconnected, the result isn't interesting
+        let (tx, _rx) = oneshot_nofail::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::Disconnect(peer_id, tx))
+            .map_err(P2pError::from)
+    }
+
+    /// Announces the current tip of the local node to all peers.
+    ///
+    /// Nothing is sent if the tip is the genesis or it was already announced.
+    pub async fn announce_tip(&mut self) -> crate::Result<()> {
+        let best_block_id = self.chainstate_handle.call(|this| this.get_best_block_id()).await??;
+        match best_block_id.classify(&self.chain_config).chain_block_id() {
+            Some(block_id) => self.announce_block(block_id).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Announces the block to all peers, unless it was the last announced block
+    async fn announce_block(&mut self, block_id: Id<Block>) -> crate::Result<()> {
+        if self.last_announced_block == Some(block_id) {
+            log::debug!("block {block_id} was already announced");
+            return Ok(());
+        }
+
+        match self.chainstate_handle.call(move |this| this.get_block(block_id)).await?? {
+            Some(block) => {
+                let res = self.peer_sync_handle.make_announcement(Announcement::Block(block));
+                if res.log_err().is_ok() {
+                    self.last_announced_block = Some(block_id);
+                }
+            }
+            None => log::error!("CRITICAL: best block not available"),
+        }
+
+        Ok(())
+    }
+
+    /// Passes the current best block to the networking service, so it can be reported to new peers
+    /// during the handshake.
+    async fn update_best_block(&mut self) -> crate::Result<()> {
+        let best_block_index =
+            self.chainstate_handle.call(|this| this.get_best_block_index()).await??;
+        self.peer_sync_handle.update_best_block(BestBlock {
+            id: best_block_index.block_id(),
+            height: best_block_index.block_height(),
+            chain_trust: *best_block_index.chain_trust(),
+        })
+    }
+
     /// Returns a receiver for the chainstate `NewTip` events.
     async fn subscribe_to_chainstate_events(
         &mut self,
@@ -534,45 +1197,366 @@ where
         Ok(rx)
     }
 
+    /// Process block announcement
+    ///
+    /// Blocks that exceed the chain's size limits (the same ones that chainstate checks) are
+    /// rejected right away and the peer is punished. If the parent of the block isn't known yet, the block is cached until
+    /// the parent arrives (see [`BlockSyncManager::on_chainstate_reorg`]).
     async fn process_block_announcement(
         &mut self,
         peer_id: T::PeerId,
         block: Block,
     ) -> crate::Result<()> {
-        let result = match self
+        if let Err(err) = check_block_size(&self.chain_config, &block) {
+            log::debug!(
+                "peer {peer_id} announced too large block {}: {err}",
+                block.get_id()
+            );
+            return self.adjust_peer_score(peer_id, err.ban_score()).await;
+        }
+
+        let prev_block_id = block.prev_block_id();
+        let parent_known = self
             .chainstate_handle
-            .call(move |this| this.preliminary_block_check(block))
+            .call(move |this| this.get_gen_block_index(&prev_block_id))
+            .await??
+            .is_some();
+
+        if !parent_known {
+            log::debug!(
+                "caching orphan block {} announced by peer {peer_id}",
+                block.get_id()
+            );
+            if let Some((evicted_peer_id, evicted_id)) =
+                self.orphan_announcements.insert(peer_id, block)
+            {
+                log::debug!("evict orphan block {evicted_id} announced by peer {evicted_peer_id}");
+
+                // Only the peer that fills most of the cache is penalized, so a peer that
+                // announces an orphan when the cache is full because of a reorg isn't punished
+                let max_orphans = *self.p2p_config.max_orphan_announcements;
+                if self.orphan_announcements.peer_orphan_count(&peer_id) * 2 > max_orphans {
+                    log::debug!("peer {peer_id} announced too many orphan blocks");
+                    let score = ProtocolError::TooManyOrphanAnnouncements.ban_score();
+                    self.adjust_peer_score(peer_id, score).await?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.process_announced_block(peer_id, block).await
+    }
+
+    /// Re-evaluate the cached orphan announcements after the chainstate tip has changed
+    ///
+    /// The orphans whose parent is known now are processed, which can make other orphans
+    /// connectable. The invalid ones are dropped and the peers that announced them are punished.
+    pub async fn on_chainstate_reorg(&mut self, new_tip: Id<Block>) -> crate::Result<()> {
+        while !self.orphan_announcements.is_empty() {
+            let missing_parents = self.orphan_announcements.missing_parents();
+            let connectable = self
+                .chainstate_handle
+                .call(move |this| {
+                    missing_parents
+                        .into_iter()
+                        .filter(|id| matches!(this.get_gen_block_index(id), Ok(Some(_))))
+                        .collect::<Vec<_>>()
+                })
+                .await?;
+
+            if connectable.is_empty() {
+                break;
+            }
+
+            for parent_id in connectable {
+                for (peer_id, block) in self.orphan_announcements.take_children(&parent_id) {
+                    log::debug!(
+                        "process orphan block {} after new tip {new_tip}",
+                        block.get_id()
+                    );
+                    self.process_announced_block(peer_id, block).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the relayed transaction to the mempool and relays it further if it's accepted
+    ///
+    /// A transaction spending the outputs of an unknown transaction is kept in the orphan pool.
+    /// Once a transaction is accepted, the orphans waiting for it are added to the mempool too.
+    /// The mempool only reports one missing parent at a time, an orphan with several missing
+    /// parents is cached again for the next one.
+    async fn process_transaction_announcement(
+        &mut self,
+        peer_id: T::PeerId,
+        tx: SignedTransaction,
+    ) -> crate::Result<()> {
+        let mut pending = VecDeque::from([(peer_id, tx)]);
+        while let Some((peer_id, tx)) = pending.pop_front() {
+            let tx_id = tx.transaction().get_id();
+            if !self.pays_min_relay_fee_rate(&tx).await? {
+                log::debug!(
+                    "transaction {tx_id} sent by peer {peer_id} pays less than the minimum relay fee rate"
+                );
+                continue;
+            }
+
+            let mempool_tx = tx.clone();
+            let result = self
+                .mempool_handle
+                .call_async_mut(move |this| this.add_transaction(mempool_tx))
+                .await?;
+
+            match result {
+                Ok(()) => {
+                    log::debug!("transaction {tx_id} sent by peer {peer_id} added to the mempool");
+                    let _ = self
+                        .peer_sync_handle
+                        .make_announcement(Announcement::Transaction(tx))
+                        .log_err();
+                    pending.extend(self.take_orphan_transactions(&tx_id));
+                }
+                Err(MempoolError::TxValidationError(TxValidationError::OutPointNotFound {
+                    outpoint,
+                    ..
+                })) => match outpoint.tx_id().get_tx_id() {
+                    Some(parent_id) => {
+                        let missing_parents = BTreeSet::from([*parent_id]);
+                        self.cache_orphan_transaction(peer_id, tx, missing_parents).await?;
+                    }
+                    None => {
+                        log::debug!("transaction {tx_id} spends an unknown block reward");
+                    }
+                },
+                Err(err) => {
+                    log::debug!("transaction {tx_id} sent by peer {peer_id} rejected: {err}");
+                    let score = err.ban_score();
+                    if score > 0 {
+                        self.adjust_peer_score(peer_id, score).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the fee of the relayed transaction against the configured minimum relay fee rate
+    ///
+    /// The transactions whose fee can't be computed pass the check, the mempool reports the reason
+    /// once they are added.
+    async fn pays_min_relay_fee_rate(&self, tx: &SignedTransaction) -> crate::Result<bool> {
+        let min_fee_rate = *self.p2p_config.min_relay_fee_rate;
+        if min_fee_rate == Amount::ZERO {
+            return Ok(true);
+        }
+
+        let mempool_tx = tx.clone();
+        let fee = match self
+            .mempool_handle
+            .call_async(move |this| {
+                Box::pin(async move { this.transaction_fee(&mempool_tx).await })
+            })
             .await?
         {
-            Ok(block) => {
-                self.chainstate_handle
-                    .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
-                    .await?
-            }
-            Err(err) => Err(err),
+            Ok(fee) => fee,
+            Err(_) => return Ok(true),
         };
 
-        let score = match result {
-            Ok(_) => 0,
-            Err(e) => match e {
-                ChainstateError::FailedToInitializeChainstate(_) => 0,
-                ChainstateError::ProcessBlockError(err) => err.ban_score(),
-                ChainstateError::FailedToReadProperty(_) => 0,
-                ChainstateError::BootstrapError(_) => 0,
-            },
+        let size = u128::try_from(tx.encoded_size()).expect("transaction size fits into u128");
+        let min_fee = (min_fee_rate * size).and_then(|fee| fee / 1000);
+        Ok(min_fee.map_or(false, |min_fee| fee >= min_fee))
+    }
+
+    /// Returns the number of the relayed transactions waiting for their parents
+    pub fn orphan_transaction_count(&self) -> usize {
+        self.orphan_transactions.len()
+    }
+
+    /// Keeps the relayed transaction whose inputs are not known yet until the missing parents
+    /// arrive (see [`BlockSyncManager::take_orphan_transactions`])
+    ///
+    /// The oldest orphan is evicted if the pool is full and the peer is penalized if it sent
+    /// most of the orphans in the pool.
+    pub async fn cache_orphan_transaction(
+        &mut self,
+        peer_id: T::PeerId,
+        tx: SignedTransaction,
+        missing_parents: BTreeSet<Id<Transaction>>,
+    ) -> crate::Result<()> {
+        log::debug!(
+            "caching orphan transaction {} sent by peer {peer_id}",
+            tx.transaction().get_id()
+        );
+
+        if let Some(evicted_id) = self.orphan_transactions.insert(peer_id, tx, missing_parents) {
+            log::debug!("evict orphan transaction {evicted_id}");
+
+            // Same as for the orphan blocks, only the peer that fills most of the pool is penalized
+            let max_orphans = *self.p2p_config.max_orphan_transactions;
+            if self.orphan_transactions.peer_orphan_count(&peer_id) * 2 > max_orphans {
+                log::debug!("peer {peer_id} sent too many orphan transactions");
+                let score = ProtocolError::TooManyOrphanTransactions.ban_score();
+                self.adjust_peer_score(peer_id, score).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes the orphan transactions that can be validated now that the transaction is known
+    ///
+    /// Should be called every time a new transaction is accepted, including the ones returned
+    /// from here, as they can be parents of other orphans.
+    pub fn take_orphan_transactions(
+        &mut self,
+        tx_id: &Id<Transaction>,
+    ) -> Vec<(T::PeerId, SignedTransaction)> {
+        self.orphan_transactions.take_children(tx_id)
+    }
+
+    /// Process the announced block whose parent is known to the local node
+    ///
+    /// The verification result is cached, so the block announced by several peers is only verified
+    /// once and the peers announcing a block that is known to be invalid are punished right away.
+    async fn process_announced_block(
+        &mut self,
+        peer_id: T::PeerId,
+        block: Block,
+    ) -> crate::Result<()> {
+        let block_id = block.get_id();
+        let score = match self.header_cache.get(&block_id) {
+            Some(HeaderStatus::Valid) => {
+                log::debug!("block {block_id} announced by peer {peer_id} is already verified");
+                return Ok(());
+            }
+            Some(HeaderStatus::Invalid { ban_score }) => {
+                log::debug!("block {block_id} announced by peer {peer_id} is known to be invalid");
+                ban_score
+            }
+            None => {
+                let result = match self
+                    .chainstate_handle
+                    .call(move |this| this.preliminary_block_check(block))
+                    .await?
+                {
+                    Ok(block) => self.process_block(block).await?,
+                    Err(err) => Err(err),
+                };
+
+                let is_valid = result.is_ok();
+                let is_time_dependent =
+                    result.as_ref().err().map_or(false, is_time_dependent_error);
+                let score = match result {
+                    Ok(_) => 0,
+                    Err(e) => match e {
+                        ChainstateError::FailedToInitializeChainstate(_) => 0,
+                        ChainstateError::ProcessBlockError(err) => err.ban_score(),
+                        ChainstateError::FailedToReadProperty(_) => 0,
+                        ChainstateError::BootstrapError(_) => 0,
+                    },
+                };
+
+                // The errors that don't make the block invalid aren't cached, neither are
+                // the errors that depend on the current time (the block may become valid later)
+                if is_valid {
+                    self.header_cache.insert(block_id, HeaderStatus::Valid);
+                } else if score > 0 && !is_time_dependent {
+                    self.header_cache.insert(block_id, HeaderStatus::Invalid { ban_score: score });
+                }
+
+                score
+            }
         };
 
         if score > 0 {
-            // TODO: better abstraction over channels
-            let (tx, rx) = oneshot_nofail::channel();
-            self.tx_peer_manager
-                .send(PeerManagerEvent::AdjustPeerScore(peer_id, score, tx))
-                .map_err(P2pError::from)?;
-            let _ = rx.await.map_err(P2pError::from)?;
+            self.adjust_peer_score(peer_id, score).await?;
         }
 
         Ok(())
     }
+
+    /// Asks the peer manager to increase the peer score
+    async fn adjust_peer_score(&mut self, peer_id: T::PeerId, score: u32) -> crate::Result<()> {
+        // TODO: better abstraction over channels
+        let (tx, rx) = oneshot_nofail::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::AdjustPeerScore(peer_id, score, tx))
+            .map_err(P2pError::from)?;
+        let _ = rx.await.map_err(P2pError::from)?;
+        Ok(())
+    }
+
+    /// Process announcement of several block headers
+    ///
+    /// If the peer is idle, the blocks that the local node doesn't have are downloaded from it.
+    /// Otherwise the peer is already syncing with the local node and the blocks are downloaded
+    /// as part of the syncing.
+    async fn process_headers_announcement(
+        &mut self,
+        peer_id: T::PeerId,
+        headers: Vec<BlockHeader>,
+    ) -> crate::Result<()> {
+        ensure!(
+            headers.len() <= HEADER_LIMIT,
+            P2pError::ProtocolError(ProtocolError::InvalidMessage),
+        );
+
+        if headers.is_empty() {
+            return Ok(());
+        }
+
+        for (a, b) in headers.iter().zip(&headers[1..]) {
+            ensure!(
+                b.prev_block_id() == &a.get_id(),
+                P2pError::ProtocolError(ProtocolError::InvalidMessage),
+            );
+        }
+
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        if peer.state() != &peer::PeerSyncState::Idle {
+            return Ok(());
+        }
+
+        let headers = self
+            .chainstate_handle
+            .call(|this| this.filter_already_existing_blocks(headers))
+            .await??;
+        peer.register_header_response(&headers);
+
+        match peer.get_header_for_download() {
+            Some(header) => self.send_block_request(peer_id, header.get_id()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns true if the block processing has failed because of the storage and may succeed later
+fn is_transient_error(err: &ChainstateError) -> bool {
+    matches!(
+        err,
+        ChainstateError::ProcessBlockError(
+            BlockError::StorageError(_) | BlockError::DatabaseCommitError(_, _, _)
+        )
+    )
+}
+
+/// Returns true if the block is rejected only because of the current time and may be accepted later
+fn is_time_dependent_error(err: &ChainstateError) -> bool {
+    matches!(
+        err,
+        ChainstateError::ProcessBlockError(BlockError::CheckBlockFailed(
+            CheckBlockError::ConsensusVerificationFailed(
+                ConsensusVerificationError::BlockFromTheFuture(_)
+            )
+        ))
+    )
 }
 
 #[cfg(test)]
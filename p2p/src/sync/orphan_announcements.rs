@@ -0,0 +1,191 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of the announced blocks whose parent is not known yet
+//!
+//! Such blocks can't be verified until the parent arrives, so they are kept in a bounded cache
+//! and processed once the chainstate knows the parent.
+
+use std::collections::{btree_map::Entry, BTreeMap, VecDeque};
+
+use common::{
+    chain::{Block, GenBlock},
+    primitives::{Id, Idable},
+};
+
+/// Bounded cache of the orphan block announcements, the oldest ones are evicted first
+pub struct OrphanAnnouncementCache<P> {
+    capacity: usize,
+    /// The announced blocks keyed by the missing parent
+    orphans: BTreeMap<Id<GenBlock>, Vec<(P, Block)>>,
+    /// The missing parent and the ID of each cached block in the order they were received
+    order: VecDeque<(Id<GenBlock>, Id<Block>)>,
+}
+
+impl<P: Copy + Eq> OrphanAnnouncementCache<P> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            orphans: BTreeMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns true if no announcements are cached
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Returns the parents the cached blocks are waiting for
+    pub fn missing_parents(&self) -> Vec<Id<GenBlock>> {
+        self.orphans.keys().copied().collect()
+    }
+
+    /// Returns the number of the cached blocks announced by the peer
+    pub fn peer_orphan_count(&self, peer_id: &P) -> usize {
+        self.orphans
+            .values()
+            .flatten()
+            .filter(|(orphan_peer_id, _)| orphan_peer_id == peer_id)
+            .count()
+    }
+
+    /// Adds the announced block whose parent is not known.
+    ///
+    /// Returns the peer and the ID of the evicted block if the cache was full.
+    pub fn insert(&mut self, peer_id: P, block: Block) -> Option<(P, Id<Block>)> {
+        let parent_id = block.prev_block_id();
+        self.order.push_back((parent_id, block.get_id()));
+        self.orphans.entry(parent_id).or_default().push((peer_id, block));
+
+        if self.order.len() > self.capacity {
+            return self.evict_oldest();
+        }
+        None
+    }
+
+    /// Takes the blocks waiting for the parent, in the order they were announced
+    pub fn take_children(&mut self, parent_id: &Id<GenBlock>) -> Vec<(P, Block)> {
+        self.order.retain(|(orphan_parent_id, _)| orphan_parent_id != parent_id);
+        self.orphans.remove(parent_id).unwrap_or_default()
+    }
+
+    fn evict_oldest(&mut self) -> Option<(P, Id<Block>)> {
+        let (parent_id, block_id) = self.order.pop_front()?;
+
+        match self.orphans.entry(parent_id) {
+            Entry::Occupied(mut entry) => {
+                let orphans = entry.get_mut();
+                let evicted = orphans
+                    .iter()
+                    .position(|(_, block)| block.get_id() == block_id)
+                    .map(|pos| orphans.remove(pos).0);
+                if orphans.is_empty() {
+                    entry.remove();
+                }
+                evicted.map(|peer_id| (peer_id, block_id))
+            }
+            Entry::Vacant(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use common::{
+        chain::block::{timestamp::BlockTimestamp, BlockReward, ConsensusData},
+        primitives::H256,
+    };
+
+    use super::*;
+
+    fn make_block(parent_id: Id<GenBlock>, timestamp: u64) -> Block {
+        Block::new(
+            vec![],
+            parent_id,
+            BlockTimestamp::from_int_seconds(timestamp),
+            ConsensusData::None,
+            BlockReward::new(Vec::new()),
+        )
+        .unwrap()
+    }
+
+    fn make_parent_id(i: u64) -> Id<GenBlock> {
+        Id::new(H256::from_low_u64_be(i))
+    }
+
+    #[test]
+    fn children_taken_by_parent() {
+        let mut cache = OrphanAnnouncementCache::new(10);
+        let parent1 = make_parent_id(1);
+        let parent2 = make_parent_id(2);
+        let blocks = [make_block(parent1, 1), make_block(parent2, 2), make_block(parent1, 3)];
+
+        assert_eq!(cache.insert(1, blocks[0].clone()), None);
+        assert_eq!(cache.insert(2, blocks[1].clone()), None);
+        assert_eq!(cache.insert(2, blocks[2].clone()), None);
+        assert_eq!(cache.order.len(), 3);
+        assert_eq!(
+            cache.missing_parents().into_iter().collect::<BTreeSet<_>>(),
+            BTreeSet::from([parent1, parent2])
+        );
+
+        // Unknown parents don't release anything
+        assert!(cache.take_children(&make_parent_id(3)).is_empty());
+        assert_eq!(cache.order.len(), 3);
+
+        assert_eq!(
+            cache.take_children(&parent1),
+            vec![(1, blocks[0].clone()), (2, blocks[2].clone())]
+        );
+        assert_eq!(cache.order.len(), 1);
+        assert_eq!(cache.missing_parents(), vec![parent2]);
+
+        assert_eq!(cache.take_children(&parent2), vec![(2, blocks[1].clone())]);
+        assert!(cache.is_empty());
+        assert!(cache.orphans.is_empty());
+    }
+
+    #[test]
+    fn oldest_evicted() {
+        let capacity = 3;
+        let mut cache = OrphanAnnouncementCache::new(capacity);
+        let parent = make_parent_id(1);
+        let blocks = (0..5).map(|i| make_block(parent, i)).collect::<Vec<_>>();
+
+        for block in blocks.iter().take(capacity) {
+            assert_eq!(cache.insert(1, block.clone()), None);
+        }
+        assert_eq!(cache.order.len(), capacity);
+
+        for i in capacity..blocks.len() {
+            assert_eq!(
+                cache.insert(2, blocks[i].clone()),
+                Some((1, blocks[i - capacity].get_id()))
+            );
+            assert_eq!(cache.order.len(), capacity);
+        }
+        assert_eq!(cache.peer_orphan_count(&1), 1);
+        assert_eq!(cache.peer_orphan_count(&2), 2);
+
+        assert_eq!(
+            cache.take_children(&parent),
+            vec![(1, blocks[2].clone()), (2, blocks[3].clone()), (2, blocks[4].clone())]
+        );
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pool of the relayed transactions whose inputs are not known yet
+//!
+//! Such transactions can't be validated until their parents arrive, so they are kept in a bounded
+//! pool and handed back for the validation once all the missing parents are known.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use common::{
+    chain::{SignedTransaction, Transaction},
+    primitives::{Id, Idable},
+};
+
+struct OrphanEntry<P> {
+    peer_id: P,
+    tx: SignedTransaction,
+    missing_parents: BTreeSet<Id<Transaction>>,
+}
+
+/// Bounded pool of the orphan transactions, the oldest ones are evicted first
+pub struct OrphanTransactionPool<P> {
+    max_size: usize,
+    orphans: BTreeMap<Id<Transaction>, OrphanEntry<P>>,
+    /// The orphans waiting for the transaction, keyed by the missing parent
+    by_parent: BTreeMap<Id<Transaction>, BTreeSet<Id<Transaction>>>,
+    /// The orphans in the order they were received
+    order: VecDeque<Id<Transaction>>,
+}
+
+impl<P: Copy + Eq> OrphanTransactionPool<P> {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            orphans: BTreeMap::new(),
+            by_parent: BTreeMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of the orphans in the pool
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    /// Returns the number of the orphans in the pool sent by the peer
+    pub fn peer_orphan_count(&self, peer_id: &P) -> usize {
+        self.orphans.values().filter(|entry| entry.peer_id == *peer_id).count()
+    }
+
+    /// Adds the transaction that spends the outputs of the missing parents.
+    ///
+    /// Returns the ID of the evicted orphan if the pool was full. A transaction that is already
+    /// in the pool is ignored.
+    pub fn insert(
+        &mut self,
+        peer_id: P,
+        tx: SignedTransaction,
+        missing_parents: BTreeSet<Id<Transaction>>,
+    ) -> Option<Id<Transaction>> {
+        let tx_id = tx.transaction().get_id();
+        if self.orphans.contains_key(&tx_id) {
+            return None;
+        }
+
+        for parent_id in &missing_parents {
+            self.by_parent.entry(*parent_id).or_default().insert(tx_id);
+        }
+        self.orphans.insert(
+            tx_id,
+            OrphanEntry {
+                peer_id,
+                tx,
+                missing_parents,
+            },
+        );
+        self.order.push_back(tx_id);
+
+        if self.order.len() > self.max_size {
+            return self.order.pop_front().map(|evicted_id| {
+                self.remove(&evicted_id);
+                evicted_id
+            });
+        }
+        None
+    }
+
+    /// Takes the orphans that have no other missing parents once the transaction is known,
+    /// in the order they were received.
+    ///
+    /// The returned transactions should be validated again, and this function called for them
+    /// in turn because they can be parents of other orphans.
+    pub fn take_children(&mut self, parent_id: &Id<Transaction>) -> Vec<(P, SignedTransaction)> {
+        let children = match self.by_parent.remove(parent_id) {
+            Some(children) => children,
+            None => return Vec::new(),
+        };
+
+        let mut ready = BTreeSet::new();
+        for child_id in children {
+            if let Some(entry) = self.orphans.get_mut(&child_id) {
+                entry.missing_parents.remove(parent_id);
+                if entry.missing_parents.is_empty() {
+                    ready.insert(child_id);
+                }
+            }
+        }
+
+        let ready_ids =
+            self.order.iter().filter(|id| ready.contains(id)).copied().collect::<Vec<_>>();
+        self.order.retain(|id| !ready.contains(id));
+        ready_ids
+            .into_iter()
+            .filter_map(|id| self.orphans.remove(&id))
+            .map(|entry| (entry.peer_id, entry.tx))
+            .collect()
+    }
+
+    /// Discards the orphans sent by the disconnected peer
+    pub fn remove_peer(&mut self, peer_id: &P) {
+        let ids = self
+            .orphans
+            .iter()
+            .filter(|(_, entry)| entry.peer_id == *peer_id)
+            .map(|(id, _)| *id)
+            .collect::<BTreeSet<_>>();
+        self.order.retain(|id| !ids.contains(id));
+        for id in ids {
+            self.remove(&id);
+        }
+    }
+
+    /// Removes the orphan, except from the receive order
+    fn remove(&mut self, tx_id: &Id<Transaction>) {
+        let entry = match self.orphans.remove(tx_id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        for parent_id in entry.missing_parents {
+            if let Some(children) = self.by_parent.get_mut(&parent_id) {
+                children.remove(tx_id);
+                if children.is_empty() {
+                    self.by_parent.remove(&parent_id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use common::{
+        chain::{
+            signature::inputsig::InputWitness, tokens::OutputValue, Destination, OutPointSourceId,
+            OutputPurpose, TxInput, TxOutput,
+        },
+        primitives::Amount,
+    };
+
+    use super::*;
+
+    /// Makes a transaction spending the first outputs of the parents
+    pub fn make_tx(parents: &[Id<Transaction>], lock_time: u32) -> SignedTransaction {
+        let inputs = parents
+            .iter()
+            .map(|parent_id| TxInput::new(OutPointSourceId::Transaction(*parent_id), 0))
+            .collect::<Vec<_>>();
+        let witnesses = vec![InputWitness::NoSignature(None); inputs.len()];
+        let outputs = vec![TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(1)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        )];
+        let tx = Transaction::new(0, inputs, outputs, lock_time).unwrap();
+        SignedTransaction::new(tx, witnesses).unwrap()
+    }
+
+    pub fn tx_id(tx: &SignedTransaction) -> Id<Transaction> {
+        tx.transaction().get_id()
+    }
+
+    #[test]
+    fn child_before_parent() {
+        let mut pool = OrphanTransactionPool::new(10);
+
+        let parent = make_tx(&[], 0);
+        let child = make_tx(&[tx_id(&parent)], 0);
+        assert_eq!(
+            pool.insert(1, child.clone(), BTreeSet::from([tx_id(&parent)])),
+            None
+        );
+        // The same transaction sent again is ignored
+        assert_eq!(
+            pool.insert(2, child.clone(), BTreeSet::from([tx_id(&parent)])),
+            None
+        );
+        assert_eq!(pool.len(), 1);
+
+        // Unrelated transactions don't release the orphan
+        assert!(pool.take_children(&tx_id(&make_tx(&[], 1))).is_empty());
+        assert_eq!(pool.len(), 1);
+
+        assert_eq!(pool.take_children(&tx_id(&parent)), vec![(1, child)]);
+        assert_eq!(pool.len(), 0);
+        assert!(pool.by_parent.is_empty());
+        assert!(pool.order.is_empty());
+    }
+
+    #[test]
+    fn orphan_with_several_missing_parents() {
+        let mut pool = OrphanTransactionPool::new(10);
+
+        let parent1 = make_tx(&[], 0);
+        let parent2 = make_tx(&[], 1);
+        let child = make_tx(&[tx_id(&parent1), tx_id(&parent2)], 0);
+        let grandchild = make_tx(&[tx_id(&child)], 0);
+        pool.insert(
+            1,
+            child.clone(),
+            BTreeSet::from([tx_id(&parent1), tx_id(&parent2)]),
+        );
+        pool.insert(1, grandchild.clone(), BTreeSet::from([tx_id(&child)]));
+
+        // The child still waits for the second parent
+        assert!(pool.take_children(&tx_id(&parent2)).is_empty());
+        assert_eq!(pool.len(), 2);
+
+        assert_eq!(
+            pool.take_children(&tx_id(&parent1)),
+            vec![(1, child.clone())]
+        );
+        assert_eq!(pool.take_children(&tx_id(&child)), vec![(1, grandchild)]);
+        assert_eq!(pool.len(), 0);
+        assert!(pool.by_parent.is_empty());
+    }
+
+    #[test]
+    fn pool_respects_cap() {
+        let max_size = 3;
+        let mut pool = OrphanTransactionPool::new(max_size);
+
+        let parents = (0..5).map(|i| make_tx(&[], i)).collect::<Vec<_>>();
+        let children =
+            parents.iter().map(|parent| make_tx(&[tx_id(parent)], 0)).collect::<Vec<_>>();
+        for (parent, child) in parents.iter().zip(&children).take(max_size) {
+            assert_eq!(
+                pool.insert(1, child.clone(), BTreeSet::from([tx_id(parent)])),
+                None
+            );
+        }
+        assert_eq!(pool.len(), max_size);
+
+        // The oldest orphans are evicted first
+        for i in max_size..parents.len() {
+            assert_eq!(
+                pool.insert(2, children[i].clone(), BTreeSet::from([tx_id(&parents[i])])),
+                Some(tx_id(&children[i - max_size]))
+            );
+            assert_eq!(pool.len(), max_size);
+        }
+        assert_eq!(pool.peer_orphan_count(&1), 1);
+        assert_eq!(pool.peer_orphan_count(&2), 2);
+        assert!(pool.take_children(&tx_id(&parents[0])).is_empty());
+
+        pool.remove_peer(&2);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.order.len(), 1);
+        assert_eq!(pool.by_parent.len(), 1);
+    }
+}
@@ -15,14 +15,16 @@
 
 use crate::{
     error::{P2pError, ProtocolError},
-    net::NetworkingService,
+    net::{types::NodeKind, NetworkingService},
 };
 use chainstate::Locator;
 use common::{
     chain::block::{Block, BlockHeader},
-    primitives::{Id, Idable},
+    primitives::{BlockHeight, Id, Idable},
+    Uint256,
 };
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
+use tokio::time::Instant;
 use utils::ensure;
 
 /// State of the peer
@@ -52,9 +54,42 @@ where
     /// State of the peer
     state: PeerSyncState,
 
+    /// Whether the peer is a full or a pruned node
+    node_kind: NodeKind,
+
+    /// Height of the best block of the peer, if known
+    tip_height: Option<BlockHeight>,
+
+    /// The most chain trust that the peer has proven to have, if known
+    ///
+    /// It's taken from the local block index of the blocks that the peer has, the peer's own
+    /// claims aren't trusted.
+    chain_trust: Option<Uint256>,
+
+    /// Whether the peer is downloading blocks from the local node
+    syncing: bool,
+
     /// List of block headers indicating which blocks
     /// still need to be downloaded from the remote peer
     work: VecDeque<BlockHeader>,
+
+    /// Block IDs of the `HasData` request sent to the peer that hasn't been answered yet
+    has_data_request: Option<Vec<Id<Block>>>,
+
+    /// Blocks that the peer reported it doesn't have in its last `DataAvailability` response
+    missing_blocks: BTreeSet<Id<Block>>,
+
+    /// When the pending header or block request times out
+    request_deadline: Option<Instant>,
+
+    /// ID of the pending block request, the block chunks of other responses are rejected
+    block_request_id: Option<T::PeerRequestId>,
+
+    /// ID of the pending header request
+    header_request_id: Option<T::PeerRequestId>,
+
+    /// When the header or block request that the peer rejected as rate limited is sent again
+    retry_at: Option<Instant>,
 }
 
 impl<T: NetworkingService> PeerContext<T> {
@@ -62,7 +97,17 @@ impl<T: NetworkingService> PeerContext<T> {
         Self {
             _peer_id,
             state: PeerSyncState::Unknown,
+            node_kind: NodeKind::Full,
+            tip_height: None,
+            chain_trust: None,
+            syncing: false,
             work: VecDeque::new(),
+            has_data_request: None,
+            missing_blocks: BTreeSet::new(),
+            request_deadline: None,
+            block_request_id: None,
+            header_request_id: None,
+            retry_at: None,
         }
     }
 
@@ -70,12 +115,24 @@ impl<T: NetworkingService> PeerContext<T> {
         Self {
             _peer_id,
             state: PeerSyncState::UploadingHeaders(locator),
+            node_kind: NodeKind::Full,
+            tip_height: None,
+            chain_trust: None,
+            syncing: false,
             work: VecDeque::new(),
+            has_data_request: None,
+            missing_blocks: BTreeSet::new(),
+            request_deadline: None,
+            block_request_id: None,
+            header_request_id: None,
+            retry_at: None,
         }
     }
 
     pub fn register_header_response(&mut self, headers: &[BlockHeader]) {
         self.state = PeerSyncState::Idle;
+        self.request_deadline = None;
+        self.header_request_id = None;
         self.work = VecDeque::from(headers.to_vec());
     }
 
@@ -94,6 +151,8 @@ impl<T: NetworkingService> PeerContext<T> {
                     P2pError::ProtocolError(ProtocolError::InvalidMessage),
                 );
 
+                self.request_deadline = None;
+                self.block_request_id = None;
                 Ok(self.get_next_block())
             }
             PeerSyncState::Idle | PeerSyncState::Unknown | PeerSyncState::UploadingHeaders(_) => {
@@ -106,6 +165,16 @@ impl<T: NetworkingService> PeerContext<T> {
         self.work.pop_front()
     }
 
+    /// Take the block headers that still need to be downloaded and mark the peer idle
+    pub fn take_work(&mut self) -> Vec<BlockHeader> {
+        self.state = PeerSyncState::Idle;
+        self.request_deadline = None;
+        self.block_request_id = None;
+        self.header_request_id = None;
+        self.retry_at = None;
+        self.work.drain(..).collect()
+    }
+
     /// Set peer state
     pub fn set_state(&mut self, state: PeerSyncState) {
         self.state = state;
@@ -115,6 +184,128 @@ impl<T: NetworkingService> PeerContext<T> {
     pub fn state(&self) -> &PeerSyncState {
         &self.state
     }
+
+    /// Set the node kind reported by the peer
+    pub fn set_node_kind(&mut self, node_kind: NodeKind) {
+        self.node_kind = node_kind;
+    }
+
+    /// Get the node kind reported by the peer
+    pub fn node_kind(&self) -> NodeKind {
+        self.node_kind
+    }
+
+    /// Update the height of the best block of the peer, it never decreases
+    pub fn set_tip_height(&mut self, height: BlockHeight) {
+        self.tip_height = Some(self.tip_height.map_or(height, |h| std::cmp::max(h, height)));
+    }
+
+    /// Get the height of the best block of the peer, if known
+    pub fn tip_height(&self) -> Option<BlockHeight> {
+        self.tip_height
+    }
+
+    /// Update the chain trust with the one of a block that the peer is known to have, it never
+    /// decreases
+    pub fn set_chain_trust(&mut self, chain_trust: Uint256) {
+        self.chain_trust =
+            Some(self.chain_trust.map_or(chain_trust, |t| std::cmp::max(t, chain_trust)));
+    }
+
+    /// Get the most chain trust that the peer has proven to have, if known
+    pub fn chain_trust(&self) -> Option<Uint256> {
+        self.chain_trust
+    }
+
+    /// Set whether the peer is downloading blocks from the local node
+    pub fn set_syncing(&mut self, syncing: bool) {
+        self.syncing = syncing;
+    }
+
+    /// Returns true if the peer is downloading blocks from the local node
+    pub fn is_syncing(&self) -> bool {
+        self.syncing
+    }
+
+    /// Set when the pending header or block request times out
+    pub fn set_request_deadline(&mut self, deadline: Instant) {
+        self.request_deadline = Some(deadline);
+    }
+
+    /// Remember the ID of the block request sent to the peer
+    pub fn set_block_request_id(&mut self, request_id: T::PeerRequestId) {
+        self.block_request_id = Some(request_id);
+    }
+
+    /// Returns the ID of the pending block request, if there is one
+    pub fn block_request_id(&self) -> Option<T::PeerRequestId> {
+        self.block_request_id
+    }
+
+    /// Remember the ID of the header request sent to the peer
+    pub fn set_header_request_id(&mut self, request_id: T::PeerRequestId) {
+        self.header_request_id = Some(request_id);
+    }
+
+    /// Returns true if the request is the pending header or block request
+    pub fn is_pending_request(&self, request_id: T::PeerRequestId) -> bool {
+        self.header_request_id == Some(request_id) || self.block_request_id == Some(request_id)
+    }
+
+    /// Drop the pending header or block request without reporting a timeout, the request is
+    /// sent again at `retry_at`
+    pub fn postpone_request(&mut self, retry_at: Instant) {
+        self.request_deadline = None;
+        self.header_request_id = None;
+        self.block_request_id = None;
+        self.retry_at = Some(retry_at);
+    }
+
+    /// Returns true if the postponed request should be sent again, the retry time is reset
+    pub fn take_request_retry(&mut self, now: Instant) -> bool {
+        match self.retry_at {
+            Some(retry_at) if retry_at <= now => {
+                self.retry_at = None;
+                true
+            }
+            Some(_) | None => false,
+        }
+    }
+
+    /// Returns true if the pending header or block request has timed out, the deadline is
+    /// reset so the timeout is reported only once
+    pub fn take_request_timeout(&mut self, now: Instant) -> bool {
+        match self.request_deadline {
+            Some(deadline) if deadline <= now => {
+                self.request_deadline = None;
+                true
+            }
+            Some(_) | None => false,
+        }
+    }
+
+    /// Remember the block IDs of the `HasData` request sent to the peer
+    pub fn set_has_data_request(&mut self, block_ids: Vec<Id<Block>>) {
+        self.has_data_request = Some(block_ids);
+    }
+
+    /// Take the block IDs of the pending `HasData` request, if there is one
+    pub fn take_has_data_request(&mut self) -> Option<Vec<Id<Block>>> {
+        self.has_data_request.take()
+    }
+
+    /// Replace the set of blocks the peer doesn't have
+    pub fn set_missing_blocks(&mut self, missing_blocks: BTreeSet<Id<Block>>) {
+        self.missing_blocks = missing_blocks;
+    }
+
+    /// Returns true if the peer can serve the block at the given height
+    ///
+    /// The node kind tells whether the block is below the prune height of the peer and the
+    /// last `DataAvailability` response tells whether the peer has the block at all.
+    pub fn has_block(&self, block_id: &Id<Block>, height: BlockHeight) -> bool {
+        self.node_kind.has_block(height) && !self.missing_blocks.contains(block_id)
+    }
 }
 
 #[cfg(test)]
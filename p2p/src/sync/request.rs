@@ -17,16 +17,22 @@
 
 use chainstate::Locator;
 use common::{
-    chain::{block::BlockHeader, Block},
+    chain::{
+        tokens::{TokenId, TokenIssuance},
+        Block,
+    },
     primitives::Id,
 };
 use logging::log;
+use serialization::Encode;
+use tokio::time::Instant;
 use utils::ensure;
 
 use crate::{
-    error::{P2pError, PeerError},
+    constants::{MAX_BLOCKS_CHUNK_SIZE, MAX_HAS_DATA_IDS},
+    error::{P2pError, PeerError, ProtocolError},
     message::{self, SyncRequest, SyncResponse},
-    sync::{peer::PeerSyncState, BlockSyncManager},
+    sync::{chunks::split_into_chunks, peer::PeerSyncState, BlockSyncManager},
     NetworkingService, SyncingMessagingService,
 };
 
@@ -39,20 +45,34 @@ where
 {
     /// Creates a blocks request message.
     pub fn make_block_request(&self, block_ids: Vec<Id<Block>>) -> SyncRequest {
-        SyncRequest::BlockListRequest(message::BlockListRequest::new(block_ids))
+        SyncRequest::BlockListRequest(message::BlockListRequest::new(
+            *self.chain_config.magic_bytes(),
+            block_ids,
+        ))
     }
 
     /// Creates a headers request message with the given locator.
     pub fn make_header_request(&self, locator: Locator) -> SyncRequest {
-        SyncRequest::HeaderListRequest(message::HeaderListRequest::new(locator))
+        SyncRequest::HeaderListRequest(message::HeaderListRequest::new(
+            *self.chain_config.magic_bytes(),
+            locator,
+        ))
+    }
+
+    /// Creates a request asking which of the blocks the peer has.
+    pub fn make_has_data_request(&self, block_ids: Vec<Id<Block>>) -> SyncRequest {
+        SyncRequest::HasData(message::HasDataRequest {
+            magic_bytes: *self.chain_config.magic_bytes(),
+            block_ids,
+        })
     }
 
     /// Make header response
     ///
     /// # Arguments
-    /// * `headers` - the headers that were requested
-    pub fn make_header_response(&self, headers: Vec<BlockHeader>) -> SyncResponse {
-        SyncResponse::HeaderListResponse(message::HeaderListResponse::new(headers))
+    /// * `response` - the headers that were requested and their fork point
+    pub fn make_header_response(&self, response: message::HeaderListResponse) -> SyncResponse {
+        SyncResponse::HeaderListResponse(response)
     }
 
     /// Make block response
@@ -60,7 +80,38 @@ where
     /// # Arguments
     /// * `blocks` - the blocks that were requested
     pub fn make_block_response(&self, blocks: Vec<Block>) -> SyncResponse {
-        SyncResponse::BlockListResponse(message::BlockListResponse::new(blocks))
+        SyncResponse::BlockListResponse(message::BlockListResponse::new(
+            *self.chain_config.magic_bytes(),
+            blocks,
+        ))
+    }
+
+    /// Make token info response
+    ///
+    /// # Arguments
+    /// * `token_id` - ID of the requested token
+    /// * `issuance` - issuance data of the token, `None` if the token is unknown
+    pub fn make_token_info_response(
+        &self,
+        token_id: TokenId,
+        issuance: Option<TokenIssuance>,
+    ) -> SyncResponse {
+        SyncResponse::TokenInfoResponse(message::TokenInfoResponse {
+            magic_bytes: *self.chain_config.magic_bytes(),
+            token_id,
+            issuance,
+        })
+    }
+
+    /// Make data availability response
+    ///
+    /// # Arguments
+    /// * `availability` - whether the local node has each of the requested blocks
+    pub fn make_data_availability_response(&self, availability: Vec<bool>) -> SyncResponse {
+        SyncResponse::DataAvailability(message::DataAvailabilityResponse {
+            magic_bytes: *self.chain_config.magic_bytes(),
+            availability,
+        })
     }
 
     /// Sends a request to the given peer.
@@ -91,12 +142,16 @@ where
 
         // send request to remote peer and start tracking its progress
         let wanted_blocks = self.make_block_request(vec![block_id]);
-        self.send_request(peer_id, wanted_blocks)?;
+        let request_id = self.peer_sync_handle.send_request(peer_id, wanted_blocks)?;
 
-        self.peers
+        let deadline = Instant::now() + *self.p2p_config.block_request_timeout;
+        let peer = self
+            .peers
             .get_mut(&peer_id)
-            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
-            .set_state(PeerSyncState::UploadingBlocks(block_id));
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        peer.set_state(PeerSyncState::UploadingBlocks(block_id));
+        peer.set_request_deadline(deadline);
+        peer.set_block_request_id(request_id);
         Ok(())
     }
 
@@ -123,12 +178,56 @@ where
 
         // send header request and start tracking its progress
         let wanted_headers = self.make_header_request(locator.clone());
-        self.send_request(peer_id, wanted_headers)?;
+        let request_id = self.peer_sync_handle.send_request(peer_id, wanted_headers)?;
+
+        let deadline = Instant::now() + *self.p2p_config.header_request_timeout;
+        let peer = self
+            .peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
+        peer.set_state(PeerSyncState::UploadingHeaders(locator));
+        peer.set_request_deadline(deadline);
+        peer.set_header_request_id(request_id);
+        Ok(())
+    }
+
+    /// Ask the remote peer which of the blocks it has
+    ///
+    /// Only one such request can be pending per peer, a new request replaces the previous one.
+    /// The answer is used to skip the peer when selecting where to download a block from.
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `block_ids` - IDs of the blocks, at most [`MAX_HAS_DATA_IDS`]
+    pub fn send_has_data_request(
+        &mut self,
+        peer_id: T::PeerId,
+        block_ids: Vec<Id<Block>>,
+    ) -> crate::Result<()> {
+        ensure!(
+            self.peers.contains_key(&peer_id),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
+        ensure!(
+            block_ids.len() <= MAX_HAS_DATA_IDS,
+            P2pError::ProtocolError(ProtocolError::TooManyBlockIds(
+                block_ids.len(),
+                MAX_HAS_DATA_IDS,
+            )),
+        );
+
+        log::trace!(
+            "send has data request to {peer_id}, {} blocks",
+            block_ids.len()
+        );
+
+        let request = self.make_has_data_request(block_ids.clone());
+        self.send_request(peer_id, request)?;
 
         self.peers
             .get_mut(&peer_id)
             .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
-            .set_state(PeerSyncState::UploadingHeaders(locator));
+            .set_has_data_request(block_ids);
         Ok(())
     }
 
@@ -139,16 +238,16 @@ where
     ///
     /// # Arguments
     /// * `request_id` - ID of the request that this is a response to
-    /// * `headers` - headers that the remote requested
+    /// * `response` - headers that the remote requested and their fork point
     pub fn send_header_response(
         &mut self,
         request_id: T::PeerRequestId,
-        headers: Vec<BlockHeader>,
+        response: message::HeaderListResponse,
     ) -> crate::Result<()> {
         log::trace!("send header response, request id {request_id:?}");
 
         // TODO: save sent header IDs somewhere and validate future requests against those?
-        let message = self.make_header_response(headers);
+        let message = self.make_header_response(response);
         self.peer_sync_handle.send_response(request_id, message)
     }
 
@@ -168,7 +267,46 @@ where
         log::trace!("send block response, request id {request_id:?}");
 
         // TODO: save sent block IDs somewhere and validate future requests against those?
-        let message = self.make_block_response(blocks);
+        let size = blocks.iter().map(Encode::encoded_size).sum::<usize>();
+        if size <= MAX_BLOCKS_CHUNK_SIZE {
+            let message = self.make_block_response(blocks);
+            return self.peer_sync_handle.send_response(request_id, message);
+        }
+
+        // The response doesn't fit into one message, send it in chunks
+        for chunk in split_into_chunks(
+            *self.chain_config.magic_bytes(),
+            blocks,
+            MAX_BLOCKS_CHUNK_SIZE,
+        ) {
+            self.peer_sync_handle
+                .send_response(request_id, SyncResponse::BlocksChunk(chunk))?;
+        }
+        Ok(())
+    }
+
+    /// Send token info response to remote peer
+    pub fn send_token_info_response(
+        &mut self,
+        request_id: T::PeerRequestId,
+        token_id: TokenId,
+        issuance: Option<TokenIssuance>,
+    ) -> crate::Result<()> {
+        log::trace!("send token info response, request id {request_id:?}");
+
+        let message = self.make_token_info_response(token_id, issuance);
+        self.peer_sync_handle.send_response(request_id, message)
+    }
+
+    /// Send data availability response to remote peer
+    pub fn send_data_availability_response(
+        &mut self,
+        request_id: T::PeerRequestId,
+        availability: Vec<bool>,
+    ) -> crate::Result<()> {
+        log::trace!("send data availability response, request id {request_id:?}");
+
+        let message = self.make_data_availability_response(availability);
         self.peer_sync_handle.send_response(request_id, message)
     }
 }
@@ -0,0 +1,667 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use p2p_test_utils::TestBlockInfo;
+use tokio::time::timeout;
+
+use crate::testing_utils::{
+    connect_services, TestTransportChannel, TestTransportMaker, TestTransportNoise,
+    TestTransportTcp,
+};
+use common::{
+    chain::{block::timestamp::BlockTimestamp, Block, GenBlock},
+    primitives::{BlockHeight, Id, Idable},
+};
+
+use chainstate::{ban_score::BanScore, BlockSizeError};
+
+use crate::{
+    config::P2pConfig,
+    error::ProtocolError,
+    event::PeerManagerEvent,
+    message::Announcement,
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            types::PeerId,
+            DefaultNetworkingService,
+        },
+        types::SyncingEvent,
+    },
+    sync::{
+        header_cache::HeaderStatus,
+        orphan_announcements::OrphanAnnouncementCache,
+        tests::{make_sync_manager, register_peer, MakeTestPeerId},
+        BlockSyncManager,
+    },
+    ConnectivityService, NetworkingService, SyncingMessagingService,
+};
+
+// the orphan block is cached and processed once its parent is imported
+async fn orphan_processed_after_reorg<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let peer_id = P::new();
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_transport(), addr).await;
+    register_peer(&mut mgr, peer_id).await;
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        2,
+    );
+    let parent_id: Id<GenBlock> = blocks[0].get_id().into();
+
+    assert_eq!(
+        mgr.process_announcement(peer_id, Announcement::Block(blocks[1].clone())).await,
+        Ok(())
+    );
+    assert_eq!(mgr.orphan_announcements.missing_parents(), vec![parent_id]);
+
+    // nothing can be connected yet
+    assert_eq!(mgr.on_chainstate_reorg(blocks[1].get_id()).await, Ok(()));
+    assert_eq!(mgr.orphan_announcements.missing_parents(), vec![parent_id]);
+
+    p2p_test_utils::import_blocks(&mgr.chainstate_handle, vec![blocks[0].clone()]).await;
+    assert_eq!(mgr.on_chainstate_reorg(blocks[0].get_id()).await, Ok(()));
+    assert!(mgr.orphan_announcements.is_empty());
+
+    let best_block_id = mgr
+        .chainstate_handle
+        .call(|this| this.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(best_block_id, Id::<GenBlock>::from(blocks[1].get_id()));
+}
+
+#[tokio::test]
+async fn orphan_processed_after_reorg_tcp() {
+    orphan_processed_after_reorg::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn orphan_processed_after_reorg_channels() {
+    orphan_processed_after_reorg::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn orphan_processed_after_reorg_noise() {
+    orphan_processed_after_reorg::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the oldest orphans are evicted when the cache is full and the flooding peer is punished
+async fn orphan_announcements_limited<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    mgr.p2p_config = Arc::new(P2pConfig {
+        max_orphan_announcements: 3.into(),
+        ..Default::default()
+    });
+    mgr.orphan_announcements = OrphanAnnouncementCache::new(3);
+    let peer_id1 = P::new();
+    register_peer(&mut mgr, peer_id1).await;
+    let peer_id2 = P::new();
+    register_peer(&mut mgr, peer_id2).await;
+
+    // None of the blocks is imported, so every block except the first one is an orphan
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        6,
+    );
+    let cached_parents = |mgr: &BlockSyncManager<T>| {
+        mgr.orphan_announcements.missing_parents().into_iter().collect::<BTreeSet<_>>()
+    };
+    let parent_ids = |blocks: &[Block]| {
+        blocks.iter().map(|block| block.prev_block_id()).collect::<BTreeSet<_>>()
+    };
+
+    for block in &blocks[1..4] {
+        assert_eq!(
+            mgr.process_announcement(peer_id1, Announcement::Block(block.clone())).await,
+            Ok(())
+        );
+    }
+    assert_eq!(cached_parents(&mgr), parent_ids(&blocks[1..4]));
+    assert!(pm.try_recv().is_err());
+
+    // The cache is full, the oldest orphan is evicted and the peer that fills the cache is punished
+    let (res, score) = tokio::join!(
+        mgr.process_announcement(peer_id1, Announcement::Block(blocks[4].clone())),
+        async {
+            match pm.recv().await.unwrap() {
+                PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                    assert_eq!(adjusted_peer_id, peer_id1);
+                    tx.send(Ok(()));
+                    score
+                }
+                _ => panic!("unexpected peer manager event"),
+            }
+        }
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(score, ProtocolError::TooManyOrphanAnnouncements.ban_score());
+    assert_eq!(cached_parents(&mgr), parent_ids(&blocks[2..5]));
+
+    // Another peer isn't punished for announcing an orphan when the cache is full
+    assert_eq!(
+        mgr.process_announcement(peer_id2, Announcement::Block(blocks[5].clone())).await,
+        Ok(())
+    );
+    assert_eq!(cached_parents(&mgr), parent_ids(&blocks[3..6]));
+    assert!(pm.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn orphan_announcements_limited_tcp() {
+    orphan_announcements_limited::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn orphan_announcements_limited_channels() {
+    orphan_announcements_limited::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn orphan_announcements_limited_noise() {
+    orphan_announcements_limited::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the announcement from a peer that is far behind the local tip is ignored
+async fn announcement_from_behind_peer_ignored<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let max_lag = *mgr.p2p_config.max_announcer_tip_lag;
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        max_lag as usize + 2,
+    );
+    let (imported, new_block) = blocks.split_at(max_lag as usize + 1);
+    p2p_test_utils::import_blocks(&mgr.chainstate_handle, imported.to_vec()).await;
+    let old_tip_id: Id<GenBlock> = imported.last().unwrap().get_id().into();
+
+    let behind_peer_id = P::new();
+    register_peer(&mut mgr, behind_peer_id).await;
+    mgr.set_peer_tip_height(behind_peer_id, BlockHeight::new(0));
+
+    let synced_peer_id = P::new();
+    register_peer(&mut mgr, synced_peer_id).await;
+    mgr.set_peer_tip_height(synced_peer_id, BlockHeight::new(max_lag + 1));
+
+    assert_eq!(
+        mgr.process_announcement(behind_peer_id, Announcement::Block(new_block[0].clone()))
+            .await,
+        Ok(())
+    );
+    let best_block_id = mgr
+        .chainstate_handle
+        .call(|this| this.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(best_block_id, old_tip_id);
+    assert!(mgr.orphan_announcements.is_empty());
+
+    assert_eq!(
+        mgr.process_announcement(synced_peer_id, Announcement::Block(new_block[0].clone()))
+            .await,
+        Ok(())
+    );
+    let best_block_id = mgr
+        .chainstate_handle
+        .call(|this| this.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(best_block_id, Id::<GenBlock>::from(new_block[0].get_id()));
+}
+
+#[tokio::test]
+async fn announcement_from_behind_peer_ignored_tcp() {
+    announcement_from_behind_peer_ignored::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn announcement_from_behind_peer_ignored_channels() {
+    announcement_from_behind_peer_ignored::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn announcement_from_behind_peer_ignored_noise() {
+    announcement_from_behind_peer_ignored::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the block announced by another peer is not verified again
+async fn verified_block_cached<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let peer_id1 = P::new();
+    register_peer(&mut mgr, peer_id1).await;
+    let peer_id2 = P::new();
+    register_peer(&mut mgr, peer_id2).await;
+
+    let block = p2p_test_utils::create_block(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+    );
+    let block_id = block.get_id();
+
+    assert_eq!(
+        mgr.process_announcement(peer_id1, Announcement::Block(block.clone())).await,
+        Ok(())
+    );
+    assert_eq!(mgr.header_cache.get(&block_id), Some(HeaderStatus::Valid));
+
+    assert_eq!(
+        mgr.process_announcement(peer_id2, Announcement::Block(block)).await,
+        Ok(())
+    );
+    assert_eq!(mgr.header_cache.get(&block_id), Some(HeaderStatus::Valid));
+    assert!(pm.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn verified_block_cached_tcp() {
+    verified_block_cached::<TestTransportTcp, PeerId, DefaultNetworkingService<TcpTransportSocket>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn verified_block_cached_channels() {
+    verified_block_cached::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn verified_block_cached_noise() {
+    verified_block_cached::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the peer announcing a block that is known to be invalid is punished right away
+async fn invalid_block_cached<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let peer_id1 = P::new();
+    register_peer(&mut mgr, peer_id1).await;
+    let peer_id2 = P::new();
+    register_peer(&mut mgr, peer_id2).await;
+
+    // a block that is older than its parent is invalid
+    let block = Block::new_with_no_consensus(
+        vec![],
+        config.genesis_block_id(),
+        BlockTimestamp::from_int_seconds(0),
+    )
+    .unwrap();
+    let block_id = block.get_id();
+
+    for peer_id in [peer_id1, peer_id2] {
+        let (res, score) = tokio::join!(
+            mgr.process_announcement(peer_id, Announcement::Block(block.clone())),
+            async {
+                match pm.recv().await.unwrap() {
+                    PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                        assert_eq!(adjusted_peer_id, peer_id);
+                        tx.send(Ok(()));
+                        score
+                    }
+                    _ => panic!("unexpected peer manager event"),
+                }
+            }
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(score, 100);
+        assert_eq!(
+            mgr.header_cache.get(&block_id),
+            Some(HeaderStatus::Invalid { ban_score: 100 })
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_block_cached_tcp() {
+    invalid_block_cached::<TestTransportTcp, PeerId, DefaultNetworkingService<TcpTransportSocket>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn invalid_block_cached_channels() {
+    invalid_block_cached::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn invalid_block_cached_noise() {
+    invalid_block_cached::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>(
+    )
+    .await;
+}
+
+// a block from the future may become valid later, so it's not cached as invalid
+async fn future_block_not_cached<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let peer_id = P::new();
+    register_peer(&mut mgr, peer_id).await;
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    let block = Block::new_with_no_consensus(
+        vec![],
+        config.genesis_block_id(),
+        BlockTimestamp::from_duration_since_epoch(now + Duration::from_secs(24 * 60 * 60)),
+    )
+    .unwrap();
+    let block_id = block.get_id();
+
+    let (res, score) = tokio::join!(
+        mgr.process_announcement(peer_id, Announcement::Block(block)),
+        async {
+            match pm.recv().await.unwrap() {
+                PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                    assert_eq!(adjusted_peer_id, peer_id);
+                    tx.send(Ok(()));
+                    score
+                }
+                _ => panic!("unexpected peer manager event"),
+            }
+        }
+    );
+    assert_eq!(res, Ok(()));
+    assert!(score > 0);
+    assert!(mgr.header_cache.get(&block_id).is_none());
+}
+
+#[tokio::test]
+async fn future_block_not_cached_tcp() {
+    future_block_not_cached::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn future_block_not_cached_channels() {
+    future_block_not_cached::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn future_block_not_cached_noise() {
+    future_block_not_cached::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the tip is announced to the connected peers only once
+async fn announce_tip<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr1, mut conn1, _sync1, _pm1) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+
+    // Nothing to announce at genesis
+    assert_eq!(mgr1.announce_tip().await, Ok(()));
+
+    let block = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    )
+    .pop()
+    .unwrap();
+    p2p_test_utils::import_blocks(&mgr1.chainstate_handle, vec![block.clone()]).await;
+
+    assert_eq!(mgr1.announce_tip().await, Ok(()));
+    assert_eq!(mgr1.announce_tip().await, Ok(()));
+
+    match timeout(Duration::from_secs(5), mgr2.handle_mut().poll_next()).await {
+        Ok(Ok(SyncingEvent::Announcement {
+            peer_id: _,
+            announcement: Announcement::Block(announced),
+        })) => assert_eq!(announced, block),
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert!(
+        timeout(Duration::from_millis(500), mgr2.handle_mut().poll_next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn announce_tip_tcp() {
+    announce_tip::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>().await;
+}
+
+#[tokio::test]
+async fn announce_tip_channels() {
+    announce_tip::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>().await;
+}
+
+#[tokio::test]
+async fn announce_tip_noise() {
+    announce_tip::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>().await;
+}
+
+// the block exceeding the chain's size limits is rejected before it's processed or cached
+async fn oversized_block_announced<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    mgr.chain_config = Arc::new(
+        common::chain::config::Builder::test_chain()
+            .max_block_header_size(16)
+            .max_block_size_with_standard_txs(0)
+            .max_block_size_with_smart_contracts(0)
+            .build(),
+    );
+    let peer_id = P::new();
+    register_peer(&mut mgr, peer_id).await;
+
+    let block = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    )
+    .pop()
+    .unwrap();
+    let block_id = block.get_id();
+    let size = block.block_size().size_from_header();
+
+    let (res, score) = tokio::join!(
+        mgr.process_announcement(peer_id, Announcement::Block(block)),
+        async {
+            match pm.recv().await.unwrap() {
+                PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                    assert_eq!(adjusted_peer_id, peer_id);
+                    tx.send(Ok(()));
+                    score
+                }
+                _ => panic!("unexpected peer manager event"),
+            }
+        }
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(score, BlockSizeError::Header(size, 16).ban_score());
+    assert!(mgr.header_cache.get(&block_id).is_none());
+    assert!(mgr.orphan_announcements.is_empty());
+}
+
+#[tokio::test]
+async fn oversized_block_announced_tcp() {
+    oversized_block_announced::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn oversized_block_announced_channels() {
+    oversized_block_announced::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn oversized_block_announced_noise() {
+    oversized_block_announced::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
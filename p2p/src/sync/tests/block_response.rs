@@ -13,15 +13,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use p2p_test_utils::TestBlockInfo;
 
 use crate::testing_utils::{
     TestTransportChannel, TestTransportMaker, TestTransportNoise, TestTransportTcp,
 };
-use chainstate::ChainstateError;
-use common::{chain::block::consensus_data::PoWData, primitives::Idable};
+use chainstate::{
+    chainstate_interface::ChainstateInterface, mock::MockChainstateInterfaceMock, BlockError,
+    ChainstateError,
+};
+use common::{
+    chain::{block::consensus_data::PoWData, Block},
+    primitives::Idable,
+};
 
 use crate::{
     error::{P2pError, PeerError, ProtocolError},
@@ -32,7 +41,9 @@ use crate::{
     },
     sync::{
         peer,
-        tests::{make_sync_manager, register_peer, MakeTestPeerId},
+        tests::{
+            make_sync_manager, make_sync_manager_with_chainstate, register_peer, MakeTestPeerId,
+        },
     },
     ConnectivityService, NetworkingService, SyncingMessagingService,
 };
@@ -306,3 +317,130 @@ async fn invalid_block_noise() {
     invalid_block::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>()
         .await;
 }
+
+fn make_mock_chainstate(
+    mock: MockChainstateInterfaceMock,
+) -> subsystem::Handle<Box<dyn ChainstateInterface>> {
+    let mut man = subsystem::Manager::new("TODO");
+    let handle = man.add_subsystem("chainstate", Box::new(mock) as Box<dyn ChainstateInterface>);
+    tokio::spawn(async move { man.main().await });
+    handle
+}
+
+fn make_block() -> Block {
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let genesis = TestBlockInfo::from_genesis(config.genesis_block());
+    p2p_test_utils::create_block(config, genesis)
+}
+
+// a transient storage failure is retried and the block is processed eventually
+async fn process_block_transient_error<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let mut chainstate = MockChainstateInterfaceMock::new();
+    let attempts_ = Arc::clone(&attempts);
+    chainstate.expect_process_block().times(2).returning(move |_, _| {
+        if attempts_.fetch_add(1, Ordering::SeqCst) == 0 {
+            Err(ChainstateError::ProcessBlockError(
+                BlockError::StorageError(chainstate_storage::Error::Storage(
+                    storage::error::Recoverable::TemporarilyUnavailable,
+                )),
+            ))
+        } else {
+            Ok(None)
+        }
+    });
+
+    let (mgr, _conn, _sync, _pm) = make_sync_manager_with_chainstate::<T>(
+        A::make_transport(),
+        A::make_address(),
+        make_mock_chainstate(chainstate),
+    )
+    .await;
+
+    assert_eq!(mgr.process_block(make_block()).await, Ok(Ok(())));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn process_block_transient_error_tcp() {
+    process_block_transient_error::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn process_block_transient_error_channels() {
+    process_block_transient_error::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn process_block_transient_error_noise() {
+    process_block_transient_error::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>(
+    )
+    .await;
+}
+
+// an invalid block is rejected right away
+async fn process_block_validation_error<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let mut chainstate = MockChainstateInterfaceMock::new();
+    chainstate.expect_process_block().times(1).returning(|_, _| {
+        Err(ChainstateError::ProcessBlockError(
+            BlockError::PrevBlockNotFound,
+        ))
+    });
+
+    let (mgr, _conn, _sync, _pm) = make_sync_manager_with_chainstate::<T>(
+        A::make_transport(),
+        A::make_address(),
+        make_mock_chainstate(chainstate),
+    )
+    .await;
+
+    assert_eq!(
+        mgr.process_block(make_block()).await,
+        Ok(Err(ChainstateError::ProcessBlockError(
+            BlockError::PrevBlockNotFound
+        )))
+    );
+}
+
+#[tokio::test]
+async fn process_block_validation_error_tcp() {
+    process_block_validation_error::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn process_block_validation_error_channels() {
+    process_block_validation_error::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn process_block_validation_error_noise() {
+    process_block_validation_error::<
+        TestTransportNoise,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
@@ -13,16 +13,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{fmt::Debug, sync::Arc};
+
+use common::primitives::BlockHeight;
+
 use crate::testing_utils::{
-    TestTransportChannel, TestTransportMaker, TestTransportNoise, TestTransportTcp,
+    connect_services, TestTransportChannel, TestTransportMaker, TestTransportNoise,
+    TestTransportTcp,
 };
 
 use crate::{
     error::{P2pError, PeerError},
-    net::default_backend::{
-        transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
-        types::PeerId,
-        DefaultNetworkingService,
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            types::PeerId,
+            DefaultNetworkingService,
+        },
+        types::NodeKind,
     },
     sync::tests::{make_sync_manager, register_peer, MakeTestPeerId},
     ConnectivityService, NetworkingService, SyncingMessagingService,
@@ -45,7 +53,7 @@ where
 
     assert_eq!(mgr.peers.len(), 1);
     assert_eq!(
-        mgr.register_peer(peer_id).await,
+        mgr.register_peer(peer_id, NodeKind::Full).await,
         Err(P2pError::PeerError(PeerError::PeerAlreadyExists))
     );
 }
@@ -116,3 +124,62 @@ async fn test_peer_disconnected_channels() {
 async fn test_peer_disconnected_noise() {
     test_peer_disconnected::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>().await;
 }
+
+// the best block is reported to new peers during the handshake
+async fn best_block_in_handshake<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let (mut mgr, mut conn1, _sync, _pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let chain_config = Arc::clone(&mgr.chain_config);
+
+    p2p_test_utils::add_more_blocks(Arc::clone(&chain_config), &mgr.chainstate_handle, 7).await;
+    mgr.update_best_block().await.unwrap();
+    let best_block_id = mgr
+        .chainstate_handle
+        .call(|this| this.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (mut conn2, _sync2) = T::start(
+        A::make_transport(),
+        vec![A::make_address()],
+        chain_config,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let (_address, peer_info1, peer_info2) = connect_services::<T>(&mut conn2, &mut conn1).await;
+
+    // The node without the sync manager doesn't know its best block
+    assert_eq!(peer_info1.best_block, None);
+
+    let best_block = peer_info2.best_block.unwrap();
+    assert_eq!(best_block.height, BlockHeight::new(7));
+    assert_eq!(best_block.id, best_block_id);
+}
+
+#[tokio::test]
+async fn best_block_in_handshake_tcp() {
+    best_block_in_handshake::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn best_block_in_handshake_channels() {
+    best_block_in_handshake::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn best_block_in_handshake_noise() {
+    best_block_in_handshake::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
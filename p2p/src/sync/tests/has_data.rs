@@ -0,0 +1,162 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt::Debug, sync::Arc};
+
+use p2p_test_utils::TestBlockInfo;
+
+use common::primitives::{BlockHeight, Id, Idable, H256};
+
+use crate::{
+    constants::MAX_HAS_DATA_IDS,
+    error::{P2pError, ProtocolError},
+    message::{HasDataRequest, SyncRequest, SyncResponse},
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            DefaultNetworkingService,
+        },
+        types::SyncingEvent,
+    },
+    sync::{
+        peer::PeerContext,
+        tests::{make_sync_manager, make_sync_manager_with_chainstate},
+    },
+    testing_utils::{
+        connect_services, TestTransportChannel, TestTransportMaker, TestTransportNoise,
+        TestTransportTcp,
+    },
+    ConnectivityService, NetworkingService, SyncingMessagingService,
+};
+
+// The responder has only some of the asked blocks and the requester doesn't download the
+// missing ones from it
+async fn has_data<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        3,
+    );
+    let known_ids = blocks.iter().map(|block| block.get_id()).collect::<Vec<_>>();
+    let chainstate = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    p2p_test_utils::import_blocks(&chainstate, blocks).await;
+
+    let (mut mgr1, mut conn1, _sync1, _pm1) =
+        make_sync_manager_with_chainstate::<T>(A::make_transport(), A::make_address(), chainstate)
+            .await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let (_address, _peer_info1, peer_info2) = connect_services::<T>(&mut conn2, &mut conn1).await;
+    let remote_peer_id = peer_info2.peer_id;
+    mgr2.peers.insert(remote_peer_id, PeerContext::new(remote_peer_id));
+
+    let unknown_ids = (1..=2).map(|i| Id::new(H256::from_low_u64_be(i))).collect::<Vec<_>>();
+    let block_ids = vec![unknown_ids[0], known_ids[0], known_ids[1], unknown_ids[1], known_ids[2]];
+    mgr2.send_has_data_request(remote_peer_id, block_ids.clone()).unwrap();
+
+    match mgr1.peer_sync_handle.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id,
+            request_id,
+            request: SyncRequest::HasData(request),
+        } => {
+            assert_eq!(&request.magic_bytes, config.magic_bytes());
+            assert_eq!(request.block_ids, block_ids);
+            mgr1.process_has_data_request(peer_id, request_id, request.block_ids)
+                .await
+                .unwrap()
+        }
+        _ => panic!("invalid event received"),
+    }
+
+    let availability = match mgr2.peer_sync_handle.poll_next().await.unwrap() {
+        SyncingEvent::Response {
+            response: SyncResponse::DataAvailability(response),
+            ..
+        } => {
+            assert_eq!(&response.magic_bytes, config.magic_bytes());
+            response.availability
+        }
+        _ => panic!("invalid event received"),
+    };
+    assert_eq!(availability, vec![false, true, true, false, true]);
+
+    mgr2.process_data_availability_response(remote_peer_id, availability).unwrap();
+    let peer = &mgr2.peers[&remote_peer_id];
+    for (id, expected) in block_ids.iter().zip([false, true, true, false, true]) {
+        assert_eq!(peer.has_block(id, BlockHeight::new(1)), expected);
+    }
+
+    // The response must answer a pending request
+    assert_eq!(
+        mgr2.process_data_availability_response(remote_peer_id, vec![true]),
+        Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+    );
+
+    // Too many blocks can't be asked about at once
+    let block_ids = vec![unknown_ids[0]; MAX_HAS_DATA_IDS + 1];
+    assert_eq!(
+        mgr2.send_has_data_request(remote_peer_id, block_ids.clone()),
+        Err(P2pError::ProtocolError(ProtocolError::TooManyBlockIds(
+            MAX_HAS_DATA_IDS + 1,
+            MAX_HAS_DATA_IDS
+        )))
+    );
+    mgr2.peer_sync_handle
+        .send_request(
+            remote_peer_id,
+            SyncRequest::HasData(HasDataRequest {
+                magic_bytes: *config.magic_bytes(),
+                block_ids,
+            }),
+        )
+        .unwrap();
+    match mgr1.peer_sync_handle.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id,
+            request_id,
+            request: SyncRequest::HasData(request),
+        } => assert_eq!(
+            mgr1.process_has_data_request(peer_id, request_id, request.block_ids).await,
+            Err(P2pError::ProtocolError(ProtocolError::TooManyBlockIds(
+                MAX_HAS_DATA_IDS + 1,
+                MAX_HAS_DATA_IDS
+            )))
+        ),
+        _ => panic!("invalid event received"),
+    }
+}
+
+#[tokio::test]
+async fn has_data_tcp() {
+    has_data::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>().await;
+}
+
+#[tokio::test]
+async fn has_data_channels() {
+    has_data::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>().await;
+}
+
+#[tokio::test]
+async fn has_data_noise() {
+    has_data::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>().await;
+}
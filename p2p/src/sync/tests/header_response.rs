@@ -20,14 +20,24 @@ use crypto::random::{Rng, SliceRandom};
 use crate::testing_utils::{
     TestTransportChannel, TestTransportMaker, TestTransportNoise, TestTransportTcp,
 };
+use chainstate::Locator;
+use common::{
+    chain::GenBlock,
+    primitives::{BlockHeight, Id, Idable, H256},
+    Uint256,
+};
 use p2p_test_utils::TestBlockInfo;
 
 use crate::{
     error::{P2pError, PeerError, ProtocolError},
-    net::default_backend::{
-        transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
-        types::PeerId,
-        DefaultNetworkingService,
+    message::HeaderListResponse,
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            types::PeerId,
+            DefaultNetworkingService,
+        },
+        types::{BestBlock, NodeKind},
     },
     sync::{
         peer,
@@ -384,3 +394,428 @@ async fn peer_doesnt_exist_noise() {
     peer_doesnt_exist::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>()
         .await;
 }
+
+// pruned peer doesn't have the blocks below its prune height so they are downloaded from another peer
+async fn pruned_peer_not_selected<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let pruned_peer_id = P::new();
+    let full_peer_id = P::new();
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_transport(), addr).await;
+    register_peer(&mut mgr, pruned_peer_id).await;
+    register_peer(&mut mgr, full_peer_id).await;
+
+    mgr.peers.get_mut(&pruned_peer_id).unwrap().set_node_kind(NodeKind::Pruned {
+        prune_height: BlockHeight::new(100),
+    });
+    mgr.peers.get_mut(&full_peer_id).unwrap().set_state(peer::PeerSyncState::Idle);
+
+    let headers = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        10,
+    )
+    .iter()
+    .map(|block| block.header().clone())
+    .collect::<Vec<_>>();
+
+    assert_eq!(
+        mgr.process_header_response(
+            pruned_peer_id,
+            HeaderListResponse::new(
+                *config.magic_bytes(),
+                config.genesis_block_id(),
+                headers.clone()
+            )
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr.peers.get(&pruned_peer_id).unwrap().state(),
+        &peer::PeerSyncState::Idle
+    );
+    assert_eq!(
+        mgr.peers.get(&full_peer_id).unwrap().state(),
+        &peer::PeerSyncState::UploadingBlocks(headers[0].get_id())
+    );
+}
+
+#[tokio::test]
+async fn pruned_peer_not_selected_tcp() {
+    pruned_peer_not_selected::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn pruned_peer_not_selected_channels() {
+    pruned_peer_not_selected::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn pruned_peer_not_selected_noise() {
+    pruned_peer_not_selected::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the idle peer with the most verified chain work is selected, not the one with the highest tip
+// or the one that claims more work
+async fn most_work_peer_selected<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let pruned_peer_id = P::new();
+    let high_peer_id = P::new();
+    let heavy_peer_id = P::new();
+    let lying_peer_id = P::new();
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_transport(), addr).await;
+    register_peer(&mut mgr, pruned_peer_id).await;
+    register_peer(&mut mgr, high_peer_id).await;
+    register_peer(&mut mgr, heavy_peer_id).await;
+    register_peer(&mut mgr, lying_peer_id).await;
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        10,
+    );
+    p2p_test_utils::import_blocks(&mgr.chainstate_handle, blocks[..5].to_vec()).await;
+    let known_block_id: Id<GenBlock> = blocks[4].get_id().into();
+    let known_chain_trust = *mgr
+        .chainstate_handle
+        .call(move |this| this.get_gen_block_index(&known_block_id))
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .chain_trust();
+    let headers = blocks.iter().map(|block| block.header().clone()).collect::<Vec<_>>();
+
+    mgr.peers.get_mut(&pruned_peer_id).unwrap().set_node_kind(NodeKind::Pruned {
+        prune_height: BlockHeight::new(100),
+    });
+
+    // the best block is unknown, so the claimed chain trust isn't recorded
+    assert_eq!(
+        mgr.set_peer_best_block(
+            high_peer_id,
+            BestBlock {
+                id: Id::new(H256::from_low_u64_be(1)),
+                height: BlockHeight::new(100),
+                chain_trust: Uint256::from_u64(u64::MAX),
+            },
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(mgr.peers.get(&high_peer_id).unwrap().chain_trust(), None);
+
+    assert_eq!(
+        mgr.set_peer_best_block(
+            heavy_peer_id,
+            BestBlock {
+                id: known_block_id,
+                height: BlockHeight::new(5),
+                chain_trust: known_chain_trust,
+            },
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr.peers.get(&heavy_peer_id).unwrap().chain_trust(),
+        Some(known_chain_trust)
+    );
+
+    // the chain trust doesn't match the one of the known block
+    assert_eq!(
+        mgr.set_peer_best_block(
+            lying_peer_id,
+            BestBlock {
+                id: known_block_id,
+                height: BlockHeight::new(5),
+                chain_trust: Uint256::from_u64(u64::MAX),
+            },
+        )
+        .await,
+        Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+    );
+    assert_eq!(mgr.peers.get(&lying_peer_id).unwrap().chain_trust(), None);
+
+    mgr.peers.get_mut(&high_peer_id).unwrap().set_state(peer::PeerSyncState::Idle);
+    mgr.peers.get_mut(&heavy_peer_id).unwrap().set_state(peer::PeerSyncState::Idle);
+
+    assert_eq!(
+        mgr.process_header_response(
+            pruned_peer_id,
+            HeaderListResponse::new(
+                *config.magic_bytes(),
+                config.genesis_block_id(),
+                headers.clone()
+            )
+        )
+        .await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr.peers.get(&heavy_peer_id).unwrap().state(),
+        &peer::PeerSyncState::UploadingBlocks(headers[5].get_id())
+    );
+    assert_eq!(
+        mgr.peers.get(&high_peer_id).unwrap().state(),
+        &peer::PeerSyncState::Idle
+    );
+}
+
+#[tokio::test]
+async fn most_work_peer_selected_tcp() {
+    most_work_peer_selected::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn most_work_peer_selected_channels() {
+    most_work_peer_selected::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn most_work_peer_selected_noise() {
+    most_work_peer_selected::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the locator is matched deep in the chain and the fork point is the matched entry
+#[tokio::test]
+async fn fork_point_deep_in_chain() {
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let handle = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        10,
+    );
+    p2p_test_utils::import_blocks(&handle, blocks.clone()).await;
+
+    // the first entries are unknown to the responder
+    let locator = Locator::new(vec![
+        Id::new(H256::from_low_u64_be(1)),
+        Id::new(H256::from_low_u64_be(2)),
+        blocks[2].get_id().into(),
+        config.genesis_block_id(),
+    ]);
+    let response = handle
+        .call(move |this| HeaderListResponse::from_chainstate(this.as_ref(), locator))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(*response.fork_point(), blocks[2].get_id());
+    assert_eq!(
+        response.headers(),
+        blocks[3..].iter().map(|block| block.header().clone()).collect::<Vec<_>>()
+    );
+
+    // the local node has all the blocks so the fork point is its tip
+    let locator = Locator::new(vec![blocks[9].get_id().into()]);
+    let response = handle
+        .call(move |this| HeaderListResponse::from_chainstate(this.as_ref(), locator))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(*response.fork_point(), blocks[9].get_id());
+    assert!(response.headers().is_empty());
+}
+
+// the headers don't descend from the reported fork point
+async fn headers_dont_descend_from_fork_point<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let peer_id = P::new();
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_transport(), addr).await;
+    register_peer(&mut mgr, peer_id).await;
+
+    let headers = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        10,
+    )
+    .iter()
+    .map(|block| block.header().clone())
+    .collect::<Vec<_>>();
+
+    // the fork point is known but the headers start one block later
+    assert_eq!(
+        mgr.process_header_response(
+            peer_id,
+            HeaderListResponse::new(
+                *config.magic_bytes(),
+                config.genesis_block_id(),
+                headers[1..].to_vec()
+            )
+        )
+        .await,
+        Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+    );
+}
+
+#[tokio::test]
+async fn headers_dont_descend_from_fork_point_tcp() {
+    headers_dont_descend_from_fork_point::<
+        TestTransportTcp,
+        PeerId,
+        DefaultNetworkingService<TcpTransportSocket>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn headers_dont_descend_from_fork_point_channels() {
+    headers_dont_descend_from_fork_point::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn headers_dont_descend_from_fork_point_noise() {
+    headers_dont_descend_from_fork_point::<
+        TestTransportNoise,
+        PeerId,
+        DefaultNetworkingService<NoiseTcpTransport>,
+    >()
+    .await;
+}
+
+// the reported fork point is neither in the sent locator nor in the local chain
+async fn unknown_fork_point<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let peer_id = P::new();
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_transport(), addr).await;
+    register_peer(&mut mgr, peer_id).await;
+
+    let headers = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        10,
+    )
+    .iter()
+    .map(|block| block.header().clone())
+    .collect::<Vec<_>>();
+
+    // an empty response must also report a known fork point
+    let unknown_fork_point = Id::new(H256::from_low_u64_be(1));
+    assert_eq!(
+        mgr.process_header_response(
+            peer_id,
+            HeaderListResponse::new(*config.magic_bytes(), unknown_fork_point, Vec::new())
+        )
+        .await,
+        Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+    );
+
+    // the headers descend from the fork point, but it isn't known to the local node
+    let unknown_fork_point = headers[3].get_id().into();
+    assert_eq!(
+        mgr.process_header_response(
+            peer_id,
+            HeaderListResponse::new(
+                *config.magic_bytes(),
+                unknown_fork_point,
+                headers[4..].to_vec()
+            )
+        )
+        .await,
+        Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+    );
+
+    // the local node is in sync with the peer
+    assert_eq!(
+        mgr.process_header_response(
+            peer_id,
+            HeaderListResponse::new(*config.magic_bytes(), config.genesis_block_id(), Vec::new())
+        )
+        .await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn unknown_fork_point_tcp() {
+    unknown_fork_point::<TestTransportTcp, PeerId, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn unknown_fork_point_channels() {
+    unknown_fork_point::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn unknown_fork_point_noise() {
+    unknown_fork_point::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
@@ -13,16 +13,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod announcement;
 mod block_response;
 mod connection;
+mod has_data;
 mod header_response;
+mod network_mismatch;
+mod orphan_transactions;
+mod relay_fee;
 mod request_response;
+mod request_timeout;
+mod token_info;
 
 use std::sync::Arc;
 
 use tokio::sync::mpsc;
 
-use chainstate::{make_chainstate, ChainstateConfig, DefaultTransactionVerificationStrategy};
+use chainstate::{
+    chainstate_interface::ChainstateInterface, make_chainstate, ChainstateConfig,
+    DefaultTransactionVerificationStrategy,
+};
 
 use crate::{
     config::{NodeType, P2pConfig},
@@ -48,8 +58,6 @@ where
     T::PeerRequestId: 'static,
     T::PeerId: 'static,
 {
-    let (tx_p2p_sync, rx_p2p_sync) = mpsc::unbounded_channel();
-    let (tx_pm, rx_pm) = mpsc::unbounded_channel();
     let storage = chainstate_storage::inmemory::Store::new_empty().unwrap();
     let chain_config = Arc::new(common::chain::config::create_unit_test_config());
     let chainstate_config = ChainstateConfig::new();
@@ -68,17 +76,83 @@ where
     );
     tokio::spawn(async move { man.main().await });
 
+    make_sync_manager_with_chainstate::<T>(transport, addr, handle).await
+}
+
+async fn make_sync_manager_with_chainstate<T>(
+    transport: T::Transport,
+    addr: T::Address,
+    handle: subsystem::Handle<Box<dyn ChainstateInterface>>,
+) -> (
+    BlockSyncManager<T>,
+    T::ConnectivityHandle,
+    mpsc::UnboundedSender<SyncControlEvent<T>>,
+    mpsc::UnboundedReceiver<PeerManagerEvent<T>>,
+)
+where
+    T: NetworkingService,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+    T::PeerRequestId: 'static,
+    T::PeerId: 'static,
+{
+    let (tx_p2p_sync, rx_p2p_sync) = mpsc::unbounded_channel();
+    let (tx_pm, rx_pm) = mpsc::unbounded_channel();
+
     let chain_config = Arc::new(common::chain::config::create_unit_test_config());
     let p2p_config = Arc::new(P2pConfig {
         bind_addresses: vec!["/ip6/::1/tcp/3031".to_owned()],
         added_nodes: Vec::new(),
+        preconfigured_peers: Default::default(),
         ban_threshold: 100.into(),
         ban_duration: Default::default(),
         outbound_connection_timeout: Default::default(),
+        handshake_timeout: Default::default(),
         ping_check_period: Default::default(),
         ping_timeout: Default::default(),
+        ping_max_retries: Default::default(),
         node_type: NodeType::Full.into(),
         allow_discover_private_ips: Default::default(),
+        observer_mode: Default::default(),
+        max_outbound_announcement_queue: Default::default(),
+        node_kind: Default::default(),
+        health_min_peers: Default::default(),
+        health_max_tip_age: Default::default(),
+        max_send_bytes_per_second: Default::default(),
+        max_addresses: Default::default(),
+        advertise_self: Default::default(),
+        max_active_connections: Default::default(),
+        max_concurrent_dials: Default::default(),
+        stall_grace_period: Default::default(),
+        min_protocol_version: Default::default(),
+        max_announcer_tip_lag: Default::default(),
+        dns_seeds: Default::default(),
+        dns_seed_refresh_interval: Default::default(),
+        send_timeout: Default::default(),
+        max_handshake_size: Default::default(),
+        disconnect_on_protocol_error: Default::default(),
+        max_addr_per_message: Default::default(),
+        max_message_size: Default::default(),
+        max_relay_peers: Default::default(),
+        max_orphan_announcements: Default::default(),
+        peer_selection_recent_period: Default::default(),
+        peer_selection_recent_weight: Default::default(),
+        peer_selection_diverse_weight: Default::default(),
+        peer_selection_failure_penalty: Default::default(),
+        disable_peer_discovery: Default::default(),
+        peerdb_encryption_key: Default::default(),
+        announcement_trickle_interval: Default::default(),
+        max_pending_requests: Default::default(),
+        stale_tip_timeout: Default::default(),
+        header_request_timeout: Default::default(),
+        block_request_timeout: Default::default(),
+        reserved_diverse_outbound_slots: Default::default(),
+        max_concurrent_serving_requests: Default::default(),
+        max_concurrent_serving_requests_per_peer: Default::default(),
+        max_orphan_transactions: Default::default(),
+        listen_backlog: Default::default(),
+        max_pending_requests_per_peer: Default::default(),
+        min_relay_fee_rate: Default::default(),
     });
     let (conn, sync) = T::start(
         transport,
@@ -88,9 +162,18 @@ where
     )
     .await
     .unwrap();
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&chain_config), handle.clone()).await;
 
     (
-        BlockSyncManager::<T>::new(chain_config, p2p_config, sync, handle, rx_p2p_sync, tx_pm),
+        BlockSyncManager::<T>::new(
+            chain_config,
+            p2p_config,
+            sync,
+            handle,
+            mempool,
+            rx_p2p_sync,
+            tx_pm,
+        ),
         conn,
         tx_p2p_sync,
         rx_pm,
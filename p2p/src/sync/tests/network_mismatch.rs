@@ -0,0 +1,134 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+
+use chainstate::{ban_score::BanScore, Locator};
+use common::primitives::H256;
+
+use crate::{
+    error::ProtocolError,
+    event::PeerManagerEvent,
+    message::{HeaderListRequest, SyncRequest, TokenInfoRequest},
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            DefaultNetworkingService,
+        },
+        types::SyncingEvent,
+    },
+    sync::tests::make_sync_manager,
+    testing_utils::{
+        connect_services, TestTransportChannel, TestTransportMaker, TestTransportNoise,
+        TestTransportTcp,
+    },
+    ConnectivityService, NetworkingService, SyncingMessagingService,
+};
+
+// The peer that sends a sync request made for another chain is punished and disconnected,
+// whatever the type of the request
+async fn different_network_request<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let requests: [fn([u8; 4]) -> SyncRequest; 2] = [
+        |magic_bytes| {
+            SyncRequest::HeaderListRequest(HeaderListRequest::new(
+                magic_bytes,
+                Locator::new(vec![]),
+            ))
+        },
+        |magic_bytes| {
+            SyncRequest::TokenInfoRequest(TokenInfoRequest {
+                magic_bytes,
+                token_id: H256::from_low_u64_be(1),
+            })
+        },
+    ];
+
+    for make_request in requests {
+        let (mut mgr1, mut conn1, _sync1, mut pm1) =
+            make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+        let (mut mgr2, mut conn2, _sync2, _pm2) =
+            make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+        let (_address, _peer_info1, peer_info2) =
+            connect_services::<T>(&mut conn1, &mut conn2).await;
+
+        let magic_bytes = *mgr1.chain_config.magic_bytes();
+        let other_magic_bytes = magic_bytes.map(|byte| !byte);
+        mgr2.peer_sync_handle
+            .send_request(peer_info2.peer_id, make_request(other_magic_bytes))
+            .unwrap();
+
+        let (peer_id, request_id, request) = match mgr1.peer_sync_handle.poll_next().await.unwrap()
+        {
+            SyncingEvent::Request {
+                peer_id,
+                request_id,
+                request,
+            } => (peer_id, request_id, request),
+            _ => panic!("invalid event received"),
+        };
+        assert_eq!(request.magic_bytes(), &other_magic_bytes);
+
+        let (res, score) =
+            tokio::join!(mgr1.process_request(peer_id, request_id, request), async {
+                match pm1.recv().await.unwrap() {
+                    PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                        assert_eq!(adjusted_peer_id, peer_id);
+                        tx.send(Ok(()));
+                        score
+                    }
+                    _ => panic!("unexpected peer manager event"),
+                }
+            });
+        assert_eq!(res, Ok(()));
+        assert_eq!(
+            score,
+            ProtocolError::DifferentNetwork(magic_bytes, other_magic_bytes).ban_score()
+        );
+
+        match pm1.recv().await.unwrap() {
+            PeerManagerEvent::Disconnect(disconnected_peer_id, _) => {
+                assert_eq!(disconnected_peer_id, peer_id)
+            }
+            _ => panic!("unexpected peer manager event"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn different_network_request_tcp() {
+    different_network_request::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn different_network_request_channels() {
+    different_network_request::<
+        TestTransportChannel,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn different_network_request_noise() {
+    different_network_request::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use chainstate::ban_score::BanScore;
+use mempool::error::TxValidationError;
+
+use crate::{
+    config::P2pConfig,
+    error::ProtocolError,
+    event::PeerManagerEvent,
+    message::Announcement,
+    net::default_backend::{
+        transport::MpscChannelTransport, types::PeerId, DefaultNetworkingService,
+    },
+    sync::{
+        orphan_transactions::{
+            tests::{make_tx, tx_id},
+            OrphanTransactionPool,
+        },
+        tests::{make_sync_manager, register_peer},
+    },
+    testing_utils::{TestTransportChannel, TestTransportMaker},
+};
+
+type TestNetworkingService = DefaultNetworkingService<MpscChannelTransport>;
+
+// the oldest orphans are evicted when the pool is full and the flooding peer is punished
+#[tokio::test]
+async fn orphan_transactions_limited() {
+    let (mut mgr, _conn, _sync, mut pm) = make_sync_manager::<TestNetworkingService>(
+        TestTransportChannel::make_transport(),
+        TestTransportChannel::make_address(),
+    )
+    .await;
+    mgr.p2p_config = Arc::new(P2pConfig {
+        max_orphan_transactions: 3.into(),
+        ..Default::default()
+    });
+    mgr.orphan_transactions = OrphanTransactionPool::new(3);
+    let peer_id1 = PeerId::new();
+    register_peer(&mut mgr, peer_id1).await;
+    let peer_id2 = PeerId::new();
+    register_peer(&mut mgr, peer_id2).await;
+
+    let parents = (0..5).map(|i| tx_id(&make_tx(&[], i))).collect::<Vec<_>>();
+    let children = parents.iter().map(|parent_id| make_tx(&[*parent_id], 0)).collect::<Vec<_>>();
+
+    for (parent_id, child) in parents.iter().zip(&children).take(3) {
+        assert_eq!(
+            mgr.cache_orphan_transaction(peer_id1, child.clone(), BTreeSet::from([*parent_id]))
+                .await,
+            Ok(())
+        );
+    }
+    assert_eq!(mgr.orphan_transaction_count(), 3);
+    assert!(pm.try_recv().is_err());
+
+    // The pool is full, the oldest orphan is evicted and the peer that fills the pool is punished
+    let (res, score) = tokio::join!(
+        mgr.cache_orphan_transaction(peer_id1, children[3].clone(), BTreeSet::from([parents[3]])),
+        async {
+            match pm.recv().await.unwrap() {
+                PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                    assert_eq!(adjusted_peer_id, peer_id1);
+                    tx.send(Ok(()));
+                    score
+                }
+                _ => panic!("unexpected peer manager event"),
+            }
+        }
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(score, ProtocolError::TooManyOrphanTransactions.ban_score());
+    assert_eq!(mgr.orphan_transaction_count(), 3);
+    assert!(mgr.take_orphan_transactions(&parents[0]).is_empty());
+
+    // Another peer isn't punished for sending an orphan when the pool is full
+    assert_eq!(
+        mgr.cache_orphan_transaction(peer_id2, children[4].clone(), BTreeSet::from([parents[4]]))
+            .await,
+        Ok(())
+    );
+    assert_eq!(mgr.orphan_transaction_count(), 3);
+    assert!(pm.try_recv().is_err());
+
+    // The orphan is handed back once its parent arrives
+    assert_eq!(
+        mgr.take_orphan_transactions(&parents[4]),
+        vec![(peer_id2, children[4].clone())]
+    );
+
+    // The orphans of the disconnected peer are discarded
+    mgr.unregister_peer(peer_id1);
+    assert_eq!(mgr.orphan_transaction_count(), 0);
+}
+
+// the relayed transaction spending the outputs of an unknown transaction ends up in the pool
+#[tokio::test]
+async fn orphan_transaction_announcement() {
+    let (mut mgr, _conn, _sync, mut pm) = make_sync_manager::<TestNetworkingService>(
+        TestTransportChannel::make_transport(),
+        TestTransportChannel::make_address(),
+    )
+    .await;
+    let peer_id = PeerId::new();
+    register_peer(&mut mgr, peer_id).await;
+
+    let parent_id = tx_id(&make_tx(&[], 0));
+    let child = make_tx(&[parent_id], 0);
+    assert_eq!(
+        mgr.process_announcement(peer_id, Announcement::Transaction(child.clone()))
+            .await,
+        Ok(())
+    );
+    assert_eq!(mgr.orphan_transaction_count(), 1);
+    assert!(pm.try_recv().is_err());
+
+    // The same orphan sent again is ignored
+    assert_eq!(
+        mgr.process_announcement(peer_id, Announcement::Transaction(child.clone()))
+            .await,
+        Ok(())
+    );
+    assert_eq!(mgr.orphan_transaction_count(), 1);
+
+    assert_eq!(
+        mgr.take_orphan_transactions(&parent_id),
+        vec![(peer_id, child)]
+    );
+}
+
+// the peer relaying a transaction that can never be valid is punished
+#[tokio::test]
+async fn invalid_transaction_announcement() {
+    let (mut mgr, _conn, _sync, mut pm) = make_sync_manager::<TestNetworkingService>(
+        TestTransportChannel::make_transport(),
+        TestTransportChannel::make_address(),
+    )
+    .await;
+    let peer_id = PeerId::new();
+    register_peer(&mut mgr, peer_id).await;
+
+    // A transaction without inputs
+    let tx = make_tx(&[], 0);
+    let (res, score) = tokio::join!(
+        mgr.process_announcement(peer_id, Announcement::Transaction(tx)),
+        async {
+            match pm.recv().await.unwrap() {
+                PeerManagerEvent::AdjustPeerScore(adjusted_peer_id, score, tx) => {
+                    assert_eq!(adjusted_peer_id, peer_id);
+                    tx.send(Ok(()));
+                    score
+                }
+                _ => panic!("unexpected peer manager event"),
+            }
+        }
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(score, TxValidationError::NoInputs.ban_score());
+    assert_eq!(mgr.orphan_transaction_count(), 0);
+}
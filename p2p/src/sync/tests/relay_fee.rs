@@ -0,0 +1,119 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::timeout;
+
+use common::{
+    chain::{
+        signature::inputsig::InputWitness, tokens::OutputValue, ChainConfig, Destination,
+        OutPointSourceId, OutputPurpose, SignedTransaction, Transaction, TxInput, TxOutput,
+    },
+    primitives::{Amount, Idable},
+};
+
+use crate::{
+    config::P2pConfig,
+    message::Announcement,
+    net::{
+        default_backend::{
+            transport::MpscChannelTransport, types::PeerId, DefaultNetworkingService,
+        },
+        types::SyncingEvent,
+    },
+    sync::tests::{make_sync_manager, register_peer},
+    testing_utils::{connect_services, TestTransportChannel, TestTransportMaker},
+    SyncingMessagingService,
+};
+
+type TestNetworkingService = DefaultNetworkingService<MpscChannelTransport>;
+
+fn make_genesis_spending_tx(config: &ChainConfig, fee: Amount) -> SignedTransaction {
+    let genesis = config.genesis_block();
+    let input = TxInput::new(OutPointSourceId::BlockReward(genesis.get_id().into()), 0);
+    let value = match genesis.utxos()[0].value() {
+        OutputValue::Coin(amount) => (*amount - fee).unwrap(),
+        OutputValue::Token(_) => panic!("unexpected genesis output"),
+    };
+    let output = TxOutput::new(
+        OutputValue::Coin(value),
+        OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+    );
+    let tx = Transaction::new(0, vec![input], vec![output], 0).unwrap();
+    SignedTransaction::new(tx, vec![InputWitness::NoSignature(None)]).unwrap()
+}
+
+// the transaction paying less than the minimum relay fee rate is ignored without punishing the peer
+#[tokio::test]
+async fn min_relay_fee_rate() {
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr1, mut conn1, _sync1, mut pm1) = make_sync_manager::<TestNetworkingService>(
+        TestTransportChannel::make_transport(),
+        TestTransportChannel::make_address(),
+    )
+    .await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<TestNetworkingService>(
+        TestTransportChannel::make_transport(),
+        TestTransportChannel::make_address(),
+    )
+    .await;
+    connect_services::<TestNetworkingService>(&mut conn1, &mut conn2).await;
+
+    // 10 atoms per byte, the mempool itself only requires 1 atom per byte
+    mgr1.p2p_config = Arc::new(P2pConfig {
+        min_relay_fee_rate: Amount::from_atoms(10_000).into(),
+        ..Default::default()
+    });
+    let peer_id = PeerId::new();
+    register_peer(&mut mgr1, peer_id).await;
+
+    let low_fee_tx = make_genesis_spending_tx(&config, Amount::from_atoms(200));
+    let low_fee_tx_id = low_fee_tx.transaction().get_id();
+    assert_eq!(
+        mgr1.process_announcement(peer_id, Announcement::Transaction(low_fee_tx)).await,
+        Ok(())
+    );
+    assert!(pm1.try_recv().is_err());
+    assert!(!mgr1
+        .mempool_handle
+        .call_async(move |this| Box::pin(
+            async move { this.contains_transaction(&low_fee_tx_id).await }
+        ))
+        .await
+        .unwrap()
+        .unwrap());
+
+    // The transaction paying enough spends the same output
+    let tx = make_genesis_spending_tx(&config, Amount::from_atoms(100_000));
+    assert_eq!(
+        mgr1.process_announcement(peer_id, Announcement::Transaction(tx.clone())).await,
+        Ok(())
+    );
+    assert!(pm1.try_recv().is_err());
+
+    match timeout(Duration::from_secs(5), mgr2.handle_mut().poll_next()).await {
+        Ok(Ok(SyncingEvent::Announcement {
+            peer_id: _,
+            announcement: Announcement::Transaction(relayed),
+        })) => assert_eq!(relayed, tx),
+        event => panic!("unexpected event: {event:?}"),
+    }
+    assert!(
+        timeout(Duration::from_millis(500), mgr2.handle_mut().poll_next())
+            .await
+            .is_err()
+    );
+}
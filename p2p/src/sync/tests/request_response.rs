@@ -54,7 +54,10 @@ where
     mgr1.peer_sync_handle
         .send_request(
             peer_info2.peer_id,
-            SyncRequest::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![]))),
+            SyncRequest::HeaderListRequest(HeaderListRequest::new(
+                *mgr1.chain_config.magic_bytes(),
+                Locator::new(vec![]),
+            )),
         )
         .unwrap();
 
@@ -66,13 +69,20 @@ where
     {
         assert_eq!(
             request,
-            SyncRequest::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![])))
+            SyncRequest::HeaderListRequest(HeaderListRequest::new(
+                *mgr1.chain_config.magic_bytes(),
+                Locator::new(vec![])
+            ))
         );
 
         mgr2.peer_sync_handle
             .send_response(
                 request_id,
-                SyncResponse::HeaderListResponse(HeaderListResponse::new(vec![])),
+                SyncResponse::HeaderListResponse(HeaderListResponse::new(
+                    *mgr2.chain_config.magic_bytes(),
+                    mgr2.chain_config.genesis_block_id(),
+                    vec![],
+                )),
             )
             .unwrap();
     } else {
@@ -119,7 +129,10 @@ where
         .peer_sync_handle
         .send_request(
             peer_info2.peer_id,
-            SyncRequest::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![]))),
+            SyncRequest::HeaderListRequest(HeaderListRequest::new(
+                *mgr1.chain_config.magic_bytes(),
+                Locator::new(vec![]),
+            )),
         )
         .unwrap();
     request_ids.insert(id);
@@ -128,7 +141,10 @@ where
         .peer_sync_handle
         .send_request(
             peer_info2.peer_id,
-            SyncRequest::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![]))),
+            SyncRequest::HeaderListRequest(HeaderListRequest::new(
+                *mgr1.chain_config.magic_bytes(),
+                Locator::new(vec![]),
+            )),
         )
         .unwrap();
     request_ids.insert(id);
@@ -142,7 +158,11 @@ where
                     mgr2.peer_sync_handle
                         .send_response(
                             request_id,
-                            SyncResponse::HeaderListResponse(HeaderListResponse::new(vec![])),
+                            SyncResponse::HeaderListResponse(HeaderListResponse::new(
+                                *mgr2.chain_config.magic_bytes(),
+                                mgr2.chain_config.genesis_block_id(),
+                                vec![],
+                            )),
                         )
                         .unwrap();
                 }
@@ -0,0 +1,197 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use p2p_test_utils::TestBlockInfo;
+use tokio::time::Instant;
+
+use common::primitives::Idable;
+
+use crate::{
+    config::{BlockRequestTimeout, HeaderRequestTimeout},
+    event::PeerManagerEvent,
+    message::{ErrorResponse, ResponseError, SyncResponse},
+    net::default_backend::{
+        transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+        types::PeerId,
+        DefaultNetworkingService,
+    },
+    sync::{
+        tests::{make_sync_manager, register_peer, MakeTestPeerId},
+        RATE_LIMITED_RETRY_DELAY,
+    },
+    testing_utils::{
+        TestTransportChannel, TestTransportMaker, TestTransportNoise, TestTransportTcp,
+    },
+    ConnectivityService, NetworkingService, SyncingMessagingService,
+};
+
+// A slow header request times out with the short timeout, a block request that takes longer
+// but fits into its own timeout succeeds
+async fn request_timeouts<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let header_timeout = *HeaderRequestTimeout::default();
+    let block_timeout = *BlockRequestTimeout::default();
+    assert!(header_timeout < block_timeout);
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let block = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    )
+    .pop()
+    .unwrap();
+
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let header_peer = P::new();
+    let block_peer = P::new();
+    register_peer(&mut mgr, header_peer).await;
+    register_peer(&mut mgr, block_peer).await;
+
+    let start = Instant::now();
+    let locator = mgr.chainstate_handle.call(|this| this.get_locator()).await.unwrap().unwrap();
+    mgr.send_header_request(header_peer, locator).unwrap();
+    mgr.send_block_request(block_peer, block.get_id()).unwrap();
+
+    // Nothing times out too early
+    mgr.check_request_timeouts(start).unwrap();
+    assert!(pm.try_recv().is_err());
+
+    // The header request takes longer than its timeout
+    let now = start + header_timeout + Duration::from_secs(1);
+    mgr.check_request_timeouts(now).unwrap();
+    match pm.try_recv() {
+        Ok(PeerManagerEvent::Disconnect(peer_id, _)) => assert_eq!(peer_id, header_peer),
+        _ => panic!("invalid event received"),
+    }
+    assert!(pm.try_recv().is_err());
+
+    // The timeout is reported once
+    mgr.check_request_timeouts(now).unwrap();
+    assert!(pm.try_recv().is_err());
+
+    // The block request is slower than the header timeout, but within its own
+    mgr.check_request_timeouts(start + block_timeout - Duration::from_secs(1))
+        .unwrap();
+    assert!(pm.try_recv().is_err());
+    mgr.process_block_response(block_peer, vec![block]).await.unwrap();
+    assert!(pm.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn request_timeouts_tcp() {
+    request_timeouts::<TestTransportTcp, PeerId, DefaultNetworkingService<TcpTransportSocket>>()
+        .await;
+}
+
+#[tokio::test]
+async fn request_timeouts_channels() {
+    request_timeouts::<TestTransportChannel, PeerId, DefaultNetworkingService<MpscChannelTransport>>()
+        .await;
+}
+
+#[tokio::test]
+async fn request_timeouts_noise() {
+    request_timeouts::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
+
+// A rate limited block request doesn't time out, it's sent again after a delay
+async fn rate_limited_request<A, P, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let block_timeout = *BlockRequestTimeout::default();
+    assert!(RATE_LIMITED_RETRY_DELAY < block_timeout);
+
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let block = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    )
+    .pop()
+    .unwrap();
+
+    let (mut mgr, _conn, _sync, mut pm) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let peer = P::new();
+    register_peer(&mut mgr, peer).await;
+
+    let start = Instant::now();
+    mgr.send_block_request(peer, block.get_id()).unwrap();
+    let request_id = mgr.peers[&peer].block_request_id().unwrap();
+
+    mgr.process_response(
+        peer,
+        request_id,
+        SyncResponse::Error(ErrorResponse {
+            magic_bytes: *config.magic_bytes(),
+            error: ResponseError::RateLimited,
+        }),
+    )
+    .await
+    .unwrap();
+    assert!(pm.try_recv().is_err());
+    assert_eq!(mgr.peers[&peer].block_request_id(), None);
+
+    // The request isn't sent again too early
+    mgr.check_request_timeouts(start).unwrap();
+    assert_eq!(mgr.peers[&peer].block_request_id(), None);
+
+    // The rejected request doesn't time out, it's sent again instead
+    mgr.check_request_timeouts(start + block_timeout + Duration::from_secs(1))
+        .unwrap();
+    assert!(pm.try_recv().is_err());
+    assert!(mgr.peers[&peer].block_request_id().is_some());
+    mgr.process_block_response(peer, vec![block]).await.unwrap();
+    assert!(pm.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn rate_limited_request_tcp() {
+    rate_limited_request::<TestTransportTcp, PeerId, DefaultNetworkingService<TcpTransportSocket>>(
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn rate_limited_request_channels() {
+    rate_limited_request::<
+        TestTransportChannel,
+        PeerId,
+        DefaultNetworkingService<MpscChannelTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn rate_limited_request_noise() {
+    rate_limited_request::<TestTransportNoise, PeerId, DefaultNetworkingService<NoiseTcpTransport>>()
+        .await;
+}
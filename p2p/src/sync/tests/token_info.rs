@@ -0,0 +1,154 @@
+// Copyright (c) 2023 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt::Debug, sync::Arc};
+
+use p2p_test_utils::TestBlockInfo;
+
+use common::{
+    chain::tokens::{TokenId, TokenIssuance},
+    primitives::{Amount, H256},
+};
+
+use crate::{
+    message::{SyncRequest, SyncResponse, TokenInfoRequest, TokenInfoResponse},
+    net::{
+        default_backend::{
+            transport::{MpscChannelTransport, NoiseTcpTransport, TcpTransportSocket},
+            DefaultNetworkingService,
+        },
+        types::SyncingEvent,
+    },
+    sync::{
+        tests::{make_sync_manager, make_sync_manager_with_chainstate},
+        BlockSyncManager,
+    },
+    testing_utils::{
+        connect_services, TestTransportChannel, TestTransportMaker, TestTransportNoise,
+        TestTransportTcp,
+    },
+    ConnectivityService, NetworkingService, SyncingMessagingService,
+};
+
+// Sends the token info request from `mgr2` and answers it by `mgr1`
+async fn request_token_info<T>(
+    mgr1: &mut BlockSyncManager<T>,
+    mgr2: &mut BlockSyncManager<T>,
+    remote_peer_id: T::PeerId,
+    token_id: TokenId,
+) -> TokenInfoResponse
+where
+    T: NetworkingService + 'static,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    mgr2.peer_sync_handle
+        .send_request(
+            remote_peer_id,
+            SyncRequest::TokenInfoRequest(TokenInfoRequest {
+                magic_bytes: *mgr2.chain_config.magic_bytes(),
+                token_id,
+            }),
+        )
+        .unwrap();
+
+    match mgr1.peer_sync_handle.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id,
+            request_id,
+            request: SyncRequest::TokenInfoRequest(request),
+        } => mgr1
+            .process_token_info_request(peer_id, request_id, request.token_id)
+            .await
+            .unwrap(),
+        _ => panic!("invalid event received"),
+    }
+
+    match mgr2.peer_sync_handle.poll_next().await.unwrap() {
+        SyncingEvent::Response {
+            response: SyncResponse::TokenInfoResponse(response),
+            ..
+        } => response,
+        _ => panic!("invalid event received"),
+    }
+}
+
+// The token is issued on one node and its info is fetched from another node
+async fn token_info<A, T>()
+where
+    A: TestTransportMaker<Transport = T::Transport, Address = T::Address>,
+    T: NetworkingService + Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let issuance = TokenIssuance {
+        token_ticker: b"XXXX".to_vec(),
+        amount_to_issue: Amount::from_atoms(1_000_000),
+        number_of_decimals: 2,
+        metadata_uri: b"http://uri".to_vec(),
+    };
+    let (block, token_id) = p2p_test_utils::create_token_issuance_block(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        issuance.clone(),
+    );
+    let chainstate = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    p2p_test_utils::import_blocks(&chainstate, vec![block]).await;
+
+    let (mut mgr1, mut conn1, _sync1, _pm1) =
+        make_sync_manager_with_chainstate::<T>(A::make_transport(), A::make_address(), chainstate)
+            .await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) =
+        make_sync_manager::<T>(A::make_transport(), A::make_address()).await;
+    let (_address, _peer_info1, peer_info2) = connect_services::<T>(&mut conn2, &mut conn1).await;
+
+    let response = request_token_info(&mut mgr1, &mut mgr2, peer_info2.peer_id, token_id).await;
+    assert_eq!(
+        response,
+        TokenInfoResponse {
+            magic_bytes: *config.magic_bytes(),
+            token_id,
+            issuance: Some(issuance),
+        }
+    );
+
+    // Unknown tokens don't have the issuance data
+    let unknown_token_id = H256::from_low_u64_be(1);
+    let response =
+        request_token_info(&mut mgr1, &mut mgr2, peer_info2.peer_id, unknown_token_id).await;
+    assert_eq!(
+        response,
+        TokenInfoResponse {
+            magic_bytes: *config.magic_bytes(),
+            token_id: unknown_token_id,
+            issuance: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn token_info_tcp() {
+    token_info::<TestTransportTcp, DefaultNetworkingService<TcpTransportSocket>>().await;
+}
+
+#[tokio::test]
+async fn token_info_channels() {
+    token_info::<TestTransportChannel, DefaultNetworkingService<MpscChannelTransport>>().await;
+}
+
+#[tokio::test]
+async fn token_info_noise() {
+    token_info::<TestTransportNoise, DefaultNetworkingService<NoiseTcpTransport>>().await;
+}
@@ -169,6 +169,7 @@ where
                 address,
                 peer_info,
                 receiver_address: _,
+                timing: _,
             } => (address, peer_info),
             event => panic!("expected `InboundAccepted`, got {event:?}"),
         },
@@ -181,6 +182,7 @@ where
                 address: _,
                 peer_info,
                 receiver_address: _,
+                timing: _,
             } => peer_info,
             event => panic!("expected `OutboundAccepted`, got {event:?}"),
         },
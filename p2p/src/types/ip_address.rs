@@ -13,6 +13,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::net::IpAddr;
+
 use serialization::{Decode, Encode};
 
 /// IPv4 address.
@@ -54,3 +56,33 @@ impl From<Ip6> for std::net::Ipv6Addr {
         ip.inner.into()
     }
 }
+
+/// Converts IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) to IPv4, other addresses are returned as is
+///
+/// A dual-stack socket reports IPv4 peers with such addresses, so they must be normalized
+/// before the addresses are compared, grouped or banned.
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(ip6) => ip6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_ip_ipv4_mapped() {
+        let ip4: IpAddr = "1.2.3.4".parse().unwrap();
+        let mapped: IpAddr = "::ffff:1.2.3.4".parse().unwrap();
+        assert_eq!(canonical_ip(mapped), ip4);
+        assert_eq!(canonical_ip(ip4), ip4);
+
+        // Other IPv6 addresses, including the deprecated IPv4-compatible ones, aren't changed
+        for ip in ["::1.2.3.4", "::1", "2a00:1450:4017:815::200e"] {
+            let ip: IpAddr = ip.parse().unwrap();
+            assert_eq!(canonical_ip(ip), ip);
+        }
+    }
+}